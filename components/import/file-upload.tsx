@@ -8,11 +8,13 @@ import { cn } from '@/lib/utils';
 
 interface FileUploadProps {
   onFileSelect: (file: File) => void;
+  /** Called instead of the browser's file picker when the drop zone is clicked */
+  onBrowse?: () => void;
   isLoading?: boolean;
   error?: string | null;
 }
 
-export function FileUpload({ onFileSelect, isLoading, error }: FileUploadProps) {
+export function FileUpload({ onFileSelect, onBrowse, isLoading, error }: FileUploadProps) {
   const [isDragging, setIsDragging] = useState(false);
   const [selectedFile, setSelectedFile] = useState<File | null>(null);
   const [localError, setLocalError] = useState<string | null>(null);
@@ -88,7 +90,7 @@ export function FileUpload({ onFileSelect, isLoading, error }: FileUploadProps)
         onDragOver={handleDragOver}
         onDragLeave={handleDragLeave}
         onDrop={handleDrop}
-        onClick={() => inputRef.current?.click()}
+        onClick={() => (onBrowse ? onBrowse() : inputRef.current?.click())}
         className={cn(
           'relative border-2 border-dashed rounded-lg p-8 text-center cursor-pointer transition-all duration-200',
           isDragging
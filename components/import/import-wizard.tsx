@@ -1,7 +1,7 @@
 'use client';
 
-import React, { useState, useCallback, useEffect } from 'react';
-import { api } from '@/lib/services';
+import React, { useState, useCallback, useEffect, useRef } from 'react';
+import { api, isTauriContext } from '@/lib/services';
 import { toast } from 'sonner';
 import { FileSpreadsheet, ArrowLeft, CheckCircle } from 'lucide-react';
 import { Card, CardContent, CardHeader, CardTitle, CardDescription } from '@/components/ui/card';
@@ -13,6 +13,19 @@ import { FileUpload } from './file-upload';
 import { ColumnMappingComponent } from './column-mapping';
 import { PreviewTable } from './preview-table';
 import { parseCSV, detectColumnMapping } from '@/lib/csv/parser';
+import {
+  chooseStatement,
+  sampleCsv,
+  importCsv,
+  importOfx,
+  importQif,
+  importCamt,
+  commitImport,
+  discardImport,
+  toParsedRows,
+  type NativeImportPreview,
+  type WatchedStatement,
+} from '@/lib/services/import';
 import { parseDate, detectDateFormat } from '@/lib/csv/date-parser';
 import { cn } from '@/lib/utils';
 import { saveLastImport, clearLastImport } from '@/lib/import-undo';
@@ -31,6 +44,10 @@ import { IMPORT_NOTES_MAX_LENGTH } from '@/lib/validations';
 type ImportStep = 'upload' | 'mapping' | 'preview' | 'complete';
 
 interface ImportWizardProps {
+  /** File to start with, e.g. a statement opened with Puffin */
+  initialFile?: File;
+  /** Statement staged from the watched folder, opened at the preview step */
+  initialBatch?: WatchedStatement;
   onComplete?: (result: ImportResult) => void;
   onCancel?: () => void;
 }
@@ -42,8 +59,41 @@ const steps: { id: ImportStep; label: string }[] = [
   { id: 'complete', label: 'Complete' },
 ];
 
-export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
-  const [currentStep, setCurrentStep] = useState<ImportStep>('upload');
+/** Extensions the desktop file picker offers */
+const STATEMENT_EXTENSIONS = ['csv', 'tsv', 'txt', 'ofx', 'qfx', 'qif', 'xml'];
+
+/**
+ * Format of a statement file that names its own fields, so it skips the
+ * mapping step; null for CSV
+ */
+function fieldedFormat(path: string): 'ofx' | 'qif' | 'camt' | null {
+  const extension = path.split('.').pop()?.toLowerCase();
+  if (extension === 'ofx' || extension === 'qfx') return 'ofx';
+  if (extension === 'qif') return 'qif';
+  if (extension === 'xml') return 'camt';
+  return null;
+}
+
+/** Preview of a natively parsed and staged batch */
+function nativePreview(
+  native: NativeImportPreview,
+  mapping: ColumnMapping | null,
+  dateFormat: DateFormat,
+  headers: string[] = []
+): ImportPreview {
+  return {
+    headers,
+    rows: toParsedRows(native.rows),
+    suggestedMapping: mapping,
+    detectedDateFormat: dateFormat,
+    duplicateCount: native.duplicate_count,
+    validCount: native.valid_count,
+    errorCount: native.error_count,
+  };
+}
+
+export function ImportWizard({ initialFile, initialBatch, onComplete, onCancel }: ImportWizardProps) {
+  const [currentStep, setCurrentStep] = useState<ImportStep>(initialBatch ? 'preview' : 'upload');
   const [parseResult, setParseResult] = useState<CSVParseResult | null>(null);
   const [columnMapping, setColumnMapping] = useState<ColumnMapping>({
     date: -1,
@@ -52,13 +102,20 @@ export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
     ignore: [],
   });
   const [dateFormat, setDateFormat] = useState<DateFormat>('auto');
-  const [preview, setPreview] = useState<ImportPreview | null>(null);
+  const [preview, setPreview] = useState<ImportPreview | null>(
+    initialBatch ? nativePreview(initialBatch, null, 'auto') : null
+  );
   const [isLoading, setIsLoading] = useState(false);
   const [error, setError] = useState<string | null>(null);
   const [importResult, setImportResult] = useState<ImportResult | null>(null);
-  const [selectedSourceId, setSelectedSourceId] = useState<string | null>(null);
+  const [selectedSourceId, setSelectedSourceId] = useState<string | null>(
+    initialBatch?.source_id ?? null
+  );
   const [sources, setSources] = useState<Source[]>([]);
-  const [selectedFile, setSelectedFile] = useState<File | null>(null);
+  // A path when the file was chosen in the desktop app, which parses and
+  // stages it natively
+  const [selectedFile, setSelectedFile] = useState<File | string | null>(null);
+  const [staged, setStaged] = useState<NativeImportPreview | null>(initialBatch ?? null);
   const [hasHeaders, setHasHeaders] = useState(false);
 
   // Fetch sources on mount
@@ -77,12 +134,14 @@ export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
   }, []);
 
   // Parse file with current hasHeaders setting
-  const parseFile = useCallback(async (file: File, withHeaders: boolean) => {
+  const parseFile = useCallback(async (file: File | string, withHeaders: boolean) => {
     setIsLoading(true);
     setError(null);
 
     try {
-      const result = await parseCSV(file, { hasHeaders: withHeaders });
+      const result = typeof file === 'string'
+        ? await sampleCsv(file, { hasHeaders: withHeaders })
+        : await parseCSV(file, { hasHeaders: withHeaders });
       setParseResult(result);
 
       // Auto-detect column mapping
@@ -112,11 +171,62 @@ export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
     }
   }, []);
 
+  // OFX, QIF and camt files are parsed and staged straight away
+  const previewStatement = useCallback(async (path: string) => {
+    setIsLoading(true);
+    setError(null);
+
+    try {
+      const format = fieldedFormat(path);
+      if (format === 'qif') {
+        const native = await importQif(path);
+        setStaged(native);
+        setPreview(nativePreview(native, null, native.date_format));
+      } else if (format === 'camt') {
+        const native = await importCamt(path);
+        setStaged(native);
+        setPreview(nativePreview(native, null, 'auto'));
+        // The source holding the statement's IBAN, unless one is already picked
+        if (native.source_id) {
+          setSelectedSourceId(current => current ?? native.source_id);
+        }
+      } else {
+        const native = await importOfx(path);
+        setStaged(native);
+        setPreview(nativePreview(native, null, 'auto'));
+      }
+      setCurrentStep('preview');
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to read statement');
+    } finally {
+      setIsLoading(false);
+    }
+  }, []);
+
   // Step 1: Handle file upload
-  const handleFileSelect = useCallback(async (file: File) => {
+  const handleFileSelect = useCallback(async (file: File | string) => {
     setSelectedFile(file);
+    if (typeof file === 'string' && fieldedFormat(file)) {
+      await previewStatement(file);
+      return;
+    }
     await parseFile(file, hasHeaders);
-  }, [hasHeaders, parseFile]);
+  }, [hasHeaders, parseFile, previewStatement]);
+
+  // Desktop app: choose the file natively so it can be read from disk
+  const handleBrowse = useCallback(async () => {
+    const path = await chooseStatement('Select Statement', 'Statement', STATEMENT_EXTENSIONS);
+    if (!path) return;
+    await handleFileSelect(path);
+  }, [handleFileSelect]);
+
+  // Start from the provided file instead of the upload step
+  const initialFileLoaded = useRef(false);
+  useEffect(() => {
+    if (!initialFile || initialFileLoaded.current) return;
+    initialFileLoaded.current = true;
+    handleFileSelect(initialFile);
+  }, [initialFile, handleFileSelect]);
 
   // Handle hasHeaders toggle - re-parse the file
   const handleHasHeadersChange = useCallback(async (checked: boolean) => {
@@ -134,6 +244,17 @@ export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
     setError(null);
     
     try {
+      // Files read from disk are parsed, checked for duplicates and staged
+      // natively; the preview shows the first rows
+      if (typeof selectedFile === 'string') {
+        if (staged) void discardImport(staged.batch_id);
+        const native = await importCsv(selectedFile, columnMapping, { hasHeaders, dateFormat });
+        setStaged(native);
+        setPreview(nativePreview(native, columnMapping, native.date_format, native.headers));
+        setCurrentStep('preview');
+        return;
+      }
+
       // Generate preview with parsed data
       const parsedRows: ParsedRow[] = parseResult.rows.map((row, index) => {
         const errors: string[] = [];
@@ -235,7 +356,7 @@ export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
     } finally {
       setIsLoading(false);
     }
-  }, [parseResult, columnMapping, dateFormat]);
+  }, [parseResult, columnMapping, dateFormat, selectedFile, staged, hasHeaders]);
 
   // Step 3: Toggle row selection - use functional update to avoid stale closure
   const handleRowToggle = useCallback((rowIndex: number) => {
@@ -287,49 +408,72 @@ export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
     setError(null);
     
     try {
-      const selectedRows = preview.rows.filter(r => r.isSelected && r.errors.length === 0);
-
-      const transactions = selectedRows.map(row => ({
-        date: row.parsed.date!,
-        description: row.parsed.description!,
-        amount: row.parsed.amount!,
-        notes: row.parsed.notes,
-        source_id: selectedSourceId,
-      }));
-      
-      // Trust the user's selection in the preview table — including any duplicates they
-      // explicitly ticked to override. The server defaults to skipDuplicates=true otherwise.
-      const result = await api.post<ImportResult>('/api/transactions/import', {
-        transactions,
-        skipDuplicates: false,
-      });
+      let data: ImportResult;
+      if (staged) {
+        // Unticked rows are left out of the staged batch. Duplicates are
+        // skipped except the ones ticked here; rows past the preview can't
+        // be ticked, so duplicates among them are always skipped.
+        const summary = await commitImport(staged.batch_id, {
+          sourceId: selectedSourceId,
+          exclude: preview.rows.filter(r => !r.isSelected).map(r => r.rowIndex),
+          force: preview.rows.filter(r => r.isDuplicate && r.isSelected).map(r => r.rowIndex),
+        });
+        setStaged(null);
+        data = {
+          success: true,
+          imported: summary.imported,
+          skipped: staged.total_rows - summary.imported - summary.duplicates,
+          duplicates: summary.duplicates,
+          autoCategorized: summary.auto_categorized,
+          errors: [],
+          batchId: summary.batch_id ?? undefined,
+        };
+      } else {
+        const selectedRows = preview.rows.filter(r => r.isSelected && r.errors.length === 0);
+
+        const transactions = selectedRows.map(row => ({
+          date: row.parsed.date!,
+          description: row.parsed.description!,
+          amount: row.parsed.amount!,
+          notes: row.parsed.notes,
+          source_id: selectedSourceId,
+        }));
+
+        // Trust the user's selection in the preview table — including any duplicates they
+        // explicitly ticked to override. The server defaults to skipDuplicates=true otherwise.
+        const result = await api.post<ImportResult>('/api/transactions/import', {
+          transactions,
+          skipDuplicates: false,
+        });
 
-      if (result.error) {
-        throw new Error(result.error || 'Import failed');
-      }
+        if (result.error) {
+          throw new Error(result.error || 'Import failed');
+        }
 
-      if (!result.data) {
-        throw new Error('Import failed - no response data');
+        if (!result.data) {
+          throw new Error('Import failed - no response data');
+        }
+        data = result.data;
       }
-      setImportResult(result.data);
+      setImportResult(data);
       setCurrentStep('complete');
 
       // Save import info for undo functionality
-      if (result.data.batchId && result.data.imported > 0) {
+      if (data.batchId && data.imported > 0) {
         const sourceName = selectedSourceId
           ? sources.find(s => s.id === selectedSourceId)?.name || null
           : null;
 
         saveLastImport({
-          batchId: result.data.batchId,
+          batchId: data.batchId,
           timestamp: Date.now(),
-          count: result.data.imported,
+          count: data.imported,
           sourceName,
         });
 
         // Show toast with undo action
         const toastId = toast.success(
-          `Imported ${result.data.imported} transaction${result.data.imported !== 1 ? 's' : ''}`,
+          `Imported ${data.imported} transaction${data.imported !== 1 ? 's' : ''}`,
           {
             description: 'You can undo this import within 5 minutes',
             duration: 10000,
@@ -339,14 +483,14 @@ export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
                 try {
                   const undoResult = await api.post<UndoImportResult>(
                     '/api/transactions/undo-import',
-                    { batchId: result.data!.batchId, confirm: true }
+                    { batchId: data.batchId, confirm: true }
                   );
 
                   if (undoResult.data?.success) {
                     clearLastImport();
                     toast.success(undoResult.data.message);
                     // Trigger a refresh by calling onComplete with updated result
-                    onComplete?.({ ...result.data!, imported: 0 });
+                    onComplete?.({ ...data, imported: 0 });
                   } else {
                     toast.error('Failed to undo import');
                   }
@@ -362,15 +506,23 @@ export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
         void toastId;
       }
 
-      onComplete?.(result.data);
+      onComplete?.(data);
     } catch (err) {
       setError(err instanceof Error ? err.message : 'Import failed');
     } finally {
       setIsLoading(false);
     }
-  }, [preview, onComplete, selectedSourceId, sources]);
+  }, [preview, staged, onComplete, selectedSourceId, sources]);
+
+  // Drop a batch that was staged but not imported
+  const handleCancel = () => {
+    if (staged) void discardImport(staged.batch_id);
+    onCancel?.();
+  };
 
   const handleReset = () => {
+    if (staged) void discardImport(staged.batch_id);
+    setStaged(null);
     setCurrentStep('upload');
     setParseResult(null);
     setColumnMapping({ date: -1, description: -1, amount: -1, ignore: [] });
@@ -383,6 +535,9 @@ export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
   };
 
   const currentStepIndex = steps.findIndex(s => s.id === currentStep);
+  // Staged without a mapping: from the watched folder, or an OFX/QIF file
+  const skipsMapping =
+    !!initialBatch || (typeof selectedFile === 'string' && fieldedFormat(selectedFile) !== null);
 
   return (
     <Card className="w-full max-w-4xl mx-auto bg-slate-900 border-slate-700">
@@ -394,7 +549,11 @@ export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
           <div>
             <CardTitle className="text-slate-100">Import Transactions</CardTitle>
             <CardDescription className="text-slate-400">
-              Import transactions from a CSV file
+              {initialBatch
+                ? `Review ${initialBatch.file_name} from your watched folder`
+                : isTauriContext()
+                  ? 'Import transactions from a CSV, OFX, QIF or camt statement'
+                  : 'Import transactions from a CSV file'}
             </CardDescription>
           </div>
         </div>
@@ -449,6 +608,7 @@ export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
         {currentStep === 'upload' && (
           <FileUpload
             onFileSelect={handleFileSelect}
+            onBrowse={isTauriContext() ? handleBrowse : undefined}
             isLoading={isLoading}
             error={error}
           />
@@ -519,15 +679,33 @@ export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
               </p>
             </div>
 
+            {staged && staged.total_rows > preview.rows.length && (
+              <p className="text-xs text-slate-400">
+                Showing the first {preview.rows.length.toLocaleString()} of{' '}
+                {staged.total_rows.toLocaleString()} rows. The rest are imported unless they
+                have errors or are duplicates.
+              </p>
+            )}
+
             <PreviewTable
               preview={preview}
               onRowToggle={handleRowToggle}
               onSelectAll={handleSelectAll}
               onDeselectAll={handleDeselectAll}
               onContinue={handleImport}
-              onBack={() => setCurrentStep('mapping')}
+              onBack={
+                initialBatch
+                  ? handleCancel
+                  : skipsMapping
+                    ? handleReset
+                    : () => setCurrentStep('mapping')
+              }
               isLoading={isLoading}
-              showNotes={columnMapping.notes !== undefined && columnMapping.notes >= 0}
+              showNotes={
+                skipsMapping
+                  ? preview.rows.some(r => r.parsed.notes)
+                  : columnMapping.notes !== undefined && columnMapping.notes >= 0
+              }
             />
           </div>
         )}
@@ -583,7 +761,7 @@ export function ImportWizard({ onComplete, onCancel }: ImportWizardProps) {
         {/* Cancel button */}
         {onCancel && currentStep !== 'complete' && (
           <div className="mt-6 pt-4 border-t border-slate-700">
-            <Button variant="ghost" onClick={onCancel} className="text-slate-400">
+            <Button variant="ghost" onClick={handleCancel} className="text-slate-400">
               <ArrowLeft className="w-4 h-4 mr-2" />
               Cancel Import
             </Button>
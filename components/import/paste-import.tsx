@@ -1,7 +1,8 @@
 'use client';
 
 import React, { useState, useCallback, useEffect, useRef } from 'react';
-import { api } from '@/lib/services';
+import { api, isTauriContext } from '@/lib/services';
+import { chooseStatement, parsePastedStatement, parsePdfStatement } from '@/lib/services/import';
 import { toast } from 'sonner';
 import {
   ClipboardPaste,
@@ -14,6 +15,7 @@ import {
   Table2,
   Trash2,
   RotateCcw,
+  FileUp,
 } from 'lucide-react';
 import { Card, CardContent, CardHeader, CardTitle, CardDescription } from '@/components/ui/card';
 import { Button } from '@/components/ui/button';
@@ -91,22 +93,12 @@ export function PasteImport({ onComplete, onCancel }: PasteImportProps) {
     fetchSources();
   }, []);
 
-  // Parse pasted text
-  const handleParse = useCallback(() => {
-    if (!pastedText.trim()) {
-      setError('Please paste some transaction data first');
-      return;
-    }
-
-    setIsLoading(true);
-    setError(null);
-
-    try {
-      const result = parsePastedText(pastedText, { hasHeaders });
+  // Use a parse result: suggest a mapping and date format, then map columns
+  const applyParseResult = useCallback(
+    (result: CSVParseResult, detectedMapping: ColumnMapping | null) => {
       setParseResult(result);
 
       // Auto-detect column mapping
-      const detectedMapping = detectPasteColumnMapping(result.headers, result.rows);
       if (detectedMapping) {
         setColumnMapping(detectedMapping);
       }
@@ -122,12 +114,58 @@ export function PasteImport({ onComplete, onCancel }: PasteImportProps) {
       }
 
       setCurrentStep('mapping');
+    },
+    []
+  );
+
+  // Parse pasted text
+  const handleParse = useCallback(async () => {
+    if (!pastedText.trim()) {
+      setError('Please paste some transaction data first');
+      return;
+    }
+
+    setIsLoading(true);
+    setError(null);
+
+    try {
+      // The desktop app parses natively, off the UI thread
+      if (isTauriContext()) {
+        const native = await parsePastedStatement(pastedText, { hasHeaders });
+        const { headers, rows, totalRows, encoding } = native;
+        applyParseResult({ headers, rows, totalRows, encoding }, native.mapping);
+      } else {
+        const result = parsePastedText(pastedText, { hasHeaders });
+        applyParseResult(result, detectPasteColumnMapping(result.headers, result.rows));
+      }
     } catch (err) {
       setError(err instanceof Error ? err.message : 'Failed to parse pasted text');
     } finally {
       setIsLoading(false);
     }
-  }, [pastedText, hasHeaders]);
+  }, [pastedText, hasHeaders, applyParseResult]);
+
+  // Read a statement PDF directly (desktop app only); its text is kept in
+  // the text area so it can be checked and parsed again
+  const handleOpenPdf = useCallback(async () => {
+    const path = await chooseStatement('Select Statement PDF', 'PDF Statement', ['pdf']);
+    if (!path) return;
+
+    setIsLoading(true);
+    setError(null);
+
+    try {
+      // The table's header row is found in the PDF when there is one
+      const native = await parsePdfStatement(path);
+      const { headers, rows, totalRows, encoding } = native;
+      setPastedText(native.text);
+      applyParseResult({ headers, rows, totalRows, encoding }, native.mapping);
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to read PDF');
+    } finally {
+      setIsLoading(false);
+    }
+  }, [applyParseResult]);
 
   // Generate preview from parse result and column mapping
   const generatePreview = useCallback(async () => {
@@ -595,6 +633,11 @@ export function PasteImport({ onComplete, onCancel }: PasteImportProps) {
                 <li>Copy the selection (Ctrl+C or Cmd+C)</li>
                 <li>Paste it in the text area below (Ctrl+V or Cmd+V)</li>
               </ol>
+              {isTauriContext() && (
+                <p className="mt-2 text-sm text-slate-400">
+                  Or use Open PDF to read the statement file directly.
+                </p>
+              )}
             </div>
 
             <div className="space-y-2">
@@ -651,18 +694,31 @@ Example:
                 <Trash2 className="w-4 h-4 mr-2" />
                 Clear
               </Button>
-              <Button
-                onClick={handleParse}
-                disabled={!pastedText.trim() || isLoading}
-                className="bg-cyan-600 hover:bg-cyan-500"
-              >
-                {isLoading ? (
-                  <Loader2 className="w-4 h-4 mr-2 animate-spin" />
-                ) : (
-                  <ArrowRight className="w-4 h-4 mr-2" />
+              <div className="flex gap-2">
+                {isTauriContext() && (
+                  <Button
+                    variant="outline"
+                    onClick={handleOpenPdf}
+                    disabled={isLoading}
+                    className="border-slate-700 text-slate-300 hover:bg-slate-800"
+                  >
+                    <FileUp className="w-4 h-4 mr-2" />
+                    Open PDF
+                  </Button>
                 )}
-                Parse Data
-              </Button>
+                <Button
+                  onClick={handleParse}
+                  disabled={!pastedText.trim() || isLoading}
+                  className="bg-cyan-600 hover:bg-cyan-500"
+                >
+                  {isLoading ? (
+                    <Loader2 className="w-4 h-4 mr-2 animate-spin" />
+                  ) : (
+                    <ArrowRight className="w-4 h-4 mr-2" />
+                  )}
+                  Parse Data
+                </Button>
+              </div>
             </div>
           </div>
         )}
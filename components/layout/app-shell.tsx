@@ -15,6 +15,8 @@ import { SettingsPage } from '@/components/pages/settings';
 import { SyncConflictDialog } from '@/components/sync-conflict-dialog';
 import { Toaster } from '@/components/ui/sonner';
 import { TooltipProvider } from '@/components/ui/tooltip';
+import { useTauri } from '@/components/tauri-provider';
+import { toast } from 'sonner';
 
 export type PageId = 'dashboard' | 'transactions' | 'monthly' | 'net-worth' | 'notes' | 'settings';
 
@@ -42,6 +44,7 @@ function AppShellContent() {
   const [sidebarOpen, setSidebarOpen] = useState(true);
   const { logout } = useAuth();
   const { syncStatus, needsResolution, isLoading, refetch } = useSyncContext();
+  const { isTauri } = useTauri();
 
   // Strip the URL hint params after we've consumed them (mounting-side effect,
   // not during render — calling replaceState in the useState initializer trips
@@ -54,6 +57,88 @@ function AppShellContent() {
     }
   }, []);
 
+  // Files opened with Puffin ("Open with", or passed to a second launch):
+  // statements go to the import wizard, backups to Data settings
+  useEffect(() => {
+    if (!isTauri) return;
+    let unlisten: (() => void) | undefined;
+    let cancelled = false;
+
+    (async () => {
+      const { onOpenFile, openStatement } = await import('@/lib/services/launch');
+      const stop = await onOpenFile(async (file) => {
+        if (file.kind === 'statement') {
+          try {
+            await openStatement(file);
+            setCurrentPage('transactions');
+          } catch (err) {
+            toast.error(`Could not open ${file.name}`, {
+              description: err instanceof Error ? err.message : String(err),
+            });
+          }
+        } else {
+          try {
+            sessionStorage.setItem('puffin_action_restore', '1');
+          } catch {
+            // ignore — user lands on Settings main view
+          }
+          setCurrentPage('settings');
+          toast.info(`To restore ${file.name}, use Restore from Backup in Data settings.`);
+        }
+      });
+      if (cancelled) stop();
+      else unlisten = stop;
+    })();
+
+    return () => {
+      cancelled = true;
+      unlisten?.();
+    };
+  }, [isTauri]);
+
+  // Statements staged from the watched folder open in the import preview on
+  // the Transactions page
+  useEffect(() => {
+    if (!isTauri) return;
+    let unlisten: (() => void) | undefined;
+    let cancelled = false;
+
+    (async () => {
+      const { onImportWatch, listWatchedImports, queueWatchedStatement, describeError } =
+        await import('@/lib/services/import');
+      const stop = await onImportWatch((event) => {
+        if (event.status === 'ready') {
+          queueWatchedStatement(event);
+          setCurrentPage('transactions');
+        } else {
+          toast.error(`Could not import ${event.file_name}`, {
+            description: describeError(event.error),
+          });
+        }
+      });
+      if (cancelled) {
+        stop();
+        return;
+      }
+      unlisten = stop;
+
+      // Staged before this listener was up (say, while the window reloaded)
+      try {
+        const waiting = await listWatchedImports();
+        if (cancelled || waiting.length === 0) return;
+        waiting.forEach(queueWatchedStatement);
+        setCurrentPage('transactions');
+      } catch (error) {
+        console.error('Failed to list watched statements:', error);
+      }
+    })();
+
+    return () => {
+      cancelled = true;
+      unlisten?.();
+    };
+  }, [isTauri]);
+
   const renderPage = () => {
     switch (currentPage) {
       case 'dashboard':
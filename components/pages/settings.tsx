@@ -14,11 +14,21 @@ type SettingsView = 'main' | 'categories' | 'rules' | 'sync' | 'data' | 'securit
  * (which sets `puffin_action_reauth` in sessionStorage before navigating).
  * Don't clear the flag here — SyncManagement consumes it once it mounts to
  * fire the OAuth flow automatically.
+ * A backup opened with Puffin (`puffin_action_restore`) or the backup
+ * password prompt (`puffin_action_backup_password`) lands on Data.
  */
 function getInitialSettingsView(): SettingsView {
   if (typeof window === 'undefined') return 'main';
   try {
     if (sessionStorage.getItem('puffin_action_reauth') === '1') return 'sync';
+    if (sessionStorage.getItem('puffin_action_restore') === '1') {
+      sessionStorage.removeItem('puffin_action_restore');
+      return 'data';
+    }
+    if (sessionStorage.getItem('puffin_action_backup_password') === '1') {
+      sessionStorage.removeItem('puffin_action_backup_password');
+      return 'data';
+    }
   } catch {
     // ignore
   }
@@ -46,6 +46,13 @@ import {
 import type { TransactionWithCategory } from '@/types/database';
 import type { ImportResult, UndoImportInfo, UndoImportResult } from '@/types/import';
 import { cn } from '@/lib/utils';
+import { OPEN_STATEMENT_EVENT, takePendingStatement } from '@/lib/services/launch';
+import {
+  WATCHED_STATEMENT_EVENT,
+  takeWatchedStatement,
+  discardImport,
+  type WatchedStatement,
+} from '@/lib/services/import';
 
 interface TransactionListResponse {
   transactions: TransactionWithCategory[];
@@ -94,6 +101,11 @@ function TransactionsPageContent() {
 
   // Modals
   const [showImport, setShowImport] = useState(false);
+  // Statement opened with Puffin, preloaded into the import wizard
+  const [openedStatement, setOpenedStatement] = useState<File | null>(null);
+  // Statement staged from the watched folder, shown in the import preview
+  const [watchedStatement, setWatchedStatement] = useState<WatchedStatement | null>(null);
+  const showingWatched = useRef(false);
   const [showTransactionForm, setShowTransactionForm] = useState(false);
   const [editingTransaction, setEditingTransaction] = useState<TransactionWithCategory | null>(null);
   const [duplicatingTransaction, setDuplicatingTransaction] = useState<TransactionWithCategory | null>(null);
@@ -186,6 +198,39 @@ function TransactionsPageContent() {
     setSelectedIds(new Set());
   }, [transactions]);
 
+  // Open the import wizard for statements opened with Puffin, whether they
+  // arrived before this page mounted or while it is showing
+  useEffect(() => {
+    const openPending = () => {
+      const file = takePendingStatement();
+      if (file) {
+        setOpenedStatement(file);
+        setShowImport(true);
+      }
+    };
+
+    openPending();
+    window.addEventListener(OPEN_STATEMENT_EVENT, openPending);
+    return () => window.removeEventListener(OPEN_STATEMENT_EVENT, openPending);
+  }, []);
+
+  // Preview statements from the watched folder one at a time; the rest wait
+  // until the current one is imported or closed
+  const openNextWatched = useCallback(() => {
+    if (showingWatched.current) return;
+    const next = takeWatchedStatement();
+    if (!next) return;
+    showingWatched.current = true;
+    setWatchedStatement(next);
+    setShowImport(true);
+  }, []);
+
+  useEffect(() => {
+    openNextWatched();
+    window.addEventListener(WATCHED_STATEMENT_EVENT, openNextWatched);
+    return () => window.removeEventListener(WATCHED_STATEMENT_EVENT, openNextWatched);
+  }, [openNextWatched]);
+
   // Check for available undo import and update timer
   useEffect(() => {
     const checkUndo = () => {
@@ -213,6 +258,21 @@ function TransactionsPageContent() {
     // Always refresh - covers both import and undo cases
     fetchTransactions();
     setShowImport(false);
+    setOpenedStatement(null);
+    setWatchedStatement(null);
+    showingWatched.current = false;
+    openNextWatched();
+  };
+
+  // A watched statement closed without importing is dropped; its file
+  // isn't offered again
+  const closeImport = () => {
+    if (watchedStatement) void discardImport(watchedStatement.batch_id);
+    setShowImport(false);
+    setOpenedStatement(null);
+    setWatchedStatement(null);
+    showingWatched.current = false;
+    openNextWatched();
   };
 
   const handleAddTransaction = () => {
@@ -789,13 +849,13 @@ function TransactionsPageContent() {
         <div className="fixed inset-0 z-50 flex items-center justify-center">
           <div 
             className="absolute inset-0 bg-black/70 backdrop-blur-sm"
-            onClick={() => setShowImport(false)}
+            onClick={closeImport}
           />
           <div className="relative z-10 w-full max-w-4xl max-h-[90vh] overflow-y-auto m-4">
             <Button
               variant="ghost"
               size="icon"
-              onClick={() => setShowImport(false)}
+              onClick={closeImport}
               className="absolute -top-12 right-0 text-slate-400 hover:text-white"
             >
               <X className="w-6 h-6" />
@@ -813,14 +873,20 @@ function TransactionsPageContent() {
               </TabsList>
               <TabsContent value="csv">
                 <ImportWizard
+                  key={
+                    watchedStatement?.batch_id ??
+                    (openedStatement ? `${openedStatement.name}-${openedStatement.lastModified}` : 'upload')
+                  }
+                  initialFile={openedStatement ?? undefined}
+                  initialBatch={watchedStatement ?? undefined}
                   onComplete={handleImportComplete}
-                  onCancel={() => setShowImport(false)}
+                  onCancel={closeImport}
                 />
               </TabsContent>
               <TabsContent value="paste">
                 <PasteImport
                   onComplete={handleImportComplete}
-                  onCancel={() => setShowImport(false)}
+                  onCancel={closeImport}
                 />
               </TabsContent>
             </Tabs>
@@ -1,7 +1,8 @@
 'use client';
 
-import { useState } from 'react';
+import { useState, useEffect } from 'react';
 import { api } from '@/lib/services';
+import type { RedirectUriInfo } from '@/lib/services/oauth-flow';
 import { Card, CardContent, CardHeader, CardTitle, CardDescription } from '@/components/ui/card';
 import { Button } from '@/components/ui/button';
 import { Input } from '@/components/ui/input';
@@ -31,6 +32,20 @@ export function CredentialsSetup({ onComplete, onCancel, isDialog = false }: Cre
   const [apiKey, setApiKey] = useState('');
   const [isSaving, setIsSaving] = useState(false);
   const [error, setError] = useState<string | null>(null);
+  const [redirectUris, setRedirectUris] = useState<RedirectUriInfo | null>(null);
+
+  // Show the exact redirect URIs in the desktop app (the instructions step)
+  useEffect(() => {
+    if (step !== 2 || redirectUris) return;
+    const isTauri = typeof window !== 'undefined' &&
+      (window.__TAURI__ || window.__TAURI_INTERNALS__);
+    if (!isTauri) return;
+
+    import('@/lib/services/oauth-flow')
+      .then(({ getRedirectUris }) => getRedirectUris(true))
+      .then(setRedirectUris)
+      .catch((err) => console.error('Failed to load redirect URIs:', err));
+  }, [step, redirectUris]);
 
   const handleSave = async () => {
     if (!clientId.trim() || !clientSecret.trim()) {
@@ -170,6 +185,26 @@ export function CredentialsSetup({ onComplete, onCancel, isDialog = false }: Cre
                   <strong>Note:</strong> Desktop apps use a secure loopback address (127.0.0.1) for OAuth.
                   No redirect URI configuration is needed.
                 </p>
+                {redirectUris && (
+                  <div className="mt-2 text-xs text-blue-300/80 space-y-1">
+                    <p>
+                      Sign-in returns to <code>{redirectUris.loopback_scheme}://{redirectUris.loopback_host}</code>
+                      {redirectUris.any_port ? ' on a random port. ' : '. '}
+                      If a provider requires exact redirect URIs, register:
+                    </p>
+                    <ul className="list-disc list-inside">
+                      {redirectUris.fixed.map((fixed) => (
+                        <li key={fixed.port}>
+                          <code>{fixed.uri}</code>
+                          {fixed.available === false && (
+                            <span className="text-amber-400"> (port currently in use)</span>
+                          )}
+                        </li>
+                      ))}
+                      <li><code>{redirectUris.deep_link_uri}</code></li>
+                    </ul>
+                  </div>
+                )}
               </div>
             </div>
 
@@ -14,6 +14,11 @@ import {
 } from '@/components/ui/dialog';
 import { Input } from '@/components/ui/input';
 import { Label } from '@/components/ui/label';
+import { Switch } from '@/components/ui/switch';
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from '@/components/ui/select';
+import type { BackupSchedule } from '@/lib/services/backup';
+import type { ImportWatchSettings } from '@/lib/services/import';
+import type { Source } from '@/types/database';
 import {
   ArrowLeft,
   Download,
@@ -27,6 +32,9 @@ import {
   AlertTriangle,
   CheckCircle2,
   Calendar,
+  KeyRound,
+  FolderOpen,
+  Plus,
 } from 'lucide-react';
 
 interface DataManagementProps {
@@ -75,6 +83,19 @@ export function DataManagement({ onBack }: DataManagementProps) {
   const [isResetting, setIsResetting] = useState(false);
   const [isDeleting, setIsDeleting] = useState(false);
 
+  // Backup password (Tauri only; stored in the native vault)
+  const [hasPassword, setHasPassword] = useState(false);
+  const [newPassword, setNewPassword] = useState('');
+  const [isSavingPassword, setIsSavingPassword] = useState(false);
+  const [restorePassword, setRestorePassword] = useState('');
+
+  // Automatic backups (Tauri only)
+  const [schedule, setSchedule] = useState<BackupSchedule | null>(null);
+
+  // Watched statements folder (Tauri only)
+  const [watch, setWatch] = useState<ImportWatchSettings | null>(null);
+  const [sources, setSources] = useState<Source[]>([]);
+
   // Messages
   const [successMessage, setSuccessMessage] = useState<string | null>(null);
   const [errorMessage, setErrorMessage] = useState<string | null>(null);
@@ -114,6 +135,53 @@ export function DataManagement({ onBack }: DataManagementProps) {
     fetchBackups();
   }, [fetchStats, fetchBackups]);
 
+  useEffect(() => {
+    if (!isTauriContext()) return;
+    import('@/lib/services/backup')
+      .then(({ hasBackupPassword }) => hasBackupPassword())
+      .then(setHasPassword)
+      .catch((error) => console.error('Failed to check backup password:', error));
+  }, []);
+
+  useEffect(() => {
+    if (!isTauriContext()) return;
+    import('@/lib/services/import')
+      .then(({ getImportWatch }) => getImportWatch())
+      .then(setWatch)
+      .catch((error) => console.error('Failed to load watched folder:', error));
+    api.get<{ sources: Source[] }>('/api/sources')
+      .then((result) => setSources(result.data?.sources || []))
+      .catch((error) => console.error('Failed to fetch sources:', error));
+  }, []);
+
+  useEffect(() => {
+    if (!isTauriContext()) return;
+    let unlisten: (() => void) | undefined;
+    let cancelled = false;
+
+    import('@/lib/services/backup').then(async ({ getBackupSchedule, onAutoBackup }) => {
+      try {
+        setSchedule(await getBackupSchedule());
+        const stop = await onAutoBackup((event) => {
+          if (event.status === 'completed') {
+            fetchBackups();
+          } else {
+            console.error('Automatic backup failed:', event.error);
+          }
+        });
+        if (cancelled) stop();
+        else unlisten = stop;
+      } catch (error) {
+        console.error('Failed to load backup schedule:', error);
+      }
+    });
+
+    return () => {
+      cancelled = true;
+      unlisten?.();
+    };
+  }, [fetchBackups]);
+
   // Format bytes to human readable
   const formatBytes = (bytes: number): string => {
     if (bytes === 0) return '0 Bytes';
@@ -187,6 +255,82 @@ export function DataManagement({ onBack }: DataManagementProps) {
     }
   };
 
+  // Save the backup password used for exports and sync
+  const handleSavePassword = async () => {
+    if (newPassword.length < 8) {
+      showError('Backup password must be at least 8 characters');
+      return;
+    }
+
+    setIsSavingPassword(true);
+    try {
+      const { setBackupPassword } = await import('@/lib/services/backup');
+      await setBackupPassword(newPassword);
+      setHasPassword(true);
+      setNewPassword('');
+      showSuccess('Backup password saved');
+    } catch (error) {
+      console.error('Save backup password error:', error);
+      showError('Failed to save backup password');
+    } finally {
+      setIsSavingPassword(false);
+    }
+  };
+
+  // Save a change to the automatic backup schedule
+  const handleScheduleChange = async (changes: Partial<BackupSchedule>) => {
+    if (!schedule) return;
+    const updated = { ...schedule, ...changes };
+    setSchedule(updated);
+    try {
+      const { setBackupSchedule } = await import('@/lib/services/backup');
+      await setBackupSchedule(updated);
+    } catch (error) {
+      console.error('Save backup schedule error:', error);
+      setSchedule(schedule);
+      showError('Failed to save backup schedule');
+    }
+  };
+
+  // Filename patterns are saved when their field loses focus, so `save` is
+  // false while typing
+  const handleWatchChange = async (changes: Partial<ImportWatchSettings>, save = true) => {
+    if (!watch) return;
+    const updated = { ...watch, ...changes };
+    setWatch(updated);
+    if (!save) return;
+    try {
+      const { setImportWatch, getImportWatch } = await import('@/lib/services/import');
+      await setImportWatch(updated);
+      setWatch(await getImportWatch());
+    } catch (error) {
+      console.error('Save watched folder error:', error);
+      setWatch(watch);
+      showError(error instanceof Error ? error.message : 'Failed to save watched folder');
+    }
+  };
+
+  const handleChooseWatchFolder = async () => {
+    const { open } = await import('@tauri-apps/plugin-dialog');
+    const folder = await open({
+      title: 'Choose Statements Folder',
+      directory: true,
+      multiple: false,
+    });
+    if (!folder || typeof folder !== 'string') return;
+    await handleWatchChange({ folder, enabled: true });
+  };
+
+  const handleWatchPatternChange = (
+    index: number,
+    changes: Partial<ImportWatchSettings['sources'][number]>,
+    save = true
+  ) => {
+    if (!watch) return;
+    const patterns = watch.sources.map((p, i) => (i === index ? { ...p, ...changes } : p));
+    handleWatchChange({ sources: patterns }, save);
+  };
+
   // Export database backup
   const handleExportBackup = async () => {
     setIsExportingBackup(true);
@@ -232,7 +376,7 @@ export function DataManagement({ onBack }: DataManagementProps) {
       console.log('[Restore] Calling import backup API...');
       const result = await api.post<{ success: boolean; cancelled?: boolean; restoredFrom?: string }>(
         '/api/data/import/backup',
-        {}
+        { password: restorePassword }
       );
       console.log('[Restore] Result:', result);
       if (result.data?.cancelled) {
@@ -379,7 +523,7 @@ export function DataManagement({ onBack }: DataManagementProps) {
     try {
       const result = await api.post<{ success: boolean }>(
         `/api/data/backups/${encodeURIComponent(selectedBackup)}`,
-        {}
+        { password: restorePassword }
       );
       if (result.data?.success) {
         showSuccess('Restored from backup. Reloading...');
@@ -487,6 +631,50 @@ export function DataManagement({ onBack }: DataManagementProps) {
         </CardContent>
       </Card>
 
+      {/* Backup Password */}
+      {isTauriContext() && (
+        <Card className="border-slate-800 bg-slate-900/50">
+          <CardHeader>
+            <div className="flex items-center gap-3">
+              <div className="p-2 rounded-lg bg-amber-950/50 border border-amber-900/50">
+                <KeyRound className="w-5 h-5 text-amber-400" />
+              </div>
+              <div>
+                <CardTitle className="text-lg text-slate-100">Backup Password</CardTitle>
+                <CardDescription className="text-slate-400">
+                  Encrypts exported backups and Google Drive sync. Use the same password on every device.
+                </CardDescription>
+              </div>
+            </div>
+          </CardHeader>
+          <CardContent className="space-y-2">
+            <div className="flex flex-col sm:flex-row gap-4">
+              <Input
+                type="password"
+                value={newPassword}
+                onChange={(e) => setNewPassword(e.target.value)}
+                placeholder={hasPassword ? 'Enter a new password to change it' : 'Choose a backup password'}
+                className="flex-1 bg-slate-800 border-slate-700 text-slate-100"
+                aria-label="Backup password"
+              />
+              <Button
+                onClick={handleSavePassword}
+                disabled={!newPassword || isSavingPassword}
+                className="bg-amber-600 hover:bg-amber-500"
+              >
+                {isSavingPassword ? <Loader2 className="w-4 h-4 mr-2 animate-spin" /> : null}
+                {hasPassword ? 'Change Password' : 'Set Password'}
+              </Button>
+            </div>
+            <p className="text-xs text-slate-500">
+              {hasPassword
+                ? 'A backup password is set. Backups made with an older password still need that password to restore.'
+                : 'No backup password set. Exports and sync are unavailable until you set one.'}
+            </p>
+          </CardContent>
+        </Card>
+      )}
+
       {/* Export Section */}
       <Card className="border-slate-800 bg-slate-900/50">
         <CardHeader>
@@ -525,7 +713,7 @@ export function DataManagement({ onBack }: DataManagementProps) {
               ) : (
                 <HardDrive className="w-4 h-4 mr-2" />
               )}
-              Export Full Backup (.db)
+              {isTauriContext() ? 'Export Encrypted Backup' : 'Export Full Backup (.db)'}
             </Button>
           </div>
         </CardContent>
@@ -544,7 +732,21 @@ export function DataManagement({ onBack }: DataManagementProps) {
             </div>
           </div>
         </CardHeader>
-        <CardContent>
+        <CardContent className="space-y-4">
+          {isTauriContext() && (
+            <div>
+              <Label htmlFor="restore-password" className="text-slate-300">
+                Backup password <span className="text-slate-500">(only if it differs from yours)</span>
+              </Label>
+              <Input
+                id="restore-password"
+                type="password"
+                value={restorePassword}
+                onChange={(e) => setRestorePassword(e.target.value)}
+                className="mt-2 bg-slate-800 border-slate-700 text-slate-100"
+              />
+            </div>
+          )}
           <div className="p-4 rounded-lg border-2 border-dashed border-slate-700 hover:border-cyan-600 transition-colors">
             {isTauriContext() ? (
               // Tauri mode: button that opens native file picker
@@ -555,7 +757,7 @@ export function DataManagement({ onBack }: DataManagementProps) {
               >
                 <FileUp className="w-8 h-8 text-slate-400 mb-2" />
                 <span className="text-sm text-slate-300">
-                  {isRestoring ? 'Restoring...' : 'Click to select a .db backup file'}
+                  {isRestoring ? 'Restoring...' : 'Click to select a backup file'}
                 </span>
                 <span className="text-xs text-slate-500 mt-1">
                   This will replace your current database
@@ -584,6 +786,115 @@ export function DataManagement({ onBack }: DataManagementProps) {
         </CardContent>
       </Card>
 
+      {/* Watched Folder */}
+      {isTauriContext() && watch && (
+        <Card className="border-slate-800 bg-slate-900/50">
+          <CardHeader>
+            <div className="flex items-center gap-3">
+              <div className="p-2 rounded-lg bg-emerald-950/50 border border-emerald-900/50">
+                <FolderOpen className="w-5 h-5 text-emerald-400" />
+              </div>
+              <div>
+                <CardTitle className="text-lg text-slate-100">Watched Folder</CardTitle>
+                <CardDescription className="text-slate-400">
+                  Offer new CSV, OFX, QFX, QIF and camt statements saved to a folder for import
+                </CardDescription>
+              </div>
+            </div>
+          </CardHeader>
+          <CardContent className="space-y-4">
+            <div className="flex items-center justify-between gap-4">
+              <div className="min-w-0">
+                <p className="text-sm text-slate-200">Folder</p>
+                <p className="text-xs text-slate-400 truncate">
+                  {watch.folder ?? 'None chosen (must be inside Downloads or Documents)'}
+                </p>
+              </div>
+              <Button
+                variant="outline"
+                size="sm"
+                onClick={handleChooseWatchFolder}
+                className="shrink-0 border-slate-700 text-slate-300"
+              >
+                Choose Folder
+              </Button>
+            </div>
+            {watch.folder && (
+              <div className="flex items-center justify-between">
+                <Label htmlFor="import-watch-enabled" className="text-slate-200">
+                  Watch for new statements
+                </Label>
+                <Switch
+                  id="import-watch-enabled"
+                  checked={watch.enabled}
+                  onCheckedChange={(enabled) => handleWatchChange({ enabled })}
+                />
+              </div>
+            )}
+            <div className="space-y-2 p-3 rounded-lg bg-slate-800/30 border border-slate-800">
+              <Label className="text-sm text-slate-400">Filename patterns</Label>
+              {watch.sources.map((rule, index) => (
+                <div key={index} className="flex items-center gap-2">
+                  <Input
+                    value={rule.pattern}
+                    placeholder="CommBank_*.csv"
+                    aria-label="Filename pattern"
+                    onChange={(e) => handleWatchPatternChange(index, { pattern: e.target.value }, false)}
+                    onBlur={() => handleWatchChange({})}
+                    className="bg-slate-800 border-slate-700 text-slate-100"
+                  />
+                  <Select
+                    value={rule.sourceId}
+                    onValueChange={(sourceId) => handleWatchPatternChange(index, { sourceId })}
+                  >
+                    <SelectTrigger className="w-48 shrink-0 bg-slate-800 border-slate-700 text-slate-100">
+                      <SelectValue placeholder="Source" />
+                    </SelectTrigger>
+                    <SelectContent className="bg-slate-900 border-slate-700">
+                      {sources.map((source) => (
+                        <SelectItem key={source.id} value={source.id}>
+                          {source.name}
+                        </SelectItem>
+                      ))}
+                    </SelectContent>
+                  </Select>
+                  <Button
+                    variant="ghost"
+                    size="sm"
+                    aria-label="Remove pattern"
+                    onClick={() =>
+                      handleWatchChange({ sources: watch.sources.filter((_, i) => i !== index) })
+                    }
+                    className="text-slate-400 hover:text-red-400"
+                  >
+                    <Trash2 className="w-4 h-4" />
+                  </Button>
+                </div>
+              ))}
+              <Button
+                variant="ghost"
+                size="sm"
+                disabled={sources.length === 0}
+                onClick={() =>
+                  handleWatchChange(
+                    { sources: [...watch.sources, { pattern: '', sourceId: sources[0].id }] },
+                    false
+                  )
+                }
+                className="text-slate-400 hover:text-emerald-400"
+              >
+                <Plus className="w-4 h-4 mr-1" />
+                Add pattern
+              </Button>
+              <p className="text-xs text-slate-500">
+                Files named like a pattern (* and ? as wildcards) go to its source. Otherwise the
+                statement&apos;s account, or a source named in the filename, is used.
+              </p>
+            </div>
+          </CardContent>
+        </Card>
+      )}
+
       {/* Local Backups */}
       <Card className="border-slate-800 bg-slate-900/50">
         <CardHeader>
@@ -594,7 +905,7 @@ export function DataManagement({ onBack }: DataManagementProps) {
             <div>
               <CardTitle className="text-lg text-slate-100">Local Backups</CardTitle>
               <CardDescription className="text-slate-400">
-                Automatic backups created before sync operations
+                Scheduled backups and snapshots taken before sync, restore and clear
               </CardDescription>
               <p className="text-xs text-slate-500 mt-1">
                 Stored in: <code className="text-slate-400">%APPDATA%\com.cuestacodes.puffin\backups</code>
@@ -602,7 +913,64 @@ export function DataManagement({ onBack }: DataManagementProps) {
             </div>
           </div>
         </CardHeader>
-        <CardContent>
+        <CardContent className="space-y-4">
+          {schedule && (
+            <div className="space-y-3 p-3 rounded-lg bg-slate-800/30 border border-slate-800">
+              <div className="flex items-center justify-between">
+                <Label htmlFor="auto-backup-enabled" className="text-slate-200">
+                  Automatic backups
+                </Label>
+                <Switch
+                  id="auto-backup-enabled"
+                  checked={schedule.enabled}
+                  onCheckedChange={(enabled) => handleScheduleChange({ enabled })}
+                />
+              </div>
+              {schedule.enabled && (
+                <>
+                  <div className="flex items-center justify-between">
+                    <Label htmlFor="auto-backup-launch" className="text-sm text-slate-400">
+                      On launch
+                    </Label>
+                    <Switch
+                      id="auto-backup-launch"
+                      checked={schedule.onLaunch}
+                      onCheckedChange={(onLaunch) => handleScheduleChange({ onLaunch })}
+                    />
+                  </div>
+                  <div className="flex items-center justify-between">
+                    <Label htmlFor="auto-backup-daily" className="text-sm text-slate-400">
+                      Daily
+                    </Label>
+                    <Switch
+                      id="auto-backup-daily"
+                      checked={schedule.daily}
+                      onCheckedChange={(daily) => handleScheduleChange({ daily })}
+                    />
+                  </div>
+                  <div className="flex items-center justify-between gap-4">
+                    <Label htmlFor="auto-backup-changes" className="text-sm text-slate-400">
+                      After this many transaction changes (0 = off)
+                    </Label>
+                    <Input
+                      id="auto-backup-changes"
+                      type="number"
+                      min={0}
+                      value={schedule.changeThreshold}
+                      onChange={(e) =>
+                        handleScheduleChange({ changeThreshold: Math.max(0, parseInt(e.target.value, 10) || 0) })
+                      }
+                      className="w-24 bg-slate-800 border-slate-700 text-slate-100"
+                    />
+                  </div>
+                  <p className="text-xs text-slate-500">
+                    Keeps the latest automatic backup from each of the last {schedule.keepDaily} days,{' '}
+                    {schedule.keepWeekly} weeks and {schedule.keepMonthly} months.
+                  </p>
+                </>
+              )}
+            </div>
+          )}
           {isLoadingBackups ? (
             <div className="flex items-center justify-center py-8">
               <Loader2 className="w-6 h-6 text-slate-400 animate-spin" />
@@ -39,6 +39,26 @@ interface PickerCredentials {
   configured: boolean;
 }
 
+/**
+ * Turn an error from the native OAuth flow (`{ kind, message? }`, see
+ * src-tauri/src/oauth/mod.rs) or a thrown Error into a user-facing message.
+ */
+function describeOAuthError(err: unknown): string {
+  if (err instanceof Error) return err.message;
+  if (typeof err === 'object' && err !== null && 'kind' in err) {
+    const { kind, message } = err as { kind: string; message?: string };
+    switch (kind) {
+      case 'timeout':
+        return 'Timed out waiting for Google sign-in. Please try again.';
+      case 'state_mismatch':
+        return 'The sign-in response did not match this request. Please try again.';
+      default:
+        return message || kind;
+    }
+  }
+  return 'Unknown error';
+}
+
 export function SyncManagement({ onBack }: SyncManagementProps) {
   // Configuration state
   const [config, setConfig] = useState<ExtendedSyncConfig | null>(null);
@@ -157,6 +177,8 @@ export function SyncManagement({ onBack }: SyncManagementProps) {
 
   // Track if OAuth is in progress
   const [isAuthenticating, setIsAuthenticating] = useState(false);
+  // ID of the native OAuth flow waiting for the browser, used to cancel it
+  const oauthFlowIdRef = useRef<string | null>(null);
 
   useEffect(() => {
     fetchConfig();
@@ -167,6 +189,14 @@ export function SyncManagement({ onBack }: SyncManagementProps) {
     await startOAuthFlow('standard');
   };
 
+  // Abort a sign-in that is waiting for the browser
+  const handleCancelAuthenticate = async () => {
+    const flowId = oauthFlowIdRef.current;
+    if (!flowId) return;
+    const { cancelOAuthFlow } = await import('@/lib/services/oauth-flow');
+    await cancelOAuthFlow(flowId);
+  };
+
   // Auto-trigger Sign in with Google when arriving here via the Reconnect modal.
   // The modal sets sessionStorage 'puffin_action_reauth' before navigating;
   // we fire OAuth once config is loaded (so credentials are available) and
@@ -209,14 +239,14 @@ export function SyncManagement({ onBack }: SyncManagementProps) {
       try {
         setIsAuthenticating(true);
 
-        // Get credentials from localStorage
-        const stored = localStorage.getItem('puffin_sync_credentials');
-        if (!stored) {
+        // Client ID from the native vault (the secret stays there)
+        const { getOAuthClient } = await import('@/lib/services/vault');
+        const creds = await getOAuthClient();
+        if (!creds) {
           setValidationError('OAuth credentials not found. Please configure your Google Cloud credentials first.');
           return;
         }
 
-        const creds = JSON.parse(stored);
         const scopes = scopeLevel === 'extended'
           ? 'https://www.googleapis.com/auth/drive https://www.googleapis.com/auth/userinfo.email'
           : 'https://www.googleapis.com/auth/drive.file https://www.googleapis.com/auth/userinfo.email';
@@ -224,14 +254,21 @@ export function SyncManagement({ onBack }: SyncManagementProps) {
         const stateData = JSON.stringify({ scopeLevel });
         const state = btoa(stateData);
 
-        // Call the Tauri command to start OAuth flow
-        const { invoke } = await import('@tauri-apps/api/core');
-        const result = await invoke<{ code?: string; state?: string; error?: string; redirect_uri?: string }>('start_oauth_flow', {
+        // Open the consent page, then wait for the loopback callback
+        const { startOAuthFlow: startNativeOAuthFlow } = await import('@/lib/services/oauth-flow');
+        const flow = await startNativeOAuthFlow({
           authUrlBase: 'https://accounts.google.com/o/oauth2/v2/auth',
           clientId: creds.clientId,
           scope: scopes,
           state,
         });
+        oauthFlowIdRef.current = flow.flowId;
+        let result: Awaited<typeof flow.result>;
+        try {
+          result = await flow.result;
+        } finally {
+          oauthFlowIdRef.current = null;
+        }
 
         if (result.error) {
           setValidationError(`Authentication failed: ${result.error}`);
@@ -248,6 +285,7 @@ export function SyncManagement({ onBack }: SyncManagementProps) {
           code: result.code,
           state: result.state,
           redirectUri: result.redirect_uri,
+          codeVerifier: result.code_verifier,
         });
 
         if (tokenResult.data?.success) {
@@ -257,8 +295,12 @@ export function SyncManagement({ onBack }: SyncManagementProps) {
           setValidationError(tokenResult.error || 'Failed to complete authentication');
         }
       } catch (err) {
+        // Cancelled from the UI - nothing to report
+        if (typeof err === 'object' && err !== null && (err as { kind?: string }).kind === 'cancelled') {
+          return;
+        }
         console.error('OAuth error:', err);
-        setValidationError(`Authentication failed: ${err instanceof Error ? err.message : 'Unknown error'}`);
+        setValidationError(`Authentication failed: ${describeOAuthError(err)}`);
       } finally {
         setIsAuthenticating(false);
       }
@@ -547,6 +589,15 @@ export function SyncManagement({ onBack }: SyncManagementProps) {
               )}
               {isAuthenticating ? 'Authenticating...' : 'Sign in with Google'}
             </Button>
+            {isAuthenticating && (
+              <Button
+                variant="ghost"
+                onClick={handleCancelAuthenticate}
+                className="ml-2 text-slate-400 hover:text-slate-200"
+              >
+                Cancel
+              </Button>
+            )}
           </CardContent>
         </Card>
       )}
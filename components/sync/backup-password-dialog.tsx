@@ -0,0 +1,116 @@
+'use client';
+
+import { useEffect, useState } from 'react';
+import { toast } from 'sonner';
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogFooter,
+  DialogHeader,
+  DialogTitle,
+} from '@/components/ui/dialog';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { BACKUP_PASSWORD_REQUIRED_EVENT } from '@/lib/services/api-client';
+
+/**
+ * Globally-mounted modal that asks for a backup password when a sync push
+ * fails with `errorCode: 'BACKUP_PASSWORD_REQUIRED'`. Sync uploads are
+ * encrypted with that password, so devices that were syncing before
+ * encryption existed hit this on their first push after updating.
+ *
+ * The password is saved to the vault the same way Data Management does; the
+ * user then syncs again. "Open Data Settings" leads to the Backup Password
+ * card instead.
+ */
+
+const MIN_PASSWORD_LENGTH = 8;
+
+export function BackupPasswordDialog() {
+  const [open, setOpen] = useState(false);
+  const [password, setPassword] = useState('');
+  const [isSaving, setIsSaving] = useState(false);
+
+  useEffect(() => {
+    const onPasswordRequired = () => setOpen(true);
+    window.addEventListener(BACKUP_PASSWORD_REQUIRED_EVENT, onPasswordRequired);
+    return () => window.removeEventListener(BACKUP_PASSWORD_REQUIRED_EVENT, onPasswordRequired);
+  }, []);
+
+  const close = () => {
+    setOpen(false);
+    setPassword('');
+  };
+
+  const handleSave = async () => {
+    if (password.length < MIN_PASSWORD_LENGTH) {
+      toast.error(`Backup password must be at least ${MIN_PASSWORD_LENGTH} characters`);
+      return;
+    }
+
+    setIsSaving(true);
+    try {
+      const { setBackupPassword } = await import('@/lib/services/backup');
+      await setBackupPassword(password);
+      close();
+      toast.success('Backup password saved. Sync again to upload.');
+    } catch (error) {
+      console.error('Save backup password error:', error);
+      toast.error('Failed to save backup password');
+    } finally {
+      setIsSaving(false);
+    }
+  };
+
+  const handleOpenSettings = () => {
+    close();
+    // Picked up by getInitialSettingsView (pages/settings.tsx) to land on Data
+    try {
+      sessionStorage.setItem('puffin_action_backup_password', '1');
+    } catch {
+      // ignore — user lands on the Settings overview
+    }
+    window.location.search = '?page=settings';
+  };
+
+  return (
+    <Dialog open={open} onOpenChange={(next) => !next && close()}>
+      <DialogContent className="bg-slate-900 border-slate-700">
+        <DialogHeader>
+          <DialogTitle className="text-slate-100">Set a Backup Password</DialogTitle>
+          <DialogDescription className="text-slate-400">
+            Google Drive sync now uploads encrypted backups. Choose a backup password to
+            continue syncing. Use the same password on every device that syncs.
+          </DialogDescription>
+        </DialogHeader>
+        <Input
+          type="password"
+          value={password}
+          onChange={(e) => setPassword(e.target.value)}
+          onKeyDown={(e) => e.key === 'Enter' && handleSave()}
+          placeholder="Choose a backup password"
+          className="bg-slate-800 border-slate-700 text-slate-100"
+          aria-label="Backup password"
+          autoFocus
+        />
+        <DialogFooter className="gap-2 sm:gap-2">
+          <Button
+            variant="outline"
+            onClick={handleOpenSettings}
+            className="border-slate-700 text-slate-300 hover:bg-slate-800"
+          >
+            Open Data Settings
+          </Button>
+          <Button
+            onClick={handleSave}
+            disabled={!password || isSaving}
+            className="bg-gradient-to-r from-cyan-500 to-blue-600 hover:from-cyan-400 hover:to-blue-500"
+          >
+            Save Password
+          </Button>
+        </DialogFooter>
+      </DialogContent>
+    </Dialog>
+  );
+}
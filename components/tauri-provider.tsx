@@ -3,6 +3,7 @@
 import { createContext, useContext, useEffect, useState, useCallback, ReactNode } from 'react';
 import { SyncBeforeCloseModal } from './sync-before-close-modal';
 import { ReconnectDialog } from './sync/reconnect-dialog';
+import { BackupPasswordDialog } from './sync/backup-password-dialog';
 
 interface TauriContextValue {
   isTauri: boolean;
@@ -187,6 +188,8 @@ export function TauriProvider({ children }: TauriProviderProps) {
       )}
       {/* Mounted globally so any sync API call surfacing REFRESH_FAILED triggers it. */}
       <ReconnectDialog />
+      {/* Likewise for a sync push that needs a backup password first. */}
+      {isTauri && <BackupPasswordDialog />}
     </TauriContext.Provider>
   );
 }
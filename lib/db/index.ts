@@ -107,6 +107,7 @@ function setSchemaVersion(database: Database.Database, version: number): void {
  * Run database migrations for schema updates.
  * Uses a schema_version table to track which migrations have been applied.
  * Each migration is idempotent and only runs if the current version is less than required.
+ * The desktop app migrates natively (src-tauri/src/db/migrations.rs); keep versions in step.
  */
 function runMigrations(database: Database.Database): void {
   const currentVersion = getSchemaVersion(database);
@@ -274,6 +275,24 @@ function runMigrations(database: Database.Database): void {
     `);
     setSchemaVersion(database, 6);
   }
+
+  // Migration 7: Add external_id (bank transaction ID, e.g. OFX FITID) for duplicate detection
+  if (currentVersion < 7) {
+    const columnExists = database.prepare(
+      "SELECT * FROM pragma_table_info('transaction') WHERE name='external_id'"
+    ).get();
+
+    if (!columnExists) {
+      database.exec(`
+        ALTER TABLE "transaction" ADD COLUMN external_id TEXT
+      `);
+    }
+    database.exec(`
+      CREATE INDEX IF NOT EXISTS idx_transaction_external_id ON "transaction"(external_id)
+    `);
+
+    setSchemaVersion(database, 7);
+  }
 }
 
 /**
@@ -53,6 +53,7 @@ CREATE TABLE IF NOT EXISTS "transaction" (
   parent_transaction_id TEXT REFERENCES "transaction"(id),
   is_deleted INTEGER NOT NULL DEFAULT 0,
   import_batch_id TEXT,
+  external_id TEXT,
   created_at TEXT NOT NULL DEFAULT (datetime('now')),
   updated_at TEXT NOT NULL DEFAULT (datetime('now'))
 );
@@ -134,6 +135,7 @@ CREATE INDEX IF NOT EXISTS idx_transaction_source ON "transaction"(source_id);
 CREATE INDEX IF NOT EXISTS idx_transaction_parent ON "transaction"(parent_transaction_id);
 CREATE INDEX IF NOT EXISTS idx_transaction_deleted ON "transaction"(is_deleted);
 CREATE INDEX IF NOT EXISTS idx_transaction_import_batch ON "transaction"(import_batch_id);
+CREATE INDEX IF NOT EXISTS idx_transaction_external_id ON "transaction"(external_id);
 CREATE INDEX IF NOT EXISTS idx_sub_category_upper ON sub_category(upper_category_id);
 CREATE INDEX IF NOT EXISTS idx_budget_period ON budget(year, month);
 CREATE INDEX IF NOT EXISTS idx_auto_rule_priority ON auto_category_rule(priority);
@@ -44,9 +44,19 @@ export interface ApiResponse<T> {
  */
 export const OAUTH_REFRESH_FAILED_EVENT = 'puffin:oauth-refresh-failed';
 
+/**
+ * Dispatched when a sync push surfaces `errorCode: 'BACKUP_PASSWORD_REQUIRED'`
+ * (uploads are encrypted with the backup password, which isn't set yet). The
+ * backup password dialog listens for this.
+ */
+export const BACKUP_PASSWORD_REQUIRED_EVENT = 'puffin:backup-password-required';
+
 function notifyIfRefreshFailed(response: ApiResponse<unknown>): void {
-  if (response.errorCode === 'REFRESH_FAILED' && typeof window !== 'undefined') {
+  if (typeof window === 'undefined') return;
+  if (response.errorCode === 'REFRESH_FAILED') {
     window.dispatchEvent(new CustomEvent(OAUTH_REFRESH_FAILED_EVENT));
+  } else if (response.errorCode === 'BACKUP_PASSWORD_REQUIRED') {
+    window.dispatchEvent(new CustomEvent(BACKUP_PASSWORD_REQUIRED_EVENT));
   }
 }
 
@@ -169,8 +179,9 @@ async function handleTauriRequest<T>(
         : undefined;
     // REFRESH_FAILED is an expected, handled state surfaced via the Reconnect
     // modal. Don't console.error — sync polls every minute and would otherwise
-    // spam the dev console / error overlay.
-    if (errorCode !== 'REFRESH_FAILED') {
+    // spam the dev console / error overlay. BACKUP_PASSWORD_REQUIRED likewise
+    // opens the backup password dialog.
+    if (errorCode !== 'REFRESH_FAILED' && errorCode !== 'BACKUP_PASSWORD_REQUIRED') {
       console.error(`API handler error for ${path}:`, error);
     }
     return {
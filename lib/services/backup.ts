@@ -0,0 +1,191 @@
+/**
+ * Encrypted Backups
+ *
+ * Wraps the backup commands (src-tauri/src/db/backup.rs). Sync uploads and
+ * manual backups are written as encrypted `.puffinbak` containers; plain
+ * `.db` files from older versions can still be read and restored.
+ *
+ * Automatic local backups (src-tauri/src/db/schedule.rs) run in the
+ * background; their schedule and retention are configured here.
+ */
+
+import { VAULT_KEYS, vaultHas, vaultSet } from './vault';
+
+export const BACKUP_EXTENSION = 'puffinbak';
+
+/** Extensions accepted when picking a backup to restore */
+export const RESTORABLE_EXTENSIONS = [BACKUP_EXTENSION, 'db'];
+
+/** `{ kind, message? }` error from the backup commands */
+export interface BackupCommandError {
+  kind:
+    | 'io'
+    | 'sqlite'
+    | 'not_a_backup'
+    | 'unsupported_version'
+    | 'password_required'
+    | 'wrong_password'
+    | 'corrupt'
+    | 'incompatible_schema';
+  message?: string | number;
+}
+
+export interface BackupInfo {
+  path: string;
+  size: number;
+  content_hash: string;
+  /** Unix seconds */
+  created_at: number;
+}
+
+export interface BackupFileInfo {
+  path: string;
+  format: 'encrypted' | 'sqlite';
+  size: number;
+  created_at: number | null;
+  content_hash: string | null;
+}
+
+function describeError(error: BackupCommandError): string {
+  switch (error.kind) {
+    case 'password_required':
+      return 'Set a backup password in Data Management first';
+    case 'wrong_password':
+      return 'Incorrect backup password';
+    case 'not_a_backup':
+      return 'File is not a Puffin backup';
+    case 'unsupported_version':
+      return 'This backup was made by a newer version of Puffin';
+    case 'corrupt':
+      return `Backup is damaged: ${error.message}`;
+    case 'incompatible_schema':
+      return `Backup can't be restored: ${error.message}`;
+    default:
+      return String(error.message ?? error.kind);
+  }
+}
+
+async function invokeBackup<T>(command: string, args: Record<string, unknown>): Promise<T> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  try {
+    return await invoke<T>(command, args);
+  } catch (err) {
+    if (err && typeof err === 'object' && 'kind' in err) {
+      throw new Error(describeError(err as BackupCommandError));
+    }
+    throw err;
+  }
+}
+
+/**
+ * Write an encrypted backup of the database to `dest`. Uses the backup
+ * password from the vault unless `password` is given.
+ */
+export async function createBackup(dest: string, password?: string): Promise<BackupInfo> {
+  return invokeBackup<BackupInfo>('create_backup', { dest, password });
+}
+
+/**
+ * Read a backup's format, date and content hash without decrypting it.
+ */
+export async function inspectBackup(path: string): Promise<BackupFileInfo> {
+  return invokeBackup<BackupFileInfo>('inspect_backup', { path });
+}
+
+/**
+ * Write the database inside backup `path` to `dest`, decrypting and
+ * verifying it. Close the database connection first when `dest` is puffin.db.
+ */
+export async function extractBackup(
+  path: string,
+  dest: string,
+  password?: string
+): Promise<BackupFileInfo> {
+  return invokeBackup<BackupFileInfo>('extract_backup', { path, dest, password });
+}
+
+export interface RestoreInfo {
+  format: 'encrypted' | 'sqlite';
+  /** Schema version of the backup before migrations run */
+  schema_version: number;
+  content_hash: string;
+  /** Snapshot of the database that was replaced */
+  safety_snapshot: string | null;
+}
+
+/**
+ * Replace the database with the one in backup `path`. The backup is checked
+ * and the current database snapshotted before the swap, which rolls back if
+ * it fails. Reconnect (`resetDatabaseConnection`) afterwards.
+ */
+export async function restoreBackup(path: string, password?: string): Promise<RestoreInfo> {
+  return invokeBackup<RestoreInfo>('restore_backup', { path, password });
+}
+
+export async function hasBackupPassword(): Promise<boolean> {
+  return vaultHas(VAULT_KEYS.backupPassword);
+}
+
+/**
+ * Store the backup password. Every device that syncs must use the same one.
+ */
+export async function setBackupPassword(password: string): Promise<void> {
+  await vaultSet(VAULT_KEYS.backupPassword, password);
+}
+
+/** Automatic backup schedule and retention (`auto-backup.json`) */
+export interface BackupSchedule {
+  enabled: boolean;
+  onLaunch: boolean;
+  daily: boolean;
+  /** Transactions added or edited before a backup runs; 0 turns this off */
+  changeThreshold: number;
+  keepDaily: number;
+  keepWeekly: number;
+  keepMonthly: number;
+}
+
+export type AutoBackupTrigger = 'launch' | 'daily' | 'changes';
+
+export type AutoBackupEvent =
+  | {
+      status: 'completed';
+      trigger: AutoBackupTrigger;
+      snapshot: { path: string; size: number; hash: string; content_hash: string };
+      /** Automatic backups pruned by the retention policy */
+      removed: string[];
+    }
+  | { status: 'failed'; trigger: AutoBackupTrigger; error: { kind: string; message?: string } };
+
+export async function getBackupSchedule(): Promise<BackupSchedule> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<BackupSchedule>('get_backup_schedule');
+}
+
+/**
+ * Save the schedule. The background task picks it up at its next check.
+ */
+export async function setBackupSchedule(settings: BackupSchedule): Promise<void> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  await invoke('set_backup_schedule', { settings });
+}
+
+/**
+ * Subscribe to automatic backup results. Returns an unsubscribe function.
+ */
+export async function onAutoBackup(handler: (event: AutoBackupEvent) => void): Promise<() => void> {
+  const { listen } = await import('@tauri-apps/api/event');
+  const unlistenCompleted = await listen<Omit<Extract<AutoBackupEvent, { status: 'completed' }>, 'status'>>(
+    'auto-backup://completed',
+    ({ payload }) => handler({ status: 'completed', ...payload })
+  );
+  const unlistenFailed = await listen<Omit<Extract<AutoBackupEvent, { status: 'failed' }>, 'status'>>(
+    'auto-backup://failed',
+    ({ payload }) => handler({ status: 'failed', ...payload })
+  );
+
+  return () => {
+    unlistenCompleted();
+    unlistenFailed();
+  };
+}
@@ -0,0 +1,30 @@
+/**
+ * Deep Links
+ *
+ * `puffin://` routes forwarded by src-tauri/src/deep_link.rs. OAuth
+ * redirects (`puffin://oauth/callback`) are consumed natively and never
+ * show up here.
+ */
+
+/** A `puffin://<route>?<params>` link */
+export interface DeepLinkRoute {
+  url: string;
+  /** Host and path without slashes around it, e.g. `import/csv` */
+  route: string;
+  params: Record<string, string>;
+}
+
+/**
+ * Subscribe to deep links. Links that launched the app are delivered first.
+ * Returns an unsubscribe function.
+ */
+export async function onDeepLink(handler: (link: DeepLinkRoute) => void): Promise<() => void> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  const { listen } = await import('@tauri-apps/api/event');
+
+  const unlisten = await listen<DeepLinkRoute>('deep-link://route', ({ payload }) => handler(payload));
+  const launched = await invoke<DeepLinkRoute[]>('take_launch_deep_links');
+  launched.forEach(handler);
+
+  return unlisten;
+}
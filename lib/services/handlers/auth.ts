@@ -516,11 +516,18 @@ export async function handleReset(ctx: HandlerContext): Promise<unknown> {
     // Continue with reset even if backup deletion fails
   }
 
+  // Clear stored secrets (OAuth tokens, client credentials)
+  try {
+    const { vaultDelete, VAULT_KEYS } = await import('../vault');
+    await vaultDelete(VAULT_KEYS.oauthTokens);
+    await vaultDelete(VAULT_KEYS.syncCredentials);
+  } catch (err) {
+    console.warn('Failed to clear vault:', err);
+  }
+
   // Clear sync-related localStorage
   if (typeof localStorage !== 'undefined') {
     localStorage.removeItem('puffin_sync_config');
-    localStorage.removeItem('puffin_sync_credentials');
-    localStorage.removeItem('puffin_oauth_tokens');
     localStorage.removeItem('puffin_oauth_authenticated');
     localStorage.removeItem('puffin_oauth_extended_scope');
     localStorage.removeItem('puffin_session');
@@ -5,7 +5,8 @@
  * in Tauri mode using native SQLite and file system APIs.
  */
 
-import { getDatabase, getDatabasePath, backup as vacuumBackup } from '../tauri-db';
+import { getDatabase, getDatabasePath, backup as snapshotDatabase } from '../tauri-db';
+import { BACKUP_EXTENSION, RESTORABLE_EXTENSIONS, createBackup, restoreBackup } from '../backup';
 
 interface HandlerContext {
   method: string;
@@ -14,6 +15,16 @@ interface HandlerContext {
   path: string;
 }
 
+/** Optional password for restoring a backup made with a different one */
+function restorePassword(body: unknown): string | undefined {
+  const password = (body as { password?: unknown } | undefined)?.password;
+  return typeof password === 'string' && password ? password : undefined;
+}
+
+function isRestorable(filename: string): boolean {
+  return RESTORABLE_EXTENSIONS.some(ext => filename.endsWith(`.${ext}`));
+}
+
 /**
  * Database stats handler - /api/data/stats
  */
@@ -90,15 +101,15 @@ export async function handleClear(ctx: HandlerContext): Promise<unknown> {
 
   const db = await getDatabase();
 
-  // Create a backup before clearing (using VACUUM INTO)
+  // Snapshot the database before clearing
   try {
     const dbPath = await getDatabasePath();
     const timestamp = new Date().toISOString().replace(/[:.]/g, '-').split('T').join('_');
     const separator = dbPath.includes('\\') ? '\\' : '/';
     const backupPath = dbPath.replace('puffin.db', `backups${separator}pre-clear-${timestamp}.db`);
 
-    // Ensure backups directory exists via parent path
-    await vacuumBackup(backupPath);
+    // The snapshot command creates the backups directory if needed
+    await snapshotDatabase(backupPath);
   } catch (err) {
     console.warn('Failed to create pre-clear backup:', err);
     // Continue with clear even if backup fails
@@ -160,7 +171,7 @@ export async function handleReset(ctx: HandlerContext): Promise<unknown> {
       try {
         const entries = await readDir(backupsDir);
         for (const entry of entries) {
-          if (entry.name?.endsWith('.db')) {
+          if (entry.name && isRestorable(entry.name)) {
             try {
               await remove(await join(backupsDir, entry.name));
             } catch (err) {
@@ -173,11 +184,18 @@ export async function handleReset(ctx: HandlerContext): Promise<unknown> {
       }
     }
 
+    // Clear stored secrets (OAuth tokens, client credentials)
+    try {
+      const { vaultDelete, VAULT_KEYS } = await import('../vault');
+      await vaultDelete(VAULT_KEYS.oauthTokens);
+      await vaultDelete(VAULT_KEYS.syncCredentials);
+    } catch (err) {
+      console.warn('Failed to clear vault:', err);
+    }
+
     // Clear sync-related localStorage
     if (typeof localStorage !== 'undefined') {
       localStorage.removeItem('puffin_sync_config');
-      localStorage.removeItem('puffin_sync_credentials');
-      localStorage.removeItem('puffin_oauth_tokens');
       localStorage.removeItem('puffin_oauth_authenticated');
       localStorage.removeItem('puffin_oauth_extended_scope');
       localStorage.removeItem('puffin_session');
@@ -218,7 +236,7 @@ export async function handleBackups(ctx: HandlerContext): Promise<unknown> {
       // Read directory contents
       const entries = await readDir(backupsDir);
       const backups = entries
-        .filter(entry => entry.name?.endsWith('.db'))
+        .filter(entry => entry.name && isRestorable(entry.name))
         .map(entry => ({
           filename: entry.name,
           size: 0, // Size not available without stat
@@ -254,23 +272,23 @@ export async function handleBackups(ctx: HandlerContext): Promise<unknown> {
         // mkdir might not be available, try backup anyway
       }
 
-      const filename = `puffin-backup-${timestamp}.db`;
+      const filename = `puffin-backup-${timestamp}.${BACKUP_EXTENSION}`;
       const backupPath = await join(backupsDir, filename);
 
-      // Use VACUUM INTO to create backup
-      await vacuumBackup(backupPath);
+      // Encrypted with the backup password
+      const info = await createBackup(backupPath);
 
       return {
         success: true,
         backup: {
           filename,
-          size: 0,
-          createdAt: new Date().toISOString(),
+          size: info.size,
+          createdAt: new Date(info.created_at * 1000).toISOString(),
         },
       };
     } catch (err) {
       console.error('Failed to create backup:', err);
-      throw new Error('Failed to create backup');
+      throw new Error(`Failed to create backup: ${err instanceof Error ? err.message : 'Unknown error'}`);
     }
   }
 
@@ -292,61 +310,24 @@ export async function handleBackup(ctx: HandlerContext): Promise<unknown> {
   if (method === 'POST') {
     // Restore from local backup
     try {
-      const { copyFile, exists, remove } = await import('@tauri-apps/plugin-fs');
+      const { exists } = await import('@tauri-apps/plugin-fs');
       const { appDataDir, join } = await import('@tauri-apps/api/path');
-      const { resetDatabaseConnection, closeDatabase } = await import('../tauri-db');
+      const { resetDatabaseConnection } = await import('../tauri-db');
 
       const dataDir = await appDataDir();
       const backupPath = await join(dataDir, 'backups', filename);
-      const dbPath = await join(dataDir, 'puffin.db');
 
       // Verify backup exists
       if (!(await exists(backupPath))) {
         throw new Error(`Backup file not found: ${filename}`);
       }
 
-      // Create a pre-restore backup of current database
-      const timestamp = new Date().toISOString().replace(/[:.]/g, '-').split('T').join('_');
-      const preRestoreBackup = await join(dataDir, 'backups', `pre-restore-${timestamp}.db`);
-
-      try {
-        if (await exists(dbPath)) {
-          await copyFile(dbPath, preRestoreBackup);
-          console.log('[Restore] Created pre-restore backup:', preRestoreBackup);
-        }
-      } catch (err) {
-        console.warn('[Restore] Failed to create pre-restore backup:', err);
-      }
-
-      // Close database connection
-      console.log('[Restore] Closing database connection...');
-      await closeDatabase();
+      // Checks the backup, snapshots the current database and swaps it in
+      console.log('[Restore] Restoring backup...');
+      const restored = await restoreBackup(backupPath, restorePassword(ctx.body));
+      console.log('[Restore] Pre-restore snapshot:', restored.safety_snapshot);
       await resetDatabaseConnection();
 
-      // Remove WAL files
-      try {
-        const walPath = dbPath + '-wal';
-        const shmPath = dbPath + '-shm';
-        if (await exists(walPath)) await remove(walPath);
-        if (await exists(shmPath)) await remove(shmPath);
-      } catch (walErr) {
-        console.warn('[Restore] WAL cleanup error:', walErr);
-      }
-
-      // Remove current database
-      if (await exists(dbPath)) {
-        await remove(dbPath);
-      }
-
-      // Copy backup to database location
-      console.log('[Restore] Copying backup file...');
-      await copyFile(backupPath, dbPath);
-
-      // Verify copy worked
-      if (!(await exists(dbPath))) {
-        throw new Error('Database file was not created after copy');
-      }
-
       console.log('[Restore] SUCCESS - Restore complete');
 
       // Force page reload
@@ -454,24 +435,18 @@ export async function handleExportBackup(ctx: HandlerContext): Promise<unknown>
   }
 
   // In Tauri mode, we need to use the save dialog to let user choose location
+  let savePath: string | null;
   try {
     const { save } = await import('@tauri-apps/plugin-dialog');
 
     const timestamp = new Date().toISOString().replace(/[:.]/g, '-').split('T').join('_');
-    const defaultName = `puffin-backup-${timestamp}.db`;
+    const defaultName = `puffin-backup-${timestamp}.${BACKUP_EXTENSION}`;
 
     // Open save dialog
-    const savePath = await save({
+    savePath = await save({
       defaultPath: defaultName,
-      filters: [{ name: 'SQLite Database', extensions: ['db'] }],
+      filters: [{ name: 'Puffin Backup', extensions: [BACKUP_EXTENSION] }],
     });
-
-    if (savePath) {
-      await vacuumBackup(savePath);
-      return { success: true, path: savePath };
-    }
-
-    return { success: false, cancelled: true };
   } catch {
     // Dialog plugin not available, provide alternative instructions
     throw new Error(
@@ -479,6 +454,14 @@ export async function handleExportBackup(ctx: HandlerContext): Promise<unknown>
       'Your database is located at: %APPDATA%/Puffin/puffin.db'
     );
   }
+
+  if (!savePath) {
+    return { success: false, cancelled: true };
+  }
+
+  // Encrypted with the backup password
+  await createBackup(savePath);
+  return { success: true, path: savePath };
 }
 
 /**
@@ -493,15 +476,15 @@ export async function handleImportBackup(ctx: HandlerContext): Promise<unknown>
 
   try {
     const { open } = await import('@tauri-apps/plugin-dialog');
-    const { copyFile, exists, remove } = await import('@tauri-apps/plugin-fs');
-    const { resetDatabaseConnection, closeDatabase } = await import('../tauri-db');
+    const { exists } = await import('@tauri-apps/plugin-fs');
+    const { resetDatabaseConnection } = await import('../tauri-db');
 
     console.log('[Import] Opening file picker...');
 
     // Open file picker to select backup
     const selectedPath = await open({
       title: 'Select Backup to Restore',
-      filters: [{ name: 'SQLite Database', extensions: ['db'] }],
+      filters: [{ name: 'Puffin Backup', extensions: RESTORABLE_EXTENSIONS }],
       multiple: false,
     });
 
@@ -519,71 +502,11 @@ export async function handleImportBackup(ctx: HandlerContext): Promise<unknown>
       throw new Error(`Source backup file does not exist: ${selectedPath}`);
     }
 
-    // Get current database path
-    const dbPath = await getDatabasePath();
-    console.log('[Import] Target database path:', dbPath);
-
-    // Create a backup of current database before replacing
-    const timestamp = new Date().toISOString().replace(/[:.]/g, '-').split('T').join('_');
-    const preRestoreBackup = dbPath.replace('puffin.db', `puffin-pre-restore-${timestamp}.db`);
-
-    try {
-      // Check if current db exists before backing up
-      const dbExists = await exists(dbPath);
-      console.log('[Import] Current DB exists:', dbExists);
-      if (dbExists) {
-        await copyFile(dbPath, preRestoreBackup);
-        console.log('[Import] Created pre-restore backup:', preRestoreBackup);
-      }
-    } catch (err) {
-      console.warn('[Import] Failed to create pre-restore backup:', err);
-      // Continue with restore anyway
-    }
-
-    // Close current database connection properly
-    console.log('[Import] Closing database connection...');
-    await closeDatabase();
+    // Checks the backup, snapshots the current database and swaps it in
+    console.log('[Import] Restoring backup...');
+    const restored = await restoreBackup(selectedPath, restorePassword(ctx.body));
+    console.log('[Import] Pre-restore snapshot:', restored.safety_snapshot);
     await resetDatabaseConnection();
-    console.log('[Import] Database connection closed and reset');
-
-    // Remove WAL files if they exist
-    try {
-      const walPath = dbPath + '-wal';
-      const shmPath = dbPath + '-shm';
-      if (await exists(walPath)) {
-        await remove(walPath);
-        console.log('[Import] Removed WAL file');
-      }
-      if (await exists(shmPath)) {
-        await remove(shmPath);
-        console.log('[Import] Removed SHM file');
-      }
-    } catch (walErr) {
-      console.warn('[Import] WAL cleanup error:', walErr);
-    }
-
-    // Remove current database file before copying
-    try {
-      if (await exists(dbPath)) {
-        await remove(dbPath);
-        console.log('[Import] Removed current database');
-      }
-    } catch (removeErr) {
-      console.error('[Import] Failed to remove current database:', removeErr);
-      throw new Error(`Failed to remove current database: ${removeErr}`);
-    }
-
-    // Copy backup to database location
-    console.log('[Import] Copying backup file...');
-    await copyFile(selectedPath, dbPath);
-    console.log('[Import] Copy complete');
-
-    // Verify the copy worked
-    const newDbExists = await exists(dbPath);
-    console.log('[Import] New database exists:', newDbExists);
-    if (!newDbExists) {
-      throw new Error('Database file was not created after copy');
-    }
 
     console.log('[Import] SUCCESS - Restore complete');
 
@@ -7,6 +7,13 @@
  */
 
 import { OAuthRefreshFailedError } from '@/lib/sync/errors';
+import {
+  VAULT_KEYS,
+  getOAuthClient,
+  migrateLegacySecrets,
+  vaultDelete,
+  vaultSetJson,
+} from '../vault';
 
 interface HandlerContext {
   method: string;
@@ -15,22 +22,20 @@ interface HandlerContext {
   path: string;
 }
 
-// localStorage keys for sync state
+// localStorage keys for sync state. Secrets (OAuth tokens, client credentials)
+// live in the native vault instead - see lib/services/vault.ts.
 const SYNC_CONFIG_KEY = 'puffin_sync_config';
-const SYNC_CREDENTIALS_KEY = 'puffin_sync_credentials';
 const OAUTH_AUTHENTICATED_KEY = 'puffin_oauth_authenticated';
 const OAUTH_EXTENDED_SCOPE_KEY = 'puffin_oauth_extended_scope';
 
-// `oauthConfigured` is derived from SYNC_CREDENTIALS_KEY (single source of truth)
+// `oauthConfigured` is derived from the stored credentials (single source of truth)
 // so the reconnect flow can't get stuck on a wizard prompt when valid credentials
 // are already on disk. A previous version maintained a separate `puffin_oauth_configured`
 // flag — leaving it stranded in localStorage on existing installs is harmless.
-function hasStoredCredentials(): boolean {
+async function hasStoredCredentials(): Promise<boolean> {
   try {
-    const stored = localStorage.getItem(SYNC_CREDENTIALS_KEY);
-    if (!stored) return false;
-    const creds = JSON.parse(stored) as Partial<SyncCredentials>;
-    return !!(creds.clientId && creds.clientSecret);
+    const client = await getOAuthClient(VAULT_KEYS.syncCredentials);
+    return !!(client?.clientId && client.hasSecret);
   } catch {
     return false;
   }
@@ -49,6 +54,8 @@ interface SyncConfig {
   lastSyncedAt: string | null;
   userEmail: string | null;
   syncedDbHash: string | null;
+  /** How syncedDbHash was computed; absent for hashes of the raw file */
+  syncedDbHashMode?: DbHashMode;
   backupFileId: string | null;
   isFileBasedSync: boolean;
 }
@@ -123,7 +130,7 @@ async function getSyncConfigStatus(): Promise<SyncConfig & { isAuthenticated: bo
   const config = getSyncConfig();
 
   // In Tauri mode, OAuth state is also stored locally
-  const oauthConfigured = hasStoredCredentials();
+  const oauthConfigured = await hasStoredCredentials();
   const isAuthenticated = !!localStorage.getItem(OAUTH_AUTHENTICATED_KEY);
   const hasExtendedScope = !!localStorage.getItem(OAUTH_EXTENDED_SCOPE_KEY);
 
@@ -406,6 +413,20 @@ export async function handleSyncCheck(ctx: HandlerContext): Promise<unknown> {
   }
 }
 
+type DbHashMode = 'file' | 'content';
+
+/**
+ * SHA-256 (hex) of the database, computed by the native side.
+ * 'content' hashes the user-data rows only, so it ignores local_user (each
+ * device's PIN) and page layout. 'file' hashes the raw file, which is what
+ * earlier versions stored in syncedDbHash.
+ */
+async function hashDatabase(mode: DbHashMode = 'content'): Promise<string> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  const { hash } = await invoke<{ mode: DbHashMode; hash: string }>('hash_database', { mode });
+  return hash;
+}
+
 /**
  * Detect local changes by computing hash of current database
  * and comparing with the stored hash from last sync.
@@ -417,22 +438,8 @@ async function detectLocalChanges(config: SyncConfig): Promise<boolean> {
   }
 
   try {
-    const { readFile } = await import('@tauri-apps/plugin-fs');
-    const { appDataDir, join } = await import('@tauri-apps/api/path');
-
-    const dataDir = await appDataDir();
-    const dbPath = await join(dataDir, 'puffin.db');
-
-    // Checkpoint WAL first
-    const { getDatabase } = await import('../tauri-db');
-    const db = await getDatabase();
-    await db.execute('PRAGMA wal_checkpoint(TRUNCATE)');
-
-    // Read and hash the database file
-    const fileData = await readFile(dbPath);
-    const hashBuffer = await crypto.subtle.digest('SHA-256', fileData);
-    const hashArray = Array.from(new Uint8Array(hashBuffer));
-    const currentHash = hashArray.map(b => b.toString(16).padStart(2, '0')).join('');
+    // Compare like with like: configs synced before content hashing hold a file hash
+    const currentHash = await hashDatabase(config.syncedDbHashMode ?? 'file');
 
     return currentHash !== config.syncedDbHash;
   } catch (error) {
@@ -470,6 +477,15 @@ export async function handleSyncPush(ctx: HandlerContext): Promise<unknown> {
     throw new Error('Sync not configured. Please select a folder or file first.');
   }
 
+  // Uploads are encrypted with the backup password; devices that synced
+  // before encryption have none yet, so ask for one instead of failing
+  const { hasBackupPassword } = await import('../backup');
+  if (!(await hasBackupPassword())) {
+    throw Object.assign(new Error('Set a backup password to encrypt synced backups.'), {
+      errorCode: 'BACKUP_PASSWORD_REQUIRED',
+    });
+  }
+
   try {
     // Import Tauri filesystem and path APIs
     const { readFile } = await import('@tauri-apps/plugin-fs');
@@ -481,25 +497,25 @@ export async function handleSyncPush(ctx: HandlerContext): Promise<unknown> {
     const backupPath = await join(dataDir, 'backups', `pre-sync-${timestamp}.db`);
 
     // Ensure backups directory exists
-    const { mkdir, exists } = await import('@tauri-apps/plugin-fs');
+    const { mkdir, exists, remove } = await import('@tauri-apps/plugin-fs');
     const backupsDir = await join(dataDir, 'backups');
     if (!await exists(backupsDir)) {
       await mkdir(backupsDir, { recursive: true });
     }
 
-    // Create backup using VACUUM INTO
-    const { getDatabase } = await import('../tauri-db');
-    const db = await getDatabase();
-    await db.execute(`VACUUM INTO '${backupPath.replace(/'/g, "''")}'`);
+    // Snapshot the current database before pushing
+    const { backup } = await import('../tauri-db');
+    await backup(backupPath);
 
-    // Checkpoint WAL to ensure all data is in main file
-    await db.execute('PRAGMA wal_checkpoint(TRUNCATE)');
-
-    // Read the database file
-    const dbPath = await join(dataDir, 'puffin.db');
-    const fileData = await readFile(dbPath);
+    // Encrypt a snapshot with the backup password; only the container is uploaded
+    const { createBackup, BACKUP_EXTENSION } = await import('../backup');
+    const uploadPath = await join(backupsDir, `sync-upload.${BACKUP_EXTENSION}`);
+    const upload = await createBackup(uploadPath);
+    const fileData = await readFile(uploadPath);
+    await remove(uploadPath).catch(() => {});
+    const dbHash = upload.content_hash;
 
-    // Upload to Google Drive
+    // Upload to Google Drive (name kept so existing sync folders still match)
     const fileName = 'puffin-backup.db';
 
     if (config.isFileBasedSync && config.backupFileId) {
@@ -597,11 +613,6 @@ export async function handleSyncPush(ctx: HandlerContext): Promise<unknown> {
       }
     }
 
-    // Compute the database hash for change detection
-    const hashBuffer = await crypto.subtle.digest('SHA-256', fileData);
-    const hashArray = Array.from(new Uint8Array(hashBuffer));
-    const dbHash = hashArray.map(b => b.toString(16).padStart(2, '0')).join('');
-
     // Get the file ID to update metadata
     let fileId: string | null = null;
     if (config.isFileBasedSync && config.backupFileId) {
@@ -651,6 +662,7 @@ export async function handleSyncPush(ctx: HandlerContext): Promise<unknown> {
       ...config,
       lastSyncedAt: new Date().toISOString(),
       syncedDbHash: dbHash,
+      syncedDbHashMode: 'content',
     });
 
     return { success: true };
@@ -670,9 +682,22 @@ export async function handleSyncDisconnect(ctx: HandlerContext): Promise<unknown
     throw new Error(`Method ${method} not allowed`);
   }
 
-  // Clear all sync-related localStorage
+  // Revoke the grant so the refresh token can't be reused (read natively
+  // from the vault). Best-effort: a network failure must not block
+  // disconnecting locally.
+  try {
+    const { invoke } = await import('@tauri-apps/api/core');
+    await invoke('revoke_oauth_token', {
+      tokensEntry: VAULT_KEYS.oauthTokens,
+    });
+  } catch (error) {
+    console.warn('Failed to revoke OAuth token:', error);
+  }
+
+  // Clear stored secrets and all sync-related localStorage
+  await vaultDelete(VAULT_KEYS.oauthTokens);
+  await vaultDelete(VAULT_KEYS.syncCredentials);
   localStorage.removeItem(SYNC_CONFIG_KEY);
-  localStorage.removeItem(SYNC_CREDENTIALS_KEY);
   localStorage.removeItem(OAUTH_AUTHENTICATED_KEY);
   localStorage.removeItem(OAUTH_EXTENDED_SCOPE_KEY);
 
@@ -697,16 +722,15 @@ export async function handleSyncCredentials(ctx: HandlerContext): Promise<unknow
   }
 }
 
-function getCredentials(): { clientId: string; apiKey: string; configured: boolean; hasApiKey: boolean } {
+async function getCredentials(): Promise<{ clientId: string; apiKey: string; configured: boolean; hasApiKey: boolean }> {
   try {
-    const stored = localStorage.getItem(SYNC_CREDENTIALS_KEY);
-    if (stored) {
-      const creds = JSON.parse(stored) as SyncCredentials;
+    const client = await getOAuthClient(VAULT_KEYS.syncCredentials);
+    if (client) {
       return {
-        clientId: creds.clientId || '',
-        apiKey: creds.apiKey || '',
-        configured: !!(creds.clientId && creds.clientSecret),
-        hasApiKey: !!creds.apiKey,
+        clientId: client.clientId || '',
+        apiKey: client.apiKey || '',
+        configured: !!(client.clientId && client.hasSecret),
+        hasApiKey: !!client.apiKey,
       };
     }
   } catch {
@@ -721,7 +745,7 @@ function getCredentials(): { clientId: string; apiKey: string; configured: boole
   };
 }
 
-function saveCredentials(data: Partial<SyncCredentials>): { success: boolean } {
+async function saveCredentials(data: Partial<SyncCredentials>): Promise<{ success: boolean }> {
   if (!data.clientId || !data.clientSecret) {
     throw new Error('Client ID and Client Secret are required');
   }
@@ -736,13 +760,13 @@ function saveCredentials(data: Partial<SyncCredentials>): { success: boolean } {
     apiKey: (data.apiKey || '').trim(),
   };
 
-  localStorage.setItem(SYNC_CREDENTIALS_KEY, JSON.stringify(creds));
+  await vaultSetJson(VAULT_KEYS.syncCredentials, creds);
 
   return { success: true };
 }
 
-function clearCredentials(): { success: boolean } {
-  localStorage.removeItem(SYNC_CREDENTIALS_KEY);
+async function clearCredentials(): Promise<{ success: boolean }> {
+  await vaultDelete(VAULT_KEYS.syncCredentials);
   localStorage.removeItem(SYNC_CONFIG_KEY);
   localStorage.removeItem(OAUTH_AUTHENTICATED_KEY);
   localStorage.removeItem(OAUTH_EXTENDED_SCOPE_KEY);
@@ -834,10 +858,10 @@ export async function handleSyncPull(ctx: HandlerContext): Promise<unknown> {
       await mkdir(backupsDir, { recursive: true });
     }
 
-    // Create backup of current database
-    const { getDatabase, resetDatabaseConnection } = await import('../tauri-db');
+    // Snapshot the current database before replacing it
+    const { backup, getDatabase, resetDatabaseConnection } = await import('../tauri-db');
+    await backup(backupPath);
     const db = await getDatabase();
-    await db.execute(`VACUUM INTO '${backupPath.replace(/'/g, "''")}'`);
 
     // CRITICAL: Save local_user data before replacing database
     // Each device should keep its own PIN independently of synced data
@@ -890,15 +914,23 @@ export async function handleSyncPull(ctx: HandlerContext): Promise<unknown> {
 
     const fileData = new Uint8Array(await downloadResponse.arrayBuffer());
 
+    // Stage the download; it's an encrypted container (or a plain database
+    // uploaded by an older version)
+    const { extractBackup, BACKUP_EXTENSION } = await import('../backup');
+    const { remove } = await import('@tauri-apps/plugin-fs');
+    const downloadPath = await join(backupsDir, `sync-download.${BACKUP_EXTENSION}`);
+    await writeFile(downloadPath, fileData);
+
     // Close the current database connection
     await resetDatabaseConnection();
 
-    // Write the downloaded file
+    // Decrypt and verify into place; a wrong password leaves puffin.db untouched
     const dbPath = await join(dataDir, 'puffin.db');
-    await writeFile(dbPath, fileData);
+    const pulled = await extractBackup(downloadPath, dbPath).finally(() =>
+      remove(downloadPath).catch(() => {})
+    );
 
     // Clean up any stale WAL files
-    const { remove } = await import('@tauri-apps/plugin-fs');
     const walPath = dbPath + '-wal';
     const shmPath = dbPath + '-shm';
 
@@ -917,6 +949,10 @@ export async function handleSyncPull(ctx: HandlerContext): Promise<unknown> {
       }
     }
 
+    // The content hash leaves out local_user, so the downloaded data's hash
+    // holds after this device's PIN is restored
+    const dbHash = pulled.content_hash ?? await hashDatabase();
+
     // CRITICAL: Restore local_user data to preserve this device's PIN
     // The downloaded database may have a different PIN from another device
     if (localUserData) {
@@ -936,15 +972,6 @@ export async function handleSyncPull(ctx: HandlerContext): Promise<unknown> {
       await newDb.execute('PRAGMA wal_checkpoint(TRUNCATE)');
     }
 
-    // Compute hash from the FINAL database state (after local_user restoration)
-    // This is critical - if we hash the downloaded file, it won't match the current
-    // state (which has local_user restored), causing false "local changes" detection
-    const { readFile } = await import('@tauri-apps/plugin-fs');
-    const finalFileData = await readFile(dbPath);
-    const hashBuffer = await crypto.subtle.digest('SHA-256', finalFileData);
-    const hashArray = Array.from(new Uint8Array(hashBuffer));
-    const dbHash = hashArray.map(b => b.toString(16).padStart(2, '0')).join('');
-
     // Clear session marker since we've synced (pulled fresh data)
     const { clearLastModifySession } = await import('../tauri-db');
     clearLastModifySession();
@@ -954,6 +981,7 @@ export async function handleSyncPull(ctx: HandlerContext): Promise<unknown> {
       ...config,
       lastSyncedAt: new Date().toISOString(),
       syncedDbHash: dbHash,
+      syncedDbHashMode: 'content',
     });
 
     return { success: true };
@@ -963,37 +991,58 @@ export async function handleSyncPull(ctx: HandlerContext): Promise<unknown> {
   }
 }
 
+
+/**
+ * Access token returned by the native OAuth commands. The refresh token and
+ * client secret stay in the vault.
+ */
+interface NativeAccessToken {
+  access_token: string;
+  /** Unix milliseconds */
+  expiry_date: number | null;
+  token_type: string;
+  scope: string | null;
+  /** A refresh token is stored */
+  can_refresh: boolean;
+}
+
 /**
- * Refresh an expired access token using the refresh token.
- * Google does NOT return a new refresh_token - keep using the original one.
+ * Error shape returned by the native OAuth commands (see src-tauri/src/oauth/token.rs).
+ */
+interface NativeTokenError {
+  kind: 'invalid_grant' | 'network' | 'provider' | 'invalid_response' | 'vault';
+  message: string;
+}
+
+function isNativeTokenError(error: unknown): error is NativeTokenError {
+  return typeof error === 'object' && error !== null && 'kind' in error && 'message' in error;
+}
+
+/**
+ * Refresh an expired access token. The refresh token and client credentials
+ * are read from the vault natively, and the new access token is stored there.
+ * Google does NOT return a new refresh_token - the original one is kept.
  * Throws OAuthRefreshFailedError specifically when invalid_grant is returned.
  */
-async function refreshAccessToken(
-  refreshToken: string,
-  credentials: SyncCredentials
-): Promise<{ access_token: string; expires_in: number }> {
-  const response = await fetch('https://oauth2.googleapis.com/token', {
-    method: 'POST',
-    headers: { 'Content-Type': 'application/x-www-form-urlencoded' },
-    body: new URLSearchParams({
-      client_id: credentials.clientId,
-      client_secret: credentials.clientSecret,
-      refresh_token: refreshToken,
-      grant_type: 'refresh_token',
-    }),
-  });
-
-  if (!response.ok) {
-    const error = await response.json().catch(() => ({}));
-    if (error.error === 'invalid_grant') {
-      throw new OAuthRefreshFailedError(
-        error.error_description || 'Refresh token rejected by Google (invalid_grant)'
-      );
+async function refreshAccessToken(): Promise<NativeAccessToken> {
+  const { invoke } = await import('@tauri-apps/api/core');
+
+  try {
+    return await invoke<NativeAccessToken>('refresh_oauth_token', {
+      credentialsEntry: VAULT_KEYS.syncCredentials,
+      tokensEntry: VAULT_KEYS.oauthTokens,
+    });
+  } catch (error) {
+    if (isNativeTokenError(error)) {
+      if (error.kind === 'invalid_grant') {
+        throw new OAuthRefreshFailedError(
+          error.message || 'Refresh token rejected by Google (invalid_grant)'
+        );
+      }
+      throw new Error(error.message || 'Failed to refresh token');
     }
-    throw new Error(error.error_description || 'Failed to refresh token');
+    throw error;
   }
-
-  return response.json();
 }
 
 /**
@@ -1003,22 +1052,20 @@ async function refreshAccessToken(
  * `errorCode: 'REFRESH_FAILED'` so the UI can prompt for reconnect.
  */
 async function getValidAccessToken(): Promise<{ token: string } | { error: string; errorCode?: string }> {
-  const stored = localStorage.getItem('puffin_oauth_tokens');
-  if (!stored) {
-    return { error: 'Not authenticated with Google. Sign in to check sync status.' };
-  }
-
-  let tokens: {
-    access_token: string;
-    refresh_token?: string;
-    expiry_date?: number;
-  };
-
+  const { invoke } = await import('@tauri-apps/api/core');
+  let tokens: NativeAccessToken | null;
   try {
-    tokens = JSON.parse(stored);
+    // Tokens from older versions may still sit in localStorage
+    await migrateLegacySecrets();
+    tokens = await invoke<NativeAccessToken | null>('get_oauth_access_token', {
+      tokensEntry: VAULT_KEYS.oauthTokens,
+    });
   } catch {
     return { error: 'Invalid token data. Please sign in again.' };
   }
+  if (!tokens) {
+    return { error: 'Not authenticated with Google. Sign in to check sync status.' };
+  }
 
   if (!tokens.access_token) {
     return { error: 'No access token. Please sign in again.' };
@@ -1027,33 +1074,16 @@ async function getValidAccessToken(): Promise<{ token: string } | { error: strin
   // Check if token is expired (with 60 second buffer)
   if (tokens.expiry_date && tokens.expiry_date < Date.now() + 60000) {
     // Token expired or expiring soon - attempt refresh
-    if (!tokens.refresh_token) {
+    if (!tokens.can_refresh) {
       return { error: 'Access token expired. Please sign in again.' };
     }
 
-    const credentialsStored = localStorage.getItem(SYNC_CREDENTIALS_KEY);
-    if (!credentialsStored) {
+    if (!(await hasStoredCredentials())) {
       return { error: 'OAuth credentials not found. Please sign in again.' };
     }
 
-    let credentials: SyncCredentials;
-    try {
-      credentials = JSON.parse(credentialsStored);
-    } catch {
-      return { error: 'Invalid credentials data. Please sign in again.' };
-    }
-
     try {
-      const refreshed = await refreshAccessToken(tokens.refresh_token, credentials);
-
-      // Update stored tokens (keep original refresh_token)
-      const updatedTokens = {
-        ...tokens,
-        access_token: refreshed.access_token,
-        expiry_date: Date.now() + (refreshed.expires_in * 1000),
-      };
-      localStorage.setItem('puffin_oauth_tokens', JSON.stringify(updatedTokens));
-
+      const refreshed = await refreshAccessToken();
       return { token: refreshed.access_token };
     } catch (e) {
       if (e instanceof OAuthRefreshFailedError) {
@@ -1111,46 +1141,45 @@ export async function handleOAuthToken(ctx: HandlerContext): Promise<unknown> {
     throw new Error(`Method ${method} not allowed`);
   }
 
-  const { code, state, redirectUri } = body as { code: string; state?: string; redirectUri?: string };
+  const { code, state, redirectUri, codeVerifier } = body as {
+    code: string;
+    state?: string;
+    redirectUri?: string;
+    codeVerifier?: string;
+  };
 
   if (!code) {
     throw new Error('Authorization code is required');
   }
 
-  // Get stored credentials
-  const stored = localStorage.getItem(SYNC_CREDENTIALS_KEY);
-  if (!stored) {
+  if (!(await hasStoredCredentials())) {
     throw new Error('OAuth credentials not found');
   }
 
-  const credentials = JSON.parse(stored) as SyncCredentials;
-
   // Use the provided redirect URI or fall back to localhost
   // The redirect URI must match what was used in the authorization request
   const finalRedirectUri = redirectUri || 'http://127.0.0.1';
 
-  // Exchange code for tokens via Google's token endpoint
-  const tokenResponse = await fetch('https://oauth2.googleapis.com/token', {
-    method: 'POST',
-    headers: {
-      'Content-Type': 'application/x-www-form-urlencoded',
-    },
-    body: new URLSearchParams({
+  // Exchange code for tokens via Google's token endpoint (runs natively with
+  // the stored credentials, PKCE verifier generated by start_oauth_flow).
+  // The tokens are stored in the vault; only the access token comes back.
+  const { invoke } = await import('@tauri-apps/api/core');
+  let tokens: NativeAccessToken;
+  try {
+    tokens = await invoke<NativeAccessToken>('exchange_oauth_code', {
+      credentialsEntry: VAULT_KEYS.syncCredentials,
+      tokensEntry: VAULT_KEYS.oauthTokens,
       code,
-      client_id: credentials.clientId,
-      client_secret: credentials.clientSecret,
-      redirect_uri: finalRedirectUri,
-      grant_type: 'authorization_code',
-    }),
-  });
-
-  if (!tokenResponse.ok) {
-    const errorData = await tokenResponse.json().catch(() => ({}));
-    throw new Error(errorData.error_description || errorData.error || 'Failed to exchange authorization code');
+      redirectUri: finalRedirectUri,
+      codeVerifier: codeVerifier ?? null,
+    });
+  } catch (error) {
+    if (isNativeTokenError(error)) {
+      throw new Error(error.message || 'Failed to exchange authorization code');
+    }
+    throw error;
   }
 
-  const tokens = await tokenResponse.json();
-
   if (!tokens.access_token) {
     throw new Error('No access token received from Google');
   }
@@ -1166,16 +1195,6 @@ export async function handleOAuthToken(ctx: HandlerContext): Promise<unknown> {
     }
   }
 
-  // Store tokens in localStorage
-  const tokenData = {
-    access_token: tokens.access_token,
-    refresh_token: tokens.refresh_token,
-    expiry_date: Date.now() + (tokens.expires_in * 1000),
-    token_type: tokens.token_type || 'Bearer',
-    scope: tokens.scope || '',
-  };
-
-  localStorage.setItem('puffin_oauth_tokens', JSON.stringify(tokenData));
   localStorage.setItem(OAUTH_AUTHENTICATED_KEY, 'true');
 
   // Check if we have extended scope (full drive access, not just drive.file)
@@ -0,0 +1,371 @@
+/**
+ * Native Statement Import
+ *
+ * Wraps the import commands (src-tauri/src/import). Statement files are
+ * streamed, parsed and checked for duplicates natively, so large exports
+ * don't block the UI. A parsed file is staged; the preview's `batch_id` is
+ * committed (or discarded) afterwards and becomes the import's batch ID for
+ * undo. The commands only read files picked with `chooseStatement` (or opened
+ * with Puffin).
+ */
+
+import type { CSVParseResult, ColumnMapping, DateFormat, ParsedRow } from '@/types/import';
+
+export interface ImportCommandError {
+  kind: 'io' | 'database' | 'invalid' | 'empty' | 'too_many' | 'not_staged' | 'not_allowed';
+  message?: string | number;
+}
+
+/** A parsed row (`ParsedRow` in types/import.ts, minus selection state) */
+export interface NativePreviewRow {
+  row_index: number;
+  raw: string[];
+  date: string | null;
+  description: string;
+  amount: number | null;
+  notes: string | null;
+  /** The bank's ID for the transaction (OFX `FITID`) */
+  external_id: string | null;
+  errors: string[];
+  is_duplicate: boolean;
+  has_default_description: boolean;
+}
+
+export interface NativeImportPreview {
+  batch_id: string;
+  total_rows: number;
+  valid_count: number;
+  duplicate_count: number;
+  error_count: number;
+  /** The first rows of the file; counts cover all of it */
+  rows: NativePreviewRow[];
+}
+
+export interface CsvImportPreview extends NativeImportPreview {
+  encoding: string;
+  delimiter: string;
+  headers: string[];
+  /** Mapping used; detected from the headers when none was given */
+  mapping: ColumnMapping;
+  date_format: DateFormat;
+}
+
+export interface OfxImportPreview extends NativeImportPreview {
+  /** `ACCTID` of the statement's account */
+  account: string | null;
+  currency: string | null;
+}
+
+export interface QifImportPreview extends NativeImportPreview {
+  /** `Bank`, `CCard` and so on */
+  account_type: string;
+  date_format: DateFormat;
+}
+
+export interface CamtImportPreview extends NativeImportPreview {
+  account: {
+    /** IBAN, or the bank's own account number */
+    number: string | null;
+    name: string | null;
+    currency: string | null;
+  };
+  /** Source whose name includes the IBAN or matches the account name */
+  source_id: string | null;
+  /** Pending entries, left out until a statement books them */
+  pending_count: number;
+}
+
+export interface CsvImportOptions {
+  hasHeaders?: boolean;
+  dateFormat?: DateFormat;
+}
+
+/** How a pasted column scored (`ColumnScore` in src-tauri/src/import/paste.rs) */
+export interface PastedColumn {
+  index: number;
+  type: 'date' | 'amount' | 'text' | 'unknown';
+  amountRole?: 'debit' | 'credit' | 'balance' | 'single';
+  /** Share of the column's values that fit its type, 0-1 */
+  confidence: number;
+}
+
+export interface PastedStatement extends CSVParseResult {
+  columns: PastedColumn[];
+  /** Suggested mapping; null without a date and an amount column */
+  mapping: ColumnMapping | null;
+}
+
+export interface PdfStatement extends PastedStatement {
+  /** Text extracted from the PDF's transaction tables, as it was parsed */
+  text: string;
+  pageCount: number;
+}
+
+export interface PasteOptions {
+  minSpaces?: number;
+  mergeMultiLine?: boolean;
+  /** Detected when unset */
+  hasHeaders?: boolean;
+}
+
+/** Watched statements folder (`import-watch.json`) */
+export interface ImportWatchSettings {
+  enabled: boolean;
+  /** Inside Downloads or Documents */
+  folder: string | null;
+  /**
+   * Files named like `pattern` (`*` and `?` wildcards, any case) belong to
+   * `sourceId`; the first match wins
+   */
+  sources: { pattern: string; sourceId: string }[];
+}
+
+export type ImportWatchEvent =
+  | ({
+      status: 'ready';
+      path: string;
+      file_name: string;
+      /** From the filename patterns, the statement's account or the filename */
+      source_id: string | null;
+    } & NativeImportPreview)
+  | { status: 'failed'; path: string; file_name: string; error: ImportCommandError };
+
+/** A statement staged from the watched folder, waiting to be reviewed */
+export type WatchedStatement = Extract<ImportWatchEvent, { status: 'ready' }>;
+
+/** Window event fired when a watched statement is waiting to be reviewed */
+export const WATCHED_STATEMENT_EVENT = 'puffin:watched-statement';
+
+const pendingWatched: WatchedStatement[] = [];
+/** Batches queued so far, so one announced and listed is queued once */
+const queuedWatched = new Set<string>();
+
+export interface NativeImportSummary {
+  /** Set when anything was imported */
+  batch_id: string | null;
+  imported: number;
+  duplicates: number;
+  auto_categorized: number;
+}
+
+export function describeError(error: ImportCommandError): string {
+  switch (error.kind) {
+    case 'empty':
+      return 'No transactions to import';
+    case 'too_many':
+      return 'Too many transactions in one import (50,000 at most)';
+    case 'not_staged':
+      return 'This import has expired; please load the file again';
+    case 'invalid':
+      return `Couldn't read statement: ${error.message}`;
+    case 'not_allowed':
+      return `Not allowed: ${error.message}`;
+    default:
+      return String(error.message ?? error.kind);
+  }
+}
+
+async function invokeImport<T>(command: string, args: Record<string, unknown>): Promise<T> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  try {
+    return await invoke<T>(command, args);
+  } catch (err) {
+    if (err && typeof err === 'object' && 'kind' in err) {
+      throw new Error(describeError(err as ImportCommandError));
+    }
+    throw err;
+  }
+}
+
+/** Preview rows as the preview table's rows, with the new valid rows selected */
+export function toParsedRows(rows: NativePreviewRow[]): ParsedRow[] {
+  return rows.map((row) => ({
+    rowIndex: row.row_index,
+    raw: row.raw,
+    parsed: {
+      date: row.date,
+      description: row.description,
+      amount: row.amount,
+      notes: row.notes,
+    },
+    errors: row.errors,
+    isDuplicate: row.is_duplicate,
+    isSelected: row.errors.length === 0 && !row.is_duplicate,
+    hasDefaultDescription: row.has_default_description,
+  }));
+}
+
+/**
+ * Ask for a statement file with the native file picker. Returns its path, or
+ * null when the user cancels.
+ */
+export async function chooseStatement(
+  title: string,
+  filterName: string,
+  extensions: string[]
+): Promise<string | null> {
+  return invokeImport<string | null>('choose_statement', { title, filterName, extensions });
+}
+
+/**
+ * Read the headers and first rows of the CSV statement at `path` for the
+ * column mapping step. Nothing is staged.
+ */
+export async function sampleCsv(
+  path: string,
+  options?: { hasHeaders?: boolean }
+): Promise<CSVParseResult> {
+  return invokeImport<CSVParseResult>('sample_csv', { path, options });
+}
+
+/**
+ * Parse and stage the CSV statement at `path`. Without `mapping` the columns
+ * are detected from the headers.
+ */
+export async function importCsv(
+  path: string,
+  mapping?: ColumnMapping,
+  options?: CsvImportOptions
+): Promise<CsvImportPreview> {
+  return invokeImport<CsvImportPreview>('import_csv', { path, mapping, options });
+}
+
+/**
+ * Parse and stage the OFX or QFX statement at `path`. Rows carry the bank's
+ * transaction ID, so a statement imported twice is caught by ID.
+ */
+export async function importOfx(path: string): Promise<OfxImportPreview> {
+  return invokeImport<OfxImportPreview>('import_ofx', { path });
+}
+
+/** Parse and stage the QIF statement at `path` */
+export async function importQif(
+  path: string,
+  options?: { dateFormat?: DateFormat }
+): Promise<QifImportPreview> {
+  return invokeImport<QifImportPreview>('import_qif', { path, options });
+}
+
+/**
+ * Parse and stage the camt.053 or camt.052 (ISO 20022) statement at `path`.
+ * Pass the matched `source_id` on to `commitImport` unless the user picks
+ * another source.
+ */
+export async function importCamt(path: string): Promise<CamtImportPreview> {
+  return invokeImport<CamtImportPreview>('import_camt', { path });
+}
+
+/**
+ * Split text pasted from a statement into columns, with each column's score
+ * and a suggested mapping. Nothing is staged; map and preview the rows as
+ * with `parsePastedText`.
+ */
+export async function parsePastedStatement(
+  text: string,
+  options?: PasteOptions
+): Promise<PastedStatement> {
+  return invokeImport<PastedStatement>('parse_pasted_statement', { text, options });
+}
+
+/**
+ * Extract the text of the statement PDF at `path` (locally, laid out in
+ * columns) and parse it as pasted text. Scanned statements have no text and
+ * fail with an `invalid` error.
+ */
+export async function parsePdfStatement(
+  path: string,
+  options?: PasteOptions
+): Promise<PdfStatement> {
+  return invokeImport<PdfStatement>('parse_pdf_statement', { path, options });
+}
+
+/**
+ * Import a staged batch. Rows in `exclude` (by row index) are left out;
+ * duplicates are skipped unless `skipDuplicates` is false, except the rows
+ * in `force` (duplicates the user ticked).
+ */
+export async function commitImport(
+  batchId: string,
+  options: {
+    sourceId?: string | null;
+    skipDuplicates?: boolean;
+    exclude?: number[];
+    force?: number[];
+  } = {}
+): Promise<NativeImportSummary> {
+  return invokeImport<NativeImportSummary>('commit_import', {
+    batchId,
+    sourceId: options.sourceId ?? null,
+    skipDuplicates: options.skipDuplicates,
+    exclude: options.exclude,
+    force: options.force,
+  });
+}
+
+/** Drop a staged batch without importing it */
+export async function discardImport(batchId: string): Promise<boolean> {
+  return invokeImport<boolean>('discard_import', { batchId });
+}
+
+export async function getImportWatch(): Promise<ImportWatchSettings> {
+  return invokeImport<ImportWatchSettings>('get_import_watch', {});
+}
+
+/**
+ * Save the watch settings. Files already in a newly chosen folder are left
+ * alone; the watcher picks up the change at its next check.
+ */
+export async function setImportWatch(settings: ImportWatchSettings): Promise<void> {
+  await invokeImport<void>('set_import_watch', { settings });
+}
+
+/**
+ * Subscribe to statements found in the watched folder. A `ready` event's
+ * batch is staged for `commitImport` until it is committed or discarded.
+ * Returns an unsubscribe function.
+ */
+export async function onImportWatch(handler: (event: ImportWatchEvent) => void): Promise<() => void> {
+  const { listen } = await import('@tauri-apps/api/event');
+  const unlistenReady = await listen<Omit<Extract<ImportWatchEvent, { status: 'ready' }>, 'status'>>(
+    'import-watch://ready',
+    ({ payload }) => handler({ status: 'ready', ...payload })
+  );
+  const unlistenFailed = await listen<Omit<Extract<ImportWatchEvent, { status: 'failed' }>, 'status'>>(
+    'import-watch://failed',
+    ({ payload }) => handler({ status: 'failed', ...payload })
+  );
+
+  return () => {
+    unlistenReady();
+    unlistenFailed();
+  };
+}
+
+/**
+ * Statements staged from the watched folder and not yet committed or
+ * discarded, oldest first. Covers those announced before `onImportWatch`
+ * was listening.
+ */
+export async function listWatchedImports(): Promise<WatchedStatement[]> {
+  const batches = await invokeImport<Omit<WatchedStatement, 'status'>[]>(
+    'list_watched_imports',
+    {}
+  );
+  return batches.map((batch) => ({ status: 'ready', ...batch }));
+}
+
+/**
+ * Queue a watched statement for the import preview and announce it. The app
+ * shell queues them; the Transactions page shows them one at a time.
+ */
+export function queueWatchedStatement(statement: WatchedStatement): void {
+  if (queuedWatched.has(statement.batch_id)) return;
+  queuedWatched.add(statement.batch_id);
+  pendingWatched.push(statement);
+  window.dispatchEvent(new Event(WATCHED_STATEMENT_EVENT));
+}
+
+/** Take the next watched statement waiting to be reviewed, if any */
+export function takeWatchedStatement(): WatchedStatement | null {
+  return pendingWatched.shift() ?? null;
+}
@@ -0,0 +1,55 @@
+/**
+ * Opened Files
+ *
+ * Files handed to Puffin through "Open with Puffin" or a second app launch
+ * (src-tauri/src/launch.rs). Statements go to the import wizard; the app
+ * shell stashes them here until the Transactions page picks them up.
+ */
+
+export interface OpenedFile {
+  path: string;
+  name: string;
+  kind: 'statement' | 'backup';
+}
+
+/** Window event fired when a statement is waiting to be imported */
+export const OPEN_STATEMENT_EVENT = 'puffin:open-statement';
+
+let pendingStatement: File | null = null;
+
+/**
+ * Subscribe to opened files. Files the app was launched with are delivered
+ * first. Returns an unsubscribe function.
+ */
+export async function onOpenFile(handler: (file: OpenedFile) => void): Promise<() => void> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  const { getCurrentWebviewWindow } = await import('@tauri-apps/api/webviewWindow');
+
+  // Emitted to the main window only, so listen on it rather than globally
+  const unlisten = await getCurrentWebviewWindow().listen<OpenedFile>(
+    'launch://open-file',
+    ({ payload }) => handler(payload)
+  );
+  const launched = await invoke<OpenedFile[]>('take_launch_files');
+  launched.forEach(handler);
+
+  return unlisten;
+}
+
+/**
+ * Read an opened statement into a File for the import wizard and announce it.
+ */
+export async function openStatement(file: OpenedFile): Promise<void> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  const text = await invoke<string>('read_opened_file', { path: file.path });
+
+  pendingStatement = new File([text], file.name, { type: 'text/csv' });
+  window.dispatchEvent(new Event(OPEN_STATEMENT_EVENT));
+}
+
+/** Take the statement waiting to be imported, if any */
+export function takePendingStatement(): File | null {
+  const file = pendingStatement;
+  pendingStatement = null;
+  return file;
+}
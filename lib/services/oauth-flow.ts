@@ -0,0 +1,162 @@
+/**
+ * Native OAuth Flow
+ *
+ * Wraps the start_oauth_flow / cancel_oauth_flow commands
+ * (src-tauri/src/oauth/mod.rs). The command returns a flow ID as soon as the
+ * browser is open; the outcome arrives later as an `oauth://` event.
+ */
+
+/** Authorization response, as emitted with `oauth://callback` */
+export interface OAuthResult {
+  code?: string;
+  state?: string;
+  error?: string;
+  redirect_uri?: string;
+  code_verifier?: string;
+}
+
+/** `{ kind, message? }` error from the native flow */
+export interface OAuthFlowError {
+  kind:
+    | 'timeout'
+    | 'state_mismatch'
+    | 'cancelled'
+    | 'server'
+    | 'browser'
+    | 'invalid_url'
+    | 'invalid_options';
+  message?: string;
+}
+
+interface OAuthFlowStarted {
+  flow_id: string;
+  redirect_uri: string;
+  port: number | null;
+}
+
+interface OAuthFlowEvent {
+  flow_id: string;
+  result?: OAuthResult;
+  error?: OAuthFlowError;
+}
+
+/**
+ * Provider-specific settings (src-tauri/src/oauth/options.rs). Omitted
+ * fields keep the Google Drive defaults.
+ */
+export interface OAuthFlowOptions {
+  /** Additional authorization URL parameters (may override access_type/prompt) */
+  extraParams?: Record<string, string>;
+  /** Don't send Google's access_type=offline and prompt=consent */
+  omitGoogleParams?: boolean;
+  /** Seconds to wait for the redirect (default 300, max 3600) */
+  timeoutSecs?: number;
+  /** Loopback address family; 'both' listens on 127.0.0.1 and [::1] */
+  bind?: 'ipv4' | 'ipv6' | 'both';
+  /** 'ephemeral' lets the OS pick the port; 'fixed' only uses `fixedPorts` */
+  port?: 'ephemeral' | 'fixed';
+  /** Pre-registered ports in order of preference (defaults to the built-in list) */
+  fixedPorts?: number[];
+  /** 'deepLink' redirects to puffin://oauth/callback instead of a loopback port */
+  redirect?: 'loopback' | 'deepLink';
+}
+
+export interface OAuthFlowParams {
+  authUrlBase: string;
+  clientId: string;
+  scope: string;
+  state: string;
+  options?: OAuthFlowOptions;
+}
+
+export interface OAuthFlowHandle {
+  flowId: string;
+  redirectUri: string;
+  /** Callback server port, null for deep-link redirects */
+  port: number | null;
+  /** Resolves with the callback, rejects with an OAuthFlowError */
+  result: Promise<OAuthResult>;
+}
+
+const OUTCOME_EVENTS = ['oauth://callback', 'oauth://timeout', 'oauth://cancelled', 'oauth://error'] as const;
+
+/**
+ * Open the consent page and wait for the loopback callback in the background.
+ *
+ * Listeners are attached before the command runs: the outcome can in theory
+ * arrive before invoke() resolves, so events are buffered until the flow ID
+ * is known.
+ */
+export async function startOAuthFlow(params: OAuthFlowParams): Promise<OAuthFlowHandle> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  const { listen } = await import('@tauri-apps/api/event');
+
+  let flowId: string | null = null;
+  const buffered: Array<{ event: string; payload: OAuthFlowEvent }> = [];
+  let settle: ((event: string, payload: OAuthFlowEvent) => void) | null = null;
+
+  const unlisteners = await Promise.all(
+    OUTCOME_EVENTS.map((event) =>
+      listen<OAuthFlowEvent>(event, ({ payload }) => {
+        if (settle && payload.flow_id === flowId) {
+          settle(event, payload);
+        } else if (!settle) {
+          buffered.push({ event, payload });
+        }
+      })
+    )
+  );
+  const stopListening = () => unlisteners.forEach((unlisten) => unlisten());
+
+  let started: OAuthFlowStarted;
+  try {
+    started = await invoke<OAuthFlowStarted>('start_oauth_flow', { ...params });
+  } catch (err) {
+    stopListening();
+    throw err;
+  }
+  flowId = started.flow_id;
+
+  const result = new Promise<OAuthResult>((resolve, reject) => {
+    settle = (event, payload) => {
+      stopListening();
+      if (event === 'oauth://callback' && payload.result) {
+        resolve(payload.result);
+      } else {
+        reject(payload.error ?? { kind: 'server', message: `Unexpected ${event} event` });
+      }
+    };
+    const early = buffered.find(({ payload }) => payload.flow_id === flowId);
+    if (early) settle(early.event, early.payload);
+  });
+
+  return { flowId: started.flow_id, redirectUri: started.redirect_uri, port: started.port, result };
+}
+
+/**
+ * Abort a pending flow. Its `result` promise rejects with `{ kind: 'cancelled' }`.
+ * Returns false if the flow had already finished.
+ */
+export async function cancelOAuthFlow(flowId: string): Promise<boolean> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<boolean>('cancel_oauth_flow', { flowId });
+}
+
+/** Redirect URIs to register with a provider (get_oauth_redirect_uri) */
+export interface RedirectUriInfo {
+  loopback_scheme: string;
+  loopback_host: string;
+  /** True when any loopback port is accepted (nothing to register for Google) */
+  any_port: boolean;
+  fixed: Array<{ port: number; uri: string; available: boolean | null }>;
+  deep_link_uri: string;
+}
+
+/**
+ * Describe the redirect URIs Puffin can receive. With `checkPorts`, each
+ * fixed port also reports whether it is currently free.
+ */
+export async function getRedirectUris(checkPorts = false): Promise<RedirectUriInfo> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<RedirectUriInfo>('get_oauth_redirect_uri', { checkPorts });
+}
@@ -8,8 +8,6 @@
  * Use dynamic imports to avoid bundling issues in non-Tauri builds.
  */
 
-import { SCHEMA_SQL, SEED_SQL } from '@/lib/db/schema';
-
 // Session tracking for sync conflict detection
 // Generate unique session ID on module load (in-memory only, lost on app restart)
 const SESSION_ID = typeof crypto !== 'undefined' ? crypto.randomUUID() : Math.random().toString(36);
@@ -78,9 +76,6 @@ let db: TauriDatabase | null = null;
 let dbPromise: Promise<TauriDatabase> | null = null;
 let isInitialized = false;
 
-// Current schema version - increment when adding new migrations
-const CURRENT_SCHEMA_VERSION = 6;
-
 /**
  * Get the database path based on environment.
  * Uses Tauri's app data directory (%APPDATA%/Puffin/ on Windows).
@@ -112,257 +107,77 @@ export async function getDatabase(): Promise<TauriDatabase> {
     const { default: Database } = await import('@tauri-apps/plugin-sql');
     const dbPath = await getDatabasePath();
 
-    // Load SQLite database (creates file if it doesn't exist)
+    // Create the database if needed and run schema migrations natively
+    // (src-tauri/src/db/migrations.rs) before the plugin connects
+    await migrateDatabase();
+
     db = await Database.load(`sqlite:${dbPath}`) as TauriDatabase;
 
     // Configure database
     await db.execute('PRAGMA journal_mode = WAL');
     await db.execute('PRAGMA foreign_keys = ON');
 
-    // Initialize schema if needed
-    await initializeSchema(db);
-
+    isInitialized = true;
     return db;
   })();
 
   return dbPromise;
 }
 
-/**
- * Initialize the database schema and seed data.
- * Runs migrations for existing databases.
- */
-async function initializeSchema(database: TauriDatabase): Promise<void> {
-  if (isInitialized) return;
-
-  // Check if tables exist
-  const tables = await database.select<{ name: string }>(
-    "SELECT name FROM sqlite_master WHERE type='table' AND name='transaction'"
-  );
-
-  if (tables.length === 0) {
-    // Fresh database - run schema creation
-    await execMultiple(database, SCHEMA_SQL);
-    await execMultiple(database, SEED_SQL);
-  } else {
-    // Existing database - run migrations
-    await runMigrations(database);
-  }
-
-  isInitialized = true;
+/** Result of the native migrate_database command */
+export interface MigrationReport {
+  from: number;
+  to: number;
+  /** Versions of the migrations that ran */
+  applied: number[];
 }
 
 /**
- * Execute multiple SQL statements separated by semicolons.
+ * Create the database if needed and bring its schema up to date.
  */
-async function execMultiple(database: TauriDatabase, sql: string): Promise<void> {
-  const statements = sql.split(';').filter(s => s.trim());
-  for (const stmt of statements) {
-    if (stmt.trim()) {
-      await database.execute(stmt);
-    }
+async function migrateDatabase(): Promise<MigrationReport> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  const report = await invoke<MigrationReport>('migrate_database');
+  if (report.applied.length > 0) {
+    console.log(`[DB] Migrated schema from version ${report.from} to ${report.to}`);
   }
+  return report;
 }
 
-/**
- * Get the current schema version from the database.
- */
-async function getSchemaVersion(database: TauriDatabase): Promise<number> {
-  // Check if schema_version table exists
-  const tables = await database.select<{ name: string }>(
-    "SELECT name FROM sqlite_master WHERE type='table' AND name='schema_version'"
-  );
-
-  if (tables.length === 0) {
-    // Create schema_version table
-    await database.execute(`
-      CREATE TABLE schema_version (
-        id INTEGER PRIMARY KEY CHECK (id = 1),
-        version INTEGER NOT NULL DEFAULT 0,
-        updated_at TEXT NOT NULL DEFAULT (datetime('now'))
-      )
-    `);
-    await database.execute(`INSERT INTO schema_version (id, version) VALUES (1, 0)`);
-    return 0;
-  }
-
-  const result = await database.select<{ version: number }>('SELECT version FROM schema_version WHERE id = 1');
-  return result[0]?.version ?? 0;
+/** A row referencing another that is gone or soft-deleted */
+export interface DatabaseOrphan {
+  id: string;
+  /** Id of the referenced row */
+  target: string;
+  /** The referenced row doesn't exist at all (rather than being soft-deleted) */
+  missing: boolean;
 }
 
-/**
- * Update the schema version in the database.
- */
-async function setSchemaVersion(database: TauriDatabase, version: number): Promise<void> {
-  await database.execute(
-    `UPDATE schema_version SET version = ?, updated_at = datetime('now') WHERE id = 1`,
-    [version]
-  );
+/** Result of the native check_database command */
+export interface DatabaseCheckReport {
+  /** Problems reported by integrity_check; empty when it passes */
+  integrity: string[];
+  foreign_keys: { table: string; rowid: number | null; parent: string }[];
+  orphaned_splits: DatabaseOrphan[];
+  orphaned_budgets: DatabaseOrphan[];
+  orphaned_rules: DatabaseOrphan[];
+  /** Set when repairing and there was something safe to fix */
+  repairs: {
+    snapshot: string;
+    detached_splits: number;
+    deleted_budgets: number;
+    deleted_rules: number;
+    deactivated_rules: number;
+  } | null;
 }
 
 /**
- * Run database migrations for schema updates.
- * Mirrors the migrations in lib/db/index.ts.
+ * Check the database for corruption and dangling references. With `repair`,
+ * safe cases are fixed after a snapshot (see src-tauri/src/db/check.rs).
  */
-async function runMigrations(database: TauriDatabase): Promise<void> {
-  const currentVersion = await getSchemaVersion(database);
-
-  // Migration 1: Add source table and source_id column to transactions
-  if (currentVersion < 1) {
-    await database.execute(`
-      CREATE TABLE IF NOT EXISTS source (
-        id TEXT PRIMARY KEY,
-        name TEXT NOT NULL UNIQUE,
-        sort_order INTEGER NOT NULL DEFAULT 0,
-        created_at TEXT NOT NULL DEFAULT (datetime('now')),
-        updated_at TEXT NOT NULL DEFAULT (datetime('now'))
-      )
-    `);
-
-    const columns = await database.select<{ name: string }>(
-      "SELECT name FROM pragma_table_info('transaction') WHERE name='source_id'"
-    );
-
-    if (columns.length === 0) {
-      await database.execute(`
-        ALTER TABLE "transaction" ADD COLUMN source_id TEXT REFERENCES source(id)
-      `);
-      await database.execute(`
-        CREATE INDEX IF NOT EXISTS idx_transaction_source ON "transaction"(source_id)
-      `);
-    }
-
-    await setSchemaVersion(database, 1);
-  }
-
-  // Migration 2: Add Sinking Funds upper category
-  if (currentVersion < 2) {
-    const sinkingExists = await database.select<{ id: string }>(
-      "SELECT id FROM upper_category WHERE id = 'sinking'"
-    );
-
-    if (sinkingExists.length === 0) {
-      // Check for temp table from failed previous attempt
-      const tempTable = await database.select<{ name: string }>(
-        "SELECT name FROM sqlite_master WHERE type='table' AND name='upper_category_new'"
-      );
-
-      await database.execute('PRAGMA foreign_keys = OFF');
-
-      try {
-        if (tempTable.length > 0) {
-          await database.execute(`DROP TABLE IF EXISTS upper_category_new`);
-        }
-
-        await database.execute(`
-          CREATE TABLE upper_category_new (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            type TEXT NOT NULL CHECK (type IN ('income', 'expense', 'saving', 'bill', 'debt', 'sinking', 'transfer')),
-            sort_order INTEGER NOT NULL DEFAULT 0,
-            created_at TEXT NOT NULL DEFAULT (datetime('now')),
-            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
-          )
-        `);
-
-        await database.execute(`INSERT INTO upper_category_new SELECT * FROM upper_category`);
-        await database.execute(`UPDATE upper_category_new SET sort_order = 7 WHERE id = 'transfer'`);
-        await database.execute(`DROP TABLE upper_category`);
-        await database.execute(`ALTER TABLE upper_category_new RENAME TO upper_category`);
-
-        const now = new Date().toISOString();
-        await database.execute(
-          `INSERT INTO upper_category (id, name, type, sort_order, created_at, updated_at)
-           VALUES ('sinking', 'Sinking Funds', 'sinking', 6, ?, ?)`,
-          [now, now]
-        );
-      } finally {
-        await database.execute('PRAGMA foreign_keys = ON');
-      }
-    }
-
-    await setSchemaVersion(database, 2);
-  }
-
-  // Migration 3: Add net_worth_entry table
-  if (currentVersion < 3) {
-    await database.execute(`
-      CREATE TABLE IF NOT EXISTS net_worth_entry (
-        id TEXT PRIMARY KEY,
-        recorded_at TEXT NOT NULL,
-        assets_data TEXT NOT NULL,
-        liabilities_data TEXT NOT NULL,
-        total_assets REAL NOT NULL,
-        total_liabilities REAL NOT NULL,
-        net_worth REAL NOT NULL,
-        notes TEXT,
-        created_at TEXT NOT NULL DEFAULT (datetime('now')),
-        updated_at TEXT NOT NULL DEFAULT (datetime('now'))
-      )
-    `);
-    await database.execute(`
-      CREATE INDEX IF NOT EXISTS idx_net_worth_recorded_at ON net_worth_entry(recorded_at)
-    `);
-
-    await setSchemaVersion(database, 3);
-  }
-
-  // Migration 4: Add import_batch_id column to transaction table for undo import feature
-  if (currentVersion < 4) {
-    const columns = await database.select<{ name: string }>(
-      "SELECT name FROM pragma_table_info('transaction') WHERE name='import_batch_id'"
-    );
-
-    if (columns.length === 0) {
-      await database.execute(`
-        ALTER TABLE "transaction" ADD COLUMN import_batch_id TEXT
-      `);
-      await database.execute(`
-        CREATE INDEX IF NOT EXISTS idx_transaction_import_batch ON "transaction"(import_batch_id)
-      `);
-    }
-
-    await setSchemaVersion(database, 4);
-  }
-
-  // Migration 5: Add note table for financial planning notes
-  if (currentVersion < 5) {
-    await database.execute(`
-      CREATE TABLE IF NOT EXISTS note (
-        id TEXT PRIMARY KEY,
-        title TEXT NOT NULL,
-        content TEXT,
-        tags TEXT,
-        is_deleted INTEGER NOT NULL DEFAULT 0,
-        created_at TEXT NOT NULL DEFAULT (datetime('now')),
-        updated_at TEXT NOT NULL DEFAULT (datetime('now'))
-      )
-    `);
-    await database.execute(`
-      CREATE INDEX IF NOT EXISTS idx_note_deleted ON note(is_deleted)
-    `);
-    await database.execute(`
-      CREATE INDEX IF NOT EXISTS idx_note_updated ON note(updated_at)
-    `);
-
-    await setSchemaVersion(database, 5);
-  }
-
-  // Migration 6: Add is_active flag to upper_category
-  if (currentVersion < 6) {
-    await database.execute(`
-      ALTER TABLE upper_category ADD COLUMN is_active INTEGER NOT NULL DEFAULT 1
-    `);
-    await setSchemaVersion(database, 6);
-  }
-
-  // Verify migrations completed successfully
-  const finalVersion = await getSchemaVersion(database);
-  if (finalVersion !== CURRENT_SCHEMA_VERSION) {
-    throw new Error(
-      `Database migration incomplete: expected version ${CURRENT_SCHEMA_VERSION}, got ${finalVersion}`
-    );
-  }
+export async function checkDatabase(repair = false): Promise<DatabaseCheckReport> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<DatabaseCheckReport>('check_database', { repair });
 }
 
 /**
@@ -445,14 +260,24 @@ export async function closeDatabase(): Promise<void> {
   }
 }
 
+/** Result of the native snapshot_database command */
+export interface SnapshotInfo {
+  path: string;
+  size: number;
+  /** SHA-256 of the snapshot file */
+  hash: string;
+  /** Hash of the user-data rows (see hash_database 'content' mode) */
+  content_hash: string;
+}
+
 /**
- * Create a backup of the database using VACUUM INTO.
+ * Snapshot the database to `targetPath` (inside the app data directory).
+ * Uses SQLite's online backup API natively, so writes still in the WAL are
+ * included, and the copy is integrity-checked before it is kept.
  */
-export async function backup(targetPath: string): Promise<void> {
-  const database = await getDatabase();
-  // Escape single quotes to prevent SQL injection
-  const safePath = targetPath.replace(/'/g, "''");
-  await database.execute(`VACUUM INTO '${safePath}'`);
+export async function backup(targetPath: string): Promise<SnapshotInfo> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<SnapshotInfo>('snapshot_database', { dest: targetPath });
 }
 
 /**
@@ -0,0 +1,102 @@
+/**
+ * Secret Vault
+ *
+ * Thin wrapper over the Rust vault commands (src-tauri/src/vault.rs).
+ * OAuth tokens and sync credentials are stored in an encrypted file in the
+ * app data directory instead of localStorage, where any script in the
+ * webview could read them. The native OAuth commands read them by entry
+ * name, so secrets don't come back here; `vaultGet` is only for exports
+ * the user asked for.
+ */
+
+/** Vault entry names (kept equal to the old localStorage keys) */
+export const VAULT_KEYS = {
+  oauthTokens: 'puffin_oauth_tokens',
+  syncCredentials: 'puffin_sync_credentials',
+  /** Password for encrypted backups (src-tauri/src/db/backup.rs) */
+  backupPassword: 'puffin_backup_password',
+} as const;
+
+export type VaultKey = (typeof VAULT_KEYS)[keyof typeof VAULT_KEYS];
+
+let migrated = false;
+
+async function invokeVault<T>(command: string, args: Record<string, unknown>): Promise<T> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<T>(command, args);
+}
+
+/**
+ * Move secrets left in localStorage by older versions into the vault.
+ * Runs once per session; the localStorage copy is removed only after the
+ * vault write succeeded. Callers that read the vault natively (the OAuth token
+ * commands) must await this first.
+ */
+export async function migrateLegacySecrets(): Promise<void> {
+  if (migrated || typeof localStorage === 'undefined') return;
+
+  for (const name of Object.values(VAULT_KEYS)) {
+    const legacy = localStorage.getItem(name);
+    if (legacy === null) continue;
+
+    const existing = await invokeVault<boolean>('vault_has', { name });
+    if (!existing) {
+      await invokeVault('vault_set', { name, value: legacy });
+    }
+    localStorage.removeItem(name);
+  }
+
+  migrated = true;
+}
+
+/**
+ * Read a secret for an export the user asked for. The app shows a native
+ * dialog first; returns null when the entry doesn't exist or the user
+ * declines.
+ */
+export async function vaultGet(name: VaultKey): Promise<string | null> {
+  await migrateLegacySecrets();
+  return invokeVault<string | null>('vault_get', { name });
+}
+
+/** The stored OAuth client's ID and API key; the secret stays native */
+export interface OAuthClientInfo {
+  clientId: string;
+  apiKey: string | null;
+  hasSecret: boolean;
+}
+
+/**
+ * Public parts of the OAuth client stored under `name`, or null when none is
+ * stored.
+ */
+export async function getOAuthClient(
+  name: VaultKey = VAULT_KEYS.syncCredentials
+): Promise<OAuthClientInfo | null> {
+  await migrateLegacySecrets();
+  return invokeVault<OAuthClientInfo | null>('get_oauth_client', { credentialsEntry: name });
+}
+
+/**
+ * Check whether a secret exists without reading it.
+ */
+export async function vaultHas(name: VaultKey): Promise<boolean> {
+  await migrateLegacySecrets();
+  return invokeVault<boolean>('vault_has', { name });
+}
+
+export async function vaultSet(name: VaultKey, value: string): Promise<void> {
+  await invokeVault('vault_set', { name, value });
+}
+
+export async function vaultSetJson(name: VaultKey, value: unknown): Promise<void> {
+  await vaultSet(name, JSON.stringify(value));
+}
+
+export async function vaultDelete(name: VaultKey): Promise<void> {
+  await invokeVault('vault_delete', { name });
+  // Also drop any legacy copy that hasn't been migrated yet
+  if (typeof localStorage !== 'undefined') {
+    localStorage.removeItem(name);
+  }
+}
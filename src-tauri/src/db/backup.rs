@@ -0,0 +1,691 @@
+//! Encrypted backup files
+//!
+//! Sync uploads and manual backups are written in this container instead of
+//! as raw SQLite files:
+//!
+//! ```text
+//! magic         8   "PUFFINBK"
+//! version       2   u16, currently 1
+//! kdf           1   1 = Argon2id
+//! m_cost        4   u32, KiB
+//! t_cost        4   u32
+//! p_cost        4   u32
+//! salt         16
+//! key_check    32   second half of the KDF output; tells a wrong password
+//!                   apart from a damaged file
+//! nonce        24   XChaCha20-Poly1305
+//! created_at    8   i64, Unix seconds
+//! content_hash 32   `hash::hash_content` of the database
+//! length        8   u64, ciphertext length
+//! ciphertext        encrypted SQLite snapshot + 16-byte tag
+//! ```
+//!
+//! Integers are little-endian. The whole header is authenticated as
+//! associated data, so the content hash can be read without the password but
+//! not altered. Files from older versions are plain SQLite databases; they
+//! are still accepted when reading.
+
+use super::hash::hash_content;
+use super::snapshot::snapshot_to;
+use super::{app_data_dir, database_path, ensure_within, integrity_check, open, DbError};
+use crate::vault::Vault;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File extension for encrypted backups
+pub const BACKUP_EXTENSION: &str = "puffinbak";
+
+/// Vault entry holding the backup password (see `lib/services/vault.ts`)
+pub const PASSWORD_ENTRY: &str = "puffin_backup_password";
+
+const MAGIC: &[u8; 8] = b"PUFFINBK";
+const FORMAT_VERSION: u16 = 1;
+const KDF_ARGON2ID: u8 = 1;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+const HASH_LEN: usize = 32;
+const HEADER_LEN: usize = 8 + 2 + 1 + 12 + SALT_LEN + KEY_LEN + NONCE_LEN + 8 + HASH_LEN + 8;
+
+/// First bytes of every SQLite database file
+const SQLITE_MAGIC: &[u8; 16] = b"SQLite format 3\0";
+
+/// Upper bounds accepted when reading, so a crafted header can't make the
+/// KDF allocate gigabytes or run for minutes
+const MAX_M_COST: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 16;
+
+/// Backup command failure, serialized as `{ kind, message }`
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum BackupError {
+    Io(String),
+    Sqlite(String),
+    /// Neither an encrypted backup nor a SQLite database
+    NotABackup,
+    /// Written by a newer version of Puffin
+    UnsupportedVersion(u16),
+    /// No password was given and none is stored in the vault
+    PasswordRequired,
+    WrongPassword,
+    /// The file fails authentication or its contents don't match the header
+    Corrupt(String),
+    /// The command may not write to this path
+    InvalidDestination(String),
+    /// Not a Puffin database, or one from a newer schema version
+    IncompatibleSchema(String),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::Io(msg) => write!(f, "Backup I/O error: {}", msg),
+            BackupError::Sqlite(msg) => write!(f, "SQLite error: {}", msg),
+            BackupError::NotABackup => write!(f, "File is not a Puffin backup"),
+            BackupError::UnsupportedVersion(v) => write!(
+                f,
+                "Backup format version {} is not supported; update Puffin",
+                v
+            ),
+            BackupError::PasswordRequired => write!(f, "Set a backup password first"),
+            BackupError::WrongPassword => write!(f, "Incorrect backup password"),
+            BackupError::Corrupt(msg) => write!(f, "Backup is damaged: {}", msg),
+            BackupError::InvalidDestination(msg) => write!(f, "Invalid destination: {}", msg),
+            BackupError::IncompatibleSchema(msg) => write!(f, "Incompatible backup: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<io::Error> for BackupError {
+    fn from(e: io::Error) -> Self {
+        BackupError::Io(e.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for BackupError {
+    fn from(e: rusqlite::Error) -> Self {
+        BackupError::Sqlite(e.to_string())
+    }
+}
+
+impl From<DbError> for BackupError {
+    fn from(e: DbError) -> Self {
+        match e {
+            DbError::Sqlite(msg) => BackupError::Sqlite(msg),
+            DbError::InvalidDestination(msg) => BackupError::InvalidDestination(msg),
+            DbError::Integrity(msg) => BackupError::Corrupt(msg),
+            DbError::Migration(msg) => BackupError::IncompatibleSchema(msg),
+            other => BackupError::Io(other.to_string()),
+        }
+    }
+}
+
+/// Argon2id cost parameters
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KdfParams {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    /// 64 MiB, 3 passes: roughly half a second on a laptop
+    fn default() -> Self {
+        Self {
+            m_cost: 64 * 1024,
+            t_cost: 3,
+            p_cost: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Header {
+    version: u16,
+    kdf: KdfParams,
+    salt: [u8; SALT_LEN],
+    key_check: [u8; KEY_LEN],
+    nonce: [u8; NONCE_LEN],
+    created_at: i64,
+    content_hash: [u8; HASH_LEN],
+    length: u64,
+}
+
+impl Header {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_LEN);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.push(KDF_ARGON2ID);
+        out.extend_from_slice(&self.kdf.m_cost.to_le_bytes());
+        out.extend_from_slice(&self.kdf.t_cost.to_le_bytes());
+        out.extend_from_slice(&self.kdf.p_cost.to_le_bytes());
+        out.extend_from_slice(&self.salt);
+        out.extend_from_slice(&self.key_check);
+        out.extend_from_slice(&self.nonce);
+        out.extend_from_slice(&self.created_at.to_le_bytes());
+        out.extend_from_slice(&self.content_hash);
+        out.extend_from_slice(&self.length.to_le_bytes());
+        out
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, BackupError> {
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(BackupError::NotABackup);
+        }
+        if bytes.len() < MAGIC.len() + 2 {
+            return Err(BackupError::Corrupt("truncated header".to_string()));
+        }
+        let version = u16::from_le_bytes([bytes[8], bytes[9]]);
+        if version != FORMAT_VERSION {
+            return Err(BackupError::UnsupportedVersion(version));
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(BackupError::Corrupt("truncated header".to_string()));
+        }
+
+        let mut reader = FieldReader { bytes, pos: 10 };
+        if reader.take::<1>()[0] != KDF_ARGON2ID {
+            return Err(BackupError::Corrupt("unknown key derivation".to_string()));
+        }
+        let kdf = KdfParams {
+            m_cost: u32::from_le_bytes(reader.take()),
+            t_cost: u32::from_le_bytes(reader.take()),
+            p_cost: u32::from_le_bytes(reader.take()),
+        };
+        if kdf.m_cost > MAX_M_COST || kdf.t_cost > MAX_T_COST || kdf.p_cost > MAX_P_COST {
+            return Err(BackupError::Corrupt(
+                "key derivation parameters out of range".to_string(),
+            ));
+        }
+        Ok(Header {
+            version,
+            kdf,
+            salt: reader.take(),
+            key_check: reader.take(),
+            nonce: reader.take(),
+            created_at: i64::from_le_bytes(reader.take()),
+            content_hash: reader.take(),
+            length: u64::from_le_bytes(reader.take()),
+        })
+    }
+}
+
+/// Sequential fixed-size fields of a header already checked for length
+struct FieldReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl FieldReader<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let mut field = [0u8; N];
+        field.copy_from_slice(&self.bytes[self.pos..self.pos + N]);
+        self.pos += N;
+        field
+    }
+}
+
+/// Encryption key and password check derived from the password
+fn derive(
+    password: &str,
+    salt: &[u8],
+    kdf: KdfParams,
+) -> Result<(Key, [u8; KEY_LEN]), BackupError> {
+    let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(KEY_LEN * 2))
+        .map_err(|e| BackupError::Corrupt(format!("invalid key derivation parameters: {}", e)))?;
+    let mut output = [0u8; KEY_LEN * 2];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut output)
+        .map_err(|e| BackupError::Corrupt(format!("key derivation failed: {}", e)))?;
+
+    let key = *Key::from_slice(&output[..KEY_LEN]);
+    let mut check = [0u8; KEY_LEN];
+    check.copy_from_slice(&output[KEY_LEN..]);
+    Ok((key, check))
+}
+
+fn decode_hash(hex: &str) -> Result<[u8; HASH_LEN], BackupError> {
+    let mut out = [0u8; HASH_LEN];
+    if hex.len() != HASH_LEN * 2 {
+        return Err(BackupError::Corrupt("invalid content hash".to_string()));
+    }
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| BackupError::Corrupt("invalid content hash".to_string()))?;
+    }
+    Ok(out)
+}
+
+fn encode_hash(hash: &[u8; HASH_LEN]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Encrypt a SQLite snapshot whose content hash is `content_hash` (hex)
+fn seal(
+    snapshot: &[u8],
+    content_hash: &str,
+    password: &str,
+    kdf: KdfParams,
+) -> Result<Vec<u8>, BackupError> {
+    let salt: [u8; SALT_LEN] = rand::random();
+    let (key, key_check) = derive(password, &salt, kdf)?;
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let header = Header {
+        version: FORMAT_VERSION,
+        kdf,
+        salt,
+        key_check,
+        nonce: nonce.into(),
+        created_at: now(),
+        content_hash: decode_hash(content_hash)?,
+        // Poly1305 tag
+        length: snapshot.len() as u64 + 16,
+    };
+    let mut out = header.encode();
+    let ciphertext = XChaCha20Poly1305::new(&key)
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: snapshot,
+                aad: &out,
+            },
+        )
+        .map_err(|_| BackupError::Corrupt("encryption failed".to_string()))?;
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+/// Decrypt a sealed backup, returning its header and the SQLite snapshot
+fn unseal(bytes: &[u8], password: &str) -> Result<(Header, Vec<u8>), BackupError> {
+    let header = Header::decode(bytes)?;
+    let ciphertext = &bytes[HEADER_LEN..];
+    if ciphertext.len() as u64 != header.length {
+        return Err(BackupError::Corrupt("unexpected length".to_string()));
+    }
+
+    let (key, key_check) = derive(password, &header.salt, header.kdf)?;
+    if key_check != header.key_check {
+        return Err(BackupError::WrongPassword);
+    }
+    let snapshot = XChaCha20Poly1305::new(&key)
+        .decrypt(
+            XNonce::from_slice(&header.nonce),
+            Payload {
+                msg: ciphertext,
+                aad: &bytes[..HEADER_LEN],
+            },
+        )
+        .map_err(|_| BackupError::Corrupt("authentication failed".to_string()))?;
+    Ok((header, snapshot))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupFormat {
+    Encrypted,
+    /// Unencrypted SQLite file from an older version
+    Sqlite,
+}
+
+/// What a backup file is, readable without the password
+#[derive(Debug, serde::Serialize)]
+pub struct BackupFileInfo {
+    pub path: String,
+    pub format: BackupFormat,
+    pub size: u64,
+    /// Unix seconds; `None` for plain SQLite files
+    pub created_at: Option<i64>,
+    /// Content hash of the database inside; `None` for plain SQLite files
+    pub content_hash: Option<String>,
+}
+
+fn inspect(path: &Path) -> Result<BackupFileInfo, BackupError> {
+    let size = fs::metadata(path)?.len();
+    let mut head = Vec::with_capacity(HEADER_LEN);
+    File::open(path)?
+        .take(HEADER_LEN as u64)
+        .read_to_end(&mut head)?;
+
+    let info = |format, created_at, content_hash| BackupFileInfo {
+        path: path.to_string_lossy().into_owned(),
+        format,
+        size,
+        created_at,
+        content_hash,
+    };
+    if head.starts_with(SQLITE_MAGIC) {
+        return Ok(info(BackupFormat::Sqlite, None, None));
+    }
+    let header = Header::decode(&head)?;
+    Ok(info(
+        BackupFormat::Encrypted,
+        Some(header.created_at),
+        Some(encode_hash(&header.content_hash)),
+    ))
+}
+
+/// Snapshot the live database and read the copy back
+fn snapshot(db_path: &Path) -> Result<(Vec<u8>, String), BackupError> {
+    let tmp = db_path.with_extension(format!("snapshot-{:08x}.tmp", rand::random::<u32>()));
+    let result = snapshot_to(db_path, &tmp)
+        .map_err(BackupError::from)
+        .and_then(|info| Ok((fs::read(&tmp)?, info.content_hash)));
+    let _ = fs::remove_file(&tmp);
+    result
+}
+
+/// Write through a temp file so a failure never leaves a partial `path`
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), BackupError> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, contents)?;
+    if let Err(e) = fs::rename(&tmp, path) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Result of `create_backup`
+#[derive(Debug, serde::Serialize)]
+pub struct BackupInfo {
+    pub path: String,
+    pub size: u64,
+    pub content_hash: String,
+    /// Unix seconds
+    pub created_at: i64,
+}
+
+/// Encrypt a snapshot of the database at `db_path` into `dest`
+pub fn write_backup(
+    db_path: &Path,
+    dest: &Path,
+    password: &str,
+    kdf: KdfParams,
+) -> Result<BackupInfo, BackupError> {
+    let (snapshot, content_hash) = snapshot(db_path)?;
+    let sealed = seal(&snapshot, &content_hash, password, kdf)?;
+    write_atomic(dest, &sealed)?;
+    let header = Header::decode(&sealed)?;
+    Ok(BackupInfo {
+        path: dest.to_string_lossy().into_owned(),
+        size: sealed.len() as u64,
+        content_hash,
+        created_at: header.created_at,
+    })
+}
+
+/// Write the SQLite database contained in `src` to `dest`. Encrypted backups
+/// are decrypted and checked against their content hash; plain SQLite files
+/// carry no hash and must pass `PRAGMA integrity_check` instead.
+pub fn extract_backup_to(
+    src: &Path,
+    dest: &Path,
+    password: Option<&str>,
+) -> Result<BackupFileInfo, BackupError> {
+    let info = inspect(src)?;
+    let (snapshot, expected_hash) = if info.format == BackupFormat::Sqlite {
+        (fs::read(src)?, None)
+    } else {
+        let password = password.ok_or(BackupError::PasswordRequired)?;
+        let (header, snapshot) = unseal(&fs::read(src)?, password)?;
+        (snapshot, Some(encode_hash(&header.content_hash)))
+    };
+
+    // Verify before replacing anything at `dest`
+    let mut staged = dest.as_os_str().to_owned();
+    staged.push(".extract");
+    let staged = PathBuf::from(staged);
+    fs::write(&staged, &snapshot)?;
+    let verified = open(&staged, true)
+        .map_err(BackupError::from)
+        .and_then(|conn| match &expected_hash {
+            None => Ok(integrity_check(&conn)?),
+            Some(expected) if hash_content(&conn)? == *expected => Ok(()),
+            Some(_) => Err(BackupError::Corrupt("content hash mismatch".to_string())),
+        });
+    if let Err(e) = verified {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+    if let Err(e) = fs::rename(&staged, dest) {
+        let _ = fs::remove_file(&staged);
+        return Err(e.into());
+    }
+    Ok(info)
+}
+
+/// The explicit password, or the one stored in the vault
+pub(crate) fn resolve_password(vault: &Vault, password: Option<String>) -> Option<String> {
+    password
+        .filter(|p| !p.is_empty())
+        .or_else(|| vault.get(PASSWORD_ENTRY))
+}
+
+/// Backups may be saved anywhere the user picks, but only under our own
+/// extension so the command can't overwrite arbitrary files
+fn check_backup_dest(dest: &Path) -> Result<(), BackupError> {
+    let ext = dest
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    if !ext.eq_ignore_ascii_case(BACKUP_EXTENSION) {
+        return Err(BackupError::InvalidDestination(format!(
+            "backups must use the .{} extension",
+            BACKUP_EXTENSION
+        )));
+    }
+    Ok(())
+}
+
+/// Write an encrypted backup of `puffin.db` to `dest`. Without `password`
+/// the backup password from the vault is used.
+#[tauri::command]
+pub async fn create_backup(
+    app: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    dest: String,
+    password: Option<String>,
+) -> Result<BackupInfo, BackupError> {
+    check_backup_dest(Path::new(&dest))?;
+    let password = resolve_password(&vault, password).ok_or(BackupError::PasswordRequired)?;
+    let db_path = database_path(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        write_backup(&db_path, Path::new(&dest), &password, KdfParams::default())
+    })
+    .await
+    .map_err(|e| BackupError::Io(e.to_string()))?
+}
+
+/// Format, date and content hash of a backup file, without decrypting it
+#[tauri::command]
+pub async fn inspect_backup(path: String) -> Result<BackupFileInfo, BackupError> {
+    tauri::async_runtime::spawn_blocking(move || inspect(Path::new(&path)))
+        .await
+        .map_err(|e| BackupError::Io(e.to_string()))?
+}
+
+/// Write the database inside backup `path` to `dest` (within the app data
+/// directory). The caller closes the SQL plugin connection first when `dest`
+/// is `puffin.db`.
+#[tauri::command]
+pub async fn extract_backup(
+    app: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    path: String,
+    dest: String,
+    password: Option<String>,
+) -> Result<BackupFileInfo, BackupError> {
+    ensure_within(&app_data_dir(&app)?, Path::new(&dest))?;
+    let password = resolve_password(&vault, password);
+    tauri::async_runtime::spawn_blocking(move || {
+        extract_backup_to(Path::new(&path), Path::new(&dest), password.as_deref())
+    })
+    .await
+    .map_err(|e| BackupError::Io(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    /// Cheap parameters so the tests don't spend seconds in the KDF
+    const TEST_KDF: KdfParams = KdfParams {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+    const HASH: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    fn create_db(path: &Path) {
+        Connection::open(path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE note (id TEXT PRIMARY KEY, title TEXT);
+                 INSERT INTO note VALUES ('a', 'Rent'), ('b', 'Groceries');",
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn round_trips_snapshot_and_header() {
+        let sealed = seal(b"SQLite format 3\0data", HASH, "correct horse", TEST_KDF).unwrap();
+        assert!(!sealed
+            .windows(b"data".len())
+            .any(|w| w == b"data".as_slice()));
+
+        let (header, snapshot) = unseal(&sealed, "correct horse").unwrap();
+        assert_eq!(snapshot, b"SQLite format 3\0data");
+        assert_eq!(header.kdf, TEST_KDF);
+        assert_eq!(encode_hash(&header.content_hash), HASH);
+    }
+
+    #[test]
+    fn rejects_wrong_password() {
+        let sealed = seal(b"snapshot", HASH, "correct horse", TEST_KDF).unwrap();
+        assert!(matches!(
+            unseal(&sealed, "battery staple"),
+            Err(BackupError::WrongPassword)
+        ));
+    }
+
+    #[test]
+    fn detects_tampering() {
+        let sealed = seal(b"snapshot", HASH, "pw", TEST_KDF).unwrap();
+
+        let mut body = sealed.clone();
+        *body.last_mut().unwrap() ^= 1;
+        assert!(matches!(unseal(&body, "pw"), Err(BackupError::Corrupt(_))));
+
+        // The content hash is readable but authenticated
+        let mut hash = sealed.clone();
+        hash[HEADER_LEN - 9] ^= 1;
+        assert!(matches!(unseal(&hash, "pw"), Err(BackupError::Corrupt(_))));
+
+        let truncated = &sealed[..sealed.len() - 1];
+        assert!(matches!(
+            unseal(truncated, "pw"),
+            Err(BackupError::Corrupt(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_other_files_and_newer_versions() {
+        assert!(matches!(
+            unseal(b"not a backup at all", "pw"),
+            Err(BackupError::NotABackup)
+        ));
+
+        let mut newer = seal(b"snapshot", HASH, "pw", TEST_KDF).unwrap();
+        newer[8] = 9;
+        assert!(matches!(
+            unseal(&newer, "pw"),
+            Err(BackupError::UnsupportedVersion(9))
+        ));
+    }
+
+    #[test]
+    fn backs_up_and_extracts_a_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("puffin.db");
+        create_db(&db);
+
+        let dest = dir.path().join("backup.puffinbak");
+        let written = write_backup(&db, &dest, "pw", TEST_KDF).unwrap();
+        assert_eq!(written.size, fs::metadata(&dest).unwrap().len());
+
+        let info = inspect(&dest).unwrap();
+        assert_eq!(info.format, BackupFormat::Encrypted);
+        assert_eq!(
+            info.content_hash.as_deref(),
+            Some(written.content_hash.as_str())
+        );
+
+        let restored = dir.path().join("restored.db");
+        assert!(matches!(
+            extract_backup_to(&dest, &restored, None),
+            Err(BackupError::PasswordRequired)
+        ));
+        extract_backup_to(&dest, &restored, Some("pw")).unwrap();
+        let conn = Connection::open(&restored).unwrap();
+        assert_eq!(hash_content(&conn).unwrap(), written.content_hash);
+    }
+
+    #[test]
+    fn backups_need_their_own_extension() {
+        assert!(check_backup_dest(Path::new("/tmp/weekly.PuffinBak")).is_ok());
+        assert!(matches!(
+            check_backup_dest(Path::new("/home/me/.bashrc")),
+            Err(BackupError::InvalidDestination(_))
+        ));
+    }
+
+    #[test]
+    fn extracts_legacy_sqlite_backups_as_is() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join("puffin-backup.db");
+        create_db(&legacy);
+        assert_eq!(inspect(&legacy).unwrap().format, BackupFormat::Sqlite);
+
+        let restored = dir.path().join("restored.db");
+        extract_backup_to(&legacy, &restored, None).unwrap();
+        assert_eq!(fs::read(&restored).unwrap(), fs::read(&legacy).unwrap());
+    }
+
+    #[test]
+    fn rejects_damaged_legacy_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join("puffin-backup.db");
+        create_db(&legacy);
+        // Keep the header but wipe the pages after it
+        let mut bytes = fs::read(&legacy).unwrap();
+        for byte in &mut bytes[100..] {
+            *byte = 0xff;
+        }
+        fs::write(&legacy, &bytes).unwrap();
+
+        let restored = dir.path().join("restored.db");
+        assert!(extract_backup_to(&legacy, &restored, None).is_err());
+        assert!(!restored.exists());
+    }
+}
@@ -0,0 +1,328 @@
+//! Database health check and repair
+//!
+//! `check_database` runs SQLite's `integrity_check` and `foreign_key_check`
+//! and looks for rows the app's own soft-delete rules leave dangling: split
+//! children whose parent row is gone, and budgets and rules that point at
+//! missing or deleted sub-categories.
+//!
+//! With `repair`, the safe cases are fixed in one transaction after a snapshot
+//! to `backups/pre-repair-<time>.db`. Orphaned split children become ordinary
+//! transactions, budgets and rules for sub-categories that no longer exist are
+//! removed, and rules for deleted sub-categories are switched off. Budgets for
+//! deleted sub-categories are only reported, since the app already hides them.
+//! Nothing is repaired while `integrity_check` fails; restore a backup instead.
+
+use super::schedule::{file_stamp, unix_now, BACKUPS_DIR};
+use super::snapshot::snapshot_to;
+use super::{app_data_dir, blocking, database_path, integrity_problems, open, DbError};
+use rusqlite::Connection;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    pub rowid: Option<i64>,
+    /// Table the missing row should be in
+    pub parent: String,
+}
+
+/// A row referencing another that is gone or soft-deleted
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct Orphan {
+    pub id: String,
+    /// Id of the referenced row
+    pub target: String,
+    /// The referenced row doesn't exist at all (rather than being soft-deleted)
+    pub missing: bool,
+}
+
+#[derive(Debug, Default, PartialEq, serde::Serialize)]
+pub struct Repairs {
+    /// Snapshot taken before repairing
+    pub snapshot: String,
+    pub detached_splits: usize,
+    pub deleted_budgets: usize,
+    pub deleted_rules: usize,
+    pub deactivated_rules: usize,
+}
+
+/// Findings from before any repair
+#[derive(Debug, Default, serde::Serialize)]
+pub struct CheckReport {
+    /// Problems reported by `integrity_check`; empty when it passes
+    pub integrity: Vec<String>,
+    pub foreign_keys: Vec<ForeignKeyViolation>,
+    /// Split children whose parent transaction is missing
+    pub orphaned_splits: Vec<Orphan>,
+    /// Budgets for missing or deleted sub-categories
+    pub orphaned_budgets: Vec<Orphan>,
+    /// Active rules for missing or deleted sub-categories
+    pub orphaned_rules: Vec<Orphan>,
+    /// Set when repairing and there was something safe to fix
+    pub repairs: Option<Repairs>,
+}
+
+impl CheckReport {
+    pub fn is_healthy(&self) -> bool {
+        self.integrity.is_empty()
+            && self.foreign_keys.is_empty()
+            && self.orphaned_splits.is_empty()
+            && self.orphaned_budgets.is_empty()
+            && self.orphaned_rules.is_empty()
+    }
+}
+
+const ORPHANED_SPLITS_SQL: &str = "SELECT t.id, t.parent_transaction_id, 1
+     FROM \"transaction\" t
+     WHERE t.parent_transaction_id IS NOT NULL
+       AND NOT EXISTS (SELECT 1 FROM \"transaction\" p WHERE p.id = t.parent_transaction_id)
+     ORDER BY t.id";
+
+const ORPHANED_BUDGETS_SQL: &str = "SELECT b.id, b.sub_category_id, sc.id IS NULL
+     FROM budget b
+     LEFT JOIN sub_category sc ON sc.id = b.sub_category_id
+     WHERE sc.id IS NULL OR sc.is_deleted = 1
+     ORDER BY b.id";
+
+const ORPHANED_RULES_SQL: &str = "SELECT r.id, r.sub_category_id, sc.id IS NULL
+     FROM auto_category_rule r
+     LEFT JOIN sub_category sc ON sc.id = r.sub_category_id
+     WHERE (sc.id IS NULL OR sc.is_deleted = 1) AND r.is_active = 1
+     ORDER BY r.id";
+
+fn orphans(conn: &Connection, sql: &str) -> Result<Vec<Orphan>, DbError> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Orphan {
+            id: row.get(0)?,
+            target: row.get(1)?,
+            missing: row.get(2)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn foreign_key_violations(conn: &Connection) -> Result<Vec<ForeignKeyViolation>, DbError> {
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ForeignKeyViolation {
+            table: row.get(0)?,
+            rowid: row.get(1)?,
+            parent: row.get(2)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn inspect(conn: &Connection) -> Result<CheckReport, DbError> {
+    let integrity = integrity_problems(conn)?;
+    if !integrity.is_empty() {
+        // Queries over a damaged file can fail or mislead
+        return Ok(CheckReport {
+            integrity,
+            ..Default::default()
+        });
+    }
+    Ok(CheckReport {
+        integrity,
+        foreign_keys: foreign_key_violations(conn)?,
+        orphaned_splits: orphans(conn, ORPHANED_SPLITS_SQL)?,
+        orphaned_budgets: orphans(conn, ORPHANED_BUDGETS_SQL)?,
+        orphaned_rules: orphans(conn, ORPHANED_RULES_SQL)?,
+        repairs: None,
+    })
+}
+
+/// Fix the safe cases in `report`; returns counts of what changed
+fn repair(conn: &mut Connection, report: &CheckReport) -> Result<Repairs, DbError> {
+    let tx = conn.transaction()?;
+    let mut repairs = Repairs::default();
+    let now = "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')";
+
+    for split in &report.orphaned_splits {
+        repairs.detached_splits += tx.execute(
+            &format!(
+                "UPDATE \"transaction\" SET parent_transaction_id = NULL, updated_at = {} \
+                 WHERE id = ?1",
+                now
+            ),
+            [&split.id],
+        )?;
+    }
+    for budget in report.orphaned_budgets.iter().filter(|b| b.missing) {
+        repairs.deleted_budgets += tx.execute("DELETE FROM budget WHERE id = ?1", [&budget.id])?;
+    }
+    for rule in &report.orphaned_rules {
+        if rule.missing {
+            repairs.deleted_rules +=
+                tx.execute("DELETE FROM auto_category_rule WHERE id = ?1", [&rule.id])?;
+        } else {
+            repairs.deactivated_rules += tx.execute(
+                &format!(
+                    "UPDATE auto_category_rule SET is_active = 0, updated_at = {} WHERE id = ?1",
+                    now
+                ),
+                [&rule.id],
+            )?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(repairs)
+}
+
+/// Check the database at `db_path`, repairing safe cases when asked. The
+/// pre-repair snapshot goes to `backups_dir`.
+pub fn check_at(
+    db_path: &Path,
+    backups_dir: &Path,
+    repair_safe: bool,
+    now: i64,
+) -> Result<CheckReport, DbError> {
+    let mut report = inspect(&open(db_path, true)?)?;
+
+    let repairable = !report.orphaned_splits.is_empty()
+        || report.orphaned_budgets.iter().any(|b| b.missing)
+        || !report.orphaned_rules.is_empty();
+    if repair_safe && report.integrity.is_empty() && repairable {
+        fs::create_dir_all(backups_dir)?;
+        let dest = backups_dir.join(format!("pre-repair-{}.db", file_stamp(now)));
+        let snapshot = snapshot_to(db_path, &dest)?.path;
+
+        let mut repairs = repair(&mut open(db_path, false)?, &report)?;
+        repairs.snapshot = snapshot;
+        report.repairs = Some(repairs);
+    }
+
+    if !report.is_healthy() {
+        log::warn!(
+            "Database check: {} integrity problem(s), {} foreign key violation(s), \
+             {} orphaned split(s), {} orphaned budget(s), {} orphaned rule(s)",
+            report.integrity.len(),
+            report.foreign_keys.len(),
+            report.orphaned_splits.len(),
+            report.orphaned_budgets.len(),
+            report.orphaned_rules.len()
+        );
+    }
+    Ok(report)
+}
+
+/// Check `puffin.db` for corruption and dangling references. With `repair`,
+/// fix the safe cases after taking a snapshot.
+#[tauri::command]
+pub async fn check_database(
+    app: tauri::AppHandle,
+    repair: Option<bool>,
+) -> Result<CheckReport, DbError> {
+    let db_path = database_path(&app)?;
+    let backups_dir = app_data_dir(&app)?.join(BACKUPS_DIR);
+    blocking(move || check_at(&db_path, &backups_dir, repair.unwrap_or(false), unix_now())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations::migrate;
+
+    /// 2024-03-09 14:05:00 UTC
+    const NOW: i64 = 1_709_993_100;
+
+    /// Migrated database with one of each kind of problem, plus healthy rows
+    fn create(path: &Path) {
+        let mut conn = Connection::open(path).unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO sub_category (id, upper_category_id, name) VALUES ('food', 'expense', 'Food');
+             INSERT INTO sub_category (id, upper_category_id, name, is_deleted) VALUES ('old', 'expense', 'Old', 1);
+             INSERT INTO \"transaction\" (id, date, description, amount, is_split) VALUES ('p', '2024-01-01', 'Split', -50, 1);
+             INSERT INTO \"transaction\" (id, date, description, amount, parent_transaction_id) VALUES
+               ('c1', '2024-01-01', 'Half', -25, 'p'),
+               ('c2', '2024-01-01', 'Lost half', -25, 'gone');
+             INSERT INTO budget (id, sub_category_id, year, month, amount) VALUES
+               ('b-ok', 'food', 2024, 1, 100),
+               ('b-old', 'old', 2024, 1, 100),
+               ('b-gone', 'gone', 2024, 1, 100);
+             INSERT INTO auto_category_rule (id, match_text, sub_category_id) VALUES
+               ('r-ok', 'COLES', 'food'),
+               ('r-old', 'OLD', 'old'),
+               ('r-gone', 'GONE', 'gone');",
+        )
+        .unwrap();
+    }
+
+    fn ids(orphans: &[Orphan]) -> Vec<&str> {
+        orphans.iter().map(|o| o.id.as_str()).collect()
+    }
+
+    #[test]
+    fn reports_dangling_references() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("puffin.db");
+        create(&path);
+
+        let report = check_at(&path, &dir.path().join("backups"), false, NOW).unwrap();
+        assert!(report.integrity.is_empty());
+        assert_eq!(ids(&report.orphaned_splits), vec!["c2"]);
+        assert_eq!(ids(&report.orphaned_budgets), vec!["b-gone", "b-old"]);
+        assert_eq!(ids(&report.orphaned_rules), vec!["r-gone", "r-old"]);
+        assert!(report.orphaned_rules[0].missing);
+        assert!(!report.orphaned_rules[1].missing);
+
+        // SQLite sees the rows pointing at missing parents too
+        let tables: Vec<&str> = report
+            .foreign_keys
+            .iter()
+            .map(|v| v.table.as_str())
+            .collect();
+        assert_eq!(tables.len(), 3);
+        for table in ["transaction", "budget", "auto_category_rule"] {
+            assert!(tables.contains(&table), "{}", table);
+        }
+        assert!(report.repairs.is_none());
+        assert!(!dir.path().join("backups").exists());
+    }
+
+    #[test]
+    fn repair_fixes_safe_cases_after_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("puffin.db");
+        create(&path);
+        let backups = dir.path().join("backups");
+
+        let report = check_at(&path, &backups, true, NOW).unwrap();
+        let repairs = report.repairs.unwrap();
+        assert_eq!(
+            (
+                repairs.detached_splits,
+                repairs.deleted_budgets,
+                repairs.deleted_rules,
+                repairs.deactivated_rules
+            ),
+            (1, 1, 1, 1)
+        );
+        assert!(repairs
+            .snapshot
+            .ends_with("pre-repair-2024-03-09_14-05-00.db"));
+        assert!(Path::new(&repairs.snapshot).exists());
+
+        // Only the budget for the soft-deleted category is left to report
+        let after = check_at(&path, &backups, true, NOW).unwrap();
+        assert!(after.foreign_keys.is_empty());
+        assert!(after.orphaned_splits.is_empty());
+        assert!(after.orphaned_rules.is_empty());
+        assert_eq!(ids(&after.orphaned_budgets), vec!["b-old"]);
+        assert!(after.repairs.is_none());
+
+        let conn = Connection::open(&path).unwrap();
+        let amount: f64 = conn
+            .query_row(
+                "SELECT amount FROM \"transaction\" WHERE id = 'c2' AND parent_transaction_id IS NULL",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(amount, -25.0);
+    }
+}
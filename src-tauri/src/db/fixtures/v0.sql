@@ -0,0 +1,107 @@
+-- Database from a release before schema versioning: no source, net worth or
+-- note tables, and no Sinking Funds category
+CREATE TABLE local_user (
+  id TEXT PRIMARY KEY,
+  password_hash TEXT NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE upper_category (
+  id TEXT PRIMARY KEY,
+  name TEXT NOT NULL,
+  type TEXT NOT NULL CHECK (type IN ('income', 'expense', 'saving', 'bill', 'debt', 'transfer')),
+  sort_order INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE sub_category (
+  id TEXT PRIMARY KEY,
+  upper_category_id TEXT NOT NULL REFERENCES upper_category(id),
+  name TEXT NOT NULL,
+  sort_order INTEGER NOT NULL DEFAULT 0,
+  is_deleted INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE "transaction" (
+  id TEXT PRIMARY KEY,
+  date TEXT NOT NULL,
+  description TEXT NOT NULL,
+  amount REAL NOT NULL,
+  notes TEXT,
+  sub_category_id TEXT REFERENCES sub_category(id),
+  is_split INTEGER NOT NULL DEFAULT 0,
+  parent_transaction_id TEXT REFERENCES "transaction"(id),
+  is_deleted INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE budget (
+  id TEXT PRIMARY KEY,
+  sub_category_id TEXT NOT NULL REFERENCES sub_category(id),
+  year INTEGER NOT NULL,
+  month INTEGER NOT NULL CHECK (month >= 1 AND month <= 12),
+  amount REAL NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+  UNIQUE(sub_category_id, year, month)
+);
+
+CREATE TABLE budget_template (
+  id TEXT PRIMARY KEY,
+  name TEXT NOT NULL,
+  template_data TEXT NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE auto_category_rule (
+  id TEXT PRIMARY KEY,
+  match_text TEXT NOT NULL,
+  sub_category_id TEXT NOT NULL REFERENCES sub_category(id),
+  priority INTEGER NOT NULL DEFAULT 0,
+  is_active INTEGER NOT NULL DEFAULT 1,
+  match_count INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE sync_log (
+  id TEXT PRIMARY KEY,
+  action TEXT NOT NULL CHECK (action IN ('push', 'pull')),
+  status TEXT NOT NULL CHECK (status IN ('success', 'failed', 'in_progress')),
+  file_name TEXT,
+  file_size INTEGER,
+  error_message TEXT,
+  started_at TEXT NOT NULL DEFAULT (datetime('now')),
+  completed_at TEXT
+);
+
+CREATE INDEX idx_transaction_date ON "transaction"(date);
+CREATE INDEX idx_transaction_sub_category ON "transaction"(sub_category_id);
+CREATE INDEX idx_transaction_parent ON "transaction"(parent_transaction_id);
+CREATE INDEX idx_transaction_deleted ON "transaction"(is_deleted);
+CREATE INDEX idx_sub_category_upper ON sub_category(upper_category_id);
+CREATE INDEX idx_budget_period ON budget(year, month);
+CREATE INDEX idx_auto_rule_priority ON auto_category_rule(priority);
+CREATE INDEX idx_sync_log_action ON sync_log(action, started_at);
+
+INSERT INTO upper_category (id, name, type, sort_order) VALUES
+  ('income', 'Income', 'income', 1),
+  ('expense', 'Expense', 'expense', 2),
+  ('saving', 'Saving', 'saving', 3),
+  ('bill', 'Bill', 'bill', 4),
+  ('debt', 'Debt', 'debt', 5),
+  ('transfer', 'Transfer', 'transfer', 6);
+
+INSERT INTO local_user (id, password_hash) VALUES ('user', '$2b$10$fixture');
+INSERT INTO sub_category (id, upper_category_id, name) VALUES ('groceries', 'expense', 'Groceries');
+INSERT INTO "transaction" (id, date, description, amount, sub_category_id) VALUES
+  ('t1', '2024-01-05', 'WOOLWORTHS 1234', -82.4, 'groceries'),
+  ('t2', '2024-01-06', 'SALARY', 3200, NULL);
+INSERT INTO budget (id, sub_category_id, year, month, amount) VALUES ('b1', 'groceries', 2024, 1, 600);
+INSERT INTO auto_category_rule (id, match_text, sub_category_id) VALUES ('r1', 'WOOLWORTHS', 'groceries');
@@ -0,0 +1,126 @@
+-- Database last migrated by the webview to schema version 3: sources,
+-- Sinking Funds and net worth, but no import batches, notes or is_active
+PRAGMA foreign_keys=OFF;
+CREATE TABLE auto_category_rule (
+  id TEXT PRIMARY KEY,
+  match_text TEXT NOT NULL,
+  sub_category_id TEXT NOT NULL REFERENCES sub_category(id),
+  priority INTEGER NOT NULL DEFAULT 0,
+  is_active INTEGER NOT NULL DEFAULT 1,
+  match_count INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+INSERT INTO "auto_category_rule" VALUES('r1','WOOLWORTHS','groceries',0,1,0,'2024-02-01 09:00:00','2024-02-01 09:00:00');
+CREATE TABLE budget (
+  id TEXT PRIMARY KEY,
+  sub_category_id TEXT NOT NULL REFERENCES sub_category(id),
+  year INTEGER NOT NULL,
+  month INTEGER NOT NULL CHECK (month >= 1 AND month <= 12),
+  amount REAL NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+  UNIQUE(sub_category_id, year, month)
+);
+INSERT INTO "budget" VALUES('b1','groceries',2024,1,600.0,'2024-02-01 09:00:00','2024-02-01 09:00:00');
+CREATE TABLE budget_template (
+  id TEXT PRIMARY KEY,
+  name TEXT NOT NULL,
+  template_data TEXT NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+CREATE TABLE local_user (
+  id TEXT PRIMARY KEY,
+  password_hash TEXT NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+INSERT INTO "local_user" VALUES('user','$2b$10$fixture','2024-02-01 09:00:00','2024-02-01 09:00:00');
+CREATE TABLE net_worth_entry (
+  id TEXT PRIMARY KEY,
+  recorded_at TEXT NOT NULL,
+  assets_data TEXT NOT NULL,
+  liabilities_data TEXT NOT NULL,
+  total_assets REAL NOT NULL,
+  total_liabilities REAL NOT NULL,
+  net_worth REAL NOT NULL,
+  notes TEXT,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+INSERT INTO "net_worth_entry" VALUES('n1','2024-02-01','[]','[]',1000.0,0.0,1000.0,NULL,'2024-02-01 09:00:00','2024-02-01 09:00:00');
+CREATE TABLE schema_version (
+  id INTEGER PRIMARY KEY CHECK (id = 1),
+  version INTEGER NOT NULL DEFAULT 0,
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+INSERT INTO "schema_version" VALUES(1,3,'2024-02-01 09:00:00');
+CREATE TABLE source (
+  id TEXT PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE,
+  sort_order INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+INSERT INTO "source" VALUES('bendigo','Bendigo',0,'2024-02-01 09:00:00','2024-02-01 09:00:00');
+CREATE TABLE sub_category (
+  id TEXT PRIMARY KEY,
+  upper_category_id TEXT NOT NULL REFERENCES upper_category(id),
+  name TEXT NOT NULL,
+  sort_order INTEGER NOT NULL DEFAULT 0,
+  is_deleted INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+INSERT INTO "sub_category" VALUES('groceries','expense','Groceries',0,0,'2024-02-01 09:00:00','2024-02-01 09:00:00');
+CREATE TABLE sync_log (
+  id TEXT PRIMARY KEY,
+  action TEXT NOT NULL CHECK (action IN ('push', 'pull')),
+  status TEXT NOT NULL CHECK (status IN ('success', 'failed', 'in_progress')),
+  file_name TEXT,
+  file_size INTEGER,
+  error_message TEXT,
+  started_at TEXT NOT NULL DEFAULT (datetime('now')),
+  completed_at TEXT
+);
+CREATE TABLE "transaction" (
+  id TEXT PRIMARY KEY,
+  date TEXT NOT NULL,
+  description TEXT NOT NULL,
+  amount REAL NOT NULL,
+  notes TEXT,
+  sub_category_id TEXT REFERENCES sub_category(id),
+  is_split INTEGER NOT NULL DEFAULT 0,
+  parent_transaction_id TEXT REFERENCES "transaction"(id),
+  is_deleted INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+, source_id TEXT REFERENCES source(id));
+INSERT INTO "transaction" VALUES('t1','2024-01-05','WOOLWORTHS 1234',-82.4,NULL,'groceries',0,NULL,0,'2024-02-01 09:00:00','2024-02-01 09:00:00','bendigo');
+INSERT INTO "transaction" VALUES('t2','2024-01-06','SALARY',3200.0,NULL,NULL,0,NULL,0,'2024-02-01 09:00:00','2024-02-01 09:00:00',NULL);
+CREATE TABLE "upper_category" (
+  id TEXT PRIMARY KEY,
+  name TEXT NOT NULL,
+  type TEXT NOT NULL CHECK (type IN ('income', 'expense', 'saving', 'bill', 'debt', 'sinking', 'transfer')),
+  sort_order INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+INSERT INTO "upper_category" VALUES('income','Income','income',1,'2024-02-01 09:00:00','2024-02-01 09:00:00');
+INSERT INTO "upper_category" VALUES('expense','Expense','expense',2,'2024-02-01 09:00:00','2024-02-01 09:00:00');
+INSERT INTO "upper_category" VALUES('saving','Saving','saving',3,'2024-02-01 09:00:00','2024-02-01 09:00:00');
+INSERT INTO "upper_category" VALUES('bill','Bill','bill',4,'2024-02-01 09:00:00','2024-02-01 09:00:00');
+INSERT INTO "upper_category" VALUES('debt','Debt','debt',5,'2024-02-01 09:00:00','2024-02-01 09:00:00');
+INSERT INTO "upper_category" VALUES('transfer','Transfer','transfer',7,'2024-02-01 09:00:00','2024-02-01 09:00:00');
+INSERT INTO "upper_category" VALUES('sinking','Sinking Funds','sinking',6,'2024-02-01T09:00:00.000Z','2024-02-01T09:00:00.000Z');
+CREATE INDEX idx_transaction_date ON "transaction"(date);
+CREATE INDEX idx_transaction_sub_category ON "transaction"(sub_category_id);
+CREATE INDEX idx_transaction_parent ON "transaction"(parent_transaction_id);
+CREATE INDEX idx_transaction_deleted ON "transaction"(is_deleted);
+CREATE INDEX idx_sub_category_upper ON sub_category(upper_category_id);
+CREATE INDEX idx_budget_period ON budget(year, month);
+CREATE INDEX idx_auto_rule_priority ON auto_category_rule(priority);
+CREATE INDEX idx_sync_log_action ON sync_log(action, started_at);
+CREATE INDEX idx_transaction_source ON "transaction"(source_id);
+CREATE INDEX idx_net_worth_recorded_at ON net_worth_entry(recorded_at);
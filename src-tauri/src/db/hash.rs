@@ -0,0 +1,313 @@
+//! Database hashes for sync change detection
+//!
+//! `File` mode streams `puffin.db` through SHA-256 after checkpointing the
+//! WAL, matching the hashes older versions computed in the webview.
+//! `Content` mode hashes the rows of the user-data tables instead, in
+//! primary-key order with columns sorted by name. Two databases holding the
+//! same data agree regardless of VACUUM state, column order or which device's
+//! PIN is in `local_user`.
+
+use super::{blocking, database_path, open, DbError};
+use rusqlite::types::ValueRef;
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Read buffer for file hashing
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Per-device tables left out of the content hash: auth data, the sync
+/// history and the TS migration marker
+const EXCLUDED_TABLES: [&str; 3] = ["local_user", "sync_log", "schema_version"];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashMode {
+    /// SHA-256 of the database file
+    #[default]
+    File,
+    /// SHA-256 of the user-data rows
+    Content,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct DatabaseHash {
+    pub mode: HashMode,
+    /// Lowercase hex SHA-256
+    pub hash: String,
+}
+
+fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// SHA-256 of a file, read in chunks
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Feed one value into the hasher, tagged with its storage class and
+/// length-prefixed so adjacent values can't run into each other
+fn hash_value(hasher: &mut Sha256, value: ValueRef<'_>) {
+    match value {
+        ValueRef::Null => hasher.update(b"n"),
+        ValueRef::Integer(i) => {
+            hasher.update(b"i");
+            hasher.update(i.to_le_bytes());
+        }
+        ValueRef::Real(r) => {
+            hasher.update(b"r");
+            hasher.update(r.to_bits().to_le_bytes());
+        }
+        ValueRef::Text(bytes) | ValueRef::Blob(bytes) => {
+            hasher.update(if matches!(value, ValueRef::Text(_)) {
+                b"t"
+            } else {
+                b"b"
+            });
+            hasher.update((bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        }
+    }
+}
+
+fn hash_str(hasher: &mut Sha256, s: &str) {
+    hash_value(hasher, ValueRef::Text(s.as_bytes()));
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Columns of `table` sorted by name, and its primary-key columns in key order
+fn table_columns(conn: &Connection, table: &str) -> Result<(Vec<String>, Vec<String>), DbError> {
+    let mut stmt = conn.prepare("SELECT name, pk FROM pragma_table_info(?1)")?;
+    let mut columns: Vec<(String, i64)> = stmt
+        .query_map([table], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    columns.sort_by_key(|(_, pk)| *pk);
+    let primary_key = columns
+        .iter()
+        .filter(|(_, pk)| *pk > 0)
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    let mut names: Vec<String> = columns.into_iter().map(|(name, _)| name).collect();
+    names.sort();
+    Ok((names, primary_key))
+}
+
+/// SHA-256 over the user-data tables: each table's name, column names and
+/// rows, tables by name and rows by primary key
+pub fn hash_content(conn: &Connection) -> Result<String, DbError> {
+    let tables: Vec<String> = conn
+        .prepare(
+            "SELECT name FROM sqlite_master \
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+
+    let mut hasher = Sha256::new();
+    for table in tables
+        .iter()
+        .filter(|t| !EXCLUDED_TABLES.contains(&t.as_str()))
+    {
+        let (columns, primary_key) = table_columns(conn, table)?;
+        let order = if primary_key.is_empty() {
+            "rowid".to_string()
+        } else {
+            primary_key
+                .iter()
+                .map(|c| quote(c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM {} ORDER BY {}",
+            columns
+                .iter()
+                .map(|c| quote(c))
+                .collect::<Vec<_>>()
+                .join(", "),
+            quote(table),
+            order
+        ))?;
+
+        hasher.update(b"T");
+        hash_str(&mut hasher, table);
+        for name in &columns {
+            hash_str(&mut hasher, name);
+        }
+
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            hasher.update(b"R");
+            for i in 0..columns.len() {
+                hash_value(&mut hasher, row.get_ref(i)?);
+            }
+        }
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
+
+fn hash_database_at(path: &Path, mode: HashMode) -> Result<DatabaseHash, DbError> {
+    let hash = match mode {
+        HashMode::File => {
+            // Fold the WAL into the main file so its bytes are current
+            let conn = open(path, false)?;
+            let busy: i64 =
+                conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))?;
+            if busy != 0 {
+                log::warn!("WAL checkpoint incomplete; file hash may lag behind");
+            }
+            drop(conn);
+            hash_file(path)?
+        }
+        HashMode::Content => hash_content(&open(path, true)?)?,
+    };
+    Ok(DatabaseHash { mode, hash })
+}
+
+/// Hash `puffin.db` without sending it to the webview
+#[tauri::command]
+pub async fn hash_database(
+    app: tauri::AppHandle,
+    mode: Option<HashMode>,
+) -> Result<DatabaseHash, DbError> {
+    let path = database_path(&app)?;
+    blocking(move || hash_database_at(&path, mode.unwrap_or_default())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create(path: &Path) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE note (id TEXT PRIMARY KEY, title TEXT, amount REAL, data BLOB);
+             INSERT INTO note VALUES ('a', 'Rent', 1200.5, NULL), ('b', 'Food', 80, x'00ff');",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn file_hash_matches_sha256_of_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            hash_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        // Larger than one chunk
+        let big = vec![7u8; CHUNK_SIZE * 3 + 5];
+        std::fs::write(&path, &big).unwrap();
+        assert_eq!(hash_file(&path).unwrap(), to_hex(&Sha256::digest(&big)));
+    }
+
+    #[test]
+    fn content_hash_ignores_page_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = create(&dir.path().join("first.db"));
+
+        // Same rows after churn and a VACUUM: different file, same content
+        let second = create(&dir.path().join("second.db"));
+        second
+            .execute_batch(
+                "CREATE TABLE scratch (x TEXT);
+                 INSERT INTO scratch SELECT hex(randomblob(500)) FROM note;
+                 DROP TABLE scratch;
+                 VACUUM;",
+            )
+            .unwrap();
+
+        assert_eq!(
+            hash_content(&first).unwrap(),
+            hash_content(&second).unwrap()
+        );
+
+        second
+            .execute("UPDATE note SET amount = 81 WHERE id = 'b'", [])
+            .unwrap();
+        assert_ne!(
+            hash_content(&first).unwrap(),
+            hash_content(&second).unwrap()
+        );
+    }
+
+    #[test]
+    fn content_hash_ignores_local_user_and_row_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = create(&dir.path().join("first.db"));
+        first
+            .execute_batch(
+                "CREATE TABLE local_user (id TEXT PRIMARY KEY, password_hash TEXT);
+                 INSERT INTO local_user VALUES ('u', 'pin-a');",
+            )
+            .unwrap();
+
+        // Same notes inserted in the opposite order, columns declared in a
+        // different order, another device's PIN
+        let second = Connection::open(dir.path().join("second.db")).unwrap();
+        second
+            .execute_batch(
+                "CREATE TABLE note (id TEXT PRIMARY KEY, data BLOB, amount REAL, title TEXT);
+                 INSERT INTO note (id, title, amount, data) VALUES ('b', 'Food', 80, x'00ff');
+                 INSERT INTO note (id, title, amount, data) VALUES ('a', 'Rent', 1200.5, NULL);
+                 CREATE TABLE local_user (id TEXT PRIMARY KEY, password_hash TEXT);
+                 INSERT INTO local_user VALUES ('u', 'pin-b');",
+            )
+            .unwrap();
+
+        assert_eq!(
+            hash_content(&first).unwrap(),
+            hash_content(&second).unwrap()
+        );
+    }
+
+    #[test]
+    fn values_of_different_types_hash_differently() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open(dir.path().join("types.db")).unwrap();
+        conn.execute_batch("CREATE TABLE t (v); INSERT INTO t VALUES (1);")
+            .unwrap();
+        let integer = hash_content(&conn).unwrap();
+        conn.execute("UPDATE t SET v = '1'", []).unwrap();
+        assert_ne!(integer, hash_content(&conn).unwrap());
+    }
+
+    #[test]
+    fn file_mode_checkpoints_before_hashing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wal.db");
+        let conn = create(&path);
+        conn.execute_batch("PRAGMA journal_mode = WAL; INSERT INTO note (id) VALUES ('c');")
+            .unwrap();
+
+        let hashed = hash_database_at(&path, HashMode::File).unwrap();
+        assert_eq!(hashed.hash, hash_file(&path).unwrap());
+        assert_eq!(
+            std::fs::metadata(dir.path().join("wal.db-wal"))
+                .map(|m| m.len())
+                .unwrap_or(0),
+            0
+        );
+    }
+}
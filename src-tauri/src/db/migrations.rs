@@ -0,0 +1,618 @@
+//! Versioned schema migrations
+//!
+//! `MIGRATIONS` is the ordered list of schema changes. The version a database
+//! has reached is kept in SQLite's `user_version` header field, and each step
+//! runs in its own transaction together with the version bump, so a failed
+//! step leaves the database at the previous version.
+//!
+//! Versions match the ones the webview's own migrations used to record in the
+//! `schema_version` table. A database with `user_version` 0 adopts that table's
+//! version, and the table is kept up to date so older builds and the web
+//! version still see the right number. Steps check for what they add, so
+//! databases whose `schema_version` row was never written (fresh installs made
+//! from `lib/db/schema.ts`) migrate cleanly from 0.
+
+use super::{app_data_dir, blocking, DbError, DB_FILE};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Transaction};
+use std::fs;
+use std::path::Path;
+
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    up: fn(&Transaction<'_>) -> rusqlite::Result<()>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial tables and transaction sources",
+        up: initial_tables_and_sources,
+    },
+    Migration {
+        version: 2,
+        description: "Sinking Funds category",
+        up: sinking_funds,
+    },
+    Migration {
+        version: 3,
+        description: "net worth entries",
+        up: net_worth,
+    },
+    Migration {
+        version: 4,
+        description: "import batches",
+        up: import_batches,
+    },
+    Migration {
+        version: 5,
+        description: "notes",
+        up: notes,
+    },
+    Migration {
+        version: 6,
+        description: "hideable upper categories",
+        up: upper_category_active,
+    },
+    Migration {
+        version: 7,
+        description: "bank transaction IDs",
+        up: external_ids,
+    },
+];
+
+/// Schema version a fully migrated database is at
+pub const LATEST_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// Tables from before schema versioning, with the default upper categories
+const INITIAL_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS local_user (
+  id TEXT PRIMARY KEY,
+  password_hash TEXT NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS upper_category (
+  id TEXT PRIMARY KEY,
+  name TEXT NOT NULL,
+  type TEXT NOT NULL CHECK (type IN ('income', 'expense', 'saving', 'bill', 'debt', 'transfer')),
+  sort_order INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS sub_category (
+  id TEXT PRIMARY KEY,
+  upper_category_id TEXT NOT NULL REFERENCES upper_category(id),
+  name TEXT NOT NULL,
+  sort_order INTEGER NOT NULL DEFAULT 0,
+  is_deleted INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS "transaction" (
+  id TEXT PRIMARY KEY,
+  date TEXT NOT NULL,
+  description TEXT NOT NULL,
+  amount REAL NOT NULL,
+  notes TEXT,
+  sub_category_id TEXT REFERENCES sub_category(id),
+  is_split INTEGER NOT NULL DEFAULT 0,
+  parent_transaction_id TEXT REFERENCES "transaction"(id),
+  is_deleted INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS budget (
+  id TEXT PRIMARY KEY,
+  sub_category_id TEXT NOT NULL REFERENCES sub_category(id),
+  year INTEGER NOT NULL,
+  month INTEGER NOT NULL CHECK (month >= 1 AND month <= 12),
+  amount REAL NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+  UNIQUE(sub_category_id, year, month)
+);
+
+CREATE TABLE IF NOT EXISTS budget_template (
+  id TEXT PRIMARY KEY,
+  name TEXT NOT NULL,
+  template_data TEXT NOT NULL,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS auto_category_rule (
+  id TEXT PRIMARY KEY,
+  match_text TEXT NOT NULL,
+  sub_category_id TEXT NOT NULL REFERENCES sub_category(id),
+  priority INTEGER NOT NULL DEFAULT 0,
+  is_active INTEGER NOT NULL DEFAULT 1,
+  match_count INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL DEFAULT (datetime('now')),
+  updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS sync_log (
+  id TEXT PRIMARY KEY,
+  action TEXT NOT NULL CHECK (action IN ('push', 'pull')),
+  status TEXT NOT NULL CHECK (status IN ('success', 'failed', 'in_progress')),
+  file_name TEXT,
+  file_size INTEGER,
+  error_message TEXT,
+  started_at TEXT NOT NULL DEFAULT (datetime('now')),
+  completed_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_date ON "transaction"(date);
+CREATE INDEX IF NOT EXISTS idx_transaction_sub_category ON "transaction"(sub_category_id);
+CREATE INDEX IF NOT EXISTS idx_transaction_parent ON "transaction"(parent_transaction_id);
+CREATE INDEX IF NOT EXISTS idx_transaction_deleted ON "transaction"(is_deleted);
+CREATE INDEX IF NOT EXISTS idx_sub_category_upper ON sub_category(upper_category_id);
+CREATE INDEX IF NOT EXISTS idx_budget_period ON budget(year, month);
+CREATE INDEX IF NOT EXISTS idx_auto_rule_priority ON auto_category_rule(priority);
+CREATE INDEX IF NOT EXISTS idx_sync_log_action ON sync_log(action, started_at);
+
+INSERT OR IGNORE INTO upper_category (id, name, type, sort_order) VALUES
+  ('income', 'Income', 'income', 1),
+  ('expense', 'Expense', 'expense', 2),
+  ('saving', 'Saving', 'saving', 3),
+  ('bill', 'Bill', 'bill', 4),
+  ('debt', 'Debt', 'debt', 5),
+  ('transfer', 'Transfer', 'transfer', 6);
+"#;
+
+fn has_column(tx: &Transaction<'_>, table: &str, column: &str) -> rusqlite::Result<bool> {
+    tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+        [table, column],
+        |row| row.get(0),
+    )
+}
+
+fn add_column(tx: &Transaction<'_>, table: &str, column: &str, def: &str) -> rusqlite::Result<()> {
+    if !has_column(tx, table, column)? {
+        tx.execute_batch(&format!(
+            "ALTER TABLE \"{}\" ADD COLUMN {} {}",
+            table, column, def
+        ))?;
+    }
+    Ok(())
+}
+
+fn initial_tables_and_sources(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+    tx.execute_batch(INITIAL_SQL)?;
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS source (
+           id TEXT PRIMARY KEY,
+           name TEXT NOT NULL UNIQUE,
+           sort_order INTEGER NOT NULL DEFAULT 0,
+           created_at TEXT NOT NULL DEFAULT (datetime('now')),
+           updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+         )",
+    )?;
+    add_column(tx, "transaction", "source_id", "TEXT REFERENCES source(id)")?;
+    tx.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_transaction_source ON \"transaction\"(source_id)",
+    )
+}
+
+/// Rebuild `upper_category` so its type CHECK allows 'sinking', and add the
+/// category before Transfer
+fn sinking_funds(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+    let exists: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM upper_category WHERE id = 'sinking')",
+        [],
+        |row| row.get(0),
+    )?;
+    if exists {
+        return Ok(());
+    }
+
+    // Keep is_active if the table somehow already has it
+    let (active_def, active_col) = if has_column(tx, "upper_category", "is_active")? {
+        ("is_active INTEGER NOT NULL DEFAULT 1,", "is_active, ")
+    } else {
+        ("", "")
+    };
+    tx.execute_batch(&format!(
+        "DROP TABLE IF EXISTS upper_category_new;
+         CREATE TABLE upper_category_new (
+           id TEXT PRIMARY KEY,
+           name TEXT NOT NULL,
+           type TEXT NOT NULL CHECK (type IN ('income', 'expense', 'saving', 'bill', 'debt', 'sinking', 'transfer')),
+           sort_order INTEGER NOT NULL DEFAULT 0,
+           {active_def}
+           created_at TEXT NOT NULL DEFAULT (datetime('now')),
+           updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+         );
+         INSERT INTO upper_category_new (id, name, type, sort_order, {active_col}created_at, updated_at)
+           SELECT id, name, type, sort_order, {active_col}created_at, updated_at FROM upper_category;
+         UPDATE upper_category_new SET sort_order = 7 WHERE id = 'transfer';
+         DROP TABLE upper_category;
+         ALTER TABLE upper_category_new RENAME TO upper_category;
+         INSERT INTO upper_category (id, name, type, sort_order)
+           VALUES ('sinking', 'Sinking Funds', 'sinking', 6);"
+    ))
+}
+
+fn net_worth(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS net_worth_entry (
+           id TEXT PRIMARY KEY,
+           recorded_at TEXT NOT NULL,
+           assets_data TEXT NOT NULL,
+           liabilities_data TEXT NOT NULL,
+           total_assets REAL NOT NULL,
+           total_liabilities REAL NOT NULL,
+           net_worth REAL NOT NULL,
+           notes TEXT,
+           created_at TEXT NOT NULL DEFAULT (datetime('now')),
+           updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+         );
+         CREATE INDEX IF NOT EXISTS idx_net_worth_recorded_at ON net_worth_entry(recorded_at);",
+    )
+}
+
+fn import_batches(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+    add_column(tx, "transaction", "import_batch_id", "TEXT")?;
+    tx.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_transaction_import_batch ON \"transaction\"(import_batch_id)",
+    )
+}
+
+fn notes(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS note (
+           id TEXT PRIMARY KEY,
+           title TEXT NOT NULL,
+           content TEXT,
+           tags TEXT,
+           is_deleted INTEGER NOT NULL DEFAULT 0,
+           created_at TEXT NOT NULL DEFAULT (datetime('now')),
+           updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+         );
+         CREATE INDEX IF NOT EXISTS idx_note_deleted ON note(is_deleted);
+         CREATE INDEX IF NOT EXISTS idx_note_updated ON note(updated_at);",
+    )
+}
+
+fn upper_category_active(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+    add_column(
+        tx,
+        "upper_category",
+        "is_active",
+        "INTEGER NOT NULL DEFAULT 1",
+    )
+}
+
+/// The bank's own ID for an imported transaction (an OFX `FITID`), used to
+/// spot it when the same statement is imported again
+fn external_ids(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+    add_column(tx, "transaction", "external_id", "TEXT")?;
+    tx.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_transaction_external_id ON \"transaction\"(external_id)",
+    )
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct MigrationReport {
+    /// Version before migrating
+    pub from: u32,
+    pub to: u32,
+    /// Versions of the steps that ran
+    pub applied: Vec<u32>,
+}
+
+/// Version a database is at: `user_version`, or for databases last migrated
+/// by the webview, the `schema_version` table
+pub fn schema_version(conn: &Connection) -> Result<u32, DbError> {
+    let user_version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if user_version > 0 {
+        return Ok(user_version);
+    }
+    let has_table: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_table {
+        return Ok(0);
+    }
+    Ok(conn
+        .query_row(
+            "SELECT version FROM schema_version WHERE id = 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?
+        .unwrap_or(0))
+}
+
+/// Record `version` in both `user_version` and the `schema_version` table
+fn set_version(tx: &Transaction<'_>, version: u32) -> rusqlite::Result<()> {
+    tx.pragma_update(None, "user_version", version)?;
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+           id INTEGER PRIMARY KEY CHECK (id = 1),
+           version INTEGER NOT NULL DEFAULT 0,
+           updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+         )",
+    )?;
+    tx.execute(
+        "INSERT INTO schema_version (id, version) VALUES (1, ?1)
+         ON CONFLICT(id) DO UPDATE SET version = excluded.version, updated_at = datetime('now')",
+        [version],
+    )?;
+    Ok(())
+}
+
+fn apply(conn: &mut Connection, migrations: &[Migration]) -> Result<MigrationReport, DbError> {
+    let from = schema_version(conn)?;
+    let latest = migrations.last().map_or(0, |m| m.version);
+    if from > latest {
+        return Err(DbError::Migration(format!(
+            "database schema version {} is newer than this version of Puffin supports ({})",
+            from, latest
+        )));
+    }
+
+    // Table rebuilds need foreign keys off, which can't change inside a
+    // transaction
+    conn.pragma_update(None, "foreign_keys", false)?;
+
+    let mut applied = Vec::new();
+    for migration in migrations.iter().filter(|m| m.version > from) {
+        let tx = conn.transaction()?;
+        (migration.up)(&tx)
+            .and_then(|()| set_version(&tx, migration.version))
+            .map_err(|e| {
+                DbError::Migration(format!(
+                    "migration {} ({}) failed: {}",
+                    migration.version, migration.description, e
+                ))
+            })?;
+        tx.commit()?;
+        applied.push(migration.version);
+    }
+
+    // Adopt a version only recorded in the schema_version table
+    let user_version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if applied.is_empty() && user_version != from {
+        let tx = conn.transaction()?;
+        set_version(&tx, from)?;
+        tx.commit()?;
+    }
+
+    Ok(MigrationReport {
+        from,
+        to: from.max(latest),
+        applied,
+    })
+}
+
+/// Bring the schema of `conn` up to `LATEST_VERSION`
+pub fn migrate(conn: &mut Connection) -> Result<MigrationReport, DbError> {
+    apply(conn, MIGRATIONS)
+}
+
+/// Open (creating if needed) and migrate the database at `path`
+pub fn migrate_file(path: &Path) -> Result<MigrationReport, DbError> {
+    let mut conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_WRITE
+            | OpenFlags::SQLITE_OPEN_CREATE
+            | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    conn.busy_timeout(std::time::Duration::from_secs(5))?;
+    migrate(&mut conn)
+}
+
+/// Create `puffin.db` if needed and bring its schema up to date. The webview
+/// runs this before connecting.
+#[tauri::command]
+pub async fn migrate_database(app: tauri::AppHandle) -> Result<MigrationReport, DbError> {
+    let dir = app_data_dir(&app)?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(DB_FILE);
+    blocking(move || migrate_file(&path)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: [(&str, &str); 3] = [
+        ("v0", include_str!("fixtures/v0.sql")),
+        ("v3", include_str!("fixtures/v3.sql")),
+        (
+            "v6-unversioned",
+            include_str!("fixtures/v6-unversioned.sql"),
+        ),
+    ];
+
+    /// Columns and indexes, sorted so column order doesn't matter
+    fn layout(conn: &Connection) -> Vec<String> {
+        let mut layout: Vec<String> = conn
+            .prepare(
+                "SELECT m.name, c.name, c.type, c.\"notnull\", c.dflt_value, c.pk
+                 FROM sqlite_master m, pragma_table_info(m.name) c
+                 WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%'",
+            )
+            .unwrap()
+            .query_map([], |row| {
+                Ok(format!(
+                    "{}.{} {} notnull={} default={:?} pk={}",
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, i64>(5)?
+                ))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let indexes: Vec<String> = conn
+            .prepare(
+                "SELECT 'index ' || name || ' on ' || tbl_name FROM sqlite_master
+                 WHERE type = 'index' AND name NOT LIKE 'sqlite_%'",
+            )
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        layout.extend(indexes);
+        layout.sort();
+        layout
+    }
+
+    fn fresh() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn empty_database_gets_current_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let report = migrate(&mut conn).unwrap();
+        assert_eq!(report.from, 0);
+        assert_eq!(report.to, LATEST_VERSION);
+        assert_eq!(report.applied, (1..=LATEST_VERSION).collect::<Vec<_>>());
+        assert_eq!(schema_version(&conn).unwrap(), LATEST_VERSION);
+
+        // Matches what the webview creates from lib/db/schema.ts: the v6
+        // schema, plus what it has gained since
+        let schema_ts = Connection::open_in_memory().unwrap();
+        schema_ts.execute_batch(FIXTURES[2].1).unwrap();
+        schema_ts
+            .execute_batch(
+                "ALTER TABLE \"transaction\" ADD COLUMN external_id TEXT;
+                 CREATE INDEX idx_transaction_external_id ON \"transaction\"(external_id);
+                 CREATE TABLE schema_version (
+                   id INTEGER PRIMARY KEY CHECK (id = 1),
+                   version INTEGER NOT NULL DEFAULT 0,
+                   updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                 )",
+            )
+            .unwrap();
+        assert_eq!(layout(&conn), layout(&schema_ts));
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM upper_category"), 7);
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT sort_order FROM upper_category WHERE id = 'transfer'"
+            ),
+            7
+        );
+    }
+
+    #[test]
+    fn fixtures_from_older_releases_migrate_to_current_schema() {
+        let expected = layout(&fresh());
+        for (name, sql) in FIXTURES {
+            let mut conn = Connection::open_in_memory().unwrap();
+            conn.execute_batch(sql).unwrap();
+            let before = count(&conn, "SELECT COUNT(*) FROM \"transaction\"")
+                + count(&conn, "SELECT COUNT(*) FROM sub_category");
+
+            let report = migrate(&mut conn).unwrap();
+            assert_eq!(report.to, LATEST_VERSION, "{}", name);
+            assert_eq!(layout(&conn), expected, "{}", name);
+            assert_eq!(schema_version(&conn).unwrap(), LATEST_VERSION, "{}", name);
+            assert_eq!(
+                count(&conn, "SELECT version FROM schema_version WHERE id = 1"),
+                LATEST_VERSION as i64,
+                "{}",
+                name
+            );
+
+            // Data survives, and every release ends up with the same categories
+            assert_eq!(
+                count(&conn, "SELECT COUNT(*) FROM \"transaction\"")
+                    + count(&conn, "SELECT COUNT(*) FROM sub_category"),
+                before,
+                "{}",
+                name
+            );
+            assert_eq!(
+                count(
+                    &conn,
+                    "SELECT COUNT(*) FROM upper_category WHERE is_active = 1"
+                ),
+                7,
+                "{}",
+                name
+            );
+            assert_eq!(
+                count(&conn, "SELECT COUNT(*) FROM pragma_foreign_key_check"),
+                0,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn webview_version_is_adopted() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(FIXTURES[1].1).unwrap();
+        let report = migrate(&mut conn).unwrap();
+        assert_eq!(report.from, 3);
+        assert_eq!(report.applied, vec![4, 5, 6, 7]);
+
+        // Already current: nothing runs
+        let report = migrate(&mut conn).unwrap();
+        assert_eq!(report.from, LATEST_VERSION);
+        assert!(report.applied.is_empty());
+    }
+
+    #[test]
+    fn failed_step_rolls_back_to_previous_version() {
+        fn broken(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+            tx.execute_batch("CREATE TABLE half_done (id TEXT); SELECT * FROM missing;")
+        }
+        let steps = [
+            Migration {
+                version: 1,
+                description: "initial",
+                up: initial_tables_and_sources,
+            },
+            Migration {
+                version: 2,
+                description: "broken",
+                up: broken,
+            },
+        ];
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        let err = apply(&mut conn, &steps).unwrap_err();
+        assert!(matches!(err, DbError::Migration(ref msg) if msg.contains("migration 2")));
+        assert_eq!(schema_version(&conn).unwrap(), 1);
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'half_done'"
+            ),
+            0
+        );
+    }
+
+    #[test]
+    fn newer_database_is_rejected() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", LATEST_VERSION + 1)
+            .unwrap();
+        assert!(matches!(migrate(&mut conn), Err(DbError::Migration(_))));
+    }
+}
@@ -0,0 +1,215 @@
+//! Native access to the Puffin database
+//!
+//! The webview reads and writes `puffin.db` through tauri-plugin-sql. The
+//! commands in this module open their own short-lived rusqlite connections
+//! for work that would otherwise copy the whole file across IPC.
+
+pub mod backup;
+pub mod check;
+pub mod hash;
+pub mod migrations;
+pub mod restore;
+pub mod schedule;
+pub mod snapshot;
+
+use rusqlite::{Connection, OpenFlags};
+use std::fmt;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use tauri::Manager;
+
+/// Database file name inside the app data directory (see `lib/services/tauri-db.ts`)
+pub const DB_FILE: &str = "puffin.db";
+
+/// Database command failure, serialized as `{ kind, message }`
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum DbError {
+    Io(String),
+    Sqlite(String),
+    /// The database file doesn't exist yet
+    NotFound(String),
+    /// `PRAGMA integrity_check` reported problems
+    Integrity(String),
+    /// The command may not write to this path
+    InvalidDestination(String),
+    /// A schema migration failed, or the schema is newer than this build
+    Migration(String),
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::Io(msg) => write!(f, "Database I/O error: {}", msg),
+            DbError::Sqlite(msg) => write!(f, "SQLite error: {}", msg),
+            DbError::NotFound(path) => write!(f, "Database not found: {}", path),
+            DbError::Integrity(msg) => write!(f, "Database integrity check failed: {}", msg),
+            DbError::InvalidDestination(msg) => write!(f, "Invalid destination: {}", msg),
+            DbError::Migration(msg) => write!(f, "Database migration failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for DbError {}
+
+impl From<io::Error> for DbError {
+    fn from(e: io::Error) -> Self {
+        DbError::Io(e.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for DbError {
+    fn from(e: rusqlite::Error) -> Self {
+        DbError::Sqlite(e.to_string())
+    }
+}
+
+pub fn app_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, DbError> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| DbError::Io(e.to_string()))
+}
+
+/// Path of `puffin.db`; fails if the database hasn't been created yet
+pub fn database_path(app: &tauri::AppHandle) -> Result<PathBuf, DbError> {
+    let path = app_data_dir(app)?.join(DB_FILE);
+    if !path.is_file() {
+        return Err(DbError::NotFound(path.display().to_string()));
+    }
+    Ok(path)
+}
+
+/// Open an existing database without creating it
+pub fn open(path: &Path, read_only: bool) -> Result<Connection, DbError> {
+    let mode = if read_only {
+        OpenFlags::SQLITE_OPEN_READ_ONLY
+    } else {
+        OpenFlags::SQLITE_OPEN_READ_WRITE
+    };
+    let conn = Connection::open_with_flags(path, mode | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+    // The webview's connection pool may hold locks briefly
+    conn.busy_timeout(std::time::Duration::from_secs(5))?;
+    Ok(conn)
+}
+
+/// Commands that write database files only write inside `dir` (the app data
+/// directory), whatever path the webview passes
+pub fn ensure_within(dir: &Path, dest: &Path) -> Result<(), DbError> {
+    let parent = dest
+        .parent()
+        .and_then(|p| p.canonicalize().ok())
+        .ok_or_else(|| DbError::InvalidDestination(dest.display().to_string()))?;
+    check_inside(dir, &parent, dest)
+}
+
+/// `ensure_within` for a `dest` whose parent directories are created
+/// afterwards: the closest existing ancestor must be inside `dir`, and the
+/// missing part may only name new directories (no `..`)
+pub fn ensure_creatable_within(dir: &Path, dest: &Path) -> Result<(), DbError> {
+    let invalid = || DbError::InvalidDestination(dest.display().to_string());
+    let parent = dest.parent().ok_or_else(invalid)?;
+    let mut existing = parent;
+    while !existing.exists() {
+        existing = existing.parent().ok_or_else(invalid)?;
+    }
+    let missing = parent.strip_prefix(existing).map_err(|_| invalid())?;
+    if missing
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(invalid());
+    }
+    check_inside(dir, &existing.canonicalize()?, dest)
+}
+
+fn check_inside(dir: &Path, parent: &Path, dest: &Path) -> Result<(), DbError> {
+    if !parent.starts_with(dir.canonicalize()?) {
+        return Err(DbError::InvalidDestination(format!(
+            "{} is outside the app data directory",
+            dest.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Problems reported by `PRAGMA integrity_check`; empty when it reports "ok"
+pub fn integrity_problems(conn: &Connection) -> Result<Vec<String>, DbError> {
+    let problems: Vec<String> = conn
+        .prepare("PRAGMA integrity_check")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    if problems.len() == 1 && problems[0] == "ok" {
+        return Ok(Vec::new());
+    }
+    Ok(problems)
+}
+
+/// Fail unless `PRAGMA integrity_check` reports "ok"
+pub fn integrity_check(conn: &Connection) -> Result<(), DbError> {
+    let problems = integrity_problems(conn)?;
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(DbError::Integrity(problems.join("; ")))
+}
+
+/// Run blocking database work off the async runtime
+pub async fn blocking<T, F>(work: F) -> Result<T, DbError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, DbError> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|e| DbError::Io(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn writes_stay_inside_the_data_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("data")).unwrap();
+        let data = dir.path().join("data");
+
+        assert!(ensure_within(&data, &data.join(DB_FILE)).is_ok());
+        assert!(matches!(
+            ensure_within(&data, &data.join("../puffin.db")),
+            Err(DbError::InvalidDestination(_))
+        ));
+        assert!(matches!(
+            ensure_within(&data, &data.join("missing/puffin.db")),
+            Err(DbError::InvalidDestination(_))
+        ));
+    }
+
+    #[test]
+    fn new_directories_stay_inside_the_data_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("data")).unwrap();
+        let data = dir.path().join("data");
+
+        assert!(ensure_creatable_within(&data, &data.join("snapshots/a/puffin.db")).is_ok());
+        assert!(matches!(
+            ensure_creatable_within(&data, &data.join("missing/../../puffin.db")),
+            Err(DbError::InvalidDestination(_))
+        ));
+        assert!(matches!(
+            ensure_creatable_within(&data, &dir.path().join("elsewhere/puffin.db")),
+            Err(DbError::InvalidDestination(_))
+        ));
+        // Nothing was created by the checks
+        assert!(!data.join("snapshots").exists());
+    }
+
+    #[test]
+    fn integrity_check_passes_healthy_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE t (id TEXT PRIMARY KEY); INSERT INTO t VALUES ('a');")
+            .unwrap();
+        assert!(integrity_check(&conn).is_ok());
+    }
+}
@@ -0,0 +1,343 @@
+//! Restoring a backup over the live database
+//!
+//! The backup (an encrypted container or a plain SQLite file) is extracted
+//! next to `puffin.db` and checked before anything is replaced: it must pass
+//! `PRAGMA integrity_check`, contain Puffin's core tables, and carry a schema
+//! version `migrations` can bring up to date. A safety snapshot of
+//! the current database is written to `backups/pre-restore-<time>.db`.
+//!
+//! For the swap the SQL plugin's connection pools are closed and kept out.
+//! The current database and its WAL files are moved aside, the checked copy is
+//! renamed into place and opened once more; if any step fails the old files
+//! are moved back. The webview reconnects afterwards
+//! (`resetDatabaseConnection` in `lib/services/tauri-db.ts`).
+
+use super::backup::{extract_backup_to, resolve_password, BackupError, BackupFormat};
+use super::hash::hash_content;
+use super::migrations::{schema_version, LATEST_VERSION};
+use super::schedule::{file_stamp, unix_now, BACKUPS_DIR};
+use super::snapshot::snapshot_to;
+use super::{app_data_dir, integrity_check, open, DB_FILE};
+use crate::vault::Vault;
+use rusqlite::Connection;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+use tauri_plugin_sql::{DbInstances, DbPool};
+
+/// Tables every Puffin database has had since the first release
+const REQUIRED_TABLES: [&str; 3] = ["upper_category", "sub_category", "transaction"];
+
+/// The database file and the WAL files SQLite keeps beside it
+const DB_FILES: [&str; 3] = ["", "-wal", "-shm"];
+
+#[derive(Debug, serde::Serialize)]
+pub struct RestoreInfo {
+    pub format: BackupFormat,
+    /// Schema version of the restored database, before migrations run
+    pub schema_version: u32,
+    pub content_hash: String,
+    /// Snapshot of the replaced database, if there was one
+    pub safety_snapshot: Option<String>,
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool, BackupError> {
+    Ok(conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+        [table],
+        |row| row.get(0),
+    )?)
+}
+
+/// Schema version of a Puffin database. Fails for other databases and for
+/// schemas newer than this build can migrate.
+fn check_schema(conn: &Connection) -> Result<u32, BackupError> {
+    for table in REQUIRED_TABLES {
+        if !table_exists(conn, table)? {
+            return Err(BackupError::IncompatibleSchema(format!(
+                "missing table {}",
+                table
+            )));
+        }
+    }
+
+    let version = schema_version(conn)?;
+    if version > LATEST_VERSION {
+        return Err(BackupError::IncompatibleSchema(format!(
+            "schema version {} is newer than this version of Puffin supports ({})",
+            version, LATEST_VERSION
+        )));
+    }
+    Ok(version)
+}
+
+/// Extract `src` to `staged` and check it; returns the backup format, schema
+/// version and content hash
+fn stage(
+    src: &Path,
+    staged: &Path,
+    password: Option<&str>,
+) -> Result<(BackupFormat, u32, String), BackupError> {
+    let format = extract_backup_to(src, staged, password)?.format;
+
+    let conn = Connection::open(staged)?;
+    // Plain `.db` backups may still be in WAL mode; their WAL isn't copied
+    conn.pragma_update(None, "journal_mode", "DELETE")?;
+    integrity_check(&conn)?;
+    let version = check_schema(&conn)?;
+    Ok((format, version, hash_content(&conn)?))
+}
+
+/// Put the database files moved aside by `swap` back in place
+fn roll_back(db_path: &Path, rollback: &Path, moved: &[&str], swapped: bool) {
+    if swapped {
+        let _ = fs::remove_file(db_path);
+    }
+    for suffix in moved {
+        if let Err(e) = fs::rename(with_suffix(rollback, suffix), with_suffix(db_path, suffix)) {
+            log::error!("Failed to roll back {}{}: {}", db_path.display(), suffix, e);
+        }
+    }
+}
+
+/// Replace `db_path` (and its WAL files) with `staged`, restoring the old
+/// files if the new database can't be put in place and opened
+fn swap(staged: &Path, db_path: &Path) -> Result<(), BackupError> {
+    let rollback = with_suffix(db_path, ".rollback");
+    for suffix in DB_FILES {
+        let _ = fs::remove_file(with_suffix(&rollback, suffix));
+    }
+
+    let mut moved = Vec::new();
+    for suffix in DB_FILES {
+        let current = with_suffix(db_path, suffix);
+        if !current.exists() {
+            continue;
+        }
+        if let Err(e) = fs::rename(&current, with_suffix(&rollback, suffix)) {
+            roll_back(db_path, &rollback, &moved, false);
+            return Err(e.into());
+        }
+        moved.push(suffix);
+    }
+
+    if let Err(e) = fs::rename(staged, db_path) {
+        roll_back(db_path, &rollback, &moved, false);
+        return Err(e.into());
+    }
+    if let Err(e) = open(db_path, true).and_then(|conn| integrity_check(&conn)) {
+        roll_back(db_path, &rollback, &moved, true);
+        return Err(e.into());
+    }
+
+    for suffix in moved {
+        let _ = fs::remove_file(with_suffix(&rollback, suffix));
+    }
+    Ok(())
+}
+
+fn staged_path(data_dir: &Path) -> PathBuf {
+    with_suffix(&data_dir.join(DB_FILE), ".restore")
+}
+
+/// Stage and check the backup at `src` and snapshot the current database.
+/// Nothing is replaced until `commit`.
+pub fn prepare(
+    src: &Path,
+    data_dir: &Path,
+    password: Option<&str>,
+    now: i64,
+) -> Result<RestoreInfo, BackupError> {
+    let db_path = data_dir.join(DB_FILE);
+    let staged = staged_path(data_dir);
+
+    let result = (|| {
+        let (format, schema_version, content_hash) = stage(src, &staged, password)?;
+        let safety_snapshot = if db_path.is_file() {
+            let dir = data_dir.join(BACKUPS_DIR);
+            fs::create_dir_all(&dir)?;
+            let dest = dir.join(format!("pre-restore-{}.db", file_stamp(now)));
+            Some(snapshot_to(&db_path, &dest)?.path)
+        } else {
+            None
+        };
+        Ok(RestoreInfo {
+            format,
+            schema_version,
+            content_hash,
+            safety_snapshot,
+        })
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+    result
+}
+
+/// Swap the prepared database in. Nothing may hold `puffin.db` open.
+pub fn commit(data_dir: &Path) -> Result<(), BackupError> {
+    let staged = staged_path(data_dir);
+    let result = swap(&staged, &data_dir.join(DB_FILE));
+    if result.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+    result
+}
+
+/// Replace `puffin.db` with the database in backup `path`. Without
+/// `password` the backup password from the vault is used for encrypted
+/// backups. The webview must reconnect to the database afterwards.
+#[tauri::command]
+pub async fn restore_backup(
+    app: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    path: String,
+    password: Option<String>,
+) -> Result<RestoreInfo, BackupError> {
+    let password = resolve_password(&vault, password);
+    let data_dir = app_data_dir(&app)?;
+
+    let dir = data_dir.clone();
+    let info = tauri::async_runtime::spawn_blocking(move || {
+        prepare(Path::new(&path), &dir, password.as_deref(), unix_now())
+    })
+    .await
+    .map_err(|e| BackupError::Io(e.to_string()))??;
+
+    // Close the SQL plugin's pools and hold the map until the swap is done so
+    // the webview can't reconnect halfway through
+    let instances = app.try_state::<DbInstances>();
+    let mut pools = match &instances {
+        Some(instances) => Some(instances.0.write().await),
+        None => None,
+    };
+    for (_, pool) in pools.iter_mut().flat_map(|pools| pools.drain()) {
+        let DbPool::Sqlite(pool) = pool;
+        pool.close().await;
+    }
+
+    let result = tauri::async_runtime::spawn_blocking(move || commit(&data_dir))
+        .await
+        .map_err(|e| BackupError::Io(e.to_string()))?;
+    drop(pools);
+    result.map(|()| info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::backup::{write_backup, KdfParams};
+
+    const TEST_KDF: KdfParams = KdfParams {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+    /// 2024-03-09 14:05:00 UTC
+    const NOW: i64 = 1_709_993_100;
+
+    fn create_db(path: &Path, version: u32, note: &str) {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE upper_category (id TEXT PRIMARY KEY);
+             CREATE TABLE sub_category (id TEXT PRIMARY KEY);
+             CREATE TABLE \"transaction\" (id TEXT PRIMARY KEY, description TEXT);
+             CREATE TABLE schema_version (id INTEGER PRIMARY KEY CHECK (id = 1), version INTEGER);",
+        )
+        .unwrap();
+        conn.execute("INSERT INTO schema_version VALUES (1, ?1)", [version])
+            .unwrap();
+        conn.execute("INSERT INTO \"transaction\" VALUES ('t', ?1)", [note])
+            .unwrap();
+    }
+
+    fn description(path: &Path) -> String {
+        Connection::open(path)
+            .unwrap()
+            .query_row("SELECT description FROM \"transaction\"", [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn restores_encrypted_backup_with_safety_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join(DB_FILE);
+        create_db(&db_path, LATEST_VERSION, "current");
+        fs::write(with_suffix(&db_path, "-shm"), b"stale").unwrap();
+
+        let source = dir.path().join("source.db");
+        create_db(&source, 4, "restored");
+        let backup = dir.path().join("weekly.puffinbak");
+        write_backup(&source, &backup, "secret", TEST_KDF).unwrap();
+
+        let info = prepare(&backup, dir.path(), Some("secret"), NOW).unwrap();
+        // Still the current database until committed
+        assert_eq!(description(&db_path), "current");
+        commit(dir.path()).unwrap();
+
+        assert_eq!(info.format, BackupFormat::Encrypted);
+        assert_eq!(info.schema_version, 4);
+        assert_eq!(description(&db_path), "restored");
+        assert!(!with_suffix(&db_path, "-shm").exists());
+        assert!(!staged_path(dir.path()).exists());
+        assert!(!with_suffix(&db_path, ".rollback").exists());
+
+        let snapshot = info.safety_snapshot.unwrap();
+        assert!(snapshot.ends_with("pre-restore-2024-03-09_14-05-00.db"));
+        assert_eq!(description(Path::new(&snapshot)), "current");
+    }
+
+    #[test]
+    fn rejects_newer_or_foreign_schema_without_touching_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join(DB_FILE);
+        create_db(&db_path, LATEST_VERSION, "current");
+
+        let newer = dir.path().join("newer.db");
+        create_db(&newer, LATEST_VERSION + 1, "newer");
+        let foreign = dir.path().join("foreign.db");
+        Connection::open(&foreign)
+            .unwrap()
+            .execute_batch("CREATE TABLE other (id TEXT);")
+            .unwrap();
+
+        for src in [&newer, &foreign] {
+            let result = prepare(src, dir.path(), None, NOW);
+            assert!(matches!(result, Err(BackupError::IncompatibleSchema(_))));
+        }
+        assert!(matches!(
+            prepare(&dir.path().join("missing.db"), dir.path(), None, NOW),
+            Err(BackupError::Io(_))
+        ));
+
+        assert_eq!(description(&db_path), "current");
+        assert!(!staged_path(dir.path()).exists());
+        assert!(!dir.path().join(BACKUPS_DIR).exists());
+    }
+
+    #[test]
+    fn failed_swap_rolls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join(DB_FILE);
+        create_db(&db_path, LATEST_VERSION, "current");
+        fs::write(with_suffix(&db_path, "-wal"), b"").unwrap();
+
+        // Not a database, so opening it after the rename fails
+        let staged = dir.path().join("staged");
+        fs::write(&staged, vec![0u8; 4096]).unwrap();
+
+        assert!(swap(&staged, &db_path).is_err());
+        assert_eq!(description(&db_path), "current");
+        assert!(with_suffix(&db_path, "-wal").exists());
+        assert!(!with_suffix(&db_path, ".rollback").exists());
+    }
+}
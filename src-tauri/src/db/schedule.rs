@@ -0,0 +1,594 @@
+//! Scheduled local backups
+//!
+//! A background thread started from `setup` snapshots `puffin.db` into the
+//! `backups` directory on launch, once a day, and after a number of
+//! transactions have been added or edited, depending on the settings in
+//! `auto-backup.json`. Automatic backups are named `auto-<UTC time>.puffinbak`
+//! so they show up next to the manual ones; only those files are pruned
+//! afterwards, to a grandfather-father-son set holding the newest backup of
+//! each of the last few days, weeks and months.
+//!
+//! Like manual backups, they are encrypted with the backup password from the
+//! vault. Until one is set they are plain `auto-<UTC time>.db` snapshots, so
+//! a fresh install is still covered; those are pruned the same way and age out
+//! once encrypted backups take over.
+//!
+//! Each run ends with an `auto-backup://completed` or `auto-backup://failed`
+//! event. Nothing runs until the frontend has created the database.
+
+use super::backup::{write_backup, KdfParams, BACKUP_EXTENSION, PASSWORD_ENTRY};
+use super::hash::hash_file;
+use super::snapshot::{snapshot_to, SnapshotInfo};
+use super::{app_data_dir, database_path, open, DbError};
+use crate::vault::Vault;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const EVENT_COMPLETED: &str = "auto-backup://completed";
+pub const EVENT_FAILED: &str = "auto-backup://failed";
+
+/// Settings file in the app data directory
+const SETTINGS_FILE: &str = "auto-backup.json";
+/// Shared with manual and safety backups (see `handleBackups` in `lib/services/handlers/data.ts`)
+pub(crate) const BACKUPS_DIR: &str = "backups";
+const FILE_PREFIX: &str = "auto-";
+/// How often the thread checks whether a backup is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ScheduleSettings {
+    pub enabled: bool,
+    /// Back up when the app starts
+    pub on_launch: bool,
+    /// Back up when the last automatic backup is a day old
+    pub daily: bool,
+    /// Back up once this many transactions were added or edited since the
+    /// last automatic backup; 0 turns this off
+    pub change_threshold: u32,
+    /// Days, weeks and months to keep a backup for
+    pub keep_daily: u32,
+    pub keep_weekly: u32,
+    pub keep_monthly: u32,
+}
+
+impl Default for ScheduleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            on_launch: true,
+            daily: true,
+            change_threshold: 100,
+            keep_daily: 7,
+            keep_weekly: 4,
+            keep_monthly: 6,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    Launch,
+    Daily,
+    Changes,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct BackupCompleted {
+    pub trigger: Trigger,
+    pub snapshot: SnapshotInfo,
+    /// Older automatic backups removed by the retention policy
+    pub removed: Vec<String>,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct BackupFailed {
+    pub trigger: Trigger,
+    pub error: DbError,
+}
+
+/// Schedule settings managed as Tauri state
+pub struct BackupSchedule {
+    path: PathBuf,
+    settings: Mutex<ScheduleSettings>,
+}
+
+impl BackupSchedule {
+    /// Load the settings in `dir`, falling back to the defaults when the file
+    /// is missing or unreadable
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join(SETTINGS_FILE);
+        let settings = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                log::error!("Invalid {}: {}; using defaults", SETTINGS_FILE, e);
+                ScheduleSettings::default()
+            }),
+            Err(_) => ScheduleSettings::default(),
+        };
+        Self {
+            path,
+            settings: Mutex::new(settings),
+        }
+    }
+
+    pub fn settings(&self) -> ScheduleSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update(&self, settings: ScheduleSettings) -> Result<(), DbError> {
+        let json = serde_json::to_vec_pretty(&settings).map_err(|e| DbError::Io(e.to_string()))?;
+        fs::write(&self.path, json)?;
+        *self.settings.lock().unwrap() = settings;
+        Ok(())
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Inverse of `days_from_civil`: (year, month, day)
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// `2024-03-09_14-05-00` (UTC) for a Unix time, for backup file names
+pub(crate) fn file_stamp(secs: i64) -> String {
+    let (year, month, day) = civil_from_days(secs.div_euclid(DAY_SECS));
+    let time = secs.rem_euclid(DAY_SECS);
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// `auto-2024-03-09_14-05-00.puffinbak` (or `.db` unencrypted) for a Unix time
+fn backup_file_name(secs: i64, encrypted: bool) -> String {
+    let extension = if encrypted { BACKUP_EXTENSION } else { "db" };
+    format!("{}{}.{}", FILE_PREFIX, file_stamp(secs), extension)
+}
+
+/// Unix time of an automatic backup from its file name
+fn parse_file_name(name: &str) -> Option<i64> {
+    let (stamp, extension) = name.strip_prefix(FILE_PREFIX)?.rsplit_once('.')?;
+    if extension != "db" && extension != BACKUP_EXTENSION {
+        return None;
+    }
+    let (date, time) = stamp.split_once('_')?;
+    let date: Vec<i64> = date
+        .split('-')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    let time: Vec<i64> = time
+        .split('-')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    match (date.as_slice(), time.as_slice()) {
+        ([y, mo, d], [h, mi, s]) if (1..=12).contains(mo) && (1..=31).contains(d) => {
+            Some(days_from_civil(*y, *mo, *d) * DAY_SECS + h * 3600 + mi * 60 + s)
+        }
+        _ => None,
+    }
+}
+
+/// Automatic backups in `dir` with their Unix times, newest first
+fn list_backups(dir: &Path) -> io::Result<Vec<(PathBuf, i64)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if let Some(secs) = entry.file_name().to_str().and_then(parse_file_name) {
+            backups.push((entry.path(), secs));
+        }
+    }
+    backups.sort_by_key(|(_, secs)| std::cmp::Reverse(*secs));
+    Ok(backups)
+}
+
+/// Indices into `times` (newest first) of the backups to keep: the newest
+/// backup of each of the last `keep_daily` days, `keep_weekly` weeks and
+/// `keep_monthly` months that have one. The newest backup is always kept.
+fn retained(times: &[i64], settings: &ScheduleSettings) -> HashSet<usize> {
+    let day = |secs: i64| secs.div_euclid(DAY_SECS);
+    // 1970-01-01 was a Thursday; weeks start on Monday
+    let week = |secs: i64| (day(secs) + 3).div_euclid(7);
+    let month = |secs: i64| {
+        let (year, month, _) = civil_from_days(day(secs));
+        year * 12 + month
+    };
+    let tiers: [(&dyn Fn(i64) -> i64, u32); 3] = [
+        (&day, settings.keep_daily),
+        (&week, settings.keep_weekly),
+        (&month, settings.keep_monthly),
+    ];
+
+    let mut keep = HashSet::new();
+    if !times.is_empty() {
+        keep.insert(0);
+    }
+    for (period, count) in tiers {
+        let mut seen = HashSet::new();
+        for (i, &secs) in times.iter().enumerate() {
+            if seen.len() >= count as usize {
+                break;
+            }
+            if seen.insert(period(secs)) {
+                keep.insert(i);
+            }
+        }
+    }
+    keep
+}
+
+/// Delete automatic backups in `dir` outside the retention set
+fn prune(dir: &Path, settings: &ScheduleSettings) -> io::Result<Vec<String>> {
+    let backups = list_backups(dir)?;
+    let times: Vec<i64> = backups.iter().map(|(_, secs)| *secs).collect();
+    let keep = retained(&times, settings);
+
+    let mut removed = Vec::new();
+    for (i, (path, _)) in backups.iter().enumerate() {
+        if !keep.contains(&i) {
+            fs::remove_file(path)?;
+            removed.push(path.to_string_lossy().into_owned());
+        }
+    }
+    Ok(removed)
+}
+
+/// Transactions added or edited at or after `since` (Unix seconds).
+/// `updated_at` holds either SQLite `datetime('now')` or ISO 8601 strings.
+fn changed_transactions(db_path: &Path, since: i64) -> Result<u32, DbError> {
+    let conn = open(db_path, true)?;
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM \"transaction\" \
+         WHERE CAST(strftime('%s', updated_at) AS INTEGER) >= ?1",
+        [since],
+        |row| row.get(0),
+    )?;
+    Ok(u32::try_from(count).unwrap_or(u32::MAX))
+}
+
+/// Which trigger, if any, is due. `last` is the newest automatic backup;
+/// `changes` counts edits since then and is only called when needed.
+fn due(
+    settings: &ScheduleSettings,
+    launching: bool,
+    last: Option<i64>,
+    now: i64,
+    changes: impl FnOnce(i64) -> Result<u32, DbError>,
+) -> Result<Option<Trigger>, DbError> {
+    if !settings.enabled {
+        return Ok(None);
+    }
+    if launching && settings.on_launch {
+        return Ok(Some(Trigger::Launch));
+    }
+    if settings.daily && last.map_or(true, |t| now - t >= DAY_SECS) {
+        return Ok(Some(Trigger::Daily));
+    }
+    if settings.change_threshold > 0 && changes(last.unwrap_or(0))? >= settings.change_threshold {
+        return Ok(Some(Trigger::Changes));
+    }
+    Ok(None)
+}
+
+/// Back up `db_path` into `dir`, encrypted when there is a `password`, and
+/// prune older automatic backups
+fn run_backup(
+    db_path: &Path,
+    dir: &Path,
+    now: i64,
+    settings: &ScheduleSettings,
+    password: Option<(&str, KdfParams)>,
+) -> Result<(SnapshotInfo, Vec<String>), DbError> {
+    fs::create_dir_all(dir)?;
+    let dest = dir.join(backup_file_name(now, password.is_some()));
+    let snapshot = match password {
+        Some((password, kdf)) => {
+            let info = write_backup(db_path, &dest, password, kdf)
+                .map_err(|e| DbError::Io(e.to_string()))?;
+            SnapshotInfo {
+                path: info.path,
+                size: info.size,
+                hash: hash_file(&dest)?,
+                content_hash: info.content_hash,
+            }
+        }
+        None => snapshot_to(db_path, &dest)?,
+    };
+    let removed = prune(dir, settings)?;
+    Ok((snapshot, removed))
+}
+
+pub(crate) fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// One pass of the scheduler
+fn tick(app: &AppHandle, launching: bool) -> Result<(), DbError> {
+    // No database yet: the frontend creates it on first run
+    let Ok(db_path) = database_path(app) else {
+        return Ok(());
+    };
+    let settings = app.state::<BackupSchedule>().settings();
+    let dir = app_data_dir(app)?.join(BACKUPS_DIR);
+    let now = unix_now();
+    let last = list_backups(&dir)?.first().map(|(_, secs)| *secs);
+
+    let Some(trigger) = due(&settings, launching, last, now, |since| {
+        changed_transactions(&db_path, since)
+    })?
+    else {
+        return Ok(());
+    };
+
+    let password = app.state::<Vault>().get(PASSWORD_ENTRY);
+    let encrypt = password.as_deref().map(|p| (p, KdfParams::default()));
+    let emitted = match run_backup(&db_path, &dir, now, &settings, encrypt) {
+        Ok((snapshot, removed)) => {
+            log::info!(
+                "Automatic backup ({:?}) written to {}",
+                trigger,
+                snapshot.path
+            );
+            app.emit(
+                EVENT_COMPLETED,
+                BackupCompleted {
+                    trigger,
+                    snapshot,
+                    removed,
+                },
+            )
+        }
+        Err(error) => {
+            log::error!("Automatic backup ({:?}) failed: {}", trigger, error);
+            app.emit(EVENT_FAILED, BackupFailed { trigger, error })
+        }
+    };
+    if let Err(e) = emitted {
+        log::error!("Failed to emit backup event: {}", e);
+    }
+    Ok(())
+}
+
+/// Load the schedule settings and start the backup thread
+pub fn init(app: &AppHandle) -> Result<(), DbError> {
+    let dir = app_data_dir(app)?;
+    fs::create_dir_all(&dir)?;
+    app.manage(BackupSchedule::load(&dir));
+
+    let app = app.clone();
+    thread::Builder::new()
+        .name("auto-backup".into())
+        .spawn(move || {
+            let mut launching = true;
+            loop {
+                if let Err(e) = tick(&app, launching) {
+                    log::error!("Backup schedule check failed: {}", e);
+                }
+                launching = false;
+                thread::sleep(CHECK_INTERVAL);
+            }
+        })?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_backup_schedule(schedule: tauri::State<'_, BackupSchedule>) -> ScheduleSettings {
+    schedule.settings()
+}
+
+/// Save the schedule; takes effect at the next check
+#[tauri::command]
+pub fn set_backup_schedule(
+    schedule: tauri::State<'_, BackupSchedule>,
+    settings: ScheduleSettings,
+) -> Result<(), DbError> {
+    schedule.update(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    /// 2024-03-09 14:05:00 UTC
+    const SAMPLE: i64 = 1_709_993_100;
+
+    #[test]
+    fn file_names_round_trip() {
+        assert_eq!(
+            backup_file_name(SAMPLE, false),
+            "auto-2024-03-09_14-05-00.db"
+        );
+        assert_eq!(
+            backup_file_name(SAMPLE, true),
+            "auto-2024-03-09_14-05-00.puffinbak"
+        );
+        assert_eq!(parse_file_name("auto-2024-03-09_14-05-00.db"), Some(SAMPLE));
+        assert_eq!(parse_file_name(&backup_file_name(0, true)), Some(0));
+        assert_eq!(parse_file_name("pre-sync-2024-03-09.db"), None);
+        assert_eq!(parse_file_name("auto-2024-13-09_14-05-00.db"), None);
+        assert_eq!(parse_file_name("auto-2024-03-09_14-05-00.db.partial"), None);
+    }
+
+    #[test]
+    fn retention_keeps_grandfather_father_son_set() {
+        let settings = ScheduleSettings {
+            keep_daily: 3,
+            keep_weekly: 2,
+            keep_monthly: 2,
+            ..Default::default()
+        };
+        // Two backups a day for 60 days, newest first
+        let times: Vec<i64> = (0..120).map(|i| SAMPLE - i * DAY_SECS / 2).collect();
+        let mut kept: Vec<usize> = retained(&times, &settings).into_iter().collect();
+        kept.sort();
+
+        // Newest of each of the last 3 days (Mar 9, 8, 7), the last 2 weeks
+        // (from Monday Mar 4: newest overall and Sunday Mar 3), and the last
+        // 2 months (newest overall and Feb 29)
+        let days: Vec<i64> = kept
+            .iter()
+            .map(|&i| times[i].div_euclid(DAY_SECS))
+            .collect();
+        let march_9 = SAMPLE.div_euclid(DAY_SECS);
+        assert_eq!(
+            days,
+            vec![march_9, march_9 - 1, march_9 - 2, march_9 - 6, march_9 - 9]
+        );
+        assert!(retained(&[], &settings).is_empty());
+    }
+
+    #[test]
+    fn trigger_follows_settings() {
+        let settings = ScheduleSettings::default();
+        let none = |_| Ok(0);
+        let hour_ago = Some(SAMPLE - 3600);
+
+        assert_eq!(
+            due(&settings, true, hour_ago, SAMPLE, none).unwrap(),
+            Some(Trigger::Launch)
+        );
+        assert_eq!(due(&settings, false, hour_ago, SAMPLE, none).unwrap(), None);
+        assert_eq!(
+            due(&settings, false, Some(SAMPLE - DAY_SECS), SAMPLE, none).unwrap(),
+            Some(Trigger::Daily)
+        );
+        assert_eq!(
+            due(&settings, false, hour_ago, SAMPLE, |since| {
+                assert_eq!(Some(since), hour_ago);
+                Ok(settings.change_threshold)
+            })
+            .unwrap(),
+            Some(Trigger::Changes)
+        );
+
+        let disabled = ScheduleSettings {
+            enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(due(&disabled, true, None, SAMPLE, none).unwrap(), None);
+    }
+
+    #[test]
+    fn counts_changes_in_both_timestamp_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("puffin.db");
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE \"transaction\" (id TEXT PRIMARY KEY, updated_at TEXT);
+                 INSERT INTO \"transaction\" VALUES
+                   ('a', '2024-03-09 14:00:00'),
+                   ('b', '2024-03-09T14:10:00.000Z'),
+                   ('c', '2024-03-09 14:20:00');",
+            )
+            .unwrap();
+
+        assert_eq!(changed_transactions(&path, SAMPLE).unwrap(), 2);
+        assert_eq!(changed_transactions(&path, 0).unwrap(), 3);
+    }
+
+    #[test]
+    fn backup_run_writes_snapshot_and_prunes() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("puffin.db");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch("CREATE TABLE note (id TEXT PRIMARY KEY);")
+            .unwrap();
+        let backups = dir.path().join(BACKUPS_DIR);
+        fs::create_dir_all(&backups).unwrap();
+        // An older backup from the same day, and a manual backup
+        fs::write(backups.join(backup_file_name(SAMPLE - 60, false)), b"old").unwrap();
+        fs::write(backups.join("puffin-backup-manual.db"), b"manual").unwrap();
+
+        let (snapshot, removed) = run_backup(
+            &db_path,
+            &backups,
+            SAMPLE,
+            &ScheduleSettings::default(),
+            None,
+        )
+        .unwrap();
+
+        assert!(snapshot.path.ends_with("auto-2024-03-09_14-05-00.db"));
+        assert_eq!(removed.len(), 1);
+        assert!(removed[0].ends_with(&backup_file_name(SAMPLE - 60, false)));
+        assert!(backups.join("puffin-backup-manual.db").exists());
+        assert_eq!(list_backups(&backups).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn backup_run_encrypts_with_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("puffin.db");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch("CREATE TABLE note (id TEXT PRIMARY KEY);")
+            .unwrap();
+        let backups = dir.path().join(BACKUPS_DIR);
+        // A plain backup from before the password was set
+        fs::create_dir_all(&backups).unwrap();
+        fs::write(backups.join(backup_file_name(SAMPLE - 60, false)), b"old").unwrap();
+
+        let kdf = KdfParams {
+            m_cost: 8,
+            t_cost: 1,
+            p_cost: 1,
+        };
+        let (snapshot, removed) = run_backup(
+            &db_path,
+            &backups,
+            SAMPLE,
+            &ScheduleSettings::default(),
+            Some(("pw", kdf)),
+        )
+        .unwrap();
+
+        assert!(snapshot
+            .path
+            .ends_with("auto-2024-03-09_14-05-00.puffinbak"));
+        assert!(fs::read(&snapshot.path).unwrap().starts_with(b"PUFFINBK"));
+        assert_eq!(removed.len(), 1);
+        assert_eq!(list_backups(&backups).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,144 @@
+//! Consistent copies of the live database
+//!
+//! The SQL plugin's pool may have `puffin.db` open with writes still in the
+//! WAL, so copying the file can capture a torn or stale state. Snapshots go
+//! through SQLite's online backup API instead, which copies a consistent
+//! view of the database (restarting if another connection writes meanwhile).
+//! The copy is switched to a rollback journal so it is a single
+//! self-contained file, and must pass `PRAGMA integrity_check` before it
+//! replaces `dest`.
+
+use super::hash::{hash_content, hash_file};
+use super::{app_data_dir, database_path, ensure_creatable_within, integrity_check, open, DbError};
+use rusqlite::{Connection, DatabaseName};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct SnapshotInfo {
+    pub path: String,
+    pub size: u64,
+    /// SHA-256 of the snapshot file
+    pub hash: String,
+    /// Content hash (see `hash::hash_content`)
+    pub content_hash: String,
+}
+
+/// Sibling of `dest` the snapshot is written to before it is verified
+fn staged_path(dest: &Path) -> PathBuf {
+    let mut staged = dest.as_os_str().to_owned();
+    staged.push(".partial");
+    PathBuf::from(staged)
+}
+
+/// Copy `src` to `staged` and check the copy; returns its content hash
+fn write_snapshot(src: &Path, staged: &Path) -> Result<String, DbError> {
+    open(src, true)?.backup(DatabaseName::Main, staged, None)?;
+
+    let conn = Connection::open(staged)?;
+    // The copy inherits WAL mode from the source header
+    conn.pragma_update(None, "journal_mode", "DELETE")?;
+    integrity_check(&conn)?;
+    hash_content(&conn)
+}
+
+/// Snapshot the database at `src` into `dest`, replacing it only once the
+/// copy is complete and passes the integrity check
+pub fn snapshot_to(src: &Path, dest: &Path) -> Result<SnapshotInfo, DbError> {
+    let staged = staged_path(dest);
+    let _ = fs::remove_file(&staged);
+
+    let content_hash = match write_snapshot(src, &staged) {
+        Ok(hash) => hash,
+        Err(e) => {
+            let _ = fs::remove_file(&staged);
+            return Err(e);
+        }
+    };
+    if let Err(e) = fs::rename(&staged, dest) {
+        let _ = fs::remove_file(&staged);
+        return Err(e.into());
+    }
+
+    Ok(SnapshotInfo {
+        path: dest.to_string_lossy().into_owned(),
+        size: fs::metadata(dest)?.len(),
+        hash: hash_file(dest)?,
+        content_hash,
+    })
+}
+
+/// Write a verified snapshot of `puffin.db` to `dest` (within the app data
+/// directory)
+#[tauri::command]
+pub async fn snapshot_database(
+    app: tauri::AppHandle,
+    dest: String,
+) -> Result<SnapshotInfo, DbError> {
+    let dest = PathBuf::from(dest);
+    // Check before creating anything, so no directory appears outside
+    ensure_creatable_within(&app_data_dir(&app)?, &dest)?;
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let src = database_path(&app)?;
+    super::blocking(move || snapshot_to(&src, &dest)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_wal_db(path: &Path) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE note (id TEXT PRIMARY KEY, title TEXT);
+             INSERT INTO note VALUES ('a', 'Rent');",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn captures_uncheckpointed_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("puffin.db");
+        // Keep the connection open so the rows stay in the WAL
+        let live = create_wal_db(&src);
+        live.execute("INSERT INTO note VALUES ('b', 'Food')", [])
+            .unwrap();
+
+        let dest = dir.path().join("backups").join("snap.db");
+        fs::create_dir_all(dest.parent().unwrap()).unwrap();
+        let info = snapshot_to(&src, &dest).unwrap();
+
+        assert_eq!(info.size, fs::metadata(&dest).unwrap().len());
+        assert_eq!(info.hash, hash_file(&dest).unwrap());
+        assert_eq!(info.content_hash, hash_content(&live).unwrap());
+        assert!(!staged_path(&dest).exists());
+
+        // Self-contained: no WAL next to the copy, and it opens on its own
+        assert!(!dir.path().join("backups/snap.db-wal").exists());
+        let copy = Connection::open(&dest).unwrap();
+        let mode: String = copy
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "delete");
+        let count: i64 = copy
+            .query_row("SELECT COUNT(*) FROM note", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn failed_snapshot_keeps_existing_dest() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("snap.db");
+        fs::write(&dest, b"previous").unwrap();
+
+        assert!(snapshot_to(&dir.path().join("missing.db"), &dest).is_err());
+        assert_eq!(fs::read(&dest).unwrap(), b"previous");
+        assert!(!staged_path(&dest).exists());
+    }
+}
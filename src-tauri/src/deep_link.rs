@@ -0,0 +1,160 @@
+//! `puffin://` deep links
+//!
+//! `puffin://oauth/callback?code=...&state=...` finishes the pending
+//! `start_oauth_flow` whose `state` matches (see `oauth::PendingFlows`).
+//! Every other route is forwarded to the frontend as a `deep-link://route`
+//! event. Links that launched the app arrive before the frontend listens, so
+//! those are queued until it calls `take_launch_deep_links`.
+
+use crate::oauth::PendingFlows;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
+use url::Url;
+
+pub const SCHEME: &str = "puffin";
+pub const EVENT_ROUTE: &str = "deep-link://route";
+
+/// Route of OAuth redirects (`puffin://oauth/callback`)
+const OAUTH_CALLBACK_ROUTE: &str = "oauth/callback";
+
+/// A `puffin://` link for the frontend, e.g. `puffin://import/csv?source=x`
+/// becomes `{ route: "import/csv", params: { source: "x" } }`
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct DeepLinkRoute {
+    pub url: String,
+    pub route: String,
+    pub params: BTreeMap<String, String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum DeepLink {
+    /// Query string of an OAuth redirect
+    OAuthCallback(String),
+    Route(DeepLinkRoute),
+}
+
+/// Routes received before the frontend was ready. Managed as Tauri state.
+#[derive(Default)]
+pub struct LaunchDeepLinks(Mutex<Vec<DeepLinkRoute>>);
+
+/// Classify a URL; `None` for anything that isn't `puffin://`
+fn parse(url: &Url) -> Option<DeepLink> {
+    if url.scheme() != SCHEME {
+        return None;
+    }
+
+    // `puffin://a/b` parses with host `a` and path `/b`
+    let route = format!("{}{}", url.host_str().unwrap_or_default(), url.path())
+        .trim_matches('/')
+        .to_string();
+    if route == OAUTH_CALLBACK_ROUTE {
+        return Some(DeepLink::OAuthCallback(
+            url.query().unwrap_or_default().to_string(),
+        ));
+    }
+
+    Some(DeepLink::Route(DeepLinkRoute {
+        url: url.to_string(),
+        route,
+        params: url.query_pairs().into_owned().collect(),
+    }))
+}
+
+/// Act on one incoming URL. `launch` marks links that started the app.
+pub fn handle_url(app: &AppHandle, url: &Url, launch: bool) {
+    match parse(url) {
+        None => log::warn!(
+            "Ignoring deep link with unexpected scheme: {}",
+            url.scheme()
+        ),
+        Some(DeepLink::OAuthCallback(query)) => {
+            if !app.state::<PendingFlows>().complete(&query) {
+                log::warn!("Ignoring OAuth deep link that matches no pending flow");
+            }
+        }
+        Some(DeepLink::Route(route)) if launch => {
+            app.state::<LaunchDeepLinks>().0.lock().unwrap().push(route);
+        }
+        Some(DeepLink::Route(route)) => {
+            if let Err(e) = app.emit(EVENT_ROUTE, route) {
+                log::error!("Failed to emit deep link: {}", e);
+            }
+        }
+    }
+}
+
+/// Register the handler; called from `setup`
+pub fn init(app: &AppHandle) {
+    app.manage(LaunchDeepLinks::default());
+
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            handle_url(&handle, &url, false);
+        }
+    });
+
+    match app.deep_link().get_current() {
+        Ok(Some(urls)) => {
+            for url in urls {
+                handle_url(app, &url, true);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to read launch deep link: {}", e),
+    }
+}
+
+/// Routes that launched the app; drained on read
+#[tauri::command]
+pub fn take_launch_deep_links(links: tauri::State<'_, LaunchDeepLinks>) -> Vec<DeepLinkRoute> {
+    std::mem::take(&mut *links.0.lock().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(url: &str) -> Option<DeepLink> {
+        parse(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn recognises_oauth_callback() {
+        assert_eq!(
+            parse_str("puffin://oauth/callback?code=4%2F0A&state=abc"),
+            Some(DeepLink::OAuthCallback("code=4%2F0A&state=abc".to_string()))
+        );
+        assert_eq!(
+            parse_str("puffin://oauth/callback/"),
+            Some(DeepLink::OAuthCallback(String::new()))
+        );
+    }
+
+    #[test]
+    fn other_routes_carry_path_and_params() {
+        let Some(DeepLink::Route(route)) = parse_str("puffin://import/csv?source=Everyday&x=1")
+        else {
+            panic!("expected a route");
+        };
+        assert_eq!(route.route, "import/csv");
+        assert_eq!(
+            route.params.get("source").map(String::as_str),
+            Some("Everyday")
+        );
+        assert_eq!(route.params.len(), 2);
+
+        let Some(DeepLink::Route(route)) = parse_str("puffin://settings") else {
+            panic!("expected a route");
+        };
+        assert_eq!(route.route, "settings");
+        assert!(route.params.is_empty());
+    }
+
+    #[test]
+    fn ignores_other_schemes() {
+        assert_eq!(parse_str("https://example.com/oauth/callback?code=x"), None);
+    }
+}
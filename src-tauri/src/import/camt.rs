@@ -0,0 +1,490 @@
+//! camt.053 and camt.052 statements
+//!
+//! ISO 20022 bank-to-customer statements (camt.053) and intraday account
+//! reports (camt.052) list entries (`<Ntry>`) under each statement. An entry
+//! becomes a row: the booking date (else the value date), the amount signed
+//! by its credit/debit indicator, the counterparty as the description and
+//! the remittance information as notes. The bank's reference
+//! (`AcctSvcrRef`, unless it's `NONREF`) is the row's bank ID. Pending
+//! entries are left out; they're booked, perhaps changed, in a later
+//! statement. A file may hold several statements, but all for one account:
+//! the rows are committed to that account's source.
+//!
+//! Only element names are matched, so any version of the schema (and any
+//! namespace prefix) reads the same.
+
+use super::date::{self, DateFormat};
+use super::{
+    check_chosen, find_account_source, mark_duplicates, push_row, read_text, row_notes,
+    ImportError, ImportPreview, PreviewRow, StagedImports, DEFAULT_DESCRIPTION,
+};
+use crate::db::{database_path, open};
+use crate::launch::OpenedFiles;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::path::Path;
+
+/// Root elements: camt.053 statements and camt.052 reports
+const DOCUMENTS: [&str; 2] = ["BkToCstmrStmt", "BkToCstmrAcctRpt"];
+
+/// Error for XML that isn't a camt document
+pub(crate) const NOT_CAMT: &str = "not a camt.053 or camt.052 statement";
+
+/// Fields of one `<Ntry>`
+#[derive(Default)]
+struct Entry {
+    amount: Option<String>,
+    indicator: Option<String>,
+    status: Option<String>,
+    booked: Option<String>,
+    value: Option<String>,
+    reference: Option<String>,
+    debtor: Option<String>,
+    creditor: Option<String>,
+    remittance: Vec<String>,
+    additional: Option<String>,
+}
+
+impl Entry {
+    /// Store `text` found at `path` (the elements inside `<Ntry>`)
+    fn set(&mut self, path: &[String], text: &str) {
+        let path: Vec<&str> = path.iter().map(String::as_str).collect();
+        let field = match path.as_slice() {
+            ["Amt"] => &mut self.amount,
+            ["CdtDbtInd"] => &mut self.indicator,
+            // `<Sts>BOOK</Sts>`, or `<Sts><Cd>BOOK</Cd></Sts>` from version 8
+            ["Sts"] | ["Sts", "Cd"] => &mut self.status,
+            ["BookgDt", _] => &mut self.booked,
+            ["ValDt", _] => &mut self.value,
+            ["AcctSvcrRef"] => &mut self.reference,
+            ["AddtlNtryInf"] => &mut self.additional,
+            [.., "RmtInf", "Ustrd"] | [.., "RmtInf", "Strd", "CdtrRefInf", "Ref"] => {
+                self.remittance.push(text.to_string());
+                return;
+            }
+            _ => match party(&path) {
+                Some("Dbtr") => &mut self.debtor,
+                Some("Cdtr") => &mut self.creditor,
+                _ => return,
+            },
+        };
+        field.get_or_insert_with(|| text.to_string());
+    }
+
+    fn is_booked(&self) -> bool {
+        self.status
+            .as_deref()
+            .map_or(true, |s| s.eq_ignore_ascii_case("BOOK"))
+    }
+
+    fn into_row(self, row_index: usize) -> PreviewRow {
+        let mut errors = Vec::new();
+        let date_text = self.booked.as_deref().or(self.value.as_deref());
+        let date = date_text.and_then(parse_date);
+        if date.is_none() {
+            errors.push(format!("Invalid date: {:?}", date_text.unwrap_or_default()));
+        }
+
+        let indicator = self.indicator.as_deref().unwrap_or_default();
+        let is_debit = indicator.eq_ignore_ascii_case("DBIT");
+        let amount = match self.amount.as_deref().map(str::trim) {
+            None => Err("Missing amount".to_string()),
+            Some(text) => text
+                .parse::<f64>()
+                .ok()
+                .filter(|a| a.is_finite())
+                .ok_or_else(|| format!("Invalid amount: {:?}", text)),
+        }
+        .and_then(|amount| match indicator.to_ascii_uppercase().as_str() {
+            "DBIT" => Ok(-amount.abs()),
+            "CRDT" => Ok(amount.abs()),
+            _ => Err(format!("Invalid credit/debit indicator: {:?}", indicator)),
+        })
+        .map_err(|e| errors.push(e))
+        .ok();
+
+        // The other party: who was paid for a debit, who paid for a credit
+        let counterparty = if is_debit {
+            self.creditor.or(self.debtor)
+        } else {
+            self.debtor.or(self.creditor)
+        };
+        let remittance = self.remittance.join(" ");
+        let raw = vec![
+            self.booked.unwrap_or_default(),
+            self.value.unwrap_or_default(),
+            self.amount.unwrap_or_default(),
+            indicator.to_string(),
+            counterparty.clone().unwrap_or_default(),
+            remittance.clone(),
+            self.reference.clone().unwrap_or_default(),
+        ];
+        let mut texts = [counterparty, Some(remittance), self.additional]
+            .into_iter()
+            .flatten()
+            .filter(|t| !t.trim().is_empty());
+        let description = texts.next();
+        let notes = texts.next().and_then(|n| row_notes(&n));
+
+        PreviewRow {
+            row_index,
+            raw,
+            has_default_description: description.is_none(),
+            description: description.unwrap_or_else(|| DEFAULT_DESCRIPTION.to_string()),
+            date,
+            amount,
+            notes,
+            external_id: self
+                .reference
+                .filter(|r| !r.trim().is_empty() && !r.trim().eq_ignore_ascii_case("NONREF")),
+            errors,
+            is_duplicate: false,
+        }
+    }
+}
+
+/// `Dbtr` or `Cdtr` when `path` is a related party's name: `Dbtr/Nm`, or
+/// `Dbtr/Pty/Nm` from version 8
+fn party<'a>(path: &[&'a str]) -> Option<&'a str> {
+    let start = path.iter().position(|p| *p == "RltdPties")?;
+    match &path[start + 1..] {
+        [party, "Nm"] | [party, "Pty", "Nm"] => Some(party),
+        _ => None,
+    }
+}
+
+/// A date (`2024-01-03`) or date and time (`2024-01-03T10:00:00`)
+fn parse_date(text: &str) -> Option<String> {
+    date::parse(text.get(..10)?, DateFormat::Iso)
+}
+
+/// The statement's account
+#[derive(Debug, Default, serde::Serialize)]
+pub struct CamtAccount {
+    /// IBAN, or the bank's own account number
+    pub number: Option<String>,
+    pub name: Option<String>,
+    pub currency: Option<String>,
+}
+
+impl CamtAccount {
+    fn set(&mut self, path: &[String], text: &str) {
+        let path: Vec<&str> = path.iter().map(String::as_str).collect();
+        let field = match path.as_slice() {
+            ["Acct", "Id", "IBAN"] | ["Acct", "Id", "Othr", "Id"] => &mut self.number,
+            ["Acct", "Nm"] => &mut self.name,
+            ["Acct", "Ccy"] => &mut self.currency,
+            _ => return,
+        };
+        field.get_or_insert_with(|| text.to_string());
+    }
+
+    /// Whether `other` is the same account: by number, else by name
+    fn is_same(&self, other: &CamtAccount) -> bool {
+        match (&self.number, &other.number) {
+            (Some(number), Some(other)) => number == other,
+            _ => self.name == other.name,
+        }
+    }
+}
+
+/// A parsed camt file, before duplicates are checked
+#[derive(Debug)]
+pub struct ParsedCamt {
+    /// Account the statements are for
+    pub account: CamtAccount,
+    pub rows: Vec<PreviewRow>,
+    /// Pending entries, left out
+    pub pending_count: usize,
+}
+
+/// Parse the text of a camt.053 or camt.052 file
+pub fn parse(text: &str) -> Result<ParsedCamt, ImportError> {
+    let invalid = |e: quick_xml::Error| ImportError::Invalid(e.to_string());
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+
+    let mut parsed = ParsedCamt {
+        account: CamtAccount::default(),
+        rows: Vec::new(),
+        pending_count: 0,
+    };
+    let mut is_camt = false;
+    // Local names of the open elements
+    let mut path: Vec<String> = Vec::new();
+    // Where in `path` the statement and the current entry start
+    let mut statement: Option<usize> = None;
+    let mut entry: Option<(usize, Entry)> = None;
+    // Account of the statement being read, and how many have been read
+    let mut account = CamtAccount::default();
+    let mut statements_seen = 0;
+
+    loop {
+        let text = match reader.read_event().map_err(invalid)? {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                match name.as_str() {
+                    n if DOCUMENTS.contains(&n) => is_camt = true,
+                    "Stmt" | "Rpt" if is_camt && statement.is_none() => {
+                        statement = Some(path.len() + 1);
+                        statements_seen += 1;
+                    }
+                    "Ntry" if statement.is_some() && entry.is_none() => {
+                        entry = Some((path.len() + 1, Entry::default()));
+                    }
+                    _ => {}
+                }
+                path.push(name);
+                continue;
+            }
+            Event::End(_) => {
+                path.pop();
+                match entry.take() {
+                    Some((start, done)) if path.len() < start => {
+                        if done.is_booked() {
+                            let row = done.into_row(parsed.rows.len());
+                            push_row(&mut parsed.rows, row)?;
+                        } else {
+                            parsed.pending_count += 1;
+                        }
+                    }
+                    open => entry = open,
+                }
+                if statement.is_some_and(|start| path.len() < start) {
+                    statement = None;
+                    let done = std::mem::take(&mut account);
+                    if statements_seen == 1 {
+                        parsed.account = done;
+                    } else if !parsed.account.is_same(&done) {
+                        return Err(ImportError::Invalid(
+                            "statements for more than one account".to_string(),
+                        ));
+                    }
+                }
+                continue;
+            }
+            Event::Text(e) => e.unescape().map_err(invalid)?.into_owned(),
+            Event::CData(e) => e
+                .decode()
+                .map_err(|e| ImportError::Invalid(e.to_string()))?
+                .into_owned(),
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        if let Some((start, fields)) = entry.as_mut() {
+            fields.set(&path[*start..], &text);
+        } else if let Some(start) = statement {
+            account.set(&path[start..], &text);
+        }
+    }
+
+    if !is_camt {
+        return Err(ImportError::Invalid(NOT_CAMT.to_string()));
+    }
+    if parsed.rows.is_empty() {
+        return Err(ImportError::Empty);
+    }
+    Ok(parsed)
+}
+
+/// Read and parse the camt file at `path`
+pub fn read(path: &Path) -> Result<ParsedCamt, ImportError> {
+    parse(&read_text(path)?)
+}
+
+/// Result of `import_camt`
+#[derive(Debug, serde::Serialize)]
+pub struct CamtPreview {
+    #[serde(flatten)]
+    pub preview: ImportPreview,
+    pub account: CamtAccount,
+    /// Source matching the account's IBAN or name, to commit with
+    pub source_id: Option<String>,
+    pub pending_count: usize,
+}
+
+/// Parse the camt.053 or camt.052 statement at `path`, check it for
+/// duplicates of existing transactions, find its source and stage it for
+/// `commit_import`
+#[tauri::command]
+pub async fn import_camt(
+    app: tauri::AppHandle,
+    staged: tauri::State<'_, StagedImports>,
+    opened: tauri::State<'_, OpenedFiles>,
+    path: String,
+) -> Result<CamtPreview, ImportError> {
+    check_chosen(&opened, Path::new(&path))?;
+    let db_path = database_path(&app)?;
+    let (mut parsed, source_id) = tauri::async_runtime::spawn_blocking(move || {
+        let mut parsed = read(Path::new(&path))?;
+        let conn = if db_path.is_file() {
+            Some(open(&db_path, true)?)
+        } else {
+            None
+        };
+        let source_id = match &conn {
+            Some(conn) => find_account_source(
+                conn,
+                parsed.account.number.as_deref(),
+                parsed.account.name.as_deref(),
+            )?,
+            None => None,
+        };
+        mark_duplicates(conn.as_ref(), &mut parsed.rows, source_id.as_deref())?;
+        Ok::<_, ImportError>((parsed, source_id))
+    })
+    .await
+    .map_err(|e| ImportError::Io(e.to_string()))??;
+
+    Ok(CamtPreview {
+        preview: staged.stage(&mut parsed.rows),
+        account: parsed.account,
+        source_id,
+        pending_count: parsed.pending_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// camt.053, version 2
+    const STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <GrpHdr><MsgId>1</MsgId></GrpHdr>
+    <Stmt>
+      <Acct>
+        <Id><IBAN>DE89370400440532013000</IBAN></Id>
+        <Ccy>EUR</Ccy>
+        <Nm>Girokonto</Nm>
+      </Acct>
+      <Bal><Amt Ccy="EUR">1000.00</Amt><CdtDbtInd>CRDT</CdtDbtInd></Bal>
+      <Ntry>
+        <Amt Ccy="EUR">42.50</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2024-01-03</Dt></BookgDt>
+        <ValDt><Dt>2024-01-04</Dt></ValDt>
+        <AcctSvcrRef>REF-1</AcctSvcrRef>
+        <NtryDtls><TxDtls>
+          <AmtDtls><TxAmt><Amt Ccy="EUR">42.50</Amt></TxAmt></AmtDtls>
+          <RltdPties>
+            <Dbtr><Nm>Max Mustermann</Nm></Dbtr>
+            <Cdtr><Nm>REWE Markt</Nm><PstlAdr><Nm>Ignored</Nm></PstlAdr></Cdtr>
+          </RltdPties>
+          <RmtInf><Ustrd>Einkauf &amp; Pfand</Ustrd><Ustrd>Karte 1234</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">2000.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><DtTm>2024-01-05T08:00:00</DtTm></BookgDt>
+        <AddtlNtryInf>GUTSCHRIFT</AddtlNtryInf>
+        <NtryDtls><TxDtls>
+          <RltdPties><Dbtr><Nm>ACME GmbH</Nm></Dbtr></RltdPties>
+          <RmtInf><Strd><CdtrRefInf><Ref>RF18539007547034</Ref></CdtrRefInf></Strd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">5.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>PDNG</Sts>
+        <BookgDt><Dt>2024-01-06</Dt></BookgDt>
+      </Ntry>
+    </Stmt>
+    <Stmt>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id></Acct>
+      <Ntry>
+        <Amt Ccy="EUR">1.00</Amt>
+        <CdtDbtInd>SOME</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <ValDt><Dt>2024-01-07</Dt></ValDt>
+        <AcctSvcrRef>NONREF</AcctSvcrRef>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    /// camt.052, version 8, with a namespace prefix
+    const REPORT: &str = r#"<c:Document xmlns:c="urn:iso:std:iso:20022:tech:xsd:camt.052.001.08">
+  <c:BkToCstmrAcctRpt><c:Rpt>
+    <c:Acct><c:Id><c:Othr><c:Id>0532013000</c:Id></c:Othr></c:Id></c:Acct>
+    <c:Ntry>
+      <c:Amt Ccy="CHF">12.00</c:Amt>
+      <c:CdtDbtInd>DBIT</c:CdtDbtInd>
+      <c:Sts><c:Cd>BOOK</c:Cd></c:Sts>
+      <c:BookgDt><c:Dt>2024-02-01</c:Dt></c:BookgDt>
+      <c:NtryDtls><c:TxDtls><c:RltdPties>
+        <c:Cdtr><c:Pty><c:Nm><![CDATA[Migros & Co]]></c:Nm></c:Pty></c:Cdtr>
+      </c:RltdPties></c:TxDtls></c:NtryDtls>
+    </c:Ntry>
+  </c:Rpt></c:BkToCstmrAcctRpt>
+</c:Document>"#;
+
+    #[test]
+    fn reads_statement_entries() {
+        let parsed = parse(STATEMENT).unwrap();
+        assert_eq!(
+            parsed.account.number.as_deref(),
+            Some("DE89370400440532013000")
+        );
+        assert_eq!(parsed.account.name.as_deref(), Some("Girokonto"));
+        assert_eq!(parsed.account.currency.as_deref(), Some("EUR"));
+        assert_eq!((parsed.rows.len(), parsed.pending_count), (3, 1));
+
+        let rewe = &parsed.rows[0];
+        assert_eq!(rewe.date.as_deref(), Some("2024-01-03"));
+        assert_eq!(rewe.amount, Some(-42.5));
+        assert_eq!(rewe.description, "REWE Markt");
+        assert_eq!(rewe.notes.as_deref(), Some("Einkauf & Pfand Karte 1234"));
+        assert_eq!(rewe.external_id.as_deref(), Some("REF-1"));
+        assert_eq!(rewe.raw[1], "2024-01-04");
+
+        let salary = &parsed.rows[1];
+        assert_eq!(salary.date.as_deref(), Some("2024-01-05"));
+        assert_eq!(salary.amount, Some(2000.0));
+        assert_eq!(salary.description, "ACME GmbH");
+        assert_eq!(salary.notes.as_deref(), Some("RF18539007547034"));
+        assert_eq!(salary.external_id, None);
+
+        // The second statement's entry, dated by its value date
+        let odd = &parsed.rows[2];
+        assert_eq!(odd.date.as_deref(), Some("2024-01-07"));
+        assert_eq!(odd.errors, vec!["Invalid credit/debit indicator: \"SOME\""]);
+        assert!(odd.has_default_description);
+        assert_eq!(odd.external_id, None);
+    }
+
+    #[test]
+    fn rejects_statements_for_several_accounts() {
+        let other = STATEMENT.replacen(
+            "<IBAN>DE89370400440532013000</IBAN></Id></Acct>",
+            "<IBAN>DE02120300000000202051</IBAN></Id></Acct>",
+            1,
+        );
+        assert!(matches!(parse(&other), Err(ImportError::Invalid(_))));
+    }
+
+    #[test]
+    fn reads_prefixed_report() {
+        let parsed = parse(REPORT).unwrap();
+        assert_eq!(parsed.account.number.as_deref(), Some("0532013000"));
+        let row = &parsed.rows[0];
+        assert_eq!(
+            (row.date.as_deref(), row.amount, row.description.as_str()),
+            (Some("2024-02-01"), Some(-12.0), "Migros & Co")
+        );
+
+        assert!(matches!(
+            parse("<Document><pain.001/></Document>"),
+            Err(ImportError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse("<Document><BkToCstmrStmt><Stmt/></BkToCstmrStmt></Document>"),
+            Err(ImportError::Empty)
+        ));
+    }
+}
@@ -0,0 +1,853 @@
+//! CSV statements
+//!
+//! `import_csv` replaces the webview's CSV pipeline (`lib/csv/parser.ts`,
+//! `date-parser.ts` and the duplicate check), which froze the UI on large
+//! multi-year exports. The file is streamed: a sample from the start picks
+//! the encoding (BOM, UTF-8, else Windows-1252) and the delimiter, then
+//! records are decoded and parsed a line at a time with the column mapping.
+//! The first records also decide, per amount column, whether decimals follow
+//! a comma (`-4,50`) rather than a point.
+//! The rows are checked for duplicates and staged; the webview gets a
+//! preview and commits the staged batch with `commit_import`. Before a
+//! mapping is chosen, `sample_csv` reads just the headers and first rows.
+
+use super::date::{self, DateFormat};
+use super::paste::{is_grouped, is_plain};
+use super::{
+    check_chosen, mark_duplicates_in, push_row, row_notes, ImportError, ImportPreview, PreviewRow,
+    StagedImports, DEFAULT_DESCRIPTION, PREVIEW_ROWS,
+};
+use crate::db::{app_data_dir, DB_FILE};
+use crate::launch::OpenedFiles;
+use encoding_rs::{Decoder, Encoding, UTF_8, WINDOWS_1252};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Bytes read from the start of the file to detect encoding and delimiter
+const SAMPLE_BYTES: usize = 64 * 1024;
+
+/// Lines of the sample used to pick the delimiter
+const SAMPLE_LINES: usize = 20;
+
+const DELIMITERS: [char; 4] = [',', '\t', ';', '|'];
+
+/// Rows whose dates and amounts pick the date format and decimal separators
+const FORMAT_SAMPLES: usize = 10;
+
+/// A record's fields, or why it couldn't be read
+type Record = Result<Vec<String>, ImportError>;
+
+/// Iterator over the records of a delimited file
+pub struct Records<R> {
+    reader: R,
+    delimiter: char,
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn new(reader: R, delimiter: char) -> Self {
+        Self { reader, delimiter }
+    }
+
+    fn read_line(&mut self, buf: &mut String) -> Result<bool, ImportError> {
+        buf.clear();
+        Ok(self.reader.read_line(buf)? > 0)
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = String::new();
+        match self.read_line(&mut buf) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(e) => return Some(Err(e)),
+        }
+
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        loop {
+            let mut chars = buf.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' if quoted => {
+                        if chars.peek() == Some(&'"') {
+                            chars.next();
+                            field.push('"');
+                        } else {
+                            quoted = false;
+                        }
+                    }
+                    '"' if field.trim().is_empty() => {
+                        field.clear();
+                        quoted = true;
+                    }
+                    c if c == self.delimiter && !quoted => {
+                        fields.push(std::mem::take(&mut field));
+                    }
+                    '\r' | '\n' if !quoted => {}
+                    c => field.push(c),
+                }
+            }
+            if !quoted {
+                break;
+            }
+            // A quoted field continues on the next line
+            match self.read_line(&mut buf) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        fields.push(field);
+        Some(Ok(fields))
+    }
+}
+
+/// Reader that decodes `inner` from `encoding` to UTF-8, dropping any BOM
+struct Decoded<R> {
+    inner: R,
+    decoder: Decoder,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    /// Decoded bytes already handed out
+    pos: usize,
+    done: bool,
+}
+
+impl<R: Read> Decoded<R> {
+    fn new(inner: R, encoding: &'static Encoding) -> Self {
+        Self {
+            inner,
+            decoder: encoding.new_decoder_with_bom_removal(),
+            input: vec![0; 8 * 1024],
+            output: Vec::new(),
+            pos: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: Read> Read for Decoded<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.output.len() {
+            if self.done {
+                return Ok(0);
+            }
+            let read = self.inner.read(&mut self.input)?;
+            let last = read == 0;
+            let capacity = self
+                .decoder
+                .max_utf8_buffer_length(read)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "file too large"))?;
+            self.output.resize(capacity, 0);
+            let (_, _, written, _) =
+                self.decoder
+                    .decode_to_utf8(&self.input[..read], &mut self.output, last);
+            self.output.truncate(written);
+            self.pos = 0;
+            self.done = last;
+        }
+        let n = buf.len().min(self.output.len() - self.pos);
+        buf[..n].copy_from_slice(&self.output[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Encoding from the BOM, else UTF-8 if the sample is valid UTF-8, else
+/// Windows-1252 (what bank exports from Excel usually are)
+pub(crate) fn detect_encoding(sample: &[u8], complete: bool) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(sample) {
+        return encoding;
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => UTF_8,
+        // Only the sample's last character was cut off
+        Err(e) if !complete && e.error_len().is_none() => UTF_8,
+        Err(_) => WINDOWS_1252,
+    }
+}
+
+/// Occurrences of `delimiter` outside quotes
+fn count_delimiters(line: &str, delimiter: char) -> usize {
+    let mut quoted = false;
+    line.chars()
+        .filter(|&c| {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            c == delimiter && !quoted
+        })
+        .count()
+}
+
+/// The candidate that splits the most sample lines into the same number of
+/// fields; commas when nothing does
+fn detect_delimiter(sample: &str, complete: bool) -> char {
+    let mut lines: Vec<&str> = sample.lines().filter(|l| !l.trim().is_empty()).collect();
+    if !complete && lines.len() > 1 {
+        lines.pop();
+    }
+    lines.truncate(SAMPLE_LINES);
+
+    let mut best = (',', 0, 0);
+    for delimiter in DELIMITERS {
+        let counts: Vec<usize> = lines
+            .iter()
+            .map(|l| count_delimiters(l, delimiter))
+            .collect();
+        // Most common non-zero count, and how many lines have it
+        let (fields, lines_agreeing) = counts
+            .iter()
+            .filter(|&&c| c > 0)
+            .map(|&c| (c, counts.iter().filter(|&&n| n == c).count()))
+            .max_by_key(|&(c, agreeing)| (agreeing, c))
+            .unwrap_or((0, 0));
+        if (lines_agreeing, fields) > (best.2, best.1) {
+            best = (delimiter, fields, lines_agreeing);
+        }
+    }
+    best.0
+}
+
+/// Which columns hold what (`ColumnMapping` in `types/import.ts`); -1 is unset
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ColumnMapping {
+    pub date: i64,
+    pub description: i64,
+    pub amount: i64,
+    /// Withdrawals, read as negative
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debit: Option<i64>,
+    /// Deposits, read as positive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credit: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<i64>,
+    #[serde(default)]
+    pub ignore: Vec<usize>,
+}
+
+fn column(index: Option<i64>) -> Option<usize> {
+    index.and_then(|i| usize::try_from(i).ok())
+}
+
+/// Guess the mapping from header names, as `detectColumnMapping` does
+pub fn detect_mapping(headers: &[String]) -> Option<ColumnMapping> {
+    let lower: Vec<String> = headers.iter().map(|h| h.trim().to_lowercase()).collect();
+    let find = |patterns: &[&str], taken: &[Option<usize>]| {
+        patterns.iter().find_map(|p| {
+            lower
+                .iter()
+                .enumerate()
+                .position(|(i, h)| h.contains(p) && !taken.contains(&Some(i)))
+        })
+    };
+
+    let date = find(
+        &[
+            "date",
+            "transaction date",
+            "trans date",
+            "posted",
+            "posted date",
+            "value date",
+        ],
+        &[],
+    );
+    let description = find(
+        &[
+            "description",
+            "desc",
+            "memo",
+            "narrative",
+            "details",
+            "transaction",
+            "merchant",
+            "payee",
+        ],
+        &[date],
+    );
+    let amount = find(
+        &["amount", "value", "sum", "debit", "credit", "money"],
+        &[date, description],
+    );
+
+    let (Some(date), Some(description), Some(amount)) = (date, description, amount) else {
+        // Fall back to Date, Description, Amount order
+        if headers.len() < 3 {
+            return None;
+        }
+        return Some(ColumnMapping {
+            date: date.map_or(0, |i| i as i64),
+            description: description.map_or(1, |i| i as i64),
+            amount: amount.map_or(2, |i| i as i64),
+            debit: None,
+            credit: None,
+            balance: None,
+            notes: None,
+            ignore: Vec::new(),
+        });
+    };
+    Some(ColumnMapping {
+        date: date as i64,
+        description: description as i64,
+        amount: amount as i64,
+        debit: None,
+        credit: None,
+        balance: None,
+        notes: None,
+        ignore: (0..headers.len())
+            .filter(|&i| i != date && i != description && i != amount)
+            .collect(),
+    })
+}
+
+/// How to read the file
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CsvOptions {
+    /// The first row names the columns
+    pub has_headers: bool,
+    /// `auto` detects the format from the first dates
+    pub date_format: DateFormat,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            has_headers: true,
+            date_format: DateFormat::Auto,
+        }
+    }
+}
+
+/// Parse an amount such as `-1,234.50`, `$12.00` or `(12.00)`. Other
+/// characters are dropped, as in the import wizard.
+pub fn parse_amount(text: &str) -> Option<f64> {
+    parse_amount_in(text, false)
+}
+
+/// [`parse_amount`], reading `-1.234,50` as -1234.5 when `decimal_comma`
+fn parse_amount_in(text: &str, decimal_comma: bool) -> Option<f64> {
+    let text = text.trim();
+    let (negative, text) = match text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        Some(inner) => (true, inner),
+        None => (false, text),
+    };
+    let (point, group) = if decimal_comma {
+        (',', '.')
+    } else {
+        ('.', ',')
+    };
+    let cleaned: String = text
+        .chars()
+        .filter(|&c| c != group)
+        .map(|c| if c == point { '.' } else { c })
+        .filter(|c| c.is_ascii_digit() || matches!(c, '.' | '-'))
+        .collect();
+    if cleaned.is_empty() {
+        return None;
+    }
+    let value: f64 = cleaned.parse().ok()?;
+    value
+        .is_finite()
+        .then_some(if negative { -value } else { value })
+}
+
+/// Whether the amounts in `samples` put their decimals after a comma
+/// (`-4,50`, `1.234,56`): some do, and none has a decimal point. Amounts
+/// like `1,234` could be either and don't count.
+fn is_decimal_comma<'a>(samples: impl Iterator<Item = &'a str>) -> bool {
+    let mut comma = false;
+    for sample in samples {
+        let number: String = sample
+            .chars()
+            .filter(|c| c.is_ascii_digit() || matches!(c, '.' | ','))
+            .collect();
+        let comma_style = number.contains(',') && is_grouped(&number, '.', ',');
+        let point_style =
+            number.contains('.') && (is_grouped(&number, ',', '.') || is_plain(&number));
+        match (comma_style, point_style) {
+            (true, false) => comma = true,
+            (false, true) => return false,
+            _ => {}
+        }
+    }
+    comma
+}
+
+/// Amount of a record from the amount column, or debit and credit columns.
+/// Columns in `decimal_commas` write decimals after a comma.
+fn record_amount(
+    record: &[String],
+    mapping: &ColumnMapping,
+    decimal_commas: &[usize],
+) -> Result<f64, String> {
+    let field = |i: Option<usize>| {
+        i.and_then(|i| record.get(i).map(|f| (i, f.trim())))
+            .filter(|(_, f)| !f.is_empty())
+    };
+    let parse = |(i, text): (usize, &str)| {
+        parse_amount_in(text, decimal_commas.contains(&i))
+            .ok_or_else(|| format!("Invalid amount: {:?}", text))
+    };
+    if let Some(field) = field(column(Some(mapping.amount))) {
+        return parse(field);
+    }
+    if let Some(field) = field(column(mapping.credit)) {
+        return parse(field).map(f64::abs);
+    }
+    if let Some(field) = field(column(mapping.debit)) {
+        return parse(field).map(|a| -a.abs());
+    }
+    Err("Missing amount".to_string())
+}
+
+fn parse_record(
+    row_index: usize,
+    record: Vec<String>,
+    mapping: &ColumnMapping,
+    format: DateFormat,
+    decimal_commas: &[usize],
+) -> PreviewRow {
+    let field = |i: Option<usize>| {
+        i.and_then(|i| record.get(i))
+            .map(|f| f.trim())
+            .unwrap_or_default()
+    };
+    let mut errors = Vec::new();
+
+    let date_text = field(column(Some(mapping.date)));
+    let date = date::parse(date_text, format);
+    if date.is_none() {
+        errors.push(format!("Invalid date: {:?}", date_text));
+    }
+    let description = field(column(Some(mapping.description)));
+    let amount = record_amount(&record, mapping, decimal_commas)
+        .map_err(|e| errors.push(e))
+        .ok();
+    let notes = row_notes(field(column(mapping.notes)));
+
+    PreviewRow {
+        row_index,
+        has_default_description: description.is_empty(),
+        description: if description.is_empty() {
+            DEFAULT_DESCRIPTION.to_string()
+        } else {
+            description.to_string()
+        },
+        date,
+        amount,
+        notes,
+        external_id: None,
+        errors,
+        is_duplicate: false,
+        raw: record,
+    }
+}
+
+/// A parsed CSV file, before duplicates are checked
+#[derive(Debug)]
+pub struct ParsedCsv {
+    pub encoding: &'static str,
+    pub delimiter: char,
+    pub headers: Vec<String>,
+    pub mapping: ColumnMapping,
+    pub date_format: DateFormat,
+    pub rows: Vec<PreviewRow>,
+}
+
+/// The records of the CSV file at `path`, blank lines left out, with the
+/// encoding and delimiter detected from its start
+fn open_records(
+    path: &Path,
+) -> Result<(&'static Encoding, char, impl Iterator<Item = Record>), ImportError> {
+    let mut file = File::open(path)?;
+    let mut sample = Vec::with_capacity(SAMPLE_BYTES);
+    (&mut file)
+        .take(SAMPLE_BYTES as u64)
+        .read_to_end(&mut sample)?;
+    let complete = sample.len() < SAMPLE_BYTES;
+    let encoding = detect_encoding(&sample, complete);
+    let delimiter = detect_delimiter(&encoding.decode_with_bom_removal(&sample).0, complete);
+
+    let file = File::open(path)?;
+    let records = Records::new(BufReader::new(Decoded::new(file, encoding)), delimiter);
+    let records = records.filter(|r| {
+        r.as_ref()
+            .map_or(true, |fields| fields.iter().any(|f| !f.trim().is_empty()))
+    });
+    Ok((encoding, delimiter, records))
+}
+
+/// The column names from the first record, or `Column 1`, `Column 2`... when
+/// it is a row, which is then handed back
+fn split_headers(first: Vec<String>, has_headers: bool) -> (Vec<String>, Option<Vec<String>>) {
+    if has_headers {
+        (first.iter().map(|h| h.trim().to_string()).collect(), None)
+    } else {
+        let headers = (1..=first.len()).map(|i| format!("Column {}", i)).collect();
+        (headers, Some(first))
+    }
+}
+
+/// Stream and parse the CSV file at `path`. Without `mapping` the columns
+/// are guessed from the headers.
+pub fn read(
+    path: &Path,
+    mapping: Option<ColumnMapping>,
+    options: &CsvOptions,
+) -> Result<ParsedCsv, ImportError> {
+    let (encoding, delimiter, mut records) = open_records(path)?;
+    let first = records.next().ok_or(ImportError::Empty)??;
+    let (headers, first) = split_headers(first, options.has_headers);
+
+    let mapping = match mapping {
+        Some(mapping) => mapping,
+        None => detect_mapping(&headers).ok_or_else(|| {
+            ImportError::Invalid("couldn't find date, description and amount columns".to_string())
+        })?,
+    };
+    for index in [mapping.date, mapping.description] {
+        if column(Some(index)).map_or(true, |i| i >= headers.len()) {
+            return Err(ImportError::Invalid(format!(
+                "column {} doesn't exist",
+                index
+            )));
+        }
+    }
+
+    // Buffer the first rows to work out the date format and decimal separators
+    let mut head: Vec<Vec<String>> = first.into_iter().collect();
+    for record in records.by_ref().take(FORMAT_SAMPLES - head.len()) {
+        head.push(record?);
+    }
+    let date_format = match options.date_format {
+        DateFormat::Auto => date::detect_format(head.iter().filter_map(|r| {
+            column(Some(mapping.date))
+                .and_then(|i| r.get(i))
+                .map(String::as_str)
+                .filter(|d| !d.trim().is_empty())
+        })),
+        format => format,
+    };
+    let decimal_commas: Vec<usize> = [Some(mapping.amount), mapping.debit, mapping.credit]
+        .into_iter()
+        .filter_map(column)
+        .filter(|&i| is_decimal_comma(head.iter().filter_map(|r| r.get(i)).map(String::as_str)))
+        .collect();
+
+    let mut rows = Vec::new();
+    for (index, record) in head.into_iter().map(Ok).chain(records).enumerate() {
+        let row = parse_record(index, record?, &mapping, date_format, &decimal_commas);
+        push_row(&mut rows, row)?;
+    }
+    if rows.is_empty() {
+        return Err(ImportError::Empty);
+    }
+
+    Ok(ParsedCsv {
+        encoding: encoding.name(),
+        delimiter,
+        headers,
+        mapping,
+        date_format,
+        rows,
+    })
+}
+
+/// Headers and first records of a CSV file, for mapping its columns
+/// (`CSVParseResult` in `types/import.ts`)
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvSample {
+    pub headers: Vec<String>,
+    /// The first `PREVIEW_ROWS` records after the headers
+    pub rows: Vec<Vec<String>>,
+    pub total_rows: usize,
+    pub encoding: String,
+}
+
+/// Read the headers and first records of the CSV file at `path`, counting
+/// the rest
+pub fn sample(path: &Path, has_headers: bool) -> Result<CsvSample, ImportError> {
+    let (encoding, _, mut records) = open_records(path)?;
+    let first = records.next().ok_or(ImportError::Empty)??;
+    let (headers, first) = split_headers(first, has_headers);
+
+    let mut rows: Vec<Vec<String>> = first.into_iter().collect();
+    let mut total_rows = rows.len();
+    for record in records {
+        let record = record?;
+        if rows.len() < PREVIEW_ROWS {
+            rows.push(record);
+        }
+        total_rows += 1;
+    }
+    Ok(CsvSample {
+        headers,
+        rows,
+        total_rows,
+        encoding: encoding.name().to_string(),
+    })
+}
+
+/// Sample the CSV file at `path` for the column mapping step. Nothing is
+/// staged; `import_csv` parses the file once the mapping is chosen.
+#[tauri::command]
+pub async fn sample_csv(
+    opened: tauri::State<'_, OpenedFiles>,
+    path: String,
+    options: Option<CsvOptions>,
+) -> Result<CsvSample, ImportError> {
+    check_chosen(&opened, Path::new(&path))?;
+    let has_headers = options.unwrap_or_default().has_headers;
+    tauri::async_runtime::spawn_blocking(move || sample(Path::new(&path), has_headers))
+        .await
+        .map_err(|e| ImportError::Io(e.to_string()))?
+}
+
+/// Result of `import_csv`
+#[derive(Debug, serde::Serialize)]
+pub struct CsvPreview {
+    #[serde(flatten)]
+    pub preview: ImportPreview,
+    pub encoding: String,
+    pub delimiter: String,
+    pub headers: Vec<String>,
+    /// The mapping used, detected when none was given
+    pub mapping: ColumnMapping,
+    pub date_format: DateFormat,
+}
+
+/// Parse the CSV statement at `path`, check it for duplicates of existing
+/// transactions and stage it for `commit_import`
+#[tauri::command]
+pub async fn import_csv(
+    app: tauri::AppHandle,
+    staged: tauri::State<'_, StagedImports>,
+    opened: tauri::State<'_, OpenedFiles>,
+    path: String,
+    mapping: Option<ColumnMapping>,
+    options: Option<CsvOptions>,
+) -> Result<CsvPreview, ImportError> {
+    check_chosen(&opened, Path::new(&path))?;
+    let db_path = app_data_dir(&app)?.join(DB_FILE);
+    let mut parsed = tauri::async_runtime::spawn_blocking(move || {
+        let mut parsed = read(Path::new(&path), mapping, &options.unwrap_or_default())?;
+        mark_duplicates_in(&db_path, &mut parsed.rows)?;
+        Ok::<_, ImportError>(parsed)
+    })
+    .await
+    .map_err(|e| ImportError::Io(e.to_string()))??;
+
+    Ok(CsvPreview {
+        preview: staged.stage(&mut parsed.rows),
+        encoding: parsed.encoding.to_string(),
+        delimiter: parsed.delimiter.to_string(),
+        headers: parsed.headers,
+        mapping: parsed.mapping,
+        date_format: parsed.date_format,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::MAX_IMPORT_ROWS;
+    use std::io::Cursor;
+
+    fn records(text: &str, delimiter: char) -> Vec<Vec<String>> {
+        Records::new(Cursor::new(text), delimiter)
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn reads_quoted_fields_across_lines() {
+        let parsed = records("a,\"b, c\",\"say \"\"hi\"\"\"\r\n\"multi\nline\",,x\n", ',');
+        assert_eq!(
+            parsed,
+            vec![
+                vec!["a", "b, c", "say \"hi\""],
+                vec!["multi\nline", "", "x"],
+            ]
+        );
+        assert_eq!(records("a;b\n", ';'), vec![vec!["a", "b"]]);
+    }
+
+    #[test]
+    fn parses_amounts() {
+        assert_eq!(parse_amount("-1,234.50"), Some(-1234.5));
+        assert_eq!(parse_amount("$12.00"), Some(12.0));
+        assert_eq!(parse_amount("(7.25)"), Some(-7.25));
+        assert_eq!(parse_amount("AUD 3.10"), Some(3.1));
+        assert_eq!(parse_amount(""), None);
+        assert_eq!(parse_amount("n/a"), None);
+        assert_eq!(parse_amount_in("-1.234,50", true), Some(-1234.5));
+        assert_eq!(parse_amount_in("€ 4,5", true), Some(4.5));
+    }
+
+    #[test]
+    fn detects_decimal_commas() {
+        assert!(is_decimal_comma(["-900", "-4,50", "1.234,56"].into_iter()));
+        assert!(!is_decimal_comma(["-4,50", "12.00"].into_iter()));
+        // Thousands separators alone don't decide
+        assert!(!is_decimal_comma(["1,234", "-900"].into_iter()));
+        assert!(!is_decimal_comma(["-1,234.50"].into_iter()));
+    }
+
+    #[test]
+    fn detects_encoding_and_delimiter() {
+        assert_eq!(detect_encoding(b"\xEF\xBB\xBFDate", true), UTF_8);
+        assert_eq!(detect_encoding(b"\xFF\xFED\0a\0", true).name(), "UTF-16LE");
+        assert_eq!(detect_encoding("Café".as_bytes(), true), UTF_8);
+        assert_eq!(detect_encoding(b"Caf\xE9", true), WINDOWS_1252);
+        // A sample cut through a character is still UTF-8
+        assert_eq!(detect_encoding(&"é".as_bytes()[..1], false), UTF_8);
+
+        assert_eq!(detect_delimiter("a;b;c\n1;\"2;3\";4\n", true), ';');
+        assert_eq!(
+            detect_delimiter("Date\tDetails, more\tAmount\n1\t2, 3\t4\n", true),
+            '\t'
+        );
+        assert_eq!(detect_delimiter("just text\n", true), ',');
+    }
+
+    #[test]
+    fn detects_mapping_from_headers() {
+        let headers = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let mapping = detect_mapping(&headers(&[
+            "Transaction Date",
+            "Narrative",
+            "Debit Amount",
+            "Balance",
+        ]))
+        .unwrap();
+        assert_eq!(
+            (mapping.date, mapping.description, mapping.amount),
+            (0, 1, 2)
+        );
+        assert_eq!(mapping.ignore, vec![3]);
+        let fallback = detect_mapping(&headers(&["a", "b", "c"])).unwrap();
+        assert_eq!(
+            (fallback.date, fallback.description, fallback.amount),
+            (0, 1, 2)
+        );
+        assert_eq!(detect_mapping(&headers(&["a", "b"])), None);
+    }
+
+    #[test]
+    fn reads_windows_1252_semicolon_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("statement.csv");
+        std::fs::write(
+            &path,
+            b"Datum;Omschrijving;Bedrag;Notes\r\n\
+              03/01/2024;Caf\xE9 Kees;-4,50;\r\n\
+              \r\n\
+              13/01/2024;\"Huur; jan\";-900;rent\r\n\
+              14/01/2024;;;\r\n",
+        )
+        .unwrap();
+        let mapping = ColumnMapping {
+            date: 0,
+            description: 1,
+            amount: 2,
+            debit: None,
+            credit: None,
+            balance: None,
+            notes: Some(3),
+            ignore: Vec::new(),
+        };
+
+        let parsed = read(&path, Some(mapping), &CsvOptions::default()).unwrap();
+        assert_eq!((parsed.encoding, parsed.delimiter), ("windows-1252", ';'));
+        assert_eq!(parsed.date_format, DateFormat::DayFirst);
+        assert_eq!(parsed.rows.len(), 3);
+
+        let cafe = &parsed.rows[0];
+        assert_eq!(cafe.description, "Café Kees");
+        assert_eq!(cafe.date.as_deref(), Some("2024-01-03"));
+        // The column's decimals follow a comma
+        assert_eq!(cafe.amount, Some(-4.5));
+        assert_eq!(parsed.rows[1].notes.as_deref(), Some("rent"));
+        assert_eq!(parsed.rows[2].errors, vec!["Missing amount"]);
+        assert!(parsed.rows[2].has_default_description);
+    }
+
+    #[test]
+    fn stops_past_the_row_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("statement.csv");
+        let mut text = String::from("Date,Description,Amount\n");
+        for _ in 0..MAX_IMPORT_ROWS + 1 {
+            text.push_str("2024-01-03,Coles,-12.50\n");
+        }
+        std::fs::write(&path, text).unwrap();
+
+        assert!(matches!(
+            read(&path, None, &CsvOptions::default()),
+            Err(ImportError::TooMany(_))
+        ));
+    }
+
+    #[test]
+    fn reads_debit_and_credit_columns_without_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("statement.csv");
+        std::fs::write(&path, "2024-01-03,Coles,12.50,\n2024-01-04,Salary,,2000\n").unwrap();
+        let mapping = ColumnMapping {
+            date: 0,
+            description: 1,
+            amount: -1,
+            debit: Some(2),
+            credit: Some(3),
+            balance: None,
+            notes: None,
+            ignore: Vec::new(),
+        };
+        let options = CsvOptions {
+            has_headers: false,
+            ..Default::default()
+        };
+
+        let parsed = read(&path, Some(mapping), &options).unwrap();
+        assert_eq!(
+            parsed.headers,
+            vec!["Column 1", "Column 2", "Column 3", "Column 4"]
+        );
+        let amounts: Vec<_> = parsed.rows.iter().map(|r| r.amount).collect();
+        assert_eq!(amounts, vec![Some(-12.5), Some(2000.0)]);
+    }
+
+    #[test]
+    fn samples_headers_and_counts_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("statement.csv");
+        let mut text = String::from("Date;Details;Amount\r\n\r\n");
+        for day in 0..PREVIEW_ROWS + 5 {
+            text.push_str(&format!("{};Coles;-{}\r\n", day, day));
+        }
+        std::fs::write(&path, text).unwrap();
+
+        let sampled = sample(&path, true).unwrap();
+        assert_eq!(sampled.headers, vec!["Date", "Details", "Amount"]);
+        assert_eq!(sampled.rows.len(), PREVIEW_ROWS);
+        assert_eq!(sampled.rows[1], vec!["1", "Coles", "-1"]);
+        assert_eq!(sampled.total_rows, PREVIEW_ROWS + 5);
+        assert_eq!(sampled.encoding, "UTF-8");
+
+        let sampled = sample(&path, false).unwrap();
+        assert_eq!(sampled.headers, vec!["Column 1", "Column 2", "Column 3"]);
+        assert_eq!(sampled.rows[0], vec!["Date", "Details", "Amount"]);
+        assert_eq!(sampled.total_rows, PREVIEW_ROWS + 6);
+    }
+}
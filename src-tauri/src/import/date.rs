@@ -0,0 +1,270 @@
+//! Statement dates
+//!
+//! A port of `lib/csv/date-parser.ts`: dates are read as ISO, day-first or
+//! month-first numbers, or with month names ("01 Jan 2024", "Jan 1, 2024",
+//! "01Jan24"), and always come out as `YYYY-MM-DD`.
+
+/// Date layouts a column can be read with (`DateFormat` in `types/import.ts`)
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum DateFormat {
+    #[serde(rename = "YYYY-MM-DD")]
+    Iso,
+    #[serde(rename = "DD/MM/YYYY")]
+    DayFirst,
+    #[serde(rename = "MM/DD/YYYY")]
+    MonthFirst,
+    #[serde(rename = "DD-MM-YYYY")]
+    DayFirstDashed,
+    /// Work each date out on its own
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+}
+
+fn is_leap(year: u32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn is_valid(year: u32, month: u32, day: u32) -> bool {
+    if !(1900..=2100).contains(&year) || !(1..=12).contains(&month) || day < 1 {
+        return false;
+    }
+    let days = match month {
+        2 if is_leap(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    day <= days
+}
+
+/// `YYYY-MM-DD`, if the parts make a real date
+fn iso(year: u32, month: u32, day: u32) -> Option<String> {
+    is_valid(year, month, day).then(|| format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+/// Two-digit years: 51-99 are 1900s, 00-50 are 2000s
+fn full_year(year: u32) -> u32 {
+    match year {
+        0..=50 => 2000 + year,
+        51..=99 => 1900 + year,
+        _ => year,
+    }
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    let month = match name.to_ascii_lowercase().as_str() {
+        "jan" | "january" => 1,
+        "feb" | "february" => 2,
+        "mar" | "march" => 3,
+        "apr" | "april" => 4,
+        "may" => 5,
+        "jun" | "june" => 6,
+        "jul" | "july" => 7,
+        "aug" | "august" => 8,
+        "sep" | "sept" | "september" => 9,
+        "oct" | "october" => 10,
+        "nov" | "november" => 11,
+        "dec" | "december" => 12,
+        _ => return None,
+    };
+    Some(month)
+}
+
+fn digits(s: &str, min: usize, max: usize) -> Option<u32> {
+    (s.len() >= min && s.len() <= max && s.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| s.parse().ok())
+        .flatten()
+}
+
+fn letters(s: &str) -> bool {
+    (3..=9).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_alphabetic())
+}
+
+/// Three numbers split on `-` or `/`
+fn numeric_parts(text: &str) -> Option<[&str; 3]> {
+    let mut parts = text.split(['-', '/']);
+    let parts = [parts.next()?, parts.next()?, parts.next()?];
+    (text.split(['-', '/']).count() == 3).then_some(parts)
+}
+
+/// Parse `text` on its own, returning the date, the layout it implies and
+/// how sure that is
+pub fn detect(text: &str) -> Option<(String, DateFormat, f32)> {
+    let text = text.trim();
+
+    if let Some([a, b, c]) = numeric_parts(text) {
+        if let (Some(year), Some(month), Some(day)) =
+            (digits(a, 4, 4), digits(b, 1, 2), digits(c, 1, 2))
+        {
+            if let Some(date) = iso(year, month, day) {
+                return Some((date, DateFormat::Iso, 1.0));
+            }
+        }
+        if let (Some(first), Some(second), Some(year)) =
+            (digits(a, 1, 2), digits(b, 1, 2), digits(c, 2, 4))
+        {
+            let year = full_year(year);
+            if first > 12 && second <= 12 {
+                return iso(year, second, first).map(|d| (d, DateFormat::DayFirst, 0.9));
+            }
+            if first <= 12 && second > 12 {
+                return iso(year, first, second).map(|d| (d, DateFormat::MonthFirst, 0.9));
+            }
+            // Ambiguous; day-first is more common outside the US
+            if let Some(date) = iso(year, second, first) {
+                return Some((date, DateFormat::DayFirst, 0.6));
+            }
+            return iso(year, first, second).map(|d| (d, DateFormat::MonthFirst, 0.5));
+        }
+    }
+
+    let words: Vec<&str> = text.split_whitespace().collect();
+    match words.as_slice() {
+        // "01 Jan 2024", "1 January 24"
+        [day, month, year] if letters(month) => {
+            let (day, year) = (digits(day, 1, 2)?, full_year(digits(year, 2, 4)?));
+            iso(year, month_number(month)?, day).map(|d| (d, DateFormat::Auto, 0.85))
+        }
+        // "Jan 01, 2024", "January 1 2024"
+        [month, day, year] if letters(month) => {
+            let day = digits(day.strip_suffix(',').unwrap_or(day), 1, 2)?;
+            let year = full_year(digits(year, 2, 4)?);
+            iso(year, month_number(month)?, day).map(|d| (d, DateFormat::Auto, 0.85))
+        }
+        // "01Jan2024", "01Jan24"
+        [compact] => {
+            let split = compact.find(|c: char| c.is_ascii_alphabetic())?;
+            let (day, rest) = compact.split_at(split);
+            let (month, year) = rest.split_at(rest.len().min(3));
+            let (day, year) = (digits(day, 1, 2)?, full_year(digits(year, 2, 4)?));
+            iso(year, month_number(month)?, day).map(|d| (d, DateFormat::Auto, 0.8))
+        }
+        _ => None,
+    }
+}
+
+/// Parse `text` as `format`, as `YYYY-MM-DD`
+pub fn parse(text: &str, format: DateFormat) -> Option<String> {
+    let text = text.trim();
+    if format == DateFormat::Auto {
+        return detect(text).map(|(date, _, _)| date);
+    }
+    let mut parts = text.split(['-', '/', '.']).map(str::trim);
+    let (a, b, c) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    let (a, b, c) = (digits(a, 1, 4)?, digits(b, 1, 4)?, digits(c, 1, 4)?);
+    let (year, month, day) = match format {
+        DateFormat::Iso => (a, b, c),
+        DateFormat::DayFirst | DateFormat::DayFirstDashed => (c, b, a),
+        DateFormat::MonthFirst => (c, a, b),
+        DateFormat::Auto => unreachable!(),
+    };
+    iso(full_year(year), month, day)
+}
+
+/// Most likely layout of a column from its first values. Ties go to the
+/// layout listed first in `DateFormat`, as in `detectDateFormat`.
+pub fn detect_format<'a>(samples: impl IntoIterator<Item = &'a str>) -> DateFormat {
+    const FORMATS: [DateFormat; 4] = [
+        DateFormat::Iso,
+        DateFormat::DayFirst,
+        DateFormat::MonthFirst,
+        DateFormat::DayFirstDashed,
+    ];
+    let mut counts = [0usize; FORMATS.len()];
+    for sample in samples {
+        let Some((_, format, confidence)) = detect(sample) else {
+            continue;
+        };
+        if confidence < 0.6 {
+            continue;
+        }
+        if let Some(i) = FORMATS.iter().position(|f| *f == format) {
+            counts[i] += 1;
+        }
+    }
+
+    let mut best = (DateFormat::Auto, 0);
+    for (format, count) in FORMATS.into_iter().zip(counts) {
+        if count > best.1 {
+            best = (format, count);
+        }
+    }
+    best.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_numeric_and_named_dates() {
+        let date = |text| detect(text).map(|(d, f, _)| (d, f));
+        assert_eq!(
+            date("2024-3-9"),
+            Some(("2024-03-09".into(), DateFormat::Iso))
+        );
+        assert_eq!(
+            date("25/12/2023"),
+            Some(("2023-12-25".into(), DateFormat::DayFirst))
+        );
+        assert_eq!(
+            date("12/25/23"),
+            Some(("2023-12-25".into(), DateFormat::MonthFirst))
+        );
+        assert_eq!(
+            date("03/04/2024"),
+            Some(("2024-04-03".into(), DateFormat::DayFirst))
+        );
+        assert_eq!(
+            date("01 Jan 2024"),
+            Some(("2024-01-01".into(), DateFormat::Auto))
+        );
+        assert_eq!(
+            date("January 5, 99"),
+            Some(("1999-01-05".into(), DateFormat::Auto))
+        );
+        assert_eq!(
+            date("29Feb24"),
+            Some(("2024-02-29".into(), DateFormat::Auto))
+        );
+        assert_eq!(date("29Feb23"), None);
+        assert_eq!(date("31/31/2024"), None);
+        assert_eq!(date("Opening balance"), None);
+    }
+
+    #[test]
+    fn parses_with_a_format() {
+        assert_eq!(
+            parse("03/04/2024", DateFormat::MonthFirst).as_deref(),
+            Some("2024-03-04")
+        );
+        assert_eq!(
+            parse("03.04.24", DateFormat::DayFirst).as_deref(),
+            Some("2024-04-03")
+        );
+        assert_eq!(
+            parse("2024/04/03", DateFormat::Iso).as_deref(),
+            Some("2024-04-03")
+        );
+        assert_eq!(parse("13/25/2024", DateFormat::MonthFirst), None);
+    }
+
+    #[test]
+    fn detects_column_format_from_unambiguous_samples() {
+        assert_eq!(
+            detect_format(["01/02/2024", "05/02/2024", "02/13/2024"]),
+            DateFormat::DayFirst
+        );
+        assert_eq!(
+            detect_format(["02/13/2024", "02/14/2024", "03/01/2024"]),
+            DateFormat::MonthFirst
+        );
+        assert_eq!(detect_format(["1 Jan 2024"]), DateFormat::Auto);
+    }
+}
@@ -0,0 +1,1035 @@
+//! Native statement import
+//!
+//! Parsers turn statement files into [`PreviewRow`]s, which are checked for
+//! duplicates and held in [`StagedImports`] until the webview commits or
+//! discards them. [`commit_rows`] writes them to `puffin.db` the way the
+//! webview's import does (`lib/services/handlers/transactions.ts`):
+//! duplicates are skipped by fingerprint, active rules categorize by
+//! description, and every row shares one `import_batch_id` (the staged batch
+//! ID) so the import can be undone as a unit.
+
+pub mod camt;
+pub mod csv;
+pub mod date;
+pub mod ofx;
+pub mod paste;
+pub mod pdf;
+pub mod qif;
+pub mod watch;
+
+use crate::db::{database_path, open, DbError};
+use crate::launch::OpenedFiles;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use tauri::Manager;
+use tauri_plugin_dialog::DialogExt;
+
+/// Most rows one import may add. The webview's own import stops at
+/// `MAX_IMPORT_TRANSACTIONS` (`lib/validations.ts`); streamed imports of
+/// multi-year exports may be larger.
+pub const MAX_IMPORT_ROWS: usize = 50_000;
+
+/// Rows returned in a preview; counts cover the whole file
+pub const PREVIEW_ROWS: usize = 200;
+
+/// Staged batches kept at once; the oldest is dropped beyond this
+const MAX_STAGED: usize = 4;
+
+/// Batches from the watched folder kept at once. They aren't dropped, so the
+/// watcher waits for a free slot before staging more.
+const MAX_WATCHED: usize = 8;
+
+/// Longest note kept per row (`IMPORT_NOTES_MAX_LENGTH` in `lib/validations.ts`)
+const NOTES_MAX_CHARS: usize = 250;
+
+/// Description of rows that have none, as in the import wizard
+const DEFAULT_DESCRIPTION: &str = "No description";
+
+/// Import failure, serialized as `{ kind, message }`
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum ImportError {
+    Io(String),
+    Database(String),
+    /// The file isn't a statement we can read
+    Invalid(String),
+    /// Nothing to import
+    Empty,
+    /// More rows than `MAX_IMPORT_ROWS`; parsers stop at the first row over
+    TooMany(usize),
+    /// No staged batch with this ID (committed, discarded or dropped)
+    NotStaged(String),
+    /// A path outside the folders the app may read
+    NotAllowed(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(msg) => write!(f, "Import I/O error: {}", msg),
+            ImportError::Database(msg) => write!(f, "Import database error: {}", msg),
+            ImportError::Invalid(msg) => write!(f, "Invalid statement: {}", msg),
+            ImportError::Empty => write!(f, "No transactions to import"),
+            ImportError::TooMany(_) => write!(
+                f,
+                "More than the {} transactions allowed per import",
+                MAX_IMPORT_ROWS
+            ),
+            ImportError::NotStaged(id) => write!(f, "Import batch not found: {}", id),
+            ImportError::NotAllowed(msg) => write!(f, "Not allowed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        ImportError::Io(e.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for ImportError {
+    fn from(e: rusqlite::Error) -> Self {
+        ImportError::Database(e.to_string())
+    }
+}
+
+impl From<DbError> for ImportError {
+    fn from(e: DbError) -> Self {
+        match e {
+            DbError::Io(msg) => ImportError::Io(msg),
+            other => ImportError::Database(other.to_string()),
+        }
+    }
+}
+
+/// One parsed statement line
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct ImportRow {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub description: String,
+    pub amount: f64,
+    pub notes: Option<String>,
+    /// The bank's own ID for the transaction (an OFX `FITID`)
+    pub external_id: Option<String>,
+}
+
+/// A statement row as parsed, for the preview (`ParsedRow` in `types/import.ts`)
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct PreviewRow {
+    pub row_index: usize,
+    /// Fields as read from the file
+    pub raw: Vec<String>,
+    pub date: Option<String>,
+    pub description: String,
+    pub amount: Option<f64>,
+    pub notes: Option<String>,
+    pub external_id: Option<String>,
+    /// Why the row can't be imported; empty when it can
+    pub errors: Vec<String>,
+    pub is_duplicate: bool,
+    /// The row had no description and got "No description"
+    pub has_default_description: bool,
+}
+
+impl PreviewRow {
+    /// The row to insert, if it parsed
+    pub fn import_row(&self) -> Option<ImportRow> {
+        match (&self.date, self.amount) {
+            (Some(date), Some(amount)) if self.errors.is_empty() => Some(ImportRow {
+                date: date.clone(),
+                description: self.description.clone(),
+                amount,
+                notes: self.notes.clone(),
+                external_id: self.external_id.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// `text` as a row's notes, cut to `NOTES_MAX_CHARS`; `None` when empty
+fn row_notes(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.chars().take(NOTES_MAX_CHARS).collect())
+}
+
+/// `description` without the parts that differ between exports of the same
+/// transaction, as `normalizeDescription` in `lib/csv/duplicate-detector.ts`:
+/// reference, authorisation and order numbers, card numbers, `*` and `#`,
+/// and embedded dates and times
+pub fn normalize_description(description: &str) -> String {
+    static NOISE: OnceLock<Vec<Regex>> = OnceLock::new();
+    let noise = NOISE.get_or_init(|| {
+        [
+            r"ref[:.]?\s*[\w-]+",
+            r"reference[:.]?\s*[\w-]+",
+            r"conf(?:irmation)?[:.]?\s*[\w-]+",
+            r"auth(?:orization)?[:.]?\s*[\w-]+",
+            r"trans(?:action)?[:.]?\s*#?\s*[\w-]+",
+            r"order[:.]?\s*#?\s*[\w-]+",
+            // Card numbers, in full or masked
+            r"\d{16,}",
+            r"x{4,}\d{4}",
+            r"\*{4,}\d{4}",
+            r"\*+",
+            r"#+",
+            // Dates (DD/MM, MM/DD, with or without a year) and times
+            r"\d{1,2}/\d{1,2}(?:/\d{2,4})?",
+            r"\d{1,2}:\d{2}(?::\d{2})?\s*(?:am|pm)?",
+        ]
+        .iter()
+        // ASCII classes, as in JavaScript
+        .map(|pattern| Regex::new(&format!("(?-u){}", pattern)).unwrap())
+        .collect()
+    });
+
+    let mut text = collapse_whitespace(&description.to_lowercase());
+    for pattern in noise {
+        text = pattern.replace_all(&text, "").into_owned();
+    }
+    collapse_whitespace(&text)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Duplicate key: the date, the amount to the cent and the normalized
+/// description
+pub fn fingerprint(date: &str, amount: f64, description: &str) -> String {
+    format!(
+        "{}|{:.2}|{}",
+        date,
+        amount,
+        normalize_description(description)
+    )
+}
+
+/// Flag rows that match an existing transaction in `conn` or an earlier row.
+/// `source_id` is where the rows will go, when known; bank IDs are only
+/// compared within it.
+pub fn mark_duplicates(
+    conn: Option<&Connection>,
+    rows: &mut [PreviewRow],
+    source_id: Option<&str>,
+) -> Result<(), ImportError> {
+    let dates = rows.iter().filter_map(|r| r.date.as_deref());
+    let (start, end) = (dates.clone().min(), dates.max());
+    let mut seen = match (conn, start, end) {
+        (Some(conn), Some(start), Some(end)) => SeenKeys::existing(conn, start, end, source_id)?,
+        _ => SeenKeys::default(),
+    };
+    for row in rows {
+        if let Some(new) = row.import_row() {
+            row.is_duplicate = seen.insert(&new);
+        }
+    }
+    Ok(())
+}
+
+/// Duplicate keys of the transactions seen so far. A row with a bank ID is a
+/// duplicate if the ID was seen, or if its fingerprint matches a transaction
+/// without one (say, the same statement imported from CSV); two rows with
+/// different IDs are never duplicates. Rows without an ID go by fingerprint.
+/// Banks number transactions per account, so IDs are only compared with
+/// transactions from the source being imported into.
+#[derive(Default)]
+struct SeenKeys {
+    ids: HashSet<String>,
+    fingerprints: HashSet<String>,
+    /// Fingerprints of transactions without a bank ID
+    without_id: HashSet<String>,
+}
+
+impl SeenKeys {
+    /// Keys of live transactions: the bank IDs of those in `source_id` (no
+    /// source when `None`), and the fingerprints of all dated `start..=end`
+    fn existing(
+        conn: &Connection,
+        start: &str,
+        end: &str,
+        source_id: Option<&str>,
+    ) -> Result<Self, ImportError> {
+        let mut seen = SeenKeys::default();
+        let mut stmt = conn.prepare(
+            "SELECT date, amount, description, external_id IS NULL FROM \"transaction\"
+             WHERE date >= ?1 AND date <= ?2 AND is_deleted = 0",
+        )?;
+        let mut rows = stmt.query([start, end])?;
+        while let Some(row) = rows.next()? {
+            let key = fingerprint(
+                &row.get::<_, String>(0)?,
+                row.get(1)?,
+                &row.get::<_, String>(2)?,
+            );
+            if row.get(3)? {
+                seen.without_id.insert(key.clone());
+            }
+            seen.fingerprints.insert(key);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT external_id FROM \"transaction\"
+             WHERE external_id IS NOT NULL AND source_id IS ?1 AND is_deleted = 0",
+        )?;
+        for id in stmt.query_map([source_id], |row| row.get(0))? {
+            seen.ids.insert(id?);
+        }
+        Ok(seen)
+    }
+
+    fn add(&mut self, key: String, id: Option<String>) {
+        match id {
+            Some(id) => {
+                self.ids.insert(id);
+            }
+            None => {
+                self.without_id.insert(key.clone());
+            }
+        }
+        self.fingerprints.insert(key);
+    }
+
+    /// Record `row`, returning whether it duplicates one seen before
+    fn insert(&mut self, row: &ImportRow) -> bool {
+        let key = fingerprint(&row.date, row.amount, &row.description);
+        let seen = match &row.external_id {
+            Some(id) => self.ids.contains(id) || self.without_id.contains(&key),
+            None => self.fingerprints.contains(&key),
+        };
+        self.add(key, row.external_id.clone());
+        seen
+    }
+}
+
+/// Add a parsed row (or entry) to `rows`, failing once there are more than
+/// `MAX_IMPORT_ROWS`, so a huge statement is rejected before all of it is
+/// parsed
+pub(crate) fn push_row<T>(rows: &mut Vec<T>, row: T) -> Result<(), ImportError> {
+    if rows.len() >= MAX_IMPORT_ROWS {
+        return Err(ImportError::TooMany(rows.len() + 1));
+    }
+    rows.push(row);
+    Ok(())
+}
+
+/// Flag duplicates against the database at `db_path`, if there is one yet.
+/// The source isn't chosen yet, so bank IDs are compared with transactions
+/// that have none; `commit_import` checks again against the chosen source.
+fn mark_duplicates_in(db_path: &Path, rows: &mut [PreviewRow]) -> Result<(), ImportError> {
+    let conn = if db_path.is_file() {
+        Some(open(db_path, true)?)
+    } else {
+        None
+    };
+    mark_duplicates(conn.as_ref(), rows, None)
+}
+
+/// Read a whole statement file as text, in the encoding `csv` detects
+fn read_text(path: &Path) -> Result<String, ImportError> {
+    let bytes = fs::read(path)?;
+    let (text, _) = csv::detect_encoding(&bytes, true).decode_with_bom_removal(&bytes);
+    Ok(text.into_owned())
+}
+
+/// A statement file's rows, and the account it is for when the file says
+#[derive(Debug)]
+pub struct Statement {
+    pub rows: Vec<PreviewRow>,
+    /// Account number: an IBAN or OFX `ACCTID`
+    pub account: Option<String>,
+    pub account_name: Option<String>,
+}
+
+/// Parse the statement at `path` by its extension: OFX/QFX, QIF, camt XML,
+/// else CSV with detected columns. `date_format` applies to CSV and QIF.
+pub fn read_statement(
+    path: &Path,
+    date_format: date::DateFormat,
+) -> Result<Statement, ImportError> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let statement = |rows| Statement {
+        rows,
+        account: None,
+        account_name: None,
+    };
+    Ok(match extension.as_str() {
+        "ofx" | "qfx" => {
+            let parsed = ofx::read(path)?;
+            Statement {
+                account: parsed.account,
+                ..statement(parsed.rows)
+            }
+        }
+        "qif" => statement(qif::read(path, &qif::QifOptions { date_format })?.rows),
+        "xml" => {
+            let parsed = camt::read(path)?;
+            Statement {
+                rows: parsed.rows,
+                account: parsed.account.number,
+                account_name: parsed.account.name,
+            }
+        }
+        _ => {
+            let options = csv::CsvOptions {
+                date_format,
+                ..Default::default()
+            };
+            statement(csv::read(path, None, &options)?.rows)
+        }
+    })
+}
+
+/// Statement paths from the webview must have been picked with
+/// [`choose_statement`] or opened with Puffin, so it can't have any other
+/// file read
+pub(crate) fn check_chosen(opened: &OpenedFiles, path: &Path) -> Result<(), ImportError> {
+    if opened.is_allowed(path) {
+        return Ok(());
+    }
+    Err(ImportError::NotAllowed(format!(
+        "{} wasn't chosen for import",
+        path.display()
+    )))
+}
+
+/// Ask the user for a statement file; `None` when they cancel. The path is
+/// recorded so the import commands accept it.
+#[tauri::command]
+pub async fn choose_statement(
+    app: tauri::AppHandle,
+    title: String,
+    filter_name: String,
+    extensions: Vec<String>,
+) -> Result<Option<String>, ImportError> {
+    let dialog = app.clone();
+    let picked = tauri::async_runtime::spawn_blocking(move || {
+        let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+        dialog
+            .dialog()
+            .file()
+            .set_title(title)
+            .add_filter(filter_name, &extensions)
+            .blocking_pick_file()
+    })
+    .await
+    .map_err(|e| ImportError::Io(e.to_string()))?;
+
+    let Some(picked) = picked else {
+        return Ok(None);
+    };
+    let path = picked
+        .into_path()
+        .map_err(|e| ImportError::Io(e.to_string()))?;
+    app.state::<OpenedFiles>().allow_path(path.clone());
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
+
+/// Summary of a staged batch (`ImportPreview` in `types/import.ts`)
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ImportPreview {
+    /// Pass to `commit_import`; becomes the rows' `import_batch_id`
+    pub batch_id: String,
+    pub total_rows: usize,
+    /// Rows that parsed and aren't duplicates
+    pub valid_count: usize,
+    pub duplicate_count: usize,
+    pub error_count: usize,
+    /// The first `PREVIEW_ROWS` rows
+    pub rows: Vec<PreviewRow>,
+}
+
+/// A staged batch: the rows that parsed, with their row index
+struct StagedBatch {
+    id: String,
+    /// The watched file the batch came from, with its preview. Such
+    /// batches are kept until committed or discarded.
+    watched: Option<(watch::WatchedFile, ImportPreview)>,
+    rows: Vec<(usize, ImportRow)>,
+}
+
+/// Parsed rows waiting for `commit_import`
+#[derive(Default)]
+pub struct StagedImports(Mutex<VecDeque<StagedBatch>>);
+
+impl StagedImports {
+    /// Stage the rows that parsed and summarize `rows`
+    pub fn stage(&self, rows: &mut Vec<PreviewRow>) -> ImportPreview {
+        self.push(rows, None)
+    }
+
+    /// Stage rows from the watched `file`; see [`Self::watched_slots`]
+    pub fn stage_watched(
+        &self,
+        rows: &mut Vec<PreviewRow>,
+        file: watch::WatchedFile,
+    ) -> ImportPreview {
+        self.push(rows, Some(file))
+    }
+
+    /// How many more batches the watcher may stage
+    pub fn watched_slots(&self) -> usize {
+        let staged = self.0.lock().unwrap();
+        MAX_WATCHED.saturating_sub(staged.iter().filter(|b| b.watched.is_some()).count())
+    }
+
+    /// Staged batches from the watched folder, oldest first
+    pub fn watched(&self) -> Vec<(watch::WatchedFile, ImportPreview)> {
+        let staged = self.0.lock().unwrap();
+        staged.iter().filter_map(|b| b.watched.clone()).collect()
+    }
+
+    /// Whether a batch from the watched file at `path` is staged
+    pub fn is_watched_file(&self, path: &Path) -> bool {
+        let staged = self.0.lock().unwrap();
+        staged.iter().any(|b| {
+            b.watched
+                .as_ref()
+                .is_some_and(|(file, _)| file.path == path)
+        })
+    }
+
+    fn push(&self, rows: &mut Vec<PreviewRow>, file: Option<watch::WatchedFile>) -> ImportPreview {
+        let valid: Vec<(usize, ImportRow)> = rows
+            .iter()
+            .filter_map(|r| r.import_row().map(|row| (r.row_index, row)))
+            .collect();
+        let duplicate_count = rows.iter().filter(|r| r.is_duplicate).count();
+        let preview = ImportPreview {
+            batch_id: uuid::Uuid::new_v4().to_string(),
+            total_rows: rows.len(),
+            valid_count: valid.len() - duplicate_count,
+            duplicate_count,
+            error_count: rows.len() - valid.len(),
+            rows: rows.drain(..rows.len().min(PREVIEW_ROWS)).collect(),
+        };
+
+        let mut staged = self.0.lock().unwrap();
+        // Only batches staged from the webview make room for each other
+        while file.is_none() && staged.iter().filter(|b| b.watched.is_none()).count() >= MAX_STAGED
+        {
+            let Some(oldest) = staged.iter().position(|b| b.watched.is_none()) else {
+                break;
+            };
+            staged.remove(oldest);
+        }
+        staged.push_back(StagedBatch {
+            id: preview.batch_id.clone(),
+            watched: file.map(|f| (f, preview.clone())),
+            rows: valid,
+        });
+        preview
+    }
+
+    /// Unstage batch `batch_id`, so it can only be committed once
+    fn take(&self, batch_id: &str) -> Option<StagedBatch> {
+        let mut staged = self.0.lock().unwrap();
+        let index = staged.iter().position(|b| b.id == batch_id)?;
+        staged.remove(index)
+    }
+
+    /// Stage a batch again after a failed commit. It goes to the front, as
+    /// the batch the next `stage` evicts.
+    fn restore(&self, batch: StagedBatch) {
+        self.0.lock().unwrap().push_front(batch);
+    }
+}
+
+/// Result of committing an import
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct ImportSummary {
+    /// `import_batch_id` of the new rows; `None` when nothing was added
+    pub batch_id: Option<String>,
+    pub imported: usize,
+    pub duplicates: usize,
+    pub auto_categorized: usize,
+}
+
+/// Id of the source called `name` (case-insensitive)
+pub fn find_source(conn: &Connection, name: &str) -> Result<Option<String>, ImportError> {
+    Ok(conn
+        .query_row(
+            "SELECT id FROM source WHERE lower(name) = lower(?1)",
+            [name.trim()],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Source for a statement's account: the first whose name includes the
+/// account number (ignoring spaces and case, so "Savings DE89 3704 ..."
+/// matches an IBAN), else one named `name`
+pub fn find_account_source(
+    conn: &Connection,
+    number: Option<&str>,
+    name: Option<&str>,
+) -> Result<Option<String>, ImportError> {
+    let compact = |text: &str| -> String {
+        text.chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    if let Some(number) = number.map(compact).filter(|n| !n.is_empty()) {
+        let mut stmt = conn.prepare("SELECT id, name FROM source ORDER BY sort_order, name")?;
+        let sources = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?;
+        for source in sources {
+            let (id, source_name): (String, String) = source?;
+            if compact(&source_name).contains(&number) {
+                return Ok(Some(id));
+            }
+        }
+    }
+    match name.filter(|n| !n.trim().is_empty()) {
+        Some(name) => find_source(conn, name),
+        None => Ok(None),
+    }
+}
+
+/// Active rules as (lowercased match text, sub-category), in priority order
+fn active_rules(conn: &Connection) -> Result<Vec<(String, String)>, ImportError> {
+    let mut stmt = conn.prepare(
+        "SELECT match_text, sub_category_id FROM auto_category_rule
+         WHERE is_active = 1 ORDER BY priority ASC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?.to_lowercase(), row.get(1)?))
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Insert `rows` in one transaction as batch `batch_id`. With
+/// `skip_duplicates`, rows matching existing transactions or each other are
+/// left out.
+pub fn commit_rows(
+    conn: &mut Connection,
+    batch_id: &str,
+    rows: &[ImportRow],
+    source_id: Option<&str>,
+    skip_duplicates: bool,
+) -> Result<ImportSummary, ImportError> {
+    let forced = vec![!skip_duplicates; rows.len()];
+    commit_rows_forcing(conn, batch_id, rows, &forced, source_id)
+}
+
+/// [`commit_rows`] skipping duplicates, except the rows whose entry in
+/// `forced` is true (duplicates the user chose to import anyway)
+pub fn commit_rows_forcing(
+    conn: &mut Connection,
+    batch_id: &str,
+    rows: &[ImportRow],
+    forced: &[bool],
+    source_id: Option<&str>,
+) -> Result<ImportSummary, ImportError> {
+    if rows.is_empty() {
+        return Err(ImportError::Empty);
+    }
+    if rows.len() > MAX_IMPORT_ROWS {
+        return Err(ImportError::TooMany(rows.len()));
+    }
+
+    let tx = conn.transaction()?;
+    let start = rows
+        .iter()
+        .map(|r| r.date.as_str())
+        .min()
+        .unwrap_or_default();
+    let end = rows
+        .iter()
+        .map(|r| r.date.as_str())
+        .max()
+        .unwrap_or_default();
+    let mut seen = if forced.iter().any(|force| !force) {
+        SeenKeys::existing(&tx, start, end, source_id)?
+    } else {
+        SeenKeys::default()
+    };
+    let rules = active_rules(&tx)?;
+
+    let mut summary = ImportSummary {
+        batch_id: None,
+        imported: 0,
+        duplicates: 0,
+        auto_categorized: 0,
+    };
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO \"transaction\" (
+               id, date, description, amount, notes, sub_category_id, source_id,
+               is_split, parent_transaction_id, is_deleted, import_batch_id, external_id,
+               created_at, updated_at
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, NULL, 0, ?8, ?9,
+               strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))",
+        )?;
+        for (row, &force) in rows.iter().zip(forced) {
+            if seen.insert(row) && !force {
+                summary.duplicates += 1;
+                continue;
+            }
+            let description = row.description.to_lowercase();
+            let category = rules
+                .iter()
+                .find(|(text, _)| description.contains(text.as_str()))
+                .map(|(_, id)| id.as_str());
+            insert.execute(rusqlite::params![
+                uuid::Uuid::new_v4().to_string(),
+                row.date,
+                row.description,
+                row.amount,
+                row.notes,
+                category,
+                source_id,
+                batch_id,
+                row.external_id,
+            ])?;
+            summary.imported += 1;
+            if category.is_some() {
+                summary.auto_categorized += 1;
+            }
+        }
+    }
+    tx.commit()?;
+
+    if summary.imported > 0 {
+        summary.batch_id = Some(batch_id.to_string());
+    }
+    Ok(summary)
+}
+
+/// Write staged batch `batch_id` to the database, leaving out the rows in
+/// `exclude`. Duplicates are skipped unless `skip_duplicates` is false,
+/// except the rows in `force` (by row index).
+#[tauri::command]
+pub async fn commit_import(
+    app: tauri::AppHandle,
+    staged: tauri::State<'_, StagedImports>,
+    batch_id: String,
+    source_id: Option<String>,
+    skip_duplicates: Option<bool>,
+    exclude: Option<Vec<usize>>,
+    force: Option<Vec<usize>>,
+) -> Result<ImportSummary, ImportError> {
+    let exclude: HashSet<usize> = exclude.unwrap_or_default().into_iter().collect();
+    let force: HashSet<usize> = force.unwrap_or_default().into_iter().collect();
+    let skip_duplicates = skip_duplicates.unwrap_or(true);
+    let db_path = database_path(&app)?;
+    // Taken rather than copied, so a second commit of the same batch (say, a
+    // double click) finds nothing staged instead of importing it again
+    let batch = staged
+        .take(&batch_id)
+        .ok_or_else(|| ImportError::NotStaged(batch_id.clone()))?;
+
+    let id = batch_id.clone();
+    let (batch, result) = tauri::async_runtime::spawn_blocking(move || {
+        let (rows, forced): (Vec<ImportRow>, Vec<bool>) = batch
+            .rows
+            .iter()
+            .filter(|(index, _)| !exclude.contains(index))
+            .map(|(index, row)| (row.clone(), !skip_duplicates || force.contains(index)))
+            .unzip();
+        let result = open(&db_path, false)
+            .map_err(ImportError::from)
+            .and_then(|mut conn| {
+                commit_rows_forcing(&mut conn, &id, &rows, &forced, source_id.as_deref())
+            });
+        (batch, result)
+    })
+    .await
+    .map_err(|e| ImportError::Io(e.to_string()))?;
+
+    match (&result, batch.watched) {
+        (Ok(_), Some((file, _))) => watch::file_done(&app, &file),
+        (Ok(_), None) => {}
+        (Err(_), watched) => staged.restore(StagedBatch { watched, ..batch }),
+    }
+    result
+}
+
+/// Drop staged batch `batch_id` without importing it
+#[tauri::command]
+pub fn discard_import(
+    app: tauri::AppHandle,
+    staged: tauri::State<'_, StagedImports>,
+    batch_id: String,
+) -> bool {
+    let Some(batch) = staged.take(&batch_id) else {
+        return false;
+    };
+    if let Some((file, _)) = &batch.watched {
+        watch::file_done(&app, file);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations::migrate;
+
+    fn row(date: &str, description: &str, amount: f64) -> ImportRow {
+        ImportRow {
+            date: date.to_string(),
+            description: description.to_string(),
+            amount,
+            notes: None,
+            external_id: None,
+        }
+    }
+
+    #[test]
+    fn commit_skips_duplicates_and_applies_rules() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO sub_category (id, upper_category_id, name) VALUES ('food', 'expense', 'Food');
+             INSERT INTO auto_category_rule (id, match_text, sub_category_id) VALUES ('r', 'coles', 'food');
+             INSERT INTO source (id, name) VALUES ('bank', 'Everyday');
+             INSERT INTO \"transaction\" (id, date, description, amount) VALUES ('t', '2024-01-02', 'Rent', -500);",
+        )
+        .unwrap();
+        let source = find_source(&conn, "everyday").unwrap();
+        assert_eq!(source.as_deref(), Some("bank"));
+
+        let rows = [
+            row("2024-01-02", " RENT ", -500.0),
+            row("2024-01-03", "COLES 123", -42.5),
+            row("2024-01-03", "coles 123", -42.5),
+            row("2024-01-04", "Salary", 2000.0),
+        ];
+        let summary = commit_rows(&mut conn, "batch", &rows, source.as_deref(), true).unwrap();
+        assert_eq!(
+            (
+                summary.imported,
+                summary.duplicates,
+                summary.auto_categorized
+            ),
+            (2, 2, 1)
+        );
+
+        let batch: (i64, Option<String>) = conn
+            .query_row(
+                "SELECT COUNT(*), MAX(sub_category_id) FROM \"transaction\"
+                 WHERE import_batch_id = ?1 AND source_id = 'bank'",
+                [summary.batch_id.as_deref().unwrap()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(batch, (2, Some("food".to_string())));
+
+        // Everything is a duplicate the second time round, unless the
+        // duplicates were chosen on purpose
+        let again = commit_rows(&mut conn, "again", &rows, None, true).unwrap();
+        assert_eq!((again.imported, again.batch_id), (0, None));
+        let forced = commit_rows(&mut conn, "forced", &rows[..1], None, false).unwrap();
+        assert_eq!(forced.imported, 1);
+        // Only the ticked duplicate goes in; the other is still skipped
+        let ticked =
+            commit_rows_forcing(&mut conn, "ticked", &rows[..2], &[true, false], None).unwrap();
+        assert_eq!((ticked.imported, ticked.duplicates), (1, 1));
+    }
+
+    #[test]
+    fn normalizes_descriptions_like_the_webview() {
+        let cases = [
+            ("  Netflix.com   Sydney ", "netflix.com sydney"),
+            (
+                "EFTPOS Coles 0871 REF: 44A-19 AUTH 667812",
+                "eftpos coles 0871",
+            ),
+            ("Amazon Order #113-4479 Conf.88ZQ", "amazon"),
+            (
+                "Card 4111111111111111 Shell XXXX1234 ****5678",
+                "card shell",
+            ),
+            ("*UBER EATS #2 12/03/2024 7:45 pm", "uber eats 2"),
+        ];
+        for (description, normalized) in cases {
+            assert_eq!(normalize_description(description), normalized);
+        }
+        assert_eq!(
+            fingerprint("2024-03-12", -9.999, "Spotify*Premium 03/12"),
+            "2024-03-12|-10.00|spotifypremium"
+        );
+    }
+
+    #[test]
+    fn finds_source_by_account() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE source (id TEXT, name TEXT, sort_order INTEGER);
+             INSERT INTO source VALUES ('giro', 'Giro DE89 3704 0044 0532 0130 00', 0);
+             INSERT INTO source VALUES ('savings', 'Savings', 1);",
+        )
+        .unwrap();
+        let find = |number, name| find_account_source(&conn, number, name).unwrap();
+        assert_eq!(
+            find(Some("DE89370400440532013000"), Some("Savings")).as_deref(),
+            Some("giro")
+        );
+        assert_eq!(
+            find(Some("DE02"), Some("savings")).as_deref(),
+            Some("savings")
+        );
+        assert_eq!(find(None, Some("Credit card")), None);
+        assert_eq!(find(Some(" "), None), None);
+    }
+
+    #[test]
+    fn bank_ids_are_duplicate_keys() {
+        let with_id = |id: &str, description: &str| ImportRow {
+            external_id: Some(id.to_string()),
+            ..row("2024-01-03", description, -42.5)
+        };
+        let mut seen = SeenKeys::default();
+        assert!(!seen.insert(&with_id("1", "Coles")));
+        // Another ID is another transaction, whatever it looks like
+        assert!(!seen.insert(&with_id("2", "Coles")));
+        assert!(seen.insert(&with_id("1", "COLES 123")));
+        // Without an ID, only the fingerprint is there to go on
+        assert!(seen.insert(&row("2024-01-03", "coles", -42.5)));
+
+        let mut seen = SeenKeys::default();
+        assert!(!seen.insert(&row("2024-01-04", "Salary", 2000.0)));
+        assert!(seen.insert(&ImportRow {
+            external_id: Some("3".to_string()),
+            ..row("2024-01-04", "Salary", 2000.0)
+        }));
+    }
+
+    #[test]
+    fn stages_parsed_rows_and_flags_duplicates() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute(
+            "INSERT INTO \"transaction\" (id, date, description, amount) VALUES ('t', '2024-01-02', 'Rent', -500)",
+            [],
+        )
+        .unwrap();
+
+        let preview_row = |index: usize, row: Option<ImportRow>| PreviewRow {
+            row_index: index,
+            raw: Vec::new(),
+            date: row.as_ref().map(|r| r.date.clone()),
+            description: row
+                .as_ref()
+                .map_or("No description".into(), |r| r.description.clone()),
+            amount: row.as_ref().map(|r| r.amount),
+            notes: None,
+            external_id: row.as_ref().and_then(|r| r.external_id.clone()),
+            errors: if row.is_some() {
+                Vec::new()
+            } else {
+                vec!["Missing amount".into()]
+            },
+            is_duplicate: false,
+            has_default_description: false,
+        };
+        let mut rows = vec![
+            preview_row(0, Some(row("2024-01-02", "RENT", -500.0))),
+            preview_row(1, Some(row("2024-01-03", "Coles", -42.5))),
+            preview_row(2, None),
+            preview_row(3, Some(row("2024-01-03", "coles ", -42.5))),
+        ];
+        mark_duplicates(Some(&conn), &mut rows, None).unwrap();
+        let flags: Vec<bool> = rows.iter().map(|r| r.is_duplicate).collect();
+        assert_eq!(flags, vec![true, false, false, true]);
+
+        let staged = StagedImports::default();
+        let preview = staged.stage(&mut rows);
+        assert_eq!(
+            (
+                preview.total_rows,
+                preview.valid_count,
+                preview.duplicate_count,
+                preview.error_count
+            ),
+            (4, 1, 2, 1)
+        );
+        assert_eq!(preview.rows.len(), 4);
+        let batch = staged.take(&preview.batch_id).unwrap();
+        assert_eq!(
+            batch.rows.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            vec![0, 1, 3]
+        );
+        assert!(staged.take(&preview.batch_id).is_none());
+
+        staged.restore(batch);
+        assert!(staged.take(&preview.batch_id).is_some());
+    }
+
+    #[test]
+    fn watched_batches_are_not_evicted() {
+        let staged = StagedImports::default();
+        let file = |name: &str| watch::WatchedFile {
+            path: std::path::PathBuf::from(name),
+            stamp: watch::FileStamp {
+                size: 1,
+                modified: 0,
+            },
+            file_name: name.to_string(),
+            source_id: None,
+        };
+        let stage = |watched: Option<&str>| {
+            let mut rows = vec![PreviewRow {
+                row_index: 0,
+                raw: Vec::new(),
+                date: Some("2024-01-02".to_string()),
+                description: "Rent".to_string(),
+                amount: Some(-500.0),
+                notes: None,
+                external_id: None,
+                errors: Vec::new(),
+                is_duplicate: false,
+                has_default_description: false,
+            }];
+            let preview = match watched {
+                Some(name) => staged.stage_watched(&mut rows, file(name)),
+                None => staged.stage(&mut rows),
+            };
+            preview.batch_id
+        };
+
+        let names: Vec<String> = (0..MAX_WATCHED - 1).map(|i| format!("{i}.csv")).collect();
+        let watched: Vec<String> = names.iter().map(|n| stage(Some(n))).collect();
+        let manual: Vec<String> = (0..MAX_STAGED + 2).map(|_| stage(None)).collect();
+        assert_eq!(staged.watched_slots(), 1);
+        assert!(staged.is_watched_file(Path::new("0.csv")));
+        let listed = staged.watched();
+        assert_eq!(listed.len(), watched.len());
+        assert_eq!(listed[1].1.batch_id, watched[1]);
+        for id in &watched {
+            assert!(staged.take(id).is_some_and(|b| b.watched.is_some()));
+        }
+        assert!(!staged.is_watched_file(Path::new("0.csv")));
+        // Webview batches still make room for each other
+        assert!(staged.take(&manual[0]).is_none());
+        assert!(staged.take(&manual[1]).is_none());
+        assert!(staged.take(&manual[2]).is_some());
+        assert_eq!(staged.watched_slots(), MAX_WATCHED);
+    }
+
+    #[test]
+    fn only_chosen_files_are_read() {
+        let opened = OpenedFiles::default();
+        let chosen = Path::new("/home/me/statement.csv");
+        opened.allow_path(chosen.to_path_buf());
+
+        assert!(check_chosen(&opened, chosen).is_ok());
+        assert!(matches!(
+            check_chosen(&opened, Path::new("/home/me/.ssh/id_rsa")),
+            Err(ImportError::NotAllowed(_))
+        ));
+    }
+}
@@ -0,0 +1,424 @@
+//! OFX and QFX statements
+//!
+//! OFX 1.x files are SGML, where closing tags of values are optional; 2.x
+//! files are XML. Both are read as a run of tags, which suits either (QFX is
+//! OFX with some of Quicken's own tags). Each `<STMTTRN>` becomes a row:
+//! `DTPOSTED`, `TRNAMT`, `NAME` as the description (`MEMO` if there's no
+//! name) and `MEMO` as notes. `FITID` is kept as the row's bank ID, so
+//! importing the same transactions again finds them by ID.
+
+use super::date::{self, DateFormat};
+use super::{
+    check_chosen, mark_duplicates_in, push_row, read_text, row_notes, ImportError, ImportPreview,
+    PreviewRow, StagedImports, DEFAULT_DESCRIPTION,
+};
+use crate::db::database_path;
+use crate::launch::OpenedFiles;
+use std::path::Path;
+
+/// Tags and the text after each, up to the next tag
+struct Tags<'a> {
+    text: &'a str,
+}
+
+impl<'a> Iterator for Tags<'a> {
+    /// Tag name (`/NAME` when closing) and its value
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let open = self.text.find('<')?;
+            let rest = &self.text[open + 1..];
+            let close = rest.find('>')?;
+            let name = rest[..close].trim();
+            let after = &rest[close + 1..];
+            let end = after.find('<').unwrap_or(after.len());
+            self.text = &after[end..];
+            // Skip `<?xml ?>`, `<?OFX ?>` and comments
+            if !name.starts_with(['?', '!']) {
+                return Some((name.trim_end_matches('/'), after[..end].trim()));
+            }
+        }
+    }
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Fields of one `<STMTTRN>`
+#[derive(Default)]
+struct Entry {
+    posted: Option<String>,
+    amount: Option<String>,
+    fitid: Option<String>,
+    name: Option<String>,
+    memo: Option<String>,
+}
+
+impl Entry {
+    fn set(&mut self, tag: &str, value: &str) {
+        let field = match tag.to_ascii_uppercase().as_str() {
+            "DTPOSTED" => &mut self.posted,
+            "TRNAMT" => &mut self.amount,
+            "FITID" => &mut self.fitid,
+            // Also the payee's name, inside `<PAYEE>`
+            "NAME" => &mut self.name,
+            "MEMO" => &mut self.memo,
+            _ => return,
+        };
+        field.get_or_insert_with(|| unescape(value));
+    }
+
+    fn into_row(self, row_index: usize) -> PreviewRow {
+        let mut errors = Vec::new();
+        let posted = self.posted.unwrap_or_default();
+        let date = parse_date(&posted);
+        if date.is_none() {
+            errors.push(format!("Invalid date: {:?}", posted));
+        }
+        let amount_text = self.amount.unwrap_or_default();
+        let amount = match parse_amount(&amount_text) {
+            Ok(amount) => Some(amount),
+            Err(e) => {
+                errors.push(e);
+                None
+            }
+        };
+
+        let name = self.name.filter(|n| !n.is_empty());
+        let memo = self.memo.filter(|m| !m.is_empty());
+        let raw = vec![
+            posted,
+            amount_text,
+            name.clone().unwrap_or_default(),
+            memo.clone().unwrap_or_default(),
+            self.fitid.clone().unwrap_or_default(),
+        ];
+        let (description, notes) = match (name, memo) {
+            (Some(name), Some(memo)) if memo != name => (Some(name), row_notes(&memo)),
+            (Some(name), _) => (Some(name), None),
+            (None, memo) => (memo, None),
+        };
+
+        PreviewRow {
+            row_index,
+            raw,
+            has_default_description: description.is_none(),
+            description: description.unwrap_or_else(|| DEFAULT_DESCRIPTION.to_string()),
+            date,
+            amount,
+            notes,
+            external_id: self.fitid.filter(|id| !id.is_empty()),
+            errors,
+            is_duplicate: false,
+        }
+    }
+}
+
+/// `YYYYMMDD`, optionally followed by a time and zone, as `YYYY-MM-DD`
+fn parse_date(text: &str) -> Option<String> {
+    let digits = text
+        .get(..8)
+        .filter(|d| d.bytes().all(|b| b.is_ascii_digit()))?;
+    let iso = format!("{}-{}-{}", &digits[..4], &digits[4..6], &digits[6..]);
+    date::parse(&iso, DateFormat::Iso)
+}
+
+/// A signed amount; some banks write `+` or a decimal comma
+fn parse_amount(text: &str) -> Result<f64, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Missing amount".to_string());
+    }
+    let number = text.strip_prefix('+').unwrap_or(text);
+    let number = if number.contains('.') {
+        number.to_string()
+    } else {
+        number.replace(',', ".")
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|a| a.is_finite())
+        .ok_or_else(|| format!("Invalid amount: {:?}", text))
+}
+
+/// A parsed OFX file, before duplicates are checked
+#[derive(Debug)]
+pub struct ParsedOfx {
+    /// `ACCTID` of the (first) statement's account
+    pub account: Option<String>,
+    /// `CURDEF`, the statement's currency
+    pub currency: Option<String>,
+    pub rows: Vec<PreviewRow>,
+}
+
+/// Parse the text of an OFX or QFX file
+pub fn parse(text: &str) -> Result<ParsedOfx, ImportError> {
+    let mut parsed = ParsedOfx {
+        account: None,
+        currency: None,
+        rows: Vec::new(),
+    };
+    let mut is_ofx = false;
+    let mut entry: Option<Entry> = None;
+
+    for (tag, value) in (Tags { text }) {
+        if tag.eq_ignore_ascii_case("OFX") {
+            is_ofx = true;
+        } else if tag.eq_ignore_ascii_case("STMTTRN") {
+            // A new entry closes one left open
+            if let Some(open) = entry.replace(Entry::default()) {
+                let row = open.into_row(parsed.rows.len());
+                push_row(&mut parsed.rows, row)?;
+            }
+        } else if tag.eq_ignore_ascii_case("/STMTTRN") || tag.eq_ignore_ascii_case("/BANKTRANLIST")
+        {
+            if let Some(done) = entry.take() {
+                let row = done.into_row(parsed.rows.len());
+                push_row(&mut parsed.rows, row)?;
+            }
+        } else if value.is_empty() {
+            continue;
+        } else if let Some(open) = entry.as_mut() {
+            open.set(tag, value);
+        } else if tag.eq_ignore_ascii_case("ACCTID") {
+            parsed.account.get_or_insert_with(|| unescape(value));
+        } else if tag.eq_ignore_ascii_case("CURDEF") {
+            parsed.currency.get_or_insert_with(|| value.to_string());
+        }
+    }
+    if let Some(open) = entry {
+        let row = open.into_row(parsed.rows.len());
+        push_row(&mut parsed.rows, row)?;
+    }
+
+    if !is_ofx {
+        return Err(ImportError::Invalid("not an OFX file".to_string()));
+    }
+    if parsed.rows.is_empty() {
+        return Err(ImportError::Empty);
+    }
+    Ok(parsed)
+}
+
+/// Read and parse the OFX or QFX file at `path`
+pub fn read(path: &Path) -> Result<ParsedOfx, ImportError> {
+    parse(&read_text(path)?)
+}
+
+/// Result of `import_ofx`
+#[derive(Debug, serde::Serialize)]
+pub struct OfxPreview {
+    #[serde(flatten)]
+    pub preview: ImportPreview,
+    pub account: Option<String>,
+    pub currency: Option<String>,
+}
+
+/// Parse the OFX or QFX statement at `path`, check it for duplicates of
+/// existing transactions and stage it for `commit_import`
+#[tauri::command]
+pub async fn import_ofx(
+    app: tauri::AppHandle,
+    staged: tauri::State<'_, StagedImports>,
+    opened: tauri::State<'_, OpenedFiles>,
+    path: String,
+) -> Result<OfxPreview, ImportError> {
+    check_chosen(&opened, Path::new(&path))?;
+    let db_path = database_path(&app)?;
+    let mut parsed = tauri::async_runtime::spawn_blocking(move || {
+        let mut parsed = read(Path::new(&path))?;
+        mark_duplicates_in(&db_path, &mut parsed.rows)?;
+        Ok::<_, ImportError>(parsed)
+    })
+    .await
+    .map_err(|e| ImportError::Io(e.to_string()))??;
+
+    Ok(OfxPreview {
+        preview: staged.stage(&mut parsed.rows),
+        account: parsed.account,
+        currency: parsed.currency,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations::migrate;
+    use crate::import::{commit_rows, mark_duplicates, ImportRow};
+    use rusqlite::Connection;
+
+    /// OFX 1.x: an SGML header and unclosed value tags
+    const SGML: &str = "OFXHEADER:100\r\nDATA:OFXSGML\r\nVERSION:102\r\n\r\n\
+        <OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS><CURDEF>AUD\
+        <BANKACCTFROM><BANKID>062000<ACCTID>12345678<ACCTTYPE>CHECKING</BANKACCTFROM>\
+        <BANKTRANLIST><DTSTART>20240101\r\n\
+        <STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20240103120000[+10:EST]<TRNAMT>-42.50\
+        <FITID>2024010301<NAME>COLES 123<MEMO>EFTPOS Purchase</STMTTRN>\r\n\
+        <STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20240103<TRNAMT>-42,50\
+        <FITID>2024010302<NAME>COLES 123\r\n\
+        <STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20240104<TRNAMT>+2000.00\
+        <FITID>2024010401<MEMO>Salary &amp; bonus</STMTTRN>\r\n\
+        <STMTTRN><DTPOSTED>2024<TRNAMT>abc<FITID>x</STMTTRN>\
+        </BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+
+    /// OFX 2.x
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<?OFX OFXHEADER="200" VERSION="220"?>
+<OFX>
+  <CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS>
+    <CURDEF>USD</CURDEF>
+    <CCACCTFROM><ACCTID>4111</ACCTID></CCACCTFROM>
+    <BANKTRANLIST>
+      <STMTTRN>
+        <TRNTYPE>DEBIT</TRNTYPE>
+        <DTPOSTED>20240105</DTPOSTED>
+        <TRNAMT>-9.99</TRNAMT>
+        <FITID>A1</FITID>
+        <PAYEE><NAME>Netflix</NAME></PAYEE>
+        <MEMO/>
+      </STMTTRN>
+    </BANKTRANLIST>
+  </CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1>
+</OFX>"#;
+
+    #[test]
+    fn reads_sgml_statement() {
+        let parsed = parse(SGML).unwrap();
+        assert_eq!(parsed.account.as_deref(), Some("12345678"));
+        assert_eq!(parsed.currency.as_deref(), Some("AUD"));
+        assert_eq!(parsed.rows.len(), 4);
+
+        let coles = &parsed.rows[0];
+        assert_eq!(coles.date.as_deref(), Some("2024-01-03"));
+        assert_eq!(coles.amount, Some(-42.5));
+        assert_eq!(coles.description, "COLES 123");
+        assert_eq!(coles.notes.as_deref(), Some("EFTPOS Purchase"));
+        assert_eq!(coles.external_id.as_deref(), Some("2024010301"));
+
+        // Left open until the next entry
+        assert_eq!(parsed.rows[1].amount, Some(-42.5));
+        assert_eq!(parsed.rows[1].external_id.as_deref(), Some("2024010302"));
+
+        let salary = &parsed.rows[2];
+        assert_eq!(salary.amount, Some(2000.0));
+        assert_eq!(salary.description, "Salary & bonus");
+        assert_eq!(salary.notes, None);
+
+        assert_eq!(
+            parsed.rows[3].errors,
+            vec!["Invalid date: \"2024\"", "Invalid amount: \"abc\""]
+        );
+    }
+
+    #[test]
+    fn reads_xml_statement() {
+        let parsed = parse(XML).unwrap();
+        assert_eq!(parsed.account.as_deref(), Some("4111"));
+        assert_eq!(parsed.currency.as_deref(), Some("USD"));
+        assert_eq!(parsed.rows.len(), 1);
+        let row = &parsed.rows[0];
+        assert_eq!(
+            (row.date.as_deref(), row.amount, row.description.as_str()),
+            (Some("2024-01-05"), Some(-9.99), "Netflix")
+        );
+        assert_eq!(row.raw, vec!["20240105", "-9.99", "Netflix", "", "A1"]);
+
+        assert!(matches!(
+            parse("Date,Amount\n"),
+            Err(ImportError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse("<OFX><BANKTRANLIST></BANKTRANLIST></OFX>"),
+            Err(ImportError::Empty)
+        ));
+    }
+
+    #[test]
+    fn fitid_marks_duplicates() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        let mut rows = parse(SGML).unwrap().rows;
+        rows.truncate(3);
+
+        // Same date, amount and description, but different bank IDs
+        mark_duplicates(Some(&conn), &mut rows, None).unwrap();
+        assert!(rows.iter().all(|r| !r.is_duplicate));
+
+        let import: Vec<_> = rows.iter().filter_map(PreviewRow::import_row).collect();
+        let summary = commit_rows(&mut conn, "batch", &import, None, true).unwrap();
+        assert_eq!(summary.imported, 3);
+
+        // The same file again is all duplicates
+        mark_duplicates(Some(&conn), &mut rows, None).unwrap();
+        assert!(rows.iter().all(|r| r.is_duplicate));
+    }
+
+    #[test]
+    fn fitids_only_repeat_within_a_source() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO source (id, name) VALUES ('cheque', 'Cheque');
+             INSERT INTO source (id, name) VALUES ('card', 'Card');",
+        )
+        .unwrap();
+        let mut first = parse(SGML).unwrap().rows;
+        first.truncate(3);
+        let import: Vec<_> = first.iter().filter_map(PreviewRow::import_row).collect();
+        commit_rows(&mut conn, "cheque", &import, Some("cheque"), true).unwrap();
+
+        // Another account whose bank happens to reuse the IDs, with new
+        // details so the fingerprints differ
+        let mut rows = first.clone();
+        for row in &mut rows {
+            row.description.push_str(" (card)");
+        }
+        mark_duplicates(Some(&conn), &mut rows, Some("card")).unwrap();
+        assert!(rows.iter().all(|r| !r.is_duplicate));
+        mark_duplicates(Some(&conn), &mut rows, Some("cheque")).unwrap();
+        assert!(rows.iter().all(|r| r.is_duplicate));
+
+        let import: Vec<_> = rows.iter().filter_map(PreviewRow::import_row).collect();
+        let summary = commit_rows(&mut conn, "card", &import, Some("card"), true).unwrap();
+        assert_eq!(summary.imported, import.len());
+    }
+
+    #[test]
+    fn noisy_memos_match_existing_rows() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        // Imported earlier from the bank's CSV export, without a bank ID
+        let existing = ImportRow {
+            date: "2024-03-12".to_string(),
+            description: "WOOLWORTHS 1234 SYDNEY".to_string(),
+            amount: -23.4,
+            notes: None,
+            external_id: None,
+        };
+        commit_rows(&mut conn, "csv", &[existing], None, true).unwrap();
+
+        let ofx = "<OFX><BANKTRANLIST>\
+            <STMTTRN><DTPOSTED>20240312<TRNAMT>-23.40<FITID>9001\
+            <MEMO>WOOLWORTHS 1234 SYDNEY xxxx5678 Ref: 884213 12/03 14:05</STMTTRN>\
+            <STMTTRN><DTPOSTED>20240312<TRNAMT>-23.40<FITID>9002\
+            <MEMO>WOOLWORTHS 1234 PARRAMATTA xxxx5678 Ref: 884214</STMTTRN>\
+            </BANKTRANLIST></OFX>";
+        let mut rows = parse(ofx).unwrap().rows;
+        mark_duplicates(Some(&conn), &mut rows, None).unwrap();
+        let flags: Vec<bool> = rows.iter().map(|r| r.is_duplicate).collect();
+        assert_eq!(flags, vec![true, false]);
+
+        let import: Vec<_> = rows.iter().filter_map(PreviewRow::import_row).collect();
+        let summary = commit_rows(&mut conn, "ofx", &import, None, true).unwrap();
+        assert_eq!((summary.imported, summary.duplicates), (1, 1));
+    }
+}
@@ -0,0 +1,970 @@
+//! Pasted statements
+//!
+//! A port of `lib/paste/parser.ts`, which ran on the UI thread. Text copied
+//! from a PDF statement or a web table is split into lines (merging
+//! transactions a PDF wrapped over several lines), then into columns on tabs,
+//! pipes or runs of spaces. A header row is kept if one is found, summary
+//! rows ("Opening balance", "Total") are dropped, and each column is scored
+//! as a date, amount or text column. Amount columns get a role (withdrawals,
+//! deposits or balance) from their header, or else from how they're filled.
+//!
+//! `parse_pasted_statement` returns the `CSVParseResult` shape the import
+//! wizard maps and previews, with each column's score and a suggested
+//! mapping.
+
+use super::csv::ColumnMapping;
+use super::ImportError;
+
+const CURRENCY_SYMBOLS: [char; 5] = ['$', '£', '€', '¥', '₹'];
+
+/// Words that mark a header row
+const HEADER_WORDS: [&str; 11] = [
+    "date",
+    "description",
+    "amount",
+    "balance",
+    "debit",
+    "credit",
+    "transaction",
+    "withdrawals",
+    "deposits",
+    "details",
+    "particulars",
+];
+
+/// Lines that pick the delimiter
+const DELIMITER_SAMPLES: usize = 10;
+
+/// One piece of a date layout
+#[derive(Clone, Copy)]
+enum Part {
+    Digits(usize, usize),
+    Letters(usize, usize),
+    /// `/`, `-` or `.`
+    Separator,
+    /// One or more spaces
+    Space,
+    /// An optional comma
+    Comma,
+}
+
+use Part::{Comma, Digits, Letters, Separator, Space};
+
+/// Date layouts, as in `isDateLike`
+const DATE_LAYOUTS: [&[Part]; 5] = [
+    // 25/12/2024, 12-25-24, 25.12.2024
+    &[
+        Digits(1, 2),
+        Separator,
+        Digits(1, 2),
+        Separator,
+        Digits(2, 4),
+    ],
+    // 2024-12-25
+    &[
+        Digits(4, 4),
+        Separator,
+        Digits(1, 2),
+        Separator,
+        Digits(1, 2),
+    ],
+    // 25 Dec 2024
+    &[Digits(1, 2), Space, Letters(3, 9), Space, Digits(2, 4)],
+    // Dec 25, 2024
+    &[
+        Letters(3, 9),
+        Space,
+        Digits(1, 2),
+        Comma,
+        Space,
+        Digits(2, 4),
+    ],
+    // 25Dec24; not taken as the start of a transaction line
+    &[Digits(1, 2), Letters(3, 3), Digits(2, 4)],
+];
+
+/// How to split the pasted text
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PasteOptions {
+    /// Spaces in a row that separate columns
+    pub min_spaces: usize,
+    /// Join rows that continue the previous row's description
+    pub merge_multi_line: bool,
+    /// Whether the first rows include a header; detected when unset
+    pub has_headers: Option<bool>,
+}
+
+impl Default for PasteOptions {
+    fn default() -> Self {
+        Self {
+            min_spaces: 2,
+            merge_multi_line: true,
+            has_headers: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    Date,
+    Amount,
+    Text,
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountRole {
+    /// Withdrawals
+    Debit,
+    /// Deposits
+    Credit,
+    Balance,
+    /// Signed amounts, or a role that couldn't be told
+    Single,
+}
+
+/// What a column looks like (`ColumnAnalysis` in `lib/paste/parser.ts`)
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnScore {
+    pub index: usize,
+    #[serde(rename = "type")]
+    pub column_type: ColumnType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_role: Option<AmountRole>,
+    /// Share of the column's values that fit its type
+    pub confidence: f64,
+}
+
+/// Result of `parse_pasted_statement`: `CSVParseResult` in `types/import.ts`,
+/// plus column scores and a suggested mapping
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PastedStatement {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub total_rows: usize,
+    pub encoding: &'static str,
+    pub columns: Vec<ColumnScore>,
+    /// Set when a date and an amount column were found
+    pub mapping: Option<ColumnMapping>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Delimiter {
+    Tab,
+    Pipe,
+    Spaces,
+    /// Tabs where a line has them, else spaces
+    Mixed,
+}
+
+/// Every length of a prefix of `text` that fits `layout`, longest first
+fn prefix_matches(text: &[u8], layout: &[Part]) -> Vec<usize> {
+    let Some((part, rest)) = layout.split_first() else {
+        return vec![0];
+    };
+    let run = |class: fn(&u8) -> bool| text.iter().take_while(|b| class(b)).count();
+    let (min, max) = match *part {
+        Digits(min, max) => (min, run(u8::is_ascii_digit).min(max)),
+        Letters(min, max) => (min, run(u8::is_ascii_alphabetic).min(max)),
+        Separator => (
+            1,
+            usize::from(matches!(text.first(), Some(b'/' | b'-' | b'.'))),
+        ),
+        Space => (1, run(u8::is_ascii_whitespace)),
+        Comma => (0, usize::from(text.first() == Some(&b','))),
+    };
+    (min..=max)
+        .rev()
+        .flat_map(|n| {
+            prefix_matches(&text[n..], rest)
+                .into_iter()
+                .map(move |end| n + end)
+        })
+        .collect()
+}
+
+/// Whether `value` is a date on its own
+pub fn is_date_like(value: &str) -> bool {
+    let value = value.trim();
+    !value.is_empty()
+        && DATE_LAYOUTS
+            .iter()
+            .any(|layout| prefix_matches(value.as_bytes(), layout).contains(&value.len()))
+}
+
+pub(super) fn starts_with_date(line: &str) -> bool {
+    DATE_LAYOUTS[..4]
+        .iter()
+        .any(|layout| !prefix_matches(line.as_bytes(), layout).is_empty())
+}
+
+/// Split a cell that starts with a date: "6 Dec 25 ACCOUNT TFR" becomes
+/// ("6 Dec 25", "ACCOUNT TFR")
+fn leading_date(cell: &str) -> Option<(&str, &str)> {
+    DATE_LAYOUTS.iter().find_map(|layout| {
+        prefix_matches(cell.as_bytes(), layout)
+            .into_iter()
+            .find_map(|end| {
+                let rest = &cell[end..];
+                (rest.starts_with(char::is_whitespace) && !rest.trim().is_empty())
+                    .then(|| (&cell[..end], rest.trim()))
+            })
+    })
+}
+
+fn is_digits(text: &str, min: usize, max: usize) -> bool {
+    (min..=max).contains(&text.len()) && text.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// `1,234.56` (or `1.234,56`): thousands split by `group`, and one or two
+/// decimals after `decimal`
+pub(super) fn is_grouped(text: &str, group: char, decimal: char) -> bool {
+    let (whole, decimals) = match text.split_once(decimal) {
+        Some((whole, decimals)) => (whole, Some(decimals)),
+        None => (text, None),
+    };
+    let mut groups = whole.split(group);
+    groups.next().is_some_and(|first| is_digits(first, 1, 3))
+        && groups.all(|g| is_digits(g, 3, 3))
+        && decimals.map_or(true, |d| is_digits(d, 1, 2))
+}
+
+/// `1234.5`: digits and one or two decimals
+pub(super) fn is_plain(text: &str) -> bool {
+    match text.split_once('.') {
+        Some((whole, decimals)) => is_digits(whole, 1, usize::MAX) && is_digits(decimals, 1, 2),
+        None => is_digits(text, 1, usize::MAX),
+    }
+}
+
+/// Whether `value` is an amount: `-1,234.56`, `1.234,56`, `$12`, `(12.00)`,
+/// `R 45.00` or `1,234.56 CR`
+pub fn is_amount_like(value: &str) -> bool {
+    let compact: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    let (number, marked) = match compact.len().checked_sub(2) {
+        Some(at)
+            if compact.is_char_boundary(at)
+                && ["dr", "cr"]
+                    .iter()
+                    .any(|m| compact[at..].eq_ignore_ascii_case(m)) =>
+        {
+            (&compact[..at], true)
+        }
+        _ => (compact.as_str(), false),
+    };
+    let number: String = number
+        .chars()
+        .filter(|c| *c != 'R' && !CURRENCY_SYMBOLS.contains(c))
+        .collect();
+
+    let unsigned = number.strip_prefix('-').unwrap_or(&number);
+    if is_plain(unsigned) || is_grouped(unsigned, ',', '.') || is_grouped(unsigned, '.', ',') {
+        return true;
+    }
+    !marked
+        && number
+            .strip_prefix('(')
+            .and_then(|n| n.strip_suffix(')'))
+            .is_some_and(|n| is_grouped(n, ',', '.'))
+}
+
+/// Length of an amount such as `$1,234.56 CR` or `-$5.00` at the start of
+/// `text`
+fn amount_at(text: &str) -> Option<usize> {
+    let body = text.strip_prefix('-').unwrap_or(text);
+    let body = body.strip_prefix(CURRENCY_SYMBOLS).unwrap_or(body);
+    let body = body.strip_prefix('-').unwrap_or(body);
+    let whole = body
+        .bytes()
+        .take_while(|b| b.is_ascii_digit() || *b == b',')
+        .count();
+    if whole == 0 {
+        return None;
+    }
+    let decimals = body[whole..].strip_prefix('.')?;
+    if !decimals.get(..2).is_some_and(|d| is_digits(d, 2, 2)) {
+        return None;
+    }
+    let end = text.len() - decimals.len() + 2;
+    let mark = text[end..].trim_start();
+    match mark.get(..2) {
+        Some(m) if m.eq_ignore_ascii_case("dr") || m.eq_ignore_ascii_case("cr") => {
+            Some(text.len() - mark.len() + 2)
+        }
+        _ => Some(end),
+    }
+}
+
+/// Amounts in `text`, with where each starts
+fn find_amounts(text: &str) -> Vec<(usize, &str)> {
+    let mut found = Vec::new();
+    let mut at = 0;
+    while let Some(c) = text[at..].chars().next() {
+        match amount_at(&text[at..]) {
+            Some(len) => {
+                found.push((at, &text[at..at + len]));
+                at += len;
+            }
+            None => at += c.len_utf8(),
+        }
+    }
+    found
+}
+
+/// Split on runs of at least `min` spaces
+fn split_on_spaces(line: &str, min: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let (mut start, mut run_start, mut run) = (0, 0, 0);
+    for (i, c) in line.char_indices() {
+        if c.is_whitespace() {
+            if run == 0 {
+                run_start = i;
+            }
+            run += 1;
+            continue;
+        }
+        if run >= min {
+            parts.push(line[start..run_start].to_string());
+            start = i;
+        }
+        run = 0;
+    }
+    if run >= min {
+        parts.push(line[start..run_start].to_string());
+        start = line.len();
+    }
+    parts.push(line[start..].to_string());
+    parts
+}
+
+/// Split a line into cells. A date leading the first cell, and amounts
+/// trailing the last, get cells of their own.
+fn split_line(line: &str, delimiter: Delimiter, min_spaces: usize) -> Vec<String> {
+    let mut parts: Vec<String> = match delimiter {
+        Delimiter::Tab => line.split('\t').map(String::from).collect(),
+        Delimiter::Pipe => line.split('|').map(String::from).collect(),
+        Delimiter::Mixed if line.contains('\t') => line.split('\t').map(String::from).collect(),
+        Delimiter::Spaces | Delimiter::Mixed => split_on_spaces(line, min_spaces),
+    };
+
+    if let Some((date, rest)) = leading_date(parts[0].trim()) {
+        let (date, rest) = (date.to_string(), rest.to_string());
+        parts.splice(0..1, [date, rest]);
+    }
+
+    let last = parts[parts.len() - 1].trim();
+    let amounts = find_amounts(last);
+    if let Some(&(first, _)) = amounts.first() {
+        let text = last[..first].trim();
+        if !text.is_empty() || amounts.len() > 1 {
+            let mut cells: Vec<String> = Vec::new();
+            if !text.is_empty() {
+                cells.push(text.to_string());
+            }
+            cells.extend(amounts.iter().map(|(_, a)| a.trim().to_string()));
+            parts.pop();
+            parts.extend(cells);
+        }
+    }
+
+    // Empty cells are kept between others, as gaps in the columns
+    let last = parts.len() - 1;
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| (i, p.trim().to_string()))
+        .filter(|(i, p)| !p.is_empty() || (*i > 0 && *i < last))
+        .map(|(_, p)| p)
+        .collect()
+}
+
+/// A line of only amounts, such as "52,243.23 52,243.23 CR"
+fn is_amount_only_line(line: &str) -> bool {
+    let rest: Vec<char> = line
+        .chars()
+        .filter(|c| !c.is_whitespace() && !"$£€¥₹,.-()DRCdrc".contains(*c))
+        .collect();
+    let bytes = line.as_bytes();
+    let has_cents = bytes.windows(4).any(|w| {
+        w[0].is_ascii_digit()
+            && matches!(w[1], b'.' | b',')
+            && w[2].is_ascii_digit()
+            && w[3].is_ascii_digit()
+    });
+    !rest.is_empty()
+        && rest.iter().all(char::is_ascii_digit)
+        && has_cents
+        && line.chars().count() < 50
+}
+
+/// Whether `text` starts with `first`, whitespace, then `second`
+fn starts_with_words(text: &str, first: &str, second: &str) -> bool {
+    text.strip_prefix(first).is_some_and(|rest| {
+        let words = rest.trim_start();
+        words.len() < rest.len() && words.starts_with(second)
+    })
+}
+
+fn is_header_or_summary(line: &str) -> bool {
+    let lower = line.to_lowercase();
+    ["date", "transaction", "withdrawals", "deposits", "balance"]
+        .iter()
+        .any(|w| lower.starts_with(w))
+        || starts_with_words(&lower, "opening", "balance")
+        || starts_with_words(&lower, "closing", "balance")
+}
+
+/// Cells that begin a summary row, such as "Opening balance" or "Totals:"
+fn is_summary_cell(cell: &str) -> bool {
+    let cell = cell.trim().to_lowercase();
+    let is_total = cell.strip_prefix("total").is_some_and(|rest| {
+        let rest = rest.strip_prefix('s').unwrap_or(rest).trim_start();
+        matches!(rest, "" | ":" | "/")
+    });
+    is_total
+        || [
+            ("opening", "balance"),
+            ("closing", "balance"),
+            ("transaction", "total"),
+            ("balance", "brought"),
+            ("balance", "carried"),
+        ]
+        .iter()
+        .any(|(first, second)| starts_with_words(&cell, first, second))
+}
+
+/// Join transactions a PDF split over lines, such as a date and description
+/// line, more description, then a line of amounts
+fn merge_transaction_lines(lines: &[&str]) -> Vec<String> {
+    let mut merged = Vec::new();
+    let mut current = String::new();
+
+    for line in lines.iter().map(|l| l.trim()) {
+        if is_header_or_summary(line) {
+            if !current.is_empty() {
+                merged.push(std::mem::take(&mut current));
+            }
+            merged.push(line.to_string());
+        } else if starts_with_date(line) {
+            if !current.is_empty() {
+                merged.push(std::mem::take(&mut current));
+            }
+            current = line.to_string();
+        } else if is_amount_only_line(line) {
+            if current.is_empty() {
+                merged.push(line.to_string());
+            } else {
+                // Two spaces keep the amounts in their own columns
+                current.push_str("  ");
+                current.push_str(line);
+                merged.push(std::mem::take(&mut current));
+            }
+        } else if !current.is_empty() {
+            current.push(' ');
+            current.push_str(line);
+        } else {
+            merged.push(line.to_string());
+        }
+    }
+    if !current.is_empty() {
+        merged.push(current);
+    }
+    merged
+}
+
+fn detect_delimiter(lines: &[String], min_spaces: usize) -> Delimiter {
+    let spaces = " ".repeat(min_spaces);
+    let (mut tabs, mut runs, mut pipes) = (0, 0, 0);
+    for line in lines.iter().take(DELIMITER_SAMPLES) {
+        tabs += usize::from(line.contains('\t'));
+        runs += usize::from(line.contains(&spaces));
+        pipes += usize::from(line.contains('|'));
+    }
+    let most = tabs.max(runs).max(pipes);
+    if tabs == most && tabs > 0 {
+        Delimiter::Tab
+    } else if pipes == most && pipes > 0 {
+        Delimiter::Pipe
+    } else if runs > 0 {
+        Delimiter::Spaces
+    } else {
+        Delimiter::Mixed
+    }
+}
+
+/// Most likely description cell: the longest that isn't a date or amount
+fn description_cell(row: &[String]) -> Option<usize> {
+    let mut best: Option<(usize, usize)> = None;
+    for (i, cell) in row.iter().enumerate() {
+        let len = cell.chars().count();
+        if !is_date_like(cell) && !is_amount_like(cell) && len > best.map_or(0, |b| b.1) {
+            best = Some((i, len));
+        }
+    }
+    best.map(|b| b.0)
+}
+
+fn is_continuation(row: &[String], previous: &[String]) -> bool {
+    if (row.len() as f64) < previous.len() as f64 * 0.5 {
+        return !row.iter().any(|c| is_date_like(c) || is_amount_like(c));
+    }
+    row[0].is_empty() && !previous[0].is_empty()
+}
+
+/// Fold rows of wrapped text into the previous row's description
+fn merge_wrapped_rows(rows: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let mut merged: Vec<Vec<String>> = Vec::new();
+    for row in rows {
+        match merged.last_mut() {
+            Some(previous) if is_continuation(&row, previous) => {
+                if let Some(i) = description_cell(previous) {
+                    previous[i] = format!("{} {}", previous[i], row.join(" "))
+                        .trim()
+                        .to_string();
+                }
+            }
+            _ => merged.push(row),
+        }
+    }
+    merged
+}
+
+/// Whether `row` names columns, with at least two header words
+fn is_header_row(row: &[String]) -> bool {
+    let lower: Vec<String> = row.iter().map(|c| c.trim().to_lowercase()).collect();
+    let exact = lower
+        .iter()
+        .filter(|c| HEADER_WORDS.contains(&c.as_str()))
+        .count();
+    let partial = lower
+        .iter()
+        .filter(|c| HEADER_WORDS.iter().any(|w| c.contains(w)))
+        .count();
+    exact >= 2 || partial >= 2
+}
+
+/// Whether a line of columns split by spaces is a header row
+pub(super) fn is_header_line(line: &str) -> bool {
+    is_header_row(&split_on_spaces(line.trim(), 2))
+}
+
+/// Take the header out of the first three rows, if one of them is
+fn take_header(rows: &mut Vec<Vec<String>>) -> Option<Vec<String>> {
+    let at = rows.iter().take(3).position(|row| is_header_row(row))?;
+    Some(rows.remove(at))
+}
+
+fn is_transaction_row(row: &[String]) -> bool {
+    row.iter().filter(|c| !c.trim().is_empty()).count() >= 2
+        && row.iter().any(|c| is_date_like(c) || is_amount_like(c))
+}
+
+fn column_type(samples: &[&str]) -> (ColumnType, f64) {
+    if samples.is_empty() {
+        return (ColumnType::Unknown, 0.0);
+    }
+    let (mut dates, mut amounts, mut text) = (0, 0, 0);
+    for sample in samples {
+        if is_date_like(sample) {
+            dates += 1;
+        } else if is_amount_like(sample) {
+            amounts += 1;
+        } else if sample.chars().count() > 3 {
+            text += 1;
+        }
+    }
+    let ratio = |count: usize| count as f64 / samples.len() as f64;
+    if ratio(dates) > 0.7 {
+        (ColumnType::Date, ratio(dates))
+    } else if ratio(amounts) > 0.7 {
+        (ColumnType::Amount, ratio(amounts))
+    } else if ratio(text) > 0.5 {
+        (ColumnType::Text, ratio(text))
+    } else {
+        (ColumnType::Unknown, 0.3)
+    }
+}
+
+/// `parseFloat` of the digits, dots and minus signs in `text`
+fn leading_number(text: &str) -> Option<f64> {
+    let kept: String = text
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
+        .collect();
+    (1..=kept.len()).rev().find_map(|n| kept[..n].parse().ok())
+}
+
+/// Role of an amount column from its header, or else from its signs
+fn amount_role(header: &str, samples: &[&str]) -> AmountRole {
+    let lower = header.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| lower.contains(w));
+    let ends = |words: &[&str]| words.iter().any(|w| lower.ends_with(w));
+
+    if has(&["withdraw", "debit", "payment", "expense", "out"]) || ends(&["dr", "dr."]) {
+        return AmountRole::Debit;
+    }
+    if has(&["deposit", "credit", "income", "received"]) || ends(&["cr", "cr.", "in"]) {
+        return AmountRole::Credit;
+    }
+    if has(&["balance", "running"]) || ends(&["total"]) {
+        return AmountRole::Balance;
+    }
+
+    let negative = |s: &&str| s.starts_with('-') || s.starts_with('(');
+    let negatives = samples.iter().any(negative);
+    let positives = samples
+        .iter()
+        .any(|s| !negative(s) && leading_number(s).is_some_and(|n| n > 0.0));
+    match (negatives, positives) {
+        (true, false) => AmountRole::Debit,
+        (false, true) => AmountRole::Credit,
+        _ => AmountRole::Single,
+    }
+}
+
+fn is_unset(role: Option<AmountRole>) -> bool {
+    matches!(role, None | Some(AmountRole::Single))
+}
+
+fn filled(row: &[String], column: usize) -> bool {
+    row.get(column).is_some_and(|c| !c.trim().is_empty())
+}
+
+/// Call two amount columns withdrawals then deposits, if rows seldom fill
+/// both
+fn split_debit_credit(columns: &mut [ColumnScore], rows: &[Vec<String>], a: usize, b: usize) {
+    let both = rows
+        .iter()
+        .filter(|row| filled(row, a) && filled(row, b))
+        .count();
+    if both as f64 >= rows.len() as f64 * 0.1 {
+        return;
+    }
+    for (column, role) in [
+        (a.min(b), AmountRole::Debit),
+        (a.max(b), AmountRole::Credit),
+    ] {
+        if is_unset(columns[column].amount_role) {
+            columns[column].amount_role = Some(role);
+        }
+    }
+}
+
+/// Roles for amount columns the headers didn't settle. A balance fills
+/// nearly every row; withdrawals and deposits take turns, usually in that
+/// order.
+fn infer_amount_roles(columns: &mut [ColumnScore], rows: &[Vec<String>]) {
+    let amounts: Vec<usize> = columns
+        .iter()
+        .filter(|c| c.column_type == ColumnType::Amount)
+        .map(|c| c.index)
+        .collect();
+    if amounts.len() < 2 || amounts.iter().all(|&i| !is_unset(columns[i].amount_role)) {
+        return;
+    }
+
+    let fill_rate =
+        |i: usize| rows.iter().filter(|row| filled(row, i)).count() as f64 / rows.len() as f64;
+    let mut by_fill: Vec<(usize, f64)> = amounts.iter().map(|&i| (i, fill_rate(i))).collect();
+    by_fill.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    match by_fill[..] {
+        [(highest, highest_rate), (_, middle_rate), _] => {
+            if highest_rate > 0.9 && middle_rate < 0.8 && is_unset(columns[highest].amount_role) {
+                columns[highest].amount_role = Some(AmountRole::Balance);
+            }
+            let rest: Vec<usize> = by_fill
+                .iter()
+                .map(|&(i, _)| i)
+                .filter(|&i| columns[i].amount_role != Some(AmountRole::Balance))
+                .collect();
+            if let [a, b] = rest[..] {
+                split_debit_credit(columns, rows, a, b);
+            }
+        }
+        [(a, _), (b, _)] => split_debit_credit(columns, rows, a, b),
+        _ => {}
+    }
+}
+
+/// Score each column of `rows`, taking amount roles from `headers` where
+/// they're named
+fn analyze(rows: &[Vec<String>], headers: &[String]) -> Vec<ColumnScore> {
+    let Some(first) = rows.first() else {
+        return Vec::new();
+    };
+    let mut columns: Vec<ColumnScore> = (0..first.len())
+        .map(|index| {
+            let samples: Vec<&str> = rows
+                .iter()
+                .filter_map(|row| row.get(index).map(String::as_str))
+                .filter(|s| !s.trim().is_empty())
+                .collect();
+            let (column_type, confidence) = column_type(&samples);
+            let header = headers
+                .get(index)
+                .map(|h| h.trim())
+                .filter(|h| !h.is_empty());
+            let amount_role = match header {
+                Some(header) if column_type == ColumnType::Amount => {
+                    Some(amount_role(header, &samples))
+                }
+                _ => None,
+            };
+            ColumnScore {
+                index,
+                column_type,
+                amount_role,
+                confidence,
+            }
+        })
+        .collect();
+    infer_amount_roles(&mut columns, rows);
+    columns
+}
+
+/// The pasted header where there was one, else a name for what the column
+/// holds
+fn column_names(columns: &[ColumnScore], header: &[String]) -> Vec<String> {
+    let (mut dates, mut texts) = (0, 0);
+    columns
+        .iter()
+        .map(|column| {
+            if let Some(name) = header.get(column.index).map(|h| h.trim()) {
+                if name.chars().count() > 1 {
+                    return name.to_string();
+                }
+            }
+            match (column.column_type, column.amount_role) {
+                (ColumnType::Date, _) => {
+                    dates += 1;
+                    numbered("Date", dates)
+                }
+                (ColumnType::Amount, Some(AmountRole::Debit)) => "Withdrawals".to_string(),
+                (ColumnType::Amount, Some(AmountRole::Credit)) => "Deposits".to_string(),
+                (ColumnType::Amount, Some(AmountRole::Balance)) => "Balance".to_string(),
+                (ColumnType::Amount, _) => "Amount".to_string(),
+                (ColumnType::Text, _) => {
+                    texts += 1;
+                    if texts == 1 {
+                        "Description".to_string()
+                    } else {
+                        numbered("Text", texts)
+                    }
+                }
+                (ColumnType::Unknown, _) => format!("Column {}", column.index + 1),
+            }
+        })
+        .collect()
+}
+
+fn numbered(name: &str, n: usize) -> String {
+    if n == 1 {
+        name.to_string()
+    } else {
+        format!("{} {}", name, n)
+    }
+}
+
+/// Date, description and amount columns, as `detectPasteColumnMapping` picks
+/// them: the first date column, the first amount column that isn't a
+/// balance, and the first text column
+fn detect_mapping(columns: &[ColumnScore]) -> Option<ColumnMapping> {
+    let first = |wanted: &dyn Fn(&ColumnScore) -> bool| columns.iter().find(|c| wanted(c));
+
+    let date = first(&|c| c.column_type == ColumnType::Date)?.index;
+    let amount = first(&|c| {
+        c.column_type == ColumnType::Amount && c.amount_role != Some(AmountRole::Balance)
+    })?
+    .index;
+    let description = first(&|c| c.column_type == ColumnType::Text)
+        .or_else(|| {
+            first(&|c| c.index != date && c.index != amount && c.column_type != ColumnType::Amount)
+        })
+        .map(|c| c.index);
+
+    Some(ColumnMapping {
+        date: date as i64,
+        description: description.map_or(-1, |i| i as i64),
+        amount: amount as i64,
+        debit: None,
+        credit: None,
+        balance: None,
+        notes: None,
+        ignore: (0..columns.len())
+            .filter(|&i| i != date && i != amount && Some(i) != description)
+            .collect(),
+    })
+}
+
+/// Parse pasted statement text into rows
+pub fn parse(text: &str, options: &PasteOptions) -> Result<PastedStatement, ImportError> {
+    // PDF viewers often copy non-breaking spaces between columns
+    let text = text
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .replace('\u{a0}', " ");
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    if lines.is_empty() {
+        return Err(ImportError::Empty);
+    }
+
+    let min_spaces = options.min_spaces.max(1);
+    let lines = merge_transaction_lines(&lines);
+    let delimiter = detect_delimiter(&lines, min_spaces);
+    let mut rows: Vec<Vec<String>> = lines
+        .iter()
+        .map(|line| split_line(line, delimiter, min_spaces))
+        .collect();
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    for row in &mut rows {
+        row.resize(width, String::new());
+    }
+    if options.merge_multi_line && rows.len() > 1 {
+        rows = merge_wrapped_rows(rows);
+    }
+
+    let header = if options.has_headers == Some(false) {
+        rows.retain(|row| !row.iter().any(|c| is_summary_cell(c)));
+        None
+    } else {
+        let header = take_header(&mut rows);
+        rows.retain(|row| is_transaction_row(row) && !row.iter().any(|c| is_summary_cell(c)));
+        header
+    };
+    if rows.is_empty() {
+        return Err(ImportError::Empty);
+    }
+
+    let header = header.unwrap_or_default();
+    let headers = column_names(&analyze(&rows, &header), &header);
+    // Scored again under the names the wizard shows, which settle more roles
+    let columns = analyze(&rows, &headers);
+    let mapping = detect_mapping(&columns);
+
+    Ok(PastedStatement {
+        total_rows: rows.len(),
+        headers,
+        rows,
+        encoding: "UTF-8",
+        columns,
+        mapping,
+    })
+}
+
+/// Parse text pasted from a statement into columns, away from the UI thread
+#[tauri::command]
+pub async fn parse_pasted_statement(
+    text: String,
+    options: Option<PasteOptions>,
+) -> Result<PastedStatement, ImportError> {
+    tauri::async_runtime::spawn_blocking(move || parse(&text, &options.unwrap_or_default()))
+        .await
+        .map_err(|e| ImportError::Io(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A statement as copied from a bank's PDF or web banking, and what it
+    /// should parse to
+    macro_rules! fixture {
+        ($name:literal) => {
+            (
+                $name,
+                include_str!(concat!("fixtures/paste/", $name, ".txt")),
+                include_str!(concat!("fixtures/paste/", $name, ".json")),
+            )
+        };
+    }
+
+    const FIXTURES: [(&str, &str, &str); 7] = [
+        fixture!("amex"),
+        fixture!("anz"),
+        fixture!("chase"),
+        fixture!("commbank"),
+        fixture!("ing"),
+        fixture!("monzo"),
+        fixture!("westpac"),
+    ];
+
+    #[test]
+    fn parses_bank_statement_fixtures() {
+        for (name, text, expected) in FIXTURES {
+            let parsed =
+                parse(text, &PasteOptions::default()).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            let expected: serde_json::Value = serde_json::from_str(expected).unwrap();
+            assert_eq!(serde_json::to_value(parsed).unwrap(), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn recognizes_dates_and_amounts() {
+        for date in [
+            "25/12/2024",
+            "2024-12-25",
+            "6 Dec 25",
+            "Dec 6, 2025",
+            "06Dec25",
+        ] {
+            assert!(is_date_like(date), "{}", date);
+        }
+        assert!(!is_date_like("6 Dec"));
+        assert!(!is_date_like("1,234.56"));
+
+        for amount in [
+            "-1,234.56",
+            "1.234,56",
+            "$12",
+            "(12.00)",
+            "R 45.00",
+            "4,210.55 CR",
+        ] {
+            assert!(is_amount_like(amount), "{}", amount);
+        }
+        assert!(!is_amount_like("12.3456"));
+        assert!(!is_amount_like("(12.00) DR"));
+        assert!(!is_amount_like("REF 4402193"));
+
+        assert_eq!(
+            split_line(
+                "6 Dec 25 ACCOUNT TFR 0064897267WL01 1,427.00 -52,243.23 DR",
+                Delimiter::Mixed,
+                2
+            ),
+            vec![
+                "6 Dec 25",
+                "ACCOUNT TFR 0064897267WL01",
+                "1,427.00",
+                "-52,243.23 DR"
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_header_rows_as_data_when_told_there_are_none() {
+        let text = "Date\tDetails\tAmount\n01/02/2024\tCoffee\t-4.50\n\tOpening balance\t10.00\n";
+        let options = PasteOptions {
+            has_headers: Some(false),
+            ..PasteOptions::default()
+        };
+        let parsed = parse(text, &options).unwrap();
+        assert_eq!(parsed.total_rows, 2);
+        assert_eq!(parsed.rows[0], vec!["Date", "Details", "Amount"]);
+
+        let parsed = parse(text, &PasteOptions::default()).unwrap();
+        assert_eq!(parsed.headers, vec!["Date", "Details", "Amount"]);
+        assert_eq!(parsed.rows, vec![vec!["01/02/2024", "Coffee", "-4.50"]]);
+
+        assert!(matches!(
+            parse(" \r\n\n", &PasteOptions::default()),
+            Err(ImportError::Empty)
+        ));
+    }
+}
@@ -0,0 +1,429 @@
+//! PDF statements
+//!
+//! `parse_pdf_statement` saves copying a statement's table out of a PDF by
+//! hand. The text is extracted locally with `pdf-extract` and laid out again
+//! from where each character sits: a line per baseline, with a gap between
+//! columns kept as a run of spaces about as wide. Each page is cut down to
+//! its transactions (from the table's header or first dated line) and the
+//! text goes to the paste parser as if it had been pasted. Scanned
+//! statements are images, with no text to extract.
+
+use super::paste::{self, PasteOptions, PastedStatement};
+use super::{check_chosen, ImportError};
+use crate::launch::OpenedFiles;
+use pdf_extract::{output_doc, Document, MediaBox, OutputDev, OutputError, Transform};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+/// One character (or ligature) drawn on the page
+struct Glyph {
+    x: f64,
+    /// Baseline, from the top of the page
+    y: f64,
+    /// Where the next character would start
+    end: f64,
+    size: f64,
+    text: String,
+}
+
+/// Collects each page's characters and lays them out as lines
+#[derive(Default)]
+struct Layout {
+    page_height: f64,
+    glyphs: Vec<Glyph>,
+    pages: Vec<String>,
+}
+
+impl Layout {
+    /// Lines of `glyphs`, top to bottom, with a blank line for a wide gap.
+    /// Spaces stand for the page's average character width, so columns
+    /// line up from one line to the next.
+    fn lines(mut glyphs: Vec<Glyph>) -> String {
+        glyphs.retain(|g| !g.text.trim().is_empty());
+        if glyphs.is_empty() {
+            return String::new();
+        }
+        let left = glyphs.iter().map(|g| g.x).fold(f64::INFINITY, f64::min);
+        let advance = glyphs.iter().map(|g| g.end - g.x).sum::<f64>() / glyphs.len() as f64;
+
+        glyphs.sort_by(|a, b| a.y.total_cmp(&b.y));
+        let mut rows: Vec<Vec<Glyph>> = Vec::new();
+        for glyph in glyphs {
+            match rows.last_mut() {
+                Some(row) if (glyph.y - row[0].y).abs() < row[0].size.max(glyph.size) * 0.5 => {
+                    row.push(glyph)
+                }
+                _ => rows.push(vec![glyph]),
+            }
+        }
+
+        let mut text = String::new();
+        let mut last_y: Option<(f64, f64)> = None;
+        for mut row in rows {
+            row.sort_by(|a, b| a.x.total_cmp(&b.x));
+            let (y, size) = (row[0].y, row[0].size);
+            if last_y.is_some_and(|(last, last_size)| y - last > last_size * 2.5) {
+                text.push('\n');
+            }
+            last_y = Some((y, size));
+            text.push_str(&Self::line(&row, left, advance));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Characters of one line, indented from `left`, with spaces as wide as
+    /// the gaps between them
+    fn line(row: &[Glyph], left: f64, advance: f64) -> String {
+        let mut line = String::new();
+        let mut end = left;
+        let mut previous: Option<&Glyph> = None;
+        for glyph in row {
+            // Bold text is sometimes drawn twice, slightly offset
+            if previous
+                .is_some_and(|p| p.text == glyph.text && (glyph.x - p.x).abs() < glyph.size * 0.1)
+            {
+                continue;
+            }
+            let gap = glyph.x - end;
+            if gap > glyph.size * 0.1 {
+                let width = if advance > 0.0 {
+                    advance
+                } else {
+                    glyph.size * 0.5
+                };
+                let spaces = (gap / width).round().max(1.0) as usize;
+                line.extend(std::iter::repeat(' ').take(spaces));
+            }
+            line.push_str(&glyph.text);
+            end = glyph.end;
+            previous = Some(glyph);
+        }
+        line
+    }
+}
+
+impl OutputDev for Layout {
+    fn begin_page(
+        &mut self,
+        _page_num: u32,
+        media_box: &MediaBox,
+        _art_box: Option<(f64, f64, f64, f64)>,
+    ) -> Result<(), OutputError> {
+        self.page_height = media_box.ury - media_box.lly;
+        self.glyphs.clear();
+        Ok(())
+    }
+
+    fn end_page(&mut self) -> Result<(), OutputError> {
+        let glyphs = std::mem::take(&mut self.glyphs);
+        self.pages.push(Self::lines(glyphs));
+        Ok(())
+    }
+
+    fn output_character(
+        &mut self,
+        trm: &Transform,
+        width: f64,
+        _spacing: f64,
+        font_size: f64,
+        char: &str,
+    ) -> Result<(), OutputError> {
+        // The text matrix scales the font; rotated text is rare on statements
+        let size = font_size * (trm.m11 * trm.m22 - trm.m12 * trm.m21).abs().sqrt();
+        self.glyphs.push(Glyph {
+            x: trm.m31,
+            y: self.page_height - trm.m32,
+            end: trm.m31 + width * size,
+            size,
+            text: char.to_string(),
+        });
+        Ok(())
+    }
+
+    fn begin_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+}
+
+/// Text of each page of the PDF at `path`, laid out as lines
+pub fn extract_pages(path: &Path) -> Result<Vec<String>, ImportError> {
+    let bytes = fs::read(path)?;
+    let invalid = |e: &dyn std::fmt::Display| ImportError::Invalid(e.to_string());
+    let mut doc = Document::load_mem(&bytes).map_err(|e| invalid(&e))?;
+    if doc.is_encrypted() && doc.decrypt("").is_err() {
+        return Err(ImportError::Invalid(
+            "the PDF is password protected".to_string(),
+        ));
+    }
+
+    // pdf-extract panics on some malformed files rather than failing
+    let mut layout = Layout::default();
+    panic::catch_unwind(AssertUnwindSafe(|| output_doc(&doc, &mut layout)))
+        .map_err(|_| ImportError::Invalid("couldn't read the PDF's text".to_string()))?
+        .map_err(|e| invalid(&e))?;
+
+    if layout.pages.iter().all(|p| p.trim().is_empty()) {
+        return Err(ImportError::Invalid(
+            "no text in the PDF; scanned statements can't be read".to_string(),
+        ));
+    }
+    Ok(layout.pages)
+}
+
+/// The part of a page with transactions: from the table's header or first
+/// dated line to the last dated line and the lines wrapped under it
+fn transaction_lines(page: &str) -> Vec<&str> {
+    let lines: Vec<&str> = page.lines().collect();
+    let Some(last) = lines
+        .iter()
+        .rposition(|l| paste::starts_with_date(l.trim()))
+    else {
+        return Vec::new();
+    };
+    let first = lines
+        .iter()
+        .position(|l| paste::starts_with_date(l.trim()) || paste::is_header_line(l))
+        .unwrap_or(last);
+    // Wrapped lines run until a blank line, such as before a page's footer
+    let end = lines[last..]
+        .iter()
+        .position(|l| l.trim().is_empty())
+        .map_or(lines.len(), |blank| last + blank);
+    lines[first..end]
+        .iter()
+        .copied()
+        .filter(|l| !l.trim().is_empty())
+        .collect()
+}
+
+/// Where each cell of `line` starts and ends; cells are split by two or
+/// more spaces
+fn cells(line: &str) -> Vec<(usize, usize)> {
+    let mut cells: Vec<(usize, usize)> = Vec::new();
+    let mut spaces = 0;
+    for (i, c) in line.char_indices() {
+        if c == ' ' {
+            spaces += 1;
+            continue;
+        }
+        match cells.last_mut() {
+            Some(cell) if spaces < 2 => cell.1 = i + c.len_utf8(),
+            _ => cells.push((i, i + c.len_utf8())),
+        }
+        spaces = 0;
+    }
+    cells
+}
+
+/// Join text wrapped under a transaction onto the cell above it, where the
+/// paste parser would add it to the end of the line
+fn join_wrapped(lines: Vec<&str>) -> Vec<String> {
+    let mut joined: Vec<String> = Vec::new();
+    let mut transaction: Option<usize> = None;
+    for line in lines {
+        let text = line.trim();
+        if paste::starts_with_date(text) {
+            transaction = Some(joined.len());
+        } else if let Some(at) = transaction.filter(|_| {
+            !paste::is_header_line(line) && !text.split_whitespace().any(paste::is_amount_like)
+        }) {
+            let indent = line.len() - line.trim_start().len();
+            let cell = cells(&joined[at])
+                .into_iter()
+                .find(|(start, _)| start.abs_diff(indent) <= 1);
+            if let Some((_, cell_end)) = cell {
+                joined[at].insert_str(cell_end, &format!(" {}", text));
+                continue;
+            }
+        } else {
+            transaction = None;
+        }
+        joined.push(line.to_string());
+    }
+    joined
+}
+
+/// Statement text of the PDF's pages, ready for the paste parser
+pub fn statement_text(pages: &[String]) -> String {
+    pages
+        .iter()
+        .flat_map(|page| join_wrapped(transaction_lines(page)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Result of `parse_pdf_statement`
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfStatement {
+    #[serde(flatten)]
+    pub statement: PastedStatement,
+    /// The text given to the paste parser, to show and edit
+    pub text: String,
+    pub page_count: usize,
+}
+
+/// Extract the statement PDF at `path` (chosen with `choose_statement`) and
+/// parse its transactions as pasted text. Nothing leaves the machine.
+#[tauri::command]
+pub async fn parse_pdf_statement(
+    opened: tauri::State<'_, OpenedFiles>,
+    path: String,
+    options: Option<PasteOptions>,
+) -> Result<PdfStatement, ImportError> {
+    check_chosen(&opened, Path::new(&path))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let pages = extract_pages(Path::new(&path))?;
+        let text = statement_text(&pages);
+        Ok(PdfStatement {
+            statement: paste::parse(&text, &options.unwrap_or_default())?,
+            text,
+            page_count: pages.len(),
+        })
+    })
+    .await
+    .map_err(|e| ImportError::Io(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdf_extract::content::{Content, Operation};
+    use pdf_extract::{Dictionary, Object, Stream};
+
+    /// A one-page PDF with each `(x, y, text)` drawn in 9pt Helvetica
+    fn pdf(texts: &[(i64, i64, &str)]) -> Vec<u8> {
+        let dict = |entries: Vec<(&str, Object)>| {
+            let mut dict = Dictionary::new();
+            for (key, value) in entries {
+                dict.set(key, value);
+            }
+            dict
+        };
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dict(vec![
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"Helvetica".to_vec())),
+        ]));
+        let resources_id = doc.add_object(dict(vec![(
+            "Font",
+            dict(vec![("F1", font_id.into())]).into(),
+        )]));
+
+        let mut operations = Vec::new();
+        for (x, y, text) in texts {
+            operations.extend([
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec![Object::Name(b"F1".to_vec()), 9.into()]),
+                Operation::new("Td", vec![(*x).into(), (*y).into()]),
+                Operation::new("Tj", vec![Object::string_literal(*text)]),
+                Operation::new("ET", vec![]),
+            ]);
+        }
+        let content = Content { operations }.encode().unwrap();
+        let content_id = doc.add_object(Stream::new(Dictionary::new(), content));
+        let page_id = doc.add_object(dict(vec![
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", pages_id.into()),
+            ("Contents", content_id.into()),
+        ]));
+        doc.objects.insert(
+            pages_id,
+            dict(vec![
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", vec![page_id.into()].into()),
+                ("Count", 1.into()),
+                ("Resources", resources_id.into()),
+                (
+                    "MediaBox",
+                    vec![0.into(), 0.into(), 595.into(), 842.into()].into(),
+                ),
+            ])
+            .into(),
+        );
+        let catalog_id = doc.add_object(dict(vec![
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", pages_id.into()),
+        ]));
+        doc.trailer.set("Root", catalog_id);
+
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn lays_out_statement_columns() {
+        let mut texts = vec![
+            (50, 800, "Everyday Account"),
+            (50, 788, "Statement period 1 Jan 2024 to 31 Jan 2024"),
+            (50, 700, "Date"),
+            (120, 700, "Description"),
+            (380, 700, "Amount"),
+            (460, 700, "Balance"),
+            (50, 40, "Page 1 of 1"),
+        ];
+        let rows = [
+            ("03/01/2024", "COLES 123 SYDNEY", "-42.50", "957.50"),
+            ("04/01/2024", "SALARY ACME", "2000.00", "2957.50"),
+            ("06/01/2024", "RENT", "-500.00", "2457.50"),
+        ];
+        for ((date, description, amount, balance), y) in rows.iter().zip([686, 672, 648]) {
+            texts.extend([
+                (50, y, *date),
+                (120, y, *description),
+                (380, y, *amount),
+                (460, y, *balance),
+            ]);
+        }
+        // The salary's description wraps onto a second line
+        texts.push((120, 660, "PAYROLL"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("statement.pdf");
+        fs::write(&path, pdf(&texts)).unwrap();
+        let pages = extract_pages(&path).unwrap();
+        assert_eq!(pages.len(), 1);
+
+        let text = statement_text(&pages);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4, "{}", text);
+        assert!(lines[0].starts_with("Date"));
+        assert!(lines[1].starts_with("03/01/2024"));
+        assert!(lines[1].contains("-42.50   "));
+        assert!(lines[2].contains("SALARY ACME PAYROLL   "));
+        assert!(!text.contains("Page 1") && !text.contains("Everyday"));
+
+        let statement = paste::parse(&text, &PasteOptions::default()).unwrap();
+        assert_eq!(
+            statement.headers,
+            vec!["Date", "Description", "Amount", "Balance"]
+        );
+        assert_eq!(statement.total_rows, 3);
+        assert_eq!(statement.rows[1][1], "SALARY ACME PAYROLL");
+        let mapping = statement.mapping.unwrap();
+        assert_eq!((mapping.date, mapping.amount), (0, 2));
+    }
+
+    #[test]
+    fn rejects_pdfs_without_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.pdf");
+        fs::write(&path, pdf(&[])).unwrap();
+        assert!(matches!(extract_pages(&path), Err(ImportError::Invalid(_))));
+        fs::write(&path, b"not a pdf").unwrap();
+        assert!(matches!(extract_pages(&path), Err(ImportError::Invalid(_))));
+    }
+}
@@ -0,0 +1,275 @@
+//! QIF statements
+//!
+//! QIF is line based: each line starts with a field code (`D` date, `T`
+//! amount, `P` payee, `M` memo, `N` cheque number) and `^` ends a
+//! transaction. Only bank, cash, card and asset/liability sections are read;
+//! account lists, categories and investment sections are skipped. QIF dates
+//! have no set layout (`1/15'24`, `15/01/2024`), so it is detected from the
+//! file's dates unless given.
+
+use super::date::{self, DateFormat};
+use super::{
+    check_chosen, csv, mark_duplicates_in, push_row, read_text, row_notes, ImportError,
+    ImportPreview, PreviewRow, StagedImports, DEFAULT_DESCRIPTION,
+};
+use crate::db::database_path;
+use crate::launch::OpenedFiles;
+use std::path::Path;
+
+/// `!Type:` sections holding bank transactions
+const TRANSACTION_TYPES: [&str; 5] = ["bank", "cash", "ccard", "oth a", "oth l"];
+
+/// Dates that pick the date format
+const DATE_SAMPLES: usize = 10;
+
+/// Fields of one transaction
+#[derive(Default)]
+struct Entry {
+    date: String,
+    amount: String,
+    payee: String,
+    memo: String,
+    number: String,
+}
+
+impl Entry {
+    fn is_empty(&self) -> bool {
+        self.date.is_empty() && self.amount.is_empty() && self.payee.is_empty()
+    }
+
+    fn into_row(self, row_index: usize, format: DateFormat) -> PreviewRow {
+        let mut errors = Vec::new();
+        let date = date::parse(&self.date, format);
+        if date.is_none() {
+            errors.push(format!("Invalid date: {:?}", self.date));
+        }
+        let amount = if self.amount.is_empty() {
+            errors.push("Missing amount".to_string());
+            None
+        } else {
+            let amount = csv::parse_amount(&self.amount);
+            if amount.is_none() {
+                errors.push(format!("Invalid amount: {:?}", self.amount));
+            }
+            amount
+        };
+
+        let (description, notes) = if self.payee.is_empty() {
+            (self.memo.clone(), None)
+        } else if self.memo == self.payee {
+            (self.payee.clone(), None)
+        } else {
+            (self.payee.clone(), row_notes(&self.memo))
+        };
+
+        PreviewRow {
+            row_index,
+            has_default_description: description.is_empty(),
+            description: if description.is_empty() {
+                DEFAULT_DESCRIPTION.to_string()
+            } else {
+                description
+            },
+            date,
+            amount,
+            notes,
+            external_id: None,
+            errors,
+            is_duplicate: false,
+            raw: vec![self.date, self.amount, self.payee, self.memo, self.number],
+        }
+    }
+}
+
+/// `1/15'24` and ` 1/ 5/24` as `1/15/24` and `1/5/24`
+fn normalize_date(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| if c == '\'' { '/' } else { c })
+        .collect()
+}
+
+/// How to read the file
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct QifOptions {
+    /// `auto` detects the format from the first dates
+    pub date_format: DateFormat,
+}
+
+/// A parsed QIF file, before duplicates are checked
+#[derive(Debug)]
+pub struct ParsedQif {
+    /// `Bank`, `CCard` and so on, from the first section read
+    pub account_type: String,
+    pub date_format: DateFormat,
+    pub rows: Vec<PreviewRow>,
+}
+
+/// Parse the text of a QIF file
+pub fn parse(text: &str, options: &QifOptions) -> Result<ParsedQif, ImportError> {
+    let mut account_type: Option<String> = None;
+    let mut in_transactions = false;
+    let mut entries = Vec::new();
+    let mut entry = Entry::default();
+
+    for line in text.lines() {
+        let line = line.trim_end();
+        if let Some(header) = line.strip_prefix('!') {
+            let kind = header
+                .get(..5)
+                .filter(|prefix| prefix.eq_ignore_ascii_case("type:"))
+                .map(|_| header[5..].trim());
+            in_transactions =
+                kind.is_some_and(|k| TRANSACTION_TYPES.contains(&k.to_lowercase().as_str()));
+            if in_transactions {
+                account_type.get_or_insert_with(|| kind.unwrap_or_default().to_string());
+            }
+            continue;
+        }
+        if !in_transactions {
+            continue;
+        }
+
+        let mut chars = line.chars();
+        let Some(code) = chars.next() else {
+            continue;
+        };
+        let value = chars.as_str().trim();
+        match code {
+            'D' => entry.date = normalize_date(value),
+            // `U` repeats the amount in newer files
+            'T' | 'U' if entry.amount.is_empty() => entry.amount = value.to_string(),
+            'P' => entry.payee = value.to_string(),
+            'M' => entry.memo = value.to_string(),
+            'N' => entry.number = value.to_string(),
+            '^' => {
+                let done = std::mem::take(&mut entry);
+                if !done.is_empty() {
+                    push_row(&mut entries, done)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    if !entry.is_empty() {
+        push_row(&mut entries, entry)?;
+    }
+
+    let account_type = account_type
+        .ok_or_else(|| ImportError::Invalid("no bank transactions in QIF file".to_string()))?;
+    if entries.is_empty() {
+        return Err(ImportError::Empty);
+    }
+
+    let date_format = match options.date_format {
+        DateFormat::Auto => date::detect_format(
+            entries
+                .iter()
+                .map(|e| e.date.as_str())
+                .filter(|d| !d.is_empty())
+                .take(DATE_SAMPLES),
+        ),
+        format => format,
+    };
+    let rows = entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| entry.into_row(index, date_format))
+        .collect();
+
+    Ok(ParsedQif {
+        account_type,
+        date_format,
+        rows,
+    })
+}
+
+/// Read and parse the QIF file at `path`
+pub fn read(path: &Path, options: &QifOptions) -> Result<ParsedQif, ImportError> {
+    parse(&read_text(path)?, options)
+}
+
+/// Result of `import_qif`
+#[derive(Debug, serde::Serialize)]
+pub struct QifPreview {
+    #[serde(flatten)]
+    pub preview: ImportPreview,
+    pub account_type: String,
+    pub date_format: DateFormat,
+}
+
+/// Parse the QIF statement at `path`, check it for duplicates of existing
+/// transactions and stage it for `commit_import`
+#[tauri::command]
+pub async fn import_qif(
+    app: tauri::AppHandle,
+    staged: tauri::State<'_, StagedImports>,
+    opened: tauri::State<'_, OpenedFiles>,
+    path: String,
+    options: Option<QifOptions>,
+) -> Result<QifPreview, ImportError> {
+    check_chosen(&opened, Path::new(&path))?;
+    let db_path = database_path(&app)?;
+    let mut parsed = tauri::async_runtime::spawn_blocking(move || {
+        let mut parsed = read(Path::new(&path), &options.unwrap_or_default())?;
+        mark_duplicates_in(&db_path, &mut parsed.rows)?;
+        Ok::<_, ImportError>(parsed)
+    })
+    .await
+    .map_err(|e| ImportError::Io(e.to_string()))??;
+
+    Ok(QifPreview {
+        preview: staged.stage(&mut parsed.rows),
+        account_type: parsed.account_type,
+        date_format: parsed.date_format,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "!Account\nNEveryday\nTBank\n^\n\
+        !Type:Bank\n\
+        D 1/ 3'24\nT-42.50\nPCOLES 123\nMGroceries\nLFood\n^\n\
+        D1/15'24\nU2,000.00\nT2,000.00\nPSalary\nN1001\n^\n\
+        D1/20'24\nT\nMOnly a memo\n^\n\
+        !Type:Invst\nD1/16'24\nNBuy\nT100.00\n^\n";
+
+    #[test]
+    fn reads_bank_section() {
+        let parsed = parse(SAMPLE, &QifOptions::default()).unwrap();
+        assert_eq!(parsed.account_type, "Bank");
+        assert_eq!(parsed.date_format, DateFormat::MonthFirst);
+        assert_eq!(parsed.rows.len(), 3);
+
+        let coles = &parsed.rows[0];
+        assert_eq!(coles.date.as_deref(), Some("2024-01-03"));
+        assert_eq!(coles.amount, Some(-42.5));
+        assert_eq!(coles.description, "COLES 123");
+        assert_eq!(coles.notes.as_deref(), Some("Groceries"));
+
+        let salary = &parsed.rows[1];
+        assert_eq!(salary.amount, Some(2000.0));
+        assert_eq!(salary.raw[4], "1001");
+
+        let broken = &parsed.rows[2];
+        assert_eq!(broken.description, "Only a memo");
+        assert_eq!(broken.date.as_deref(), Some("2024-01-20"));
+        assert_eq!(broken.errors, vec!["Missing amount"]);
+    }
+
+    #[test]
+    fn rejects_files_without_bank_transactions() {
+        let investments = "!Type:Invst\nD1/16'24\nT100.00\n^\n";
+        assert!(matches!(
+            parse(investments, &QifOptions::default()),
+            Err(ImportError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse("!Type:CCard\n", &QifOptions::default()),
+            Err(ImportError::Empty)
+        ));
+    }
+}
@@ -0,0 +1,647 @@
+//! Watched statement folder
+//!
+//! A background thread started from `setup` looks for new CSV, OFX/QFX, QIF
+//! and camt XML files in a folder the user picks (inside Downloads or
+//! Documents, the app's file scopes). A file is taken once its size and
+//! modified time hold still between two checks, so downloads still being
+//! written are left alone. It is parsed, checked for duplicates and staged
+//! as `import_csv` would, then announced with an `import-watch://ready`
+//! event carrying the preview and the source it seems to belong to; the
+//! webview commits it with `commit_import` or drops it with
+//! `discard_import`. Watched batches stay staged until then, so files wait
+//! in the folder while too many are pending, and `list_watched_imports`
+//! lists them for a webview that missed the events. Statements that can't
+//! be parsed get `import-watch://failed`, except XML files that aren't
+//! camt, which are skipped; files that can't be read are tried again at the
+//! next check.
+//!
+//! The source comes from the filename patterns in `import-watch.json`, then
+//! the account named in the statement, then a source whose name is part of
+//! the filename. Files done with (committed, discarded or unusable; by path,
+//! size and modified time) are remembered in `import-watch-files.json`, so
+//! they aren't offered again after a restart. A statement still staged when
+//! the app closes is offered again. Files already in a folder when it is
+//! chosen are not new.
+
+use super::date::DateFormat;
+use super::{
+    camt, find_account_source, mark_duplicates, read_statement, ImportError, ImportPreview,
+    StagedImports,
+};
+use crate::db::{app_data_dir, database_path, open};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const EVENT_READY: &str = "import-watch://ready";
+pub const EVENT_FAILED: &str = "import-watch://failed";
+
+/// Settings file in the app data directory
+const SETTINGS_FILE: &str = "import-watch.json";
+/// Files already handled, in the app data directory
+const FILES_FILE: &str = "import-watch-files.json";
+/// Statement files picked up
+const EXTENSIONS: [&str; 5] = ["csv", "ofx", "qfx", "qif", "xml"];
+/// How often the folder is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Files named like `pattern` belong to source `source_id`
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourcePattern {
+    /// `*` matches any run of characters and `?` any one, in any case:
+    /// `CommBank_*.csv`
+    pub pattern: String,
+    pub source_id: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WatchSettings {
+    pub enabled: bool,
+    /// Folder to watch, inside Downloads or Documents
+    pub folder: Option<String>,
+    /// Checked in order; the first match wins
+    pub sources: Vec<SourcePattern>,
+}
+
+/// Size and modified time (Unix seconds) of a file as last seen
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FileStamp {
+    pub size: u64,
+    pub modified: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct HandledFile {
+    path: String,
+    #[serde(flatten)]
+    stamp: FileStamp,
+}
+
+/// A statement staged from the watched folder
+#[derive(Clone, Debug)]
+pub struct WatchedFile {
+    pub path: PathBuf,
+    /// Remembered as handled once the batch is committed or discarded
+    pub stamp: FileStamp,
+    pub file_name: String,
+    /// Source the file seems to belong to
+    pub source_id: Option<String>,
+}
+
+/// Payload of `import-watch://ready`, and of `list_watched_imports`
+#[derive(Debug, serde::Serialize)]
+pub struct ImportReady {
+    pub path: String,
+    pub file_name: String,
+    /// Source the file seems to belong to
+    pub source_id: Option<String>,
+    #[serde(flatten)]
+    pub preview: ImportPreview,
+}
+
+impl ImportReady {
+    fn new(file: &WatchedFile, preview: ImportPreview) -> Self {
+        Self {
+            path: file.path.display().to_string(),
+            file_name: file.file_name.clone(),
+            source_id: file.source_id.clone(),
+            preview,
+        }
+    }
+}
+
+/// Payload of `import-watch://failed`
+#[derive(Debug, serde::Serialize)]
+pub struct ImportFailed {
+    pub path: String,
+    pub file_name: String,
+    pub error: ImportError,
+}
+
+fn read_json<T: serde::de::DeserializeOwned + Default>(path: &Path) -> T {
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+            log::error!("Invalid {}: {}; starting afresh", path.display(), e);
+            T::default()
+        }),
+        Err(_) => T::default(),
+    }
+}
+
+fn write_json<T: serde::Serialize>(path: &Path, value: &T) -> Result<(), ImportError> {
+    let json = serde_json::to_vec_pretty(value).map_err(|e| ImportError::Io(e.to_string()))?;
+    fs::write(path, json)?;
+    Ok(())
+}
+
+/// Watch settings and handled files, managed as Tauri state
+pub struct ImportWatch {
+    dir: PathBuf,
+    settings: Mutex<WatchSettings>,
+    handled: Mutex<Vec<HandledFile>>,
+}
+
+impl ImportWatch {
+    /// Load the settings and handled files in `dir`, falling back to none
+    /// when a file is missing or unreadable
+    pub fn load(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            settings: Mutex::new(read_json(&dir.join(SETTINGS_FILE))),
+            handled: Mutex::new(read_json(&dir.join(FILES_FILE))),
+        }
+    }
+
+    pub fn settings(&self) -> WatchSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update(&self, settings: WatchSettings) -> Result<(), ImportError> {
+        write_json(&self.dir.join(SETTINGS_FILE), &settings)?;
+        *self.settings.lock().unwrap() = settings;
+        Ok(())
+    }
+
+    fn is_handled(&self, path: &Path, stamp: FileStamp) -> bool {
+        let path = path.to_string_lossy();
+        self.handled
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|f| f.path == path && f.stamp == stamp)
+    }
+
+    /// Remember `files` as handled, forgetting files that are gone
+    fn set_handled(&self, files: &[(PathBuf, FileStamp)]) -> Result<(), ImportError> {
+        let mut handled = self.handled.lock().unwrap();
+        handled.retain(|f| Path::new(&f.path).is_file());
+        for (path, stamp) in files {
+            let path = path.to_string_lossy().into_owned();
+            handled.retain(|f| f.path != path);
+            handled.push(HandledFile {
+                path,
+                stamp: *stamp,
+            });
+        }
+        write_json(&self.dir.join(FILES_FILE), &*handled)
+    }
+}
+
+fn stamp(metadata: &fs::Metadata) -> FileStamp {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs() as i64);
+    FileStamp {
+        size: metadata.len(),
+        modified,
+    }
+}
+
+/// Statement files directly inside `folder`
+pub fn statement_files(folder: &Path) -> Result<Vec<(PathBuf, FileStamp)>, ImportError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(folder)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_statement = path
+            .extension()
+            .is_some_and(|e| EXTENSIONS.contains(&e.to_string_lossy().to_lowercase().as_str()));
+        let metadata = entry.metadata()?;
+        if is_statement && metadata.is_file() {
+            files.push((path, stamp(&metadata)));
+        }
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
+
+/// Whether `name` fits `pattern`, where `*` matches any run of characters
+/// and `?` any one, ignoring case
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and how much of `name` it has taken
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Letters and digits of `text`, lowercased
+fn compact(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Source with the longest name that is part of `file_name`, ignoring case
+/// and punctuation ("Everyday" for `everyday-2024-01.csv`)
+fn source_in_name(conn: &Connection, file_name: &str) -> Result<Option<String>, ImportError> {
+    let name = compact(file_name);
+    let mut stmt = conn.prepare("SELECT id, name FROM source")?;
+    let sources = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?;
+    let mut best: Option<(String, usize)> = None;
+    for source in sources {
+        let (id, source_name): (String, String) = source?;
+        let source_name = compact(&source_name);
+        if !source_name.is_empty()
+            && name.contains(&source_name)
+            && best
+                .as_ref()
+                .map_or(true, |(_, len)| source_name.len() > *len)
+        {
+            best = Some((id, source_name.len()));
+        }
+    }
+    Ok(best.map(|(id, _)| id))
+}
+
+/// Parse the statement at `path` and check it for duplicates against
+/// `conn`, with the source it belongs to
+pub fn read_file(
+    conn: Option<&Connection>,
+    path: &Path,
+    patterns: &[SourcePattern],
+) -> Result<(Vec<super::PreviewRow>, Option<String>), ImportError> {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut statement = read_statement(path, DateFormat::Auto)?;
+
+    let mut source_id = patterns
+        .iter()
+        .find(|p| matches_pattern(&p.pattern, &file_name))
+        .map(|p| p.source_id.clone());
+    if let (None, Some(conn)) = (&source_id, conn) {
+        source_id = find_account_source(
+            conn,
+            statement.account.as_deref(),
+            statement.account_name.as_deref(),
+        )?;
+        if source_id.is_none() {
+            source_id = source_in_name(conn, &file_name)?;
+        }
+    }
+    mark_duplicates(conn, &mut statement.rows, source_id.as_deref())?;
+    Ok((statement.rows, source_id))
+}
+
+/// Whether reading a statement failed because of the file itself, so
+/// trying again won't help
+fn is_bad_statement(error: &ImportError) -> bool {
+    matches!(
+        error,
+        ImportError::Invalid(_) | ImportError::Empty | ImportError::TooMany(_)
+    )
+}
+
+/// Stage the file at `path` and announce it. Returns whether the file is
+/// done with now: not a statement that can be imported. A staged file is
+/// done with once its batch is committed or discarded (see [`file_done`]).
+fn handle_file(
+    app: &AppHandle,
+    db_path: &Path,
+    path: &Path,
+    stamp: FileStamp,
+    patterns: &[SourcePattern],
+) -> bool {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let read = open(db_path, true)
+        .map_err(ImportError::from)
+        .and_then(|conn| read_file(Some(&conn), path, patterns));
+    let emitted = match read {
+        Ok((mut rows, source_id)) => {
+            log::info!("Staged {} from the watched folder", path.display());
+            let file = WatchedFile {
+                path: path.to_path_buf(),
+                stamp,
+                file_name,
+                source_id,
+            };
+            let preview = app
+                .state::<StagedImports>()
+                .stage_watched(&mut rows, file.clone());
+            if let Err(e) = app.emit(EVENT_READY, &ImportReady::new(&file, preview)) {
+                log::error!("Failed to emit import event: {}", e);
+            }
+            return false;
+        }
+        Err(error) if !is_bad_statement(&error) => {
+            log::warn!("Couldn't read {}, will retry: {}", path.display(), error);
+            return false;
+        }
+        // Other XML files saved to the folder aren't statements
+        Err(ImportError::Invalid(msg)) if msg == camt::NOT_CAMT => {
+            log::info!("Skipped {}: {}", path.display(), msg);
+            return true;
+        }
+        Err(error) => {
+            log::error!("Couldn't import {}: {}", path.display(), error);
+            app.emit(
+                EVENT_FAILED,
+                &ImportFailed {
+                    path: path.display().to_string(),
+                    file_name,
+                    error,
+                },
+            )
+        }
+    };
+    if let Err(e) = emitted {
+        log::error!("Failed to emit import event: {}", e);
+    }
+    true
+}
+
+/// One pass of the watcher. `seen` holds files found in the last pass that
+/// haven't been handled yet.
+fn tick(app: &AppHandle, seen: &mut HashMap<PathBuf, FileStamp>) -> Result<(), ImportError> {
+    let watch = app.state::<ImportWatch>();
+    let settings = watch.settings();
+    let Some(folder) = settings.folder.as_deref().filter(|_| settings.enabled) else {
+        seen.clear();
+        return Ok(());
+    };
+    // No database yet: the frontend creates it on first run
+    let Ok(db_path) = database_path(app) else {
+        return Ok(());
+    };
+
+    let files = statement_files(Path::new(folder))?;
+    let staged = app.state::<StagedImports>();
+    // Staged files wait for the webview to commit or discard them
+    let new: Vec<(PathBuf, FileStamp)> = files
+        .into_iter()
+        .filter(|(path, stamp)| !watch.is_handled(path, *stamp) && !staged.is_watched_file(path))
+        .collect();
+    // Taken once they've stopped changing since the last pass
+    let mut ready: Vec<(PathBuf, FileStamp)> = new
+        .iter()
+        .filter(|(path, stamp)| seen.get(path) == Some(stamp))
+        .cloned()
+        .collect();
+    *seen = new.into_iter().collect();
+    // The rest stay in `seen` and are taken as slots free up
+    ready.truncate(staged.watched_slots());
+    if ready.is_empty() {
+        return Ok(());
+    }
+
+    let mut done = Vec::new();
+    for (path, stamp) in ready {
+        if handle_file(app, &db_path, &path, stamp, &settings.sources) {
+            seen.remove(&path);
+            done.push((path, stamp));
+        }
+    }
+    if done.is_empty() {
+        return Ok(());
+    }
+    watch.set_handled(&done)
+}
+
+/// Remember the staged `file` as handled, once its batch is committed or
+/// discarded
+pub fn file_done(app: &AppHandle, file: &WatchedFile) {
+    let Some(watch) = app.try_state::<ImportWatch>() else {
+        return;
+    };
+    if let Err(e) = watch.set_handled(&[(file.path.clone(), file.stamp)]) {
+        log::error!(
+            "Couldn't remember {} as handled: {}",
+            file.path.display(),
+            e
+        );
+    }
+}
+
+/// Load the watch settings and start the watcher thread
+pub fn init(app: &AppHandle) -> Result<(), ImportError> {
+    let dir = app_data_dir(app)?;
+    fs::create_dir_all(&dir)?;
+    app.manage(ImportWatch::load(&dir));
+
+    let app = app.clone();
+    thread::Builder::new()
+        .name("import-watch".into())
+        .spawn(move || {
+            let mut seen = HashMap::new();
+            loop {
+                if let Err(e) = tick(&app, &mut seen) {
+                    log::error!("Watched folder check failed: {}", e);
+                }
+                thread::sleep(CHECK_INTERVAL);
+            }
+        })?;
+    Ok(())
+}
+
+/// `folder` if it is a directory inside one of `roots`
+fn allowed_folder(folder: &Path, roots: &[PathBuf]) -> Result<PathBuf, ImportError> {
+    let folder = folder
+        .canonicalize()
+        .ok()
+        .filter(|f| f.is_dir())
+        .ok_or_else(|| ImportError::NotAllowed(format!("{} isn't a folder", folder.display())))?;
+    roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| folder.starts_with(root))
+        .then_some(folder)
+        .ok_or_else(|| {
+            ImportError::NotAllowed("the folder must be in Downloads or Documents".to_string())
+        })
+}
+
+/// Statements staged from the watched folder and not yet committed or
+/// discarded, oldest first: those announced before the webview listened
+#[tauri::command]
+pub fn list_watched_imports(staged: tauri::State<'_, StagedImports>) -> Vec<ImportReady> {
+    staged
+        .watched()
+        .into_iter()
+        .map(|(file, preview)| ImportReady::new(&file, preview))
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_import_watch(watch: tauri::State<'_, ImportWatch>) -> WatchSettings {
+    watch.settings()
+}
+
+/// Save the watch settings; a new folder's existing files are left alone.
+/// Takes effect at the next check.
+#[tauri::command]
+pub fn set_import_watch(
+    app: AppHandle,
+    watch: tauri::State<'_, ImportWatch>,
+    mut settings: WatchSettings,
+) -> Result<(), ImportError> {
+    if let Some(folder) = &settings.folder {
+        let roots: Vec<PathBuf> = [app.path().download_dir(), app.path().document_dir()]
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect();
+        let folder = allowed_folder(Path::new(folder), &roots)?;
+        if watch.settings().folder.as_deref() != Some(&*folder.to_string_lossy()) {
+            watch.set_handled(&statement_files(&folder)?)?;
+        }
+        settings.folder = Some(folder.to_string_lossy().into_owned());
+    }
+    watch.update(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::migrations::migrate;
+
+    #[test]
+    fn matches_filename_patterns() {
+        assert!(matches_pattern("CommBank_*.csv", "commbank_2024-01.CSV"));
+        assert!(matches_pattern("*everyday*", "Statement-Everyday-Jan.ofx"));
+        assert!(matches_pattern("stmt-??.qif", "stmt-01.qif"));
+        assert!(matches_pattern("*", ""));
+        assert!(!matches_pattern("stmt-??.qif", "stmt-1.qif"));
+        assert!(!matches_pattern("CommBank_*.csv", "ANZ_2024.csv"));
+        assert!(!matches_pattern("*.csv", "statement.csv.part"));
+    }
+
+    #[test]
+    fn reads_files_with_their_source() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO source (id, name) VALUES ('card', 'Credit Card'), ('every', 'Everyday'),
+                 ('everysaver', 'Everyday Saver');
+             INSERT INTO \"transaction\" (id, date, description, amount) VALUES ('t', '2024-01-03', 'Coles', -42.5);",
+        )
+        .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let csv = "Date,Description,Amount\n2024-01-03,Coles,-42.50\n2024-01-04,Salary,2000\n";
+        for name in ["everyday-saver-jan.csv", "amex_2024.csv", "notes.txt"] {
+            fs::write(dir.path().join(name), csv).unwrap();
+        }
+        fs::create_dir(dir.path().join("old.csv")).unwrap();
+
+        let files = statement_files(dir.path()).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|(p, _)| p.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["amex_2024.csv", "everyday-saver-jan.csv"]);
+        assert_eq!(files[0].1.size, csv.len() as u64);
+
+        let patterns = [SourcePattern {
+            pattern: "amex_*".to_string(),
+            source_id: "card".to_string(),
+        }];
+        let (rows, source) = read_file(Some(&conn), &files[0].0, &patterns).unwrap();
+        assert_eq!(source.as_deref(), Some("card"));
+        assert_eq!(
+            rows.iter().map(|r| r.is_duplicate).collect::<Vec<_>>(),
+            vec![true, false]
+        );
+        // The longest source name in the filename wins
+        let (_, source) = read_file(Some(&conn), &files[1].0, &patterns).unwrap();
+        assert_eq!(source.as_deref(), Some("everysaver"));
+    }
+
+    #[test]
+    fn retries_files_that_could_not_be_read() {
+        assert!(is_bad_statement(&ImportError::Empty));
+        assert!(is_bad_statement(&ImportError::Invalid("no dates".into())));
+        assert!(is_bad_statement(&ImportError::TooMany(60_000)));
+        assert!(!is_bad_statement(&ImportError::Io("locked".into())));
+        assert!(!is_bad_statement(&ImportError::Database("busy".into())));
+    }
+
+    #[test]
+    fn remembers_handled_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let statement = dir.path().join("statement.csv");
+        fs::write(&statement, "Date,Description,Amount\n").unwrap();
+        let files = statement_files(dir.path()).unwrap();
+        let (path, stamp) = files[0].clone();
+
+        let watch = ImportWatch::load(dir.path());
+        assert!(!watch.is_handled(&path, stamp));
+        watch.set_handled(&files).unwrap();
+
+        let watch = ImportWatch::load(dir.path());
+        assert!(watch.is_handled(&path, stamp));
+        // A new download under the same name is another file
+        let changed = FileStamp {
+            size: stamp.size + 1,
+            ..stamp
+        };
+        assert!(!watch.is_handled(&path, changed));
+
+        fs::remove_file(&statement).unwrap();
+        watch.set_handled(&[]).unwrap();
+        assert!(ImportWatch::load(dir.path())
+            .handled
+            .lock()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn watch_folder_must_be_in_a_scope() {
+        let roots = tempfile::tempdir().unwrap();
+        let downloads = roots.path().join("Downloads");
+        let statements = downloads.join("Statements");
+        fs::create_dir_all(&statements).unwrap();
+        let elsewhere = roots.path().join("Elsewhere");
+        fs::create_dir(&elsewhere).unwrap();
+
+        let allowed = [downloads.clone()];
+        assert_eq!(
+            allowed_folder(&statements, &allowed).unwrap(),
+            statements.canonicalize().unwrap()
+        );
+        assert!(matches!(
+            allowed_folder(&elsewhere, &allowed),
+            Err(ImportError::NotAllowed(_))
+        ));
+        assert!(matches!(
+            allowed_folder(&downloads.join("Statements/../../Elsewhere"), &allowed),
+            Err(ImportError::NotAllowed(_))
+        ));
+        assert!(allowed_folder(&downloads.join("missing"), &allowed).is_err());
+    }
+}
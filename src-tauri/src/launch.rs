@@ -0,0 +1,246 @@
+//! Files and links passed on the command line
+//!
+//! "Open with Puffin" starts the app with the file path in argv; once Puffin
+//! is running, the single-instance plugin hands the second instance's argv to
+//! [`handle_second_instance`] instead. Statement files (CSV) and database
+//! backups (encrypted or plain `.db`) are reported to the main window as
+//! `launch://open-file` events, `puffin://` URLs go through the deep-link
+//! handler.
+//!
+//! The webview can only read files it was handed this way
+//! (`read_opened_file`), not arbitrary paths. Statements picked in the import
+//! wizard (`import::choose_statement`) are recorded here too, so the import
+//! commands can hold their paths to the same rule.
+
+use crate::db::backup;
+use crate::deep_link;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use url::Url;
+
+pub const EVENT_OPEN_FILE: &str = "launch://open-file";
+
+/// Label of the window that receives launch events
+const MAIN_WINDOW: &str = "main";
+
+/// Largest statement `read_opened_file` will return
+const MAX_STATEMENT_BYTES: u64 = 20 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    /// Bank statement to import
+    Statement,
+    /// Database backup to restore
+    Backup,
+}
+
+impl FileKind {
+    fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "csv" | "tsv" => Some(FileKind::Statement),
+            "db" | backup::BACKUP_EXTENSION => Some(FileKind::Backup),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct OpenedFile {
+    pub path: String,
+    /// File name without directories, for display
+    pub name: String,
+    pub kind: FileKind,
+}
+
+/// What a command line asked for
+#[derive(Debug, Default, PartialEq)]
+pub struct LaunchArgs {
+    pub files: Vec<OpenedFile>,
+    pub urls: Vec<Url>,
+}
+
+/// Parse argv (including the executable name). Relative paths are resolved
+/// against `cwd`; flags, missing files and unsupported types are skipped.
+pub fn parse(args: &[String], cwd: &Path) -> LaunchArgs {
+    let mut launch = LaunchArgs::default();
+
+    for arg in args.iter().skip(1) {
+        if arg.starts_with('-') {
+            continue;
+        }
+        if arg.starts_with(&format!("{}:", deep_link::SCHEME)) {
+            match Url::parse(arg) {
+                Ok(url) => launch.urls.push(url),
+                Err(e) => log::warn!("Ignoring malformed link argument: {}", e),
+            }
+            continue;
+        }
+
+        let path = cwd.join(arg);
+        let Some(kind) = FileKind::from_path(&path) else {
+            log::warn!("Ignoring unsupported file argument: {}", arg);
+            continue;
+        };
+        if !path.is_file() {
+            log::warn!("Ignoring missing file argument: {}", arg);
+            continue;
+        }
+        launch.files.push(OpenedFile {
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path: path.to_string_lossy().into_owned(),
+            kind,
+        });
+    }
+
+    launch.files.dedup();
+    launch
+}
+
+/// Files handed to the app, managed as Tauri state
+#[derive(Default)]
+pub struct OpenedFiles {
+    /// Every path the webview may read or import
+    allowed: Mutex<HashSet<PathBuf>>,
+    /// Opened at startup, before the frontend was listening
+    at_launch: Mutex<Vec<OpenedFile>>,
+}
+
+impl OpenedFiles {
+    fn allow(&self, file: &OpenedFile) {
+        self.allow_path(PathBuf::from(&file.path));
+    }
+
+    /// Let the webview use `path`, a file the user chose
+    pub fn allow_path(&self, path: PathBuf) {
+        self.allowed.lock().unwrap().insert(path);
+    }
+
+    /// Whether `path` was opened with Puffin or chosen by the user
+    pub fn is_allowed(&self, path: &Path) -> bool {
+        self.allowed.lock().unwrap().contains(path)
+    }
+}
+
+/// Queue the files the app was started with; called from `setup`.
+/// Links are left to the deep-link plugin, which reads argv itself.
+pub fn init(app: &AppHandle) {
+    let opened = OpenedFiles::default();
+    if let Ok(cwd) = std::env::current_dir() {
+        let args: Vec<String> = std::env::args().collect();
+        for file in parse(&args, &cwd).files {
+            opened.allow(&file);
+            opened.at_launch.lock().unwrap().push(file);
+        }
+    }
+    app.manage(opened);
+}
+
+/// Single-instance callback: route another launch's argv to this instance
+pub fn handle_second_instance(app: &AppHandle, args: &[String], cwd: &str) {
+    let launch = parse(args, Path::new(cwd));
+
+    for url in &launch.urls {
+        deep_link::handle_url(app, url, false);
+    }
+    for file in launch.files {
+        app.state::<OpenedFiles>().allow(&file);
+        if let Err(e) = app.emit_to(MAIN_WINDOW, EVENT_OPEN_FILE, file) {
+            log::error!("Failed to emit opened file: {}", e);
+        }
+    }
+}
+
+/// Files the app was started with; drained on read
+#[tauri::command]
+pub fn take_launch_files(opened: tauri::State<'_, OpenedFiles>) -> Vec<OpenedFile> {
+    std::mem::take(&mut *opened.at_launch.lock().unwrap())
+}
+
+/// Read a statement that was opened with Puffin
+#[tauri::command]
+pub fn read_opened_file(
+    opened: tauri::State<'_, OpenedFiles>,
+    path: String,
+) -> Result<String, String> {
+    let path = PathBuf::from(path);
+    if !opened.is_allowed(&path) {
+        return Err("File was not opened with Puffin".to_string());
+    }
+    if FileKind::from_path(&path) != Some(FileKind::Statement) {
+        return Err("Only statement files can be read".to_string());
+    }
+    let size = std::fs::metadata(&path).map_err(|e| e.to_string())?.len();
+    if size > MAX_STATEMENT_BYTES {
+        return Err("File is too large to import".to_string());
+    }
+
+    let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        std::iter::once("puffin")
+            .chain(list.iter().copied())
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn classifies_files_and_links() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("March.CSV"), "date,amount").unwrap();
+        fs::write(dir.path().join("puffin-backup.db"), "").unwrap();
+        fs::write(dir.path().join("weekly.puffinbak"), "").unwrap();
+        fs::write(dir.path().join("notes.pdf"), "").unwrap();
+
+        let launch = parse(
+            &args(&[
+                "--flag",
+                "March.CSV",
+                "puffin-backup.db",
+                "weekly.puffinbak",
+                "notes.pdf",
+                "missing.csv",
+                "puffin://import/csv?source=x",
+            ]),
+            dir.path(),
+        );
+
+        assert_eq!(launch.files.len(), 3);
+        assert_eq!(launch.files[0].kind, FileKind::Statement);
+        assert_eq!(launch.files[0].name, "March.CSV");
+        assert_eq!(launch.files[1].kind, FileKind::Backup);
+        assert_eq!(launch.files[2].kind, FileKind::Backup);
+        assert_eq!(launch.urls.len(), 1);
+        assert_eq!(launch.urls[0].host_str(), Some("import"));
+    }
+
+    #[test]
+    fn skips_executable_and_keeps_absolute_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let statement = dir.path().join("statement.csv");
+        fs::write(&statement, "").unwrap();
+
+        let launch = parse(
+            &[
+                statement.to_string_lossy().into_owned(),
+                statement.to_string_lossy().into_owned(),
+            ],
+            Path::new("/nonexistent"),
+        );
+        assert_eq!(launch.files.len(), 1);
+        assert_eq!(launch.files[0].path, statement.to_string_lossy());
+    }
+}
@@ -1,11 +1,18 @@
 //! Puffin Desktop Application Entry Point
 //!
 //! This module initializes the Tauri application with required plugins:
-//! - single-instance: Prevents multiple app instances
+//! - single-instance: Prevents multiple app instances; files and links passed
+//!   to a second instance are routed to the running one (see `launch`)
 //! - sql: Native SQLite database access
 //! - deep-link: Handles OAuth callbacks via puffin:// protocol
 //! - log: Debug logging (development builds only)
 //!
+//! It also registers the encrypted secret vault (see `vault`) as managed state.
+//! Commands in `db` open `puffin.db` natively for whole-database work; `cli`
+//! runs the same operations headless when the binary is started with a
+//! command such as `puffin backup`. Statement files are parsed and staged
+//! natively by `import`.
+//!
 //! # Security Notes
 //!
 //! The CSP in tauri.conf.json includes `'unsafe-inline'` for styles because:
@@ -15,218 +22,21 @@
 //!
 //! Future improvement: Consider nonce-based CSP for stricter security.
 
-use std::net::TcpListener;
-use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
 use tauri::{Emitter, Manager};
-use tiny_http::{Response, Server};
-use url::Url;
-
-/// Result of the OAuth flow
-#[derive(serde::Serialize)]
-pub struct OAuthResult {
-    pub code: Option<String>,
-    pub state: Option<String>,
-    pub error: Option<String>,
-    pub redirect_uri: Option<String>,
-}
-
-/// Find an available port for the OAuth callback server
-fn find_available_port() -> Option<u16> {
-    // Try ports in the range 49152-65535 (dynamic/private ports)
-    for port in 49152..65535 {
-        if TcpListener::bind(("127.0.0.1", port)).is_ok() {
-            return Some(port);
-        }
-    }
-    None
-}
-
-/// Start OAuth flow with a local callback server
-/// Returns the authorization code or an error
-#[tauri::command]
-async fn start_oauth_flow(
-    app: tauri::AppHandle,
-    auth_url_base: String,
-    client_id: String,
-    scope: String,
-    state: String,
-) -> Result<OAuthResult, String> {
-    // Find an available port
-    let port = find_available_port().ok_or("No available port found")?;
-    let redirect_uri = format!("http://127.0.0.1:{}", port);
-
-    // Build the full OAuth URL
-    let mut auth_url = Url::parse(&auth_url_base).map_err(|e| e.to_string())?;
-    auth_url
-        .query_pairs_mut()
-        .append_pair("client_id", &client_id)
-        .append_pair("redirect_uri", &redirect_uri)
-        .append_pair("response_type", "code")
-        .append_pair("scope", &scope)
-        .append_pair("access_type", "offline")
-        .append_pair("prompt", "consent")
-        .append_pair("state", &state);
-
-    // Start the callback server in a separate thread
-    let (tx, rx) = mpsc::channel::<OAuthResult>();
-
-    let server_port = port;
-    let redirect_uri_clone = redirect_uri.clone();
-    thread::spawn(move || {
-        let addr = format!("127.0.0.1:{}", server_port);
-        let server = match Server::http(&addr) {
-            Ok(s) => s,
-            Err(e) => {
-                let _ = tx.send(OAuthResult {
-                    code: None,
-                    state: None,
-                    error: Some(format!("Failed to start server: {}", e)),
-                    redirect_uri: Some(redirect_uri_clone.clone()),
-                });
-                return;
-            }
-        };
-
-        // Wait for a single request (with timeout)
-        // Set server timeout
-        let timeout = Duration::from_secs(300); // 5 minute timeout
-
-        match server.recv_timeout(timeout) {
-            Ok(Some(request)) => {
-                let url_str = format!("http://127.0.0.1{}", request.url());
-                let parsed = Url::parse(&url_str);
-
-                let result = match parsed {
-                    Ok(url) => {
-                        let params: std::collections::HashMap<_, _> =
-                            url.query_pairs().into_owned().collect();
 
-                        OAuthResult {
-                            code: params.get("code").cloned(),
-                            state: params.get("state").cloned(),
-                            error: params.get("error").cloned(),
-                            redirect_uri: Some(redirect_uri_clone.clone()),
-                        }
-                    }
-                    Err(e) => OAuthResult {
-                        code: None,
-                        state: None,
-                        error: Some(format!("Failed to parse callback URL: {}", e)),
-                        redirect_uri: Some(redirect_uri_clone.clone()),
-                    },
-                };
-
-                // Send a response to the browser
-                let html = if result.code.is_some() {
-                    r#"<!DOCTYPE html>
-<html>
-<head>
-    <title>Authentication Successful</title>
-    <style>
-        body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
-               display: flex; justify-content: center; align-items: center; height: 100vh;
-               margin: 0; background: #1a1a2e; color: #eee; }
-        .container { text-align: center; padding: 2rem; }
-        .success { color: #10b981; font-size: 3rem; margin-bottom: 1rem; }
-        h1 { margin: 0 0 1rem 0; }
-        p { color: #888; }
-    </style>
-</head>
-<body>
-    <div class="container">
-        <div class="success">✓</div>
-        <h1>Authentication Successful</h1>
-        <p>You can close this window and return to Puffin.</p>
-    </div>
-</body>
-</html>"#
-                } else {
-                    r#"<!DOCTYPE html>
-<html>
-<head>
-    <title>Authentication Failed</title>
-    <style>
-        body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
-               display: flex; justify-content: center; align-items: center; height: 100vh;
-               margin: 0; background: #1a1a2e; color: #eee; }
-        .container { text-align: center; padding: 2rem; }
-        .error { color: #ef4444; font-size: 3rem; margin-bottom: 1rem; }
-        h1 { margin: 0 0 1rem 0; }
-        p { color: #888; }
-    </style>
-</head>
-<body>
-    <div class="container">
-        <div class="error">✗</div>
-        <h1>Authentication Failed</h1>
-        <p>Please close this window and try again in Puffin.</p>
-    </div>
-</body>
-</html>"#
-                };
-
-                let response = Response::from_string(html)
-                    .with_header(
-                        tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..])
-                            .unwrap(),
-                    );
-                let _ = request.respond(response);
-
-                let _ = tx.send(result);
-            }
-            Ok(None) => {
-                let _ = tx.send(OAuthResult {
-                    code: None,
-                    state: None,
-                    error: Some("OAuth timeout - no callback received".to_string()),
-                    redirect_uri: Some(redirect_uri_clone.clone()),
-                });
-            }
-            Err(e) => {
-                let _ = tx.send(OAuthResult {
-                    code: None,
-                    state: None,
-                    error: Some(format!("Server error: {}", e)),
-                    redirect_uri: Some(redirect_uri_clone.clone()),
-                });
-            }
-        }
-    });
-
-    // Open the OAuth URL in the default browser
-    if let Err(e) = open::that(auth_url.as_str()) {
-        return Err(format!("Failed to open browser: {}", e));
-    }
-
-    // Focus the main window after a short delay to let the browser open
-    let handle = app.clone();
-    thread::spawn(move || {
-        thread::sleep(Duration::from_millis(500));
-        if let Some(window) = handle.get_webview_window("main") {
-            let _ = window.set_focus();
-        }
-    });
-
-    // Wait for the callback result
-    match rx.recv_timeout(Duration::from_secs(300)) {
-        Ok(result) => Ok(result),
-        Err(_) => Err("OAuth timeout - no response received".to_string()),
-    }
-}
-
-/// Get the redirect URI for OAuth configuration
-#[tauri::command]
-fn get_oauth_redirect_uri() -> String {
-    // Return a placeholder - the actual port is determined at runtime
-    "http://127.0.0.1".to_string()
-}
+pub mod cli;
+mod db;
+mod deep_link;
+mod import;
+mod launch;
+mod oauth;
+mod vault;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
-        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            launch::handle_second_instance(app, &args, &cwd);
             // Focus the main window when a second instance is launched
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.set_focus();
@@ -237,7 +47,48 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
-        .invoke_handler(tauri::generate_handler![start_oauth_flow, get_oauth_redirect_uri])
+        .manage(oauth::PendingFlows::default())
+        .manage(import::StagedImports::default())
+        .invoke_handler(tauri::generate_handler![
+            oauth::start_oauth_flow,
+            oauth::cancel_oauth_flow,
+            deep_link::take_launch_deep_links,
+            launch::take_launch_files,
+            launch::read_opened_file,
+            oauth::get_oauth_redirect_uri,
+            oauth::token::exchange_oauth_code,
+            oauth::token::get_oauth_access_token,
+            oauth::token::refresh_oauth_token,
+            oauth::token::revoke_oauth_token,
+            oauth::token::get_oauth_client,
+            vault::vault_get,
+            vault::vault_has,
+            vault::vault_set,
+            vault::vault_delete,
+            db::hash::hash_database,
+            db::backup::create_backup,
+            db::backup::inspect_backup,
+            db::backup::extract_backup,
+            db::snapshot::snapshot_database,
+            db::restore::restore_backup,
+            db::check::check_database,
+            db::migrations::migrate_database,
+            db::schedule::get_backup_schedule,
+            db::schedule::set_backup_schedule,
+            import::camt::import_camt,
+            import::csv::import_csv,
+            import::csv::sample_csv,
+            import::ofx::import_ofx,
+            import::qif::import_qif,
+            import::choose_statement,
+            import::commit_import,
+            import::discard_import,
+            import::paste::parse_pasted_statement,
+            import::pdf::parse_pdf_statement,
+            import::watch::list_watched_imports,
+            import::watch::get_import_watch,
+            import::watch::set_import_watch
+        ])
         .setup(|app| {
             if cfg!(debug_assertions) {
                 app.handle().plugin(
@@ -247,6 +98,20 @@ pub fn run() {
                 )?;
             }
 
+            // Secrets (OAuth tokens, sync credentials) live in an encrypted
+            // file in the app data dir rather than webview localStorage
+            let data_dir = app.path().app_data_dir()?;
+            app.manage(vault::Vault::open(&data_dir.join(vault::VAULT_DIR))?);
+
+            // puffin:// links: OAuth redirects and routes for the frontend
+            deep_link::init(app.handle());
+            // Files passed via "Open with Puffin"
+            launch::init(app.handle());
+            // Automatic local backups on launch, daily or after edits
+            db::schedule::init(app.handle())?;
+            // Statements downloaded into the watched folder
+            import::watch::init(app.handle())?;
+
             // Emit ready event
             let _ = app.emit("app-ready", ());
 
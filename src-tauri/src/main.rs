@@ -2,5 +2,13 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // `puffin <command>` runs headless (see `cli`); anything else opens the app
+    let args: Vec<String> = std::env::args_os()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    if let Some(code) = puffin_lib::cli::run(&args) {
+        std::process::exit(code);
+    }
     puffin_lib::run();
 }
@@ -0,0 +1,363 @@
+//! Loopback callback server
+//!
+//! Waits on the `tiny_http` server for the provider's redirect. Anything that
+//! isn't a callback for the expected path (favicon fetches, port scanners,
+//! stray browser tabs) is answered with a 404 and ignored. A callback whose
+//! `state` doesn't match is rejected but doesn't end the flow, so a local
+//! process can't abort sign-in by racing the browser to the port.
+//!
+//! With IPv4 and IPv6 listeners on the same port (`BindMode::Both`), each
+//! server is polled in turn for a short slice. Deep-link flows have no server
+//! at all and only wait to be interrupted.
+
+use super::OAuthFlowError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Request, Response, Server};
+use url::Url;
+
+/// Path component of the registered redirect URI (`http://127.0.0.1:<port>`)
+pub const CALLBACK_PATH: &str = "/";
+
+/// Per-server wait when listening on more than one address
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+const SUCCESS_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Authentication Successful</title>
+    <style>
+        body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
+               display: flex; justify-content: center; align-items: center; height: 100vh;
+               margin: 0; background: #1a1a2e; color: #eee; }
+        .container { text-align: center; padding: 2rem; }
+        .success { color: #10b981; font-size: 3rem; margin-bottom: 1rem; }
+        h1 { margin: 0 0 1rem 0; }
+        p { color: #888; }
+    </style>
+</head>
+<body>
+    <div class="container">
+        <div class="success">✓</div>
+        <h1>Authentication Successful</h1>
+        <p>You can close this window and return to Puffin.</p>
+    </div>
+</body>
+</html>"#;
+
+const FAILURE_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Authentication Failed</title>
+    <style>
+        body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
+               display: flex; justify-content: center; align-items: center; height: 100vh;
+               margin: 0; background: #1a1a2e; color: #eee; }
+        .container { text-align: center; padding: 2rem; }
+        .error { color: #ef4444; font-size: 3rem; margin-bottom: 1rem; }
+        h1 { margin: 0 0 1rem 0; }
+        p { color: #888; }
+    </style>
+</head>
+<body>
+    <div class="container">
+        <div class="error">✗</div>
+        <h1>Authentication Failed</h1>
+        <p>Please close this window and try again in Puffin.</p>
+    </div>
+</body>
+</html>"#;
+
+/// Query parameters of an accepted callback
+#[derive(Debug, PartialEq)]
+pub struct CallbackParams {
+    pub code: Option<String>,
+    pub state: String,
+    /// Error reported by the provider (e.g. `access_denied`)
+    pub error: Option<String>,
+}
+
+/// How a single incoming request relates to the pending flow
+#[derive(Debug, PartialEq)]
+pub enum Callback {
+    /// Wrong path, or no `code`/`error` parameter - not a redirect for us
+    Unrelated,
+    /// A redirect whose `state` doesn't match the one we sent
+    StateMismatch,
+    Accepted(CallbackParams),
+}
+
+/// Classify a request target such as `/?code=...&state=...`
+pub fn parse_callback(target: &str, expected_state: &str) -> Callback {
+    let url = match Url::parse("http://127.0.0.1").and_then(|base| base.join(target)) {
+        Ok(url) => url,
+        Err(_) => return Callback::Unrelated,
+    };
+    if url.path() != CALLBACK_PATH {
+        return Callback::Unrelated;
+    }
+    parse_query(url.query().unwrap_or_default(), expected_state)
+}
+
+/// Classify the query string of a redirect, however it arrived (loopback
+/// request or `puffin://oauth/callback` deep link)
+pub fn parse_query(query: &str, expected_state: &str) -> Callback {
+    let mut code = None;
+    let mut state = None;
+    let mut error = None;
+    for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "code" => code = Some(value.into_owned()),
+            "state" => state = Some(value.into_owned()),
+            "error" => error = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+
+    if code.is_none() && error.is_none() {
+        return Callback::Unrelated;
+    }
+    match state {
+        Some(state) if state == expected_state => {
+            Callback::Accepted(CallbackParams { code, state, error })
+        }
+        _ => Callback::StateMismatch,
+    }
+}
+
+fn respond_html(request: Request, status: u16, html: &str) {
+    let response = Response::from_string(html)
+        .with_status_code(status)
+        .with_header(
+            Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..]).unwrap(),
+        );
+    let _ = request.respond(response);
+}
+
+/// Serve requests on `servers` until a callback with the expected `state`
+/// arrives, `timeout` elapses, or `cancelled` is set (followed by
+/// `Server::unblock` on each server).
+///
+/// Times out with `StateMismatch` instead of `Timeout` if the only callbacks
+/// seen carried the wrong state, since that points at a misbehaving client
+/// rather than a user who never finished signing in.
+pub fn wait_for_callback(
+    servers: &[Server],
+    expected_state: &str,
+    timeout: Duration,
+    cancelled: &AtomicBool,
+) -> Result<CallbackParams, OAuthFlowError> {
+    let deadline = Instant::now() + timeout;
+    let mut saw_mismatch = false;
+
+    loop {
+        if cancelled.load(Ordering::SeqCst) {
+            return Err(OAuthFlowError::Cancelled);
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(if saw_mismatch {
+                OAuthFlowError::StateMismatch
+            } else {
+                OAuthFlowError::Timeout
+            });
+        }
+
+        let slice = if servers.len() == 1 {
+            remaining
+        } else {
+            remaining.min(POLL_INTERVAL)
+        };
+        if servers.is_empty() {
+            // Deep-link flow: nothing to serve, just wait to be interrupted
+            thread::sleep(slice);
+            continue;
+        }
+        for server in servers {
+            let request = match server.recv_timeout(slice) {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(e) => return Err(OAuthFlowError::Server(e.to_string())),
+            };
+
+            match parse_callback(request.url(), expected_state) {
+                Callback::Unrelated => {
+                    let _ = request.respond(Response::empty(404));
+                }
+                Callback::StateMismatch => {
+                    log::warn!("Ignoring OAuth callback with mismatched state");
+                    saw_mismatch = true;
+                    respond_html(request, 400, FAILURE_PAGE);
+                }
+                Callback::Accepted(params) => {
+                    let page = if params.code.is_some() {
+                        SUCCESS_PAGE
+                    } else {
+                        FAILURE_PAGE
+                    };
+                    respond_html(request, 200, page);
+                    return Ok(params);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    #[test]
+    fn accepts_matching_callback() {
+        assert_eq!(
+            parse_callback("/?state=abc&code=4%2F0Ax&scope=email", "abc"),
+            Callback::Accepted(CallbackParams {
+                code: Some("4/0Ax".to_string()),
+                state: "abc".to_string(),
+                error: None,
+            })
+        );
+    }
+
+    #[test]
+    fn provider_errors_are_accepted_callbacks() {
+        assert!(matches!(
+            parse_callback("/?error=access_denied&state=abc", "abc"),
+            Callback::Accepted(CallbackParams { error: Some(ref e), code: None, .. }) if e == "access_denied"
+        ));
+    }
+
+    #[test]
+    fn ignores_unrelated_requests() {
+        assert_eq!(parse_callback("/favicon.ico", "abc"), Callback::Unrelated);
+        assert_eq!(parse_callback("/", "abc"), Callback::Unrelated);
+        assert_eq!(
+            parse_callback("/other?code=x&state=abc", "abc"),
+            Callback::Unrelated
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_or_missing_state() {
+        assert_eq!(
+            parse_callback("/?code=x&state=evil", "abc"),
+            Callback::StateMismatch
+        );
+        assert_eq!(parse_callback("/?code=x", "abc"), Callback::StateMismatch);
+    }
+
+    fn get(port: u16, target: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: 127.0.0.1\r\nConnection: close\r\n\r\n",
+            target
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn keeps_listening_past_noise_until_valid_callback() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+
+        let client = thread::spawn(move || {
+            let favicon = get(port, "/favicon.ico");
+            let forged = get(port, "/?code=stolen&state=evil");
+            let real = get(port, "/?code=real&state=abc");
+            (favicon, forged, real)
+        });
+
+        let params = wait_for_callback(
+            &[server],
+            "abc",
+            Duration::from_secs(10),
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        assert_eq!(params.code.as_deref(), Some("real"));
+
+        let (favicon, forged, real) = client.join().unwrap();
+        assert!(favicon.starts_with("HTTP/1.1 404"));
+        assert!(forged.starts_with("HTTP/1.1 400"));
+        assert!(real.starts_with("HTTP/1.1 200"));
+    }
+
+    #[test]
+    fn reports_mismatch_when_only_forged_callbacks_arrive() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let client = thread::spawn(move || get(port, "/?code=stolen&state=evil"));
+
+        let err = wait_for_callback(
+            &[server],
+            "abc",
+            Duration::from_millis(500),
+            &AtomicBool::new(false),
+        )
+        .unwrap_err();
+        assert!(matches!(err, OAuthFlowError::StateMismatch));
+        client.join().unwrap();
+    }
+
+    #[test]
+    fn times_out_without_callbacks() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let err = wait_for_callback(
+            &[server],
+            "abc",
+            Duration::from_millis(50),
+            &AtomicBool::new(false),
+        )
+        .unwrap_err();
+        assert!(matches!(err, OAuthFlowError::Timeout));
+    }
+
+    #[test]
+    fn accepts_callback_on_any_server() {
+        let servers = [
+            Server::http("127.0.0.1:0").unwrap(),
+            Server::http("127.0.0.1:0").unwrap(),
+        ];
+        let port = servers[1].server_addr().to_ip().unwrap().port();
+        let client = thread::spawn(move || get(port, "/?code=second&state=abc"));
+
+        let params = wait_for_callback(
+            &servers,
+            "abc",
+            Duration::from_secs(10),
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        assert_eq!(params.code.as_deref(), Some("second"));
+        client.join().unwrap();
+    }
+
+    #[test]
+    fn cancel_unblocks_waiting_server() {
+        let servers = std::sync::Arc::new([Server::http("127.0.0.1:0").unwrap()]);
+        let cancelled = std::sync::Arc::new(AtomicBool::new(false));
+
+        let waiter = {
+            let servers = servers.clone();
+            let cancelled = cancelled.clone();
+            thread::spawn(move || {
+                wait_for_callback(&*servers, "abc", Duration::from_secs(60), &cancelled)
+            })
+        };
+        cancelled.store(true, Ordering::SeqCst);
+        servers[0].unblock();
+
+        assert!(matches!(
+            waiter.join().unwrap(),
+            Err(OAuthFlowError::Cancelled)
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+//! Callback server sockets
+//!
+//! By default the OS assigns the port (bind to port 0) and the socket is
+//! handed straight to `tiny_http`, so no other process can take the port
+//! between choosing and listening. Providers that only accept pre-registered
+//! redirect URIs use one of the fixed ports instead (`PortMode::Fixed`);
+//! those are also the fallback if the OS can't assign a port.
+
+use super::options::PortMode;
+use super::OAuthFlowError;
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use tiny_http::Server;
+
+/// Fixed loopback ports, in order of preference. Register
+/// `http://127.0.0.1:<port>` for each with providers that need exact URIs.
+pub const FALLBACK_PORTS: [u16; 3] = [47615, 47616, 47617];
+
+/// With several addresses the port picked for the first one may be taken on
+/// another; retry with a fresh port this many times.
+const EPHEMERAL_ATTEMPTS: usize = 5;
+
+/// Listening callback servers, one per address, all on `port`
+pub struct BoundServers {
+    pub servers: Vec<Server>,
+    pub port: u16,
+}
+
+/// Bind every address in `hosts` on the same port. With `port` 0 the OS picks
+/// it for the first address.
+fn bind_all(hosts: &[IpAddr], port: u16) -> io::Result<(Vec<TcpListener>, u16)> {
+    let mut listeners = Vec::with_capacity(hosts.len());
+    let mut port = port;
+    for &host in hosts {
+        let listener = TcpListener::bind(SocketAddr::new(host, port))?;
+        port = listener.local_addr()?.port();
+        listeners.push(listener);
+    }
+    Ok((listeners, port))
+}
+
+fn try_bind(
+    hosts: &[IpAddr],
+    mode: PortMode,
+    fixed_ports: &[u16],
+) -> Option<(Vec<TcpListener>, u16)> {
+    if mode == PortMode::Ephemeral {
+        for _ in 0..EPHEMERAL_ATTEMPTS {
+            match bind_all(hosts, 0) {
+                Ok(bound) => return Some(bound),
+                Err(e) => log::warn!("Failed to bind an ephemeral callback port: {}", e),
+            }
+        }
+    }
+    fixed_ports
+        .iter()
+        .find_map(|&port| bind_all(hosts, port).ok())
+}
+
+/// Start a callback server on each of `hosts`
+pub fn bind(
+    hosts: &[IpAddr],
+    mode: PortMode,
+    fixed_ports: &[u16],
+) -> Result<BoundServers, OAuthFlowError> {
+    let (listeners, port) = try_bind(hosts, mode, fixed_ports)
+        .ok_or_else(|| OAuthFlowError::Server("No available port found".to_string()))?;
+
+    let servers = listeners
+        .into_iter()
+        .map(|listener| Server::from_listener(listener, None))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| OAuthFlowError::Server(format!("Failed to start server: {}", e)))?;
+    Ok(BoundServers { servers, port })
+}
+
+/// Whether `port` can currently be bound on every address in `hosts`
+pub fn is_free(hosts: &[IpAddr], port: u16) -> bool {
+    bind_all(hosts, port).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const LOCALHOST: [IpAddr; 1] = [IpAddr::V4(Ipv4Addr::LOCALHOST)];
+
+    #[test]
+    fn ephemeral_port_is_held_by_the_server() {
+        let bound = bind(&LOCALHOST, PortMode::Ephemeral, &[]).unwrap();
+        assert_ne!(bound.port, 0);
+        assert_eq!(
+            bound.servers[0].server_addr().to_ip().unwrap().port(),
+            bound.port
+        );
+        assert!(TcpListener::bind(("127.0.0.1", bound.port)).is_err());
+    }
+
+    #[test]
+    fn fixed_mode_skips_busy_ports() {
+        // Two OS-assigned ports stand in for the fixed list
+        let busy = TcpListener::bind("127.0.0.1:0").unwrap();
+        let free = TcpListener::bind("127.0.0.1:0").unwrap();
+        let ports = [
+            busy.local_addr().unwrap().port(),
+            free.local_addr().unwrap().port(),
+        ];
+        drop(free);
+
+        let bound = bind(&LOCALHOST, PortMode::Fixed, &ports).unwrap();
+        assert_eq!(bound.port, ports[1]);
+
+        drop(bound);
+        drop(busy);
+    }
+
+    #[test]
+    fn fixed_mode_fails_when_all_ports_are_taken() {
+        let busy = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = busy.local_addr().unwrap().port();
+        assert!(matches!(
+            bind(&LOCALHOST, PortMode::Fixed, &[port]),
+            Err(OAuthFlowError::Server(_))
+        ));
+    }
+}
@@ -0,0 +1,416 @@
+//! OAuth loopback flow for cloud storage providers
+//!
+//! Opens the provider's consent page in the system browser and waits for the
+//! redirect on a short-lived `tiny_http` server bound to the loopback
+//! interface (127.0.0.1 by default, see [`options`]). The flow
+//! uses PKCE (RFC 7636) so an intercepted authorization code is useless
+//! without the verifier held by this process. The code is then exchanged
+//! natively through the commands in [`token`].
+//!
+//! `start_oauth_flow` returns a flow ID as soon as the browser is opened. The
+//! outcome is reported through events (all payloads carry `flow_id`):
+//! - `oauth://opened` - consent page opened, waiting for the redirect
+//! - `oauth://callback` - redirect received, payload holds the `OAuthResult`
+//! - `oauth://timeout` - no valid redirect before the timeout
+//! - `oauth://cancelled` - aborted with `cancel_oauth_flow`
+//! - `oauth://error` - the flow failed, payload holds the `OAuthFlowError`
+//!
+//! Providers that accept custom-scheme redirects can use
+//! `puffin://oauth/callback` instead (`RedirectMode::DeepLink`); the
+//! deep-link handler hands those callbacks to [`PendingFlows::complete`].
+
+mod callback;
+pub mod listener;
+pub mod options;
+mod pkce;
+pub mod token;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tiny_http::Server;
+
+use callback::{Callback, CallbackParams};
+use options::{OAuthFlowOptions, RedirectMode};
+
+/// Redirect URI for `RedirectMode::DeepLink`
+pub const DEEP_LINK_REDIRECT_URI: &str = "puffin://oauth/callback";
+
+pub const EVENT_OPENED: &str = "oauth://opened";
+pub const EVENT_CALLBACK: &str = "oauth://callback";
+pub const EVENT_TIMEOUT: &str = "oauth://timeout";
+pub const EVENT_CANCELLED: &str = "oauth://cancelled";
+pub const EVENT_ERROR: &str = "oauth://error";
+
+/// Result of the OAuth flow
+#[derive(Clone, serde::Serialize)]
+pub struct OAuthResult {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Error reported by the provider in the redirect (e.g. `access_denied`)
+    pub error: Option<String>,
+    pub redirect_uri: Option<String>,
+    /// PKCE verifier to send with the token exchange (only set alongside `code`)
+    pub code_verifier: Option<String>,
+}
+
+/// Why the flow ended without a callback, serialized as `{ kind, message }`
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum OAuthFlowError {
+    /// No valid callback arrived before the timeout
+    Timeout,
+    /// Callbacks arrived, but none carried the `state` we sent
+    StateMismatch,
+    /// Aborted through `cancel_oauth_flow`
+    Cancelled,
+    /// The callback server couldn't be started or failed
+    Server(String),
+    /// The system browser couldn't be opened
+    Browser(String),
+    /// The authorization endpoint URL is malformed
+    InvalidUrl(String),
+    /// The `options` argument is inconsistent (reserved parameter, bad timeout)
+    InvalidOptions(String),
+}
+
+impl fmt::Display for OAuthFlowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OAuthFlowError::Timeout => write!(f, "OAuth timeout - no callback received"),
+            OAuthFlowError::StateMismatch => {
+                write!(f, "OAuth callback rejected - state parameter did not match")
+            }
+            OAuthFlowError::Cancelled => write!(f, "OAuth flow cancelled"),
+            OAuthFlowError::Server(msg) => write!(f, "Callback server error: {}", msg),
+            OAuthFlowError::Browser(msg) => write!(f, "Failed to open browser: {}", msg),
+            OAuthFlowError::InvalidUrl(msg) => write!(f, "Invalid authorization URL: {}", msg),
+            OAuthFlowError::InvalidOptions(msg) => write!(f, "Invalid OAuth options: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for OAuthFlowError {}
+
+/// Returned by `start_oauth_flow` once the consent page is open
+#[derive(Clone, serde::Serialize)]
+pub struct OAuthFlowStarted {
+    pub flow_id: String,
+    pub redirect_uri: String,
+    /// Loopback port the callback server is bound to (`None` for deep links)
+    pub port: Option<u16>,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct CallbackEvent {
+    flow_id: String,
+    result: OAuthResult,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct FlowEndedEvent {
+    flow_id: String,
+    error: OAuthFlowError,
+}
+
+/// A flow whose callback servers are still listening
+struct PendingFlow {
+    servers: Vec<Server>,
+    /// `state` sent with the authorization request
+    state: String,
+    cancelled: AtomicBool,
+    /// Callback received out of band (deep link)
+    delivered: Mutex<Option<CallbackParams>>,
+}
+
+impl PendingFlow {
+    /// Wake the waiting thread; it drops the servers, which frees the port
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        for server in &self.servers {
+            server.unblock();
+        }
+    }
+}
+
+/// Flows waiting for a callback, keyed by flow ID. Managed as Tauri state.
+#[derive(Default)]
+pub struct PendingFlows(Mutex<HashMap<String, Arc<PendingFlow>>>);
+
+impl PendingFlows {
+    fn insert(&self, flow_id: &str, flow: Arc<PendingFlow>) {
+        self.0.lock().unwrap().insert(flow_id.to_string(), flow);
+    }
+
+    fn remove(&self, flow_id: &str) -> Option<Arc<PendingFlow>> {
+        self.0.lock().unwrap().remove(flow_id)
+    }
+
+    /// Finish the pending flow whose `state` matches the query of a
+    /// `puffin://oauth/callback` deep link. Returns false if no flow matched.
+    pub fn complete(&self, query: &str) -> bool {
+        let mut flows = self.0.lock().unwrap();
+        let matched = flows.iter().find_map(|(flow_id, flow)| {
+            match callback::parse_query(query, &flow.state) {
+                Callback::Accepted(params) => Some((flow_id.clone(), params)),
+                _ => None,
+            }
+        });
+        let Some((flow_id, params)) = matched else {
+            return false;
+        };
+
+        let flow = flows.remove(&flow_id).unwrap();
+        *flow.delivered.lock().unwrap() = Some(params);
+        flow.cancel();
+        true
+    }
+}
+
+/// Start OAuth flow with a local callback server
+///
+/// Returns as soon as the consent page has been opened; the authorization
+/// code (plus PKCE verifier) arrives later in an `oauth://callback` event.
+#[tauri::command]
+pub async fn start_oauth_flow(
+    app: tauri::AppHandle,
+    flows: tauri::State<'_, PendingFlows>,
+    auth_url_base: String,
+    client_id: String,
+    scope: String,
+    state: String,
+    options: Option<OAuthFlowOptions>,
+) -> Result<OAuthFlowStarted, OAuthFlowError> {
+    let options = options.unwrap_or_default();
+    let timeout = options.timeout()?;
+    let fixed_ports = options.fixed_ports()?;
+
+    // Bind the callback server; the sockets stay open until the flow ends
+    let (servers, port, redirect_uri) = match options.redirect {
+        RedirectMode::Loopback => {
+            let hosts = options.bind.addresses();
+            let bound = listener::bind(&hosts, options.port, &fixed_ports)?;
+            // The first address is the one registered with the provider
+            let redirect_uri = format!("http://{}", SocketAddr::new(hosts[0], bound.port));
+            (bound.servers, Some(bound.port), redirect_uri)
+        }
+        RedirectMode::DeepLink => (Vec::new(), None, DEEP_LINK_REDIRECT_URI.to_string()),
+    };
+    let pkce = pkce::PkcePair::generate();
+
+    // Build the full OAuth URL
+    let auth_url = options.auth_url(
+        &auth_url_base,
+        &client_id,
+        &redirect_uri,
+        &scope,
+        &state,
+        &pkce.challenge,
+    )?;
+
+    let flow_id = format!("{:032x}", rand::random::<u128>());
+    let flow = Arc::new(PendingFlow {
+        servers,
+        state,
+        cancelled: AtomicBool::new(false),
+        delivered: Mutex::new(None),
+    });
+    flows.insert(&flow_id, flow.clone());
+
+    // Open the OAuth URL in the default browser. Nothing waits on the flow
+    // yet, so a failure ends it without any event besides this error.
+    if let Err(e) = open::that(auth_url.as_str()) {
+        flows.remove(&flow_id);
+        return Err(OAuthFlowError::Browser(e.to_string()));
+    }
+
+    // Wait for the callback in a separate thread and report the outcome
+    let handle = app.clone();
+    let thread_flow_id = flow_id.clone();
+    let thread_redirect_uri = redirect_uri.clone();
+    thread::spawn(move || {
+        let outcome =
+            callback::wait_for_callback(&flow.servers, &flow.state, timeout, &flow.cancelled);
+        // Dropping the last reference to the flow closes the listener
+        handle.state::<PendingFlows>().remove(&thread_flow_id);
+        // A deep-link callback stops the wait the same way a cancel does
+        let delivered = flow.delivered.lock().unwrap().take();
+        let outcome = match (outcome, delivered) {
+            (Err(OAuthFlowError::Cancelled), Some(params)) => Ok(params),
+            (outcome, _) => outcome,
+        };
+        drop(flow);
+
+        let flow_id = thread_flow_id;
+        let emitted = match outcome {
+            Ok(params) => {
+                let code_verifier = params.code.as_ref().map(|_| pkce.verifier);
+                let result = OAuthResult {
+                    code: params.code,
+                    state: Some(params.state),
+                    error: params.error,
+                    redirect_uri: Some(thread_redirect_uri),
+                    code_verifier,
+                };
+                handle.emit(EVENT_CALLBACK, CallbackEvent { flow_id, result })
+            }
+            Err(error) => {
+                let event = match error {
+                    OAuthFlowError::Timeout => EVENT_TIMEOUT,
+                    OAuthFlowError::Cancelled => EVENT_CANCELLED,
+                    _ => EVENT_ERROR,
+                };
+                handle.emit(event, FlowEndedEvent { flow_id, error })
+            }
+        };
+        if let Err(e) = emitted {
+            log::error!("Failed to emit OAuth event: {}", e);
+        }
+    });
+
+    let started = OAuthFlowStarted {
+        flow_id,
+        redirect_uri,
+        port,
+    };
+    let _ = app.emit(EVENT_OPENED, started.clone());
+
+    // Focus the main window after a short delay to let the browser open
+    let handle = app.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(500));
+        if let Some(window) = handle.get_webview_window("main") {
+            let _ = window.set_focus();
+        }
+    });
+
+    Ok(started)
+}
+
+/// Abort a pending flow and free its callback port.
+/// Returns false if the flow already finished (or never existed).
+#[tauri::command]
+pub fn cancel_oauth_flow(flows: tauri::State<'_, PendingFlows>, flow_id: String) -> bool {
+    match flows.remove(&flow_id) {
+        Some(flow) => {
+            flow.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// One of the fixed loopback redirect URIs
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct FixedRedirectUri {
+    pub port: u16,
+    pub uri: String,
+    /// Whether the port is free right now (`None` unless checked)
+    pub available: Option<bool>,
+}
+
+/// Redirect URIs to register with a provider
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct RedirectUriInfo {
+    /// Always `http`; loopback redirects never use TLS
+    pub loopback_scheme: String,
+    pub loopback_host: String,
+    /// True when the provider accepts any loopback port (RFC 8252 §7.3),
+    /// so nothing needs registering in the default ephemeral-port mode
+    pub any_port: bool,
+    /// URIs for `PortMode::Fixed`, in order of preference
+    pub fixed: Vec<FixedRedirectUri>,
+    /// URI for `RedirectMode::DeepLink`
+    pub deep_link_uri: String,
+}
+
+fn describe_redirect_uris(fixed_ports: &[u16], check_ports: bool) -> RedirectUriInfo {
+    let hosts = options::BindMode::default().addresses();
+    let fixed = fixed_ports
+        .iter()
+        .map(|&port| FixedRedirectUri {
+            port,
+            uri: format!("http://{}", SocketAddr::new(hosts[0], port)),
+            available: check_ports.then(|| listener::is_free(&hosts, port)),
+        })
+        .collect();
+
+    RedirectUriInfo {
+        loopback_scheme: "http".to_string(),
+        loopback_host: hosts[0].to_string(),
+        any_port: true,
+        fixed,
+        deep_link_uri: DEEP_LINK_REDIRECT_URI.to_string(),
+    }
+}
+
+/// Describe the redirect URIs this app can receive, for the provider's
+/// console. With `check_ports`, also test whether the fixed ports are free.
+#[tauri::command]
+pub fn get_oauth_redirect_uri(check_ports: Option<bool>) -> RedirectUriInfo {
+    describe_redirect_uris(&listener::FALLBACK_PORTS, check_ports.unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(state: &str) -> Arc<PendingFlow> {
+        Arc::new(PendingFlow {
+            servers: Vec::new(),
+            state: state.to_string(),
+            cancelled: AtomicBool::new(false),
+            delivered: Mutex::new(None),
+        })
+    }
+
+    #[test]
+    fn deep_link_completes_flow_with_matching_state() {
+        let flows = PendingFlows::default();
+        let first = pending("first");
+        let second = pending("second");
+        flows.insert("a", first.clone());
+        flows.insert("b", second.clone());
+
+        assert!(!flows.complete("code=x&state=unknown"));
+        assert!(flows.complete("code=x&state=second"));
+
+        assert!(second.cancelled.load(Ordering::SeqCst));
+        assert_eq!(
+            second
+                .delivered
+                .lock()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .code
+                .as_deref(),
+            Some("x")
+        );
+        assert!(!first.cancelled.load(Ordering::SeqCst));
+        assert!(flows.remove("b").is_none());
+        assert!(flows.remove("a").is_some());
+    }
+
+    #[test]
+    fn describes_fixed_and_deep_link_redirects() {
+        let busy = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let busy_port = busy.local_addr().unwrap().port();
+
+        let info = describe_redirect_uris(&[busy_port], true);
+        assert_eq!(info.loopback_host, "127.0.0.1");
+        assert!(info.any_port);
+        assert_eq!(info.fixed[0].uri, format!("http://127.0.0.1:{}", busy_port));
+        assert_eq!(info.fixed[0].available, Some(false));
+        assert_eq!(info.deep_link_uri, "puffin://oauth/callback");
+
+        let unchecked = describe_redirect_uris(&listener::FALLBACK_PORTS, false);
+        assert_eq!(unchecked.fixed.len(), listener::FALLBACK_PORTS.len());
+        assert!(unchecked.fixed.iter().all(|f| f.available.is_none()));
+    }
+}
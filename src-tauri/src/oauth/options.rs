@@ -0,0 +1,274 @@
+//! Per-provider options for `start_oauth_flow`
+//!
+//! The defaults reproduce the original Google Drive flow: `access_type=offline`
+//! and `prompt=consent` (so Google always returns a refresh token), a
+//! 5-minute timeout and an IPv4 loopback listener on an OS-assigned port.
+//! Other providers can drop the Google parameters, add their own, pick the
+//! loopback address family and ports they registered, or have the provider
+//! redirect to `puffin://oauth/callback` instead of a loopback port.
+
+use super::listener::FALLBACK_PORTS;
+use super::{pkce, OAuthFlowError};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+use url::Url;
+
+/// How long to wait for the provider to redirect back, unless overridden
+pub const DEFAULT_TIMEOUT_SECS: u64 = 300;
+/// Upper bound for `timeout_secs`; the callback server holds a port meanwhile
+pub const MAX_TIMEOUT_SECS: u64 = 3600;
+
+/// Parameters only Google understands, sent unless `omit_google_params` is set
+const GOOGLE_PARAMS: [(&str, &str); 2] = [("access_type", "offline"), ("prompt", "consent")];
+
+/// Parameters owned by the flow itself; `extra_params` may not override them
+const RESERVED_PARAMS: [&str; 7] = [
+    "client_id",
+    "redirect_uri",
+    "response_type",
+    "scope",
+    "state",
+    "code_challenge",
+    "code_challenge_method",
+];
+
+/// Loopback address family for the callback server
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BindMode {
+    /// `127.0.0.1` only
+    #[default]
+    Ipv4,
+    /// `[::1]` only
+    Ipv6,
+    /// Both loopback addresses on the same port; the redirect URI uses `127.0.0.1`
+    Both,
+}
+
+impl BindMode {
+    /// Addresses to listen on, the first one being used in the redirect URI
+    pub fn addresses(self) -> Vec<IpAddr> {
+        let v4 = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        match self {
+            BindMode::Ipv4 => vec![v4],
+            BindMode::Ipv6 => vec![v6],
+            BindMode::Both => vec![v4, v6],
+        }
+    }
+}
+
+/// How the callback port is chosen
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortMode {
+    /// Any port the OS assigns, falling back to the fixed ports. For providers
+    /// that accept any loopback port (RFC 8252 §7.3), like Google.
+    #[default]
+    Ephemeral,
+    /// Only the fixed ports, for providers that need exact redirect URIs
+    Fixed,
+}
+
+/// Where the provider sends the user back to
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RedirectMode {
+    /// `http://127.0.0.1:<port>` served by the callback server
+    #[default]
+    Loopback,
+    /// `puffin://oauth/callback`, delivered by the deep-link handler. Only
+    /// for providers that accept custom-scheme redirects.
+    DeepLink,
+}
+
+/// Optional `options` argument of `start_oauth_flow`
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct OAuthFlowOptions {
+    /// Additional query parameters for the authorization URL. Can override
+    /// the Google parameters, but not the ones the flow depends on.
+    pub extra_params: BTreeMap<String, String>,
+    /// Don't send `access_type=offline` / `prompt=consent`
+    pub omit_google_params: bool,
+    /// Seconds to wait for the redirect (default 300, max 3600)
+    pub timeout_secs: Option<u64>,
+    pub bind: BindMode,
+    pub port: PortMode,
+    /// Fixed ports in order of preference (default `FALLBACK_PORTS`)
+    pub fixed_ports: Option<Vec<u16>>,
+    pub redirect: RedirectMode,
+}
+
+impl OAuthFlowOptions {
+    pub fn timeout(&self) -> Result<Duration, OAuthFlowError> {
+        match self.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS) {
+            secs @ 1..=MAX_TIMEOUT_SECS => Ok(Duration::from_secs(secs)),
+            secs => Err(OAuthFlowError::InvalidOptions(format!(
+                "timeout must be between 1 and {} seconds, got {}",
+                MAX_TIMEOUT_SECS, secs
+            ))),
+        }
+    }
+
+    pub fn fixed_ports(&self) -> Result<Vec<u16>, OAuthFlowError> {
+        match &self.fixed_ports {
+            None => Ok(FALLBACK_PORTS.to_vec()),
+            Some(ports) if ports.is_empty() || ports.contains(&0) => {
+                Err(OAuthFlowError::InvalidOptions(
+                    "fixed ports must be a non-empty list of non-zero ports".to_string(),
+                ))
+            }
+            Some(ports) => Ok(ports.clone()),
+        }
+    }
+
+    /// Build the authorization URL for this flow
+    pub fn auth_url(
+        &self,
+        auth_url_base: &str,
+        client_id: &str,
+        redirect_uri: &str,
+        scope: &str,
+        state: &str,
+        code_challenge: &str,
+    ) -> Result<Url, OAuthFlowError> {
+        if let Some(key) = self
+            .extra_params
+            .keys()
+            .find(|key| RESERVED_PARAMS.contains(&key.as_str()))
+        {
+            return Err(OAuthFlowError::InvalidOptions(format!(
+                "extra parameter '{}' is set by the flow itself",
+                key
+            )));
+        }
+
+        let mut url =
+            Url::parse(auth_url_base).map_err(|e| OAuthFlowError::InvalidUrl(e.to_string()))?;
+        let mut query = url.query_pairs_mut();
+        query
+            .append_pair("client_id", client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("response_type", "code")
+            .append_pair("scope", scope);
+        if !self.omit_google_params {
+            for (key, value) in GOOGLE_PARAMS {
+                if !self.extra_params.contains_key(key) {
+                    query.append_pair(key, value);
+                }
+            }
+        }
+        for (key, value) in &self.extra_params {
+            query.append_pair(key, value);
+        }
+        query
+            .append_pair("state", state)
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("code_challenge_method", pkce::CHALLENGE_METHOD);
+        drop(query);
+
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(options: &OAuthFlowOptions) -> Vec<(String, String)> {
+        options
+            .auth_url(
+                "https://auth.example/authorize",
+                "client",
+                "http://127.0.0.1:5000",
+                "files",
+                "xyz",
+                "challenge",
+            )
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect()
+    }
+
+    fn get<'a>(params: &'a [(String, String)], key: &str) -> Option<&'a str> {
+        params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    #[test]
+    fn defaults_match_google_flow() {
+        let options: OAuthFlowOptions = serde_json::from_str("{}").unwrap();
+        let params = params(&options);
+
+        assert_eq!(get(&params, "access_type"), Some("offline"));
+        assert_eq!(get(&params, "prompt"), Some("consent"));
+        assert_eq!(get(&params, "response_type"), Some("code"));
+        assert_eq!(get(&params, "code_challenge_method"), Some("S256"));
+        assert_eq!(options.timeout().unwrap(), Duration::from_secs(300));
+        assert_eq!(options.bind, BindMode::Ipv4);
+        assert_eq!(options.redirect, RedirectMode::Loopback);
+        assert_eq!(options.port, PortMode::Ephemeral);
+        assert_eq!(options.fixed_ports().unwrap(), FALLBACK_PORTS);
+    }
+
+    #[test]
+    fn extra_params_replace_google_params() {
+        let options: OAuthFlowOptions = serde_json::from_str(
+            r#"{"omitGoogleParams":true,"extraParams":{"token_access_type":"offline"},"bind":"both","timeoutSecs":60,"redirect":"deepLink"}"#,
+        )
+        .unwrap();
+        let params = params(&options);
+
+        assert_eq!(get(&params, "access_type"), None);
+        assert_eq!(get(&params, "prompt"), None);
+        assert_eq!(get(&params, "token_access_type"), Some("offline"));
+        assert_eq!(options.bind.addresses().len(), 2);
+        assert_eq!(options.timeout().unwrap(), Duration::from_secs(60));
+        assert_eq!(options.redirect, RedirectMode::DeepLink);
+
+        let mut options = OAuthFlowOptions::default();
+        options
+            .extra_params
+            .insert("prompt".into(), "select_account".into());
+        let params = self::params(&options);
+        assert_eq!(get(&params, "prompt"), Some("select_account"));
+        assert_eq!(params.iter().filter(|(k, _)| k == "prompt").count(), 1);
+    }
+
+    #[test]
+    fn rejects_reserved_params_and_bad_timeouts() {
+        let mut options = OAuthFlowOptions::default();
+        options.extra_params.insert("state".into(), "forged".into());
+        assert!(matches!(
+            options.auth_url("https://auth.example", "c", "r", "s", "x", "ch"),
+            Err(OAuthFlowError::InvalidOptions(_))
+        ));
+
+        for ports in [vec![], vec![8080, 0]] {
+            let options = OAuthFlowOptions {
+                fixed_ports: Some(ports),
+                ..Default::default()
+            };
+            assert!(matches!(
+                options.fixed_ports(),
+                Err(OAuthFlowError::InvalidOptions(_))
+            ));
+        }
+
+        for secs in [0, MAX_TIMEOUT_SECS + 1] {
+            let options = OAuthFlowOptions {
+                timeout_secs: Some(secs),
+                ..Default::default()
+            };
+            assert!(matches!(
+                options.timeout(),
+                Err(OAuthFlowError::InvalidOptions(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,549 @@
+//! OAuth token endpoint calls
+//!
+//! Authorization-code exchange and refresh (RFC 6749 §4.1.3, §6) plus token
+//! revocation (RFC 7009), run natively so the webview never needs to talk to
+//! the token endpoint itself. Errors are typed so the frontend can tell a
+//! dead refresh token (reconnect required) apart from a flaky network.
+//!
+//! The commands take vault entry names rather than secrets: the client
+//! secret and refresh token are read from the [`Vault`] here, new tokens are
+//! stored there, and the webview only ever gets the access token back.
+//! The endpoints are fixed here too, so no caller can have the secrets sent
+//! anywhere but Google.
+
+use crate::vault::Vault;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_REVOKE_URL: &str = "https://oauth2.googleapis.com/revoke";
+
+/// Upper bound for a single token endpoint round trip
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Successful token endpoint response
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    /// Only present on the initial exchange (and on refresh for some providers)
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Lifetime of the access token in seconds
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+    #[serde(default = "default_token_type")]
+    pub token_type: String,
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+fn default_token_type() -> String {
+    "Bearer".to_string()
+}
+
+/// Token endpoint failure, serialized as `{ kind, message }` for the frontend
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum TokenError {
+    /// The grant was rejected (code reused/expired or refresh token revoked).
+    /// The user has to go through the consent flow again.
+    InvalidGrant(String),
+    /// No response from the endpoint (offline, DNS, TLS, timeout). Retryable.
+    Network(String),
+    /// Any other OAuth error returned by the provider
+    Provider(String),
+    /// The endpoint answered with something that isn't a token response
+    InvalidResponse(String),
+    /// The vault has no usable client or tokens entry, or couldn't be written
+    Vault(String),
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenError::InvalidGrant(msg) => write!(f, "invalid_grant: {}", msg),
+            TokenError::Network(msg) => write!(f, "network error: {}", msg),
+            TokenError::Provider(msg) => write!(f, "provider error: {}", msg),
+            TokenError::InvalidResponse(msg) => write!(f, "invalid response: {}", msg),
+            TokenError::Vault(msg) => write!(f, "vault error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TokenError {}
+
+impl From<reqwest::Error> for TokenError {
+    fn from(e: reqwest::Error) -> Self {
+        TokenError::Network(e.to_string())
+    }
+}
+
+/// OAuth error body (RFC 6749 §5.2)
+#[derive(serde::Deserialize)]
+struct ErrorBody {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+fn http_client() -> Result<reqwest::Client, TokenError> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(TokenError::from)
+}
+
+/// Map a non-2xx response body onto a `TokenError`
+fn error_from_body(status: reqwest::StatusCode, body: &[u8]) -> TokenError {
+    match serde_json::from_slice::<ErrorBody>(body) {
+        Ok(err) => {
+            let message = err.error_description.unwrap_or_else(|| err.error.clone());
+            if err.error == "invalid_grant" {
+                TokenError::InvalidGrant(message)
+            } else {
+                TokenError::Provider(format!("{}: {}", err.error, message))
+            }
+        }
+        Err(_) => TokenError::InvalidResponse(format!("HTTP {}", status)),
+    }
+}
+
+/// POST a form to the token endpoint and parse the token response
+async fn request_token(url: &str, form: &[(&str, &str)]) -> Result<TokenResponse, TokenError> {
+    let response = http_client()?.post(url).form(form).send().await?;
+    let status = response.status();
+    let body = response.bytes().await?;
+
+    if !status.is_success() {
+        return Err(error_from_body(status, &body));
+    }
+
+    serde_json::from_slice(&body).map_err(|e| TokenError::InvalidResponse(e.to_string()))
+}
+
+/// Tokens as stored in the vault (`StoredTokens` in `lib/services/handlers/sync.ts`)
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct StoredTokens {
+    access_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+    /// When the access token expires, in Unix milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expiry_date: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+}
+
+/// OAuth client as stored in the vault (`SyncCredentials` in
+/// `lib/services/handlers/sync.ts`)
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredClient {
+    client_id: String,
+    #[serde(default)]
+    client_secret: Option<String>,
+    #[serde(default)]
+    api_key: Option<String>,
+}
+
+/// A usable access token, without the refresh token
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct AccessToken {
+    pub access_token: String,
+    /// Unix milliseconds
+    pub expiry_date: Option<i64>,
+    pub token_type: String,
+    pub scope: Option<String>,
+    /// A refresh token is stored, so `refresh_oauth_token` can renew it
+    pub can_refresh: bool,
+}
+
+impl From<&StoredTokens> for AccessToken {
+    fn from(tokens: &StoredTokens) -> Self {
+        AccessToken {
+            access_token: tokens.access_token.clone(),
+            expiry_date: tokens.expiry_date,
+            token_type: tokens.token_type.clone().unwrap_or_else(default_token_type),
+            scope: tokens.scope.clone(),
+            can_refresh: tokens.refresh_token.is_some(),
+        }
+    }
+}
+
+/// The parts of the stored OAuth client the webview needs (the Google Picker
+/// takes the client ID and API key); never the secret
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthClient {
+    pub client_id: String,
+    pub api_key: Option<String>,
+    pub has_secret: bool,
+}
+
+fn read_entry<T: serde::de::DeserializeOwned>(
+    vault: &Vault,
+    entry: &str,
+) -> Result<Option<T>, TokenError> {
+    vault
+        .get(entry)
+        .map(|json| {
+            serde_json::from_str(&json)
+                .map_err(|e| TokenError::Vault(format!("{} is unreadable: {}", entry, e)))
+        })
+        .transpose()
+}
+
+fn stored_client(vault: &Vault, entry: &str) -> Result<StoredClient, TokenError> {
+    read_entry(vault, entry)?.ok_or_else(|| TokenError::Vault(format!("no {} stored", entry)))
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Store a token response, keeping `refresh_token` when the provider didn't
+/// send a new one (Google only sends it on the first exchange)
+fn store_tokens(
+    vault: &Vault,
+    entry: &str,
+    response: TokenResponse,
+    refresh_token: Option<String>,
+) -> Result<AccessToken, TokenError> {
+    let tokens = StoredTokens {
+        access_token: response.access_token,
+        refresh_token: response.refresh_token.or(refresh_token),
+        expiry_date: Some(now_millis() + response.expires_in.unwrap_or(3600) as i64 * 1000),
+        token_type: Some(response.token_type),
+        scope: response.scope,
+    };
+    let json = serde_json::to_string(&tokens).map_err(|e| TokenError::Vault(e.to_string()))?;
+    vault
+        .set(entry, &json)
+        .map_err(|e| TokenError::Vault(e.to_string()))?;
+    Ok(AccessToken::from(&tokens))
+}
+
+async fn exchange_code(
+    token_url: &str,
+    client: &StoredClient,
+    code: &str,
+    redirect_uri: &str,
+    code_verifier: Option<&str>,
+) -> Result<TokenResponse, TokenError> {
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("client_id", client.client_id.as_str()),
+        ("redirect_uri", redirect_uri),
+    ];
+    if let Some(secret) = client.client_secret.as_deref() {
+        form.push(("client_secret", secret));
+    }
+    if let Some(verifier) = code_verifier {
+        form.push(("code_verifier", verifier));
+    }
+
+    request_token(token_url, &form).await
+}
+
+/// Refresh the tokens in `tokens_entry` with the client in
+/// `credentials_entry`, storing the new access token
+async fn refresh_stored(
+    vault: &Vault,
+    token_url: &str,
+    credentials_entry: &str,
+    tokens_entry: &str,
+) -> Result<AccessToken, TokenError> {
+    let client = stored_client(vault, credentials_entry)?;
+    let refresh_token = read_entry::<StoredTokens>(vault, tokens_entry)?
+        .and_then(|t| t.refresh_token)
+        .ok_or_else(|| TokenError::Vault(format!("no refresh token in {}", tokens_entry)))?;
+
+    let mut form = vec![
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token.as_str()),
+        ("client_id", client.client_id.as_str()),
+    ];
+    if let Some(secret) = client.client_secret.as_deref() {
+        form.push(("client_secret", secret));
+    }
+
+    let response = request_token(token_url, &form).await?;
+    store_tokens(vault, tokens_entry, response, Some(refresh_token))
+}
+
+/// Exchange an authorization code (from `start_oauth_flow`) for tokens using
+/// the client in `credentials_entry`, and store them in `tokens_entry`
+#[tauri::command]
+pub async fn exchange_oauth_code(
+    vault: tauri::State<'_, Vault>,
+    credentials_entry: String,
+    tokens_entry: String,
+    code: String,
+    redirect_uri: String,
+    code_verifier: Option<String>,
+) -> Result<AccessToken, TokenError> {
+    let client = stored_client(&vault, &credentials_entry)?;
+    let response = exchange_code(
+        GOOGLE_TOKEN_URL,
+        &client,
+        &code,
+        &redirect_uri,
+        code_verifier.as_deref(),
+    )
+    .await?;
+    store_tokens(&vault, &tokens_entry, response, None)
+}
+
+/// The access token stored in `tokens_entry`, expired or not
+#[tauri::command]
+pub fn get_oauth_access_token(
+    vault: tauri::State<'_, Vault>,
+    tokens_entry: String,
+) -> Result<Option<AccessToken>, TokenError> {
+    Ok(read_entry::<StoredTokens>(&vault, &tokens_entry)?
+        .as_ref()
+        .map(AccessToken::from))
+}
+
+/// Get a new access token with the refresh token in `tokens_entry` and the
+/// client in `credentials_entry`; the new token is stored as well
+#[tauri::command]
+pub async fn refresh_oauth_token(
+    vault: tauri::State<'_, Vault>,
+    credentials_entry: String,
+    tokens_entry: String,
+) -> Result<AccessToken, TokenError> {
+    refresh_stored(&vault, GOOGLE_TOKEN_URL, &credentials_entry, &tokens_entry).await
+}
+
+/// Client ID and API key of the client in `credentials_entry`
+#[tauri::command]
+pub fn get_oauth_client(
+    vault: tauri::State<'_, Vault>,
+    credentials_entry: String,
+) -> Result<Option<OAuthClient>, TokenError> {
+    Ok(
+        read_entry::<StoredClient>(&vault, &credentials_entry)?.map(|client| OAuthClient {
+            has_secret: client
+                .client_secret
+                .as_deref()
+                .is_some_and(|s| !s.is_empty()),
+            client_id: client.client_id,
+            api_key: client.api_key.filter(|k| !k.is_empty()),
+        }),
+    )
+}
+
+async fn revoke_token(revoke_url: &str, token: &str) -> Result<(), TokenError> {
+    let response = http_client()?
+        .post(revoke_url)
+        .form(&[("token", token)])
+        .send()
+        .await?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let body = response.bytes().await?;
+    match serde_json::from_slice::<ErrorBody>(&body) {
+        // Google answers 400 invalid_token for already-revoked tokens
+        Ok(err) if err.error == "invalid_token" => Ok(()),
+        _ => Err(error_from_body(status, &body)),
+    }
+}
+
+/// Revoke the refresh token (else the access token) stored in `tokens_entry`
+///
+/// A token the provider no longer recognises counts as revoked, and so does
+/// having none stored.
+#[tauri::command]
+pub async fn revoke_oauth_token(
+    vault: tauri::State<'_, Vault>,
+    tokens_entry: String,
+) -> Result<(), TokenError> {
+    let Some(tokens) = read_entry::<StoredTokens>(&vault, &tokens_entry)? else {
+        return Ok(());
+    };
+    let token = tokens.refresh_token.unwrap_or(tokens.access_token);
+    revoke_token(GOOGLE_REVOKE_URL, &token).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+    use tiny_http::{Header, Response, Server};
+
+    /// One-shot stub token endpoint. Returns its URL and a handle yielding
+    /// the form body it received.
+    fn stub_endpoint(status: u16, body: &'static str) -> (String, thread::JoinHandle<String>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/token", server.server_addr().to_ip().unwrap());
+        let handle = thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let mut received = String::new();
+            request.as_reader().read_to_string(&mut received).unwrap();
+            let response = Response::from_string(body)
+                .with_status_code(status)
+                .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+            request.respond(response).unwrap();
+            received
+        });
+        (url, handle)
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tauri::async_runtime::block_on(future)
+    }
+
+    /// Vault holding a client (with `secret`, if any) and a refresh token
+    fn stored_vault(dir: &std::path::Path, secret: Option<&str>) -> Vault {
+        let vault = Vault::open(dir).unwrap();
+        let client = match secret {
+            Some(secret) => format!(r#"{{"clientId":"client","clientSecret":"{}"}}"#, secret),
+            None => r#"{"clientId":"client"}"#.to_string(),
+        };
+        vault.set("credentials", &client).unwrap();
+        vault
+            .set(
+                "tokens",
+                r#"{"access_token":"old","refresh_token":"rt","expiry_date":1}"#,
+            )
+            .unwrap();
+        vault
+    }
+
+    #[test]
+    fn exchange_sends_verifier_and_parses_tokens() {
+        let (url, server) = stub_endpoint(
+            200,
+            r#"{"access_token":"at","refresh_token":"rt","expires_in":3599,"scope":"drive.file"}"#,
+        );
+        let client = StoredClient {
+            client_id: "client".into(),
+            client_secret: Some("secret".into()),
+            api_key: None,
+        };
+
+        let tokens = block_on(exchange_code(
+            &url,
+            &client,
+            "auth-code",
+            "http://127.0.0.1:5000",
+            Some("verifier"),
+        ))
+        .unwrap();
+
+        assert_eq!(tokens.access_token, "at");
+        assert_eq!(tokens.refresh_token.as_deref(), Some("rt"));
+        assert_eq!(tokens.expires_in, Some(3599));
+        assert_eq!(tokens.token_type, "Bearer");
+
+        let form = server.join().unwrap();
+        assert!(form.contains("grant_type=authorization_code"));
+        assert!(form.contains("code=auth-code"));
+        assert!(form.contains("code_verifier=verifier"));
+        assert!(form.contains("client_secret=secret"));
+    }
+
+    #[test]
+    fn refresh_reads_secrets_from_the_vault_and_stores_the_new_token() {
+        let (url, server) = stub_endpoint(200, r#"{"access_token":"new","expires_in":3599}"#);
+        let dir = tempfile::tempdir().unwrap();
+        let vault = stored_vault(dir.path(), Some("secret"));
+
+        let token = block_on(refresh_stored(&vault, &url, "credentials", "tokens")).unwrap();
+        assert_eq!(token.access_token, "new");
+        assert!(token.can_refresh);
+        assert!(token.expiry_date.unwrap() > now_millis());
+        // Only the access token goes back to the webview
+        let json = serde_json::to_string(&token).unwrap();
+        assert!(!json.contains("rt") && !json.contains("secret"));
+
+        let form = server.join().unwrap();
+        assert!(form.contains("refresh_token=rt"));
+        assert!(form.contains("client_secret=secret"));
+
+        // Google sends no new refresh token, so the old one is kept
+        let stored: StoredTokens = read_entry(&vault, "tokens").unwrap().unwrap();
+        assert_eq!(stored.access_token, "new");
+        assert_eq!(stored.refresh_token.as_deref(), Some("rt"));
+    }
+
+    #[test]
+    fn refresh_needs_stored_client_and_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = Vault::open(dir.path()).unwrap();
+        let err = block_on(refresh_stored(
+            &vault,
+            "http://unused",
+            "credentials",
+            "tokens",
+        ))
+        .unwrap_err();
+        assert!(matches!(err, TokenError::Vault(_)));
+    }
+
+    #[test]
+    fn refresh_maps_invalid_grant() {
+        let (url, server) = stub_endpoint(
+            400,
+            r#"{"error":"invalid_grant","error_description":"Token has been expired or revoked."}"#,
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let vault = stored_vault(dir.path(), None);
+
+        let err = block_on(refresh_stored(&vault, &url, "credentials", "tokens")).unwrap_err();
+        assert!(matches!(err, TokenError::InvalidGrant(ref m) if m.contains("revoked")));
+        assert!(!server.join().unwrap().contains("client_secret"));
+    }
+
+    #[test]
+    fn other_oauth_errors_are_provider_errors() {
+        let (url, _server) = stub_endpoint(401, r#"{"error":"invalid_client"}"#);
+        let dir = tempfile::tempdir().unwrap();
+        let vault = stored_vault(dir.path(), None);
+
+        let err = block_on(refresh_stored(&vault, &url, "credentials", "tokens")).unwrap_err();
+        assert!(matches!(err, TokenError::Provider(ref m) if m.starts_with("invalid_client")));
+    }
+
+    #[test]
+    fn unreachable_endpoint_is_network_error() {
+        // Grab a free port and release it so nothing is listening there
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let url = format!("http://127.0.0.1:{}/token", port);
+        let dir = tempfile::tempdir().unwrap();
+        let vault = stored_vault(dir.path(), None);
+
+        let err = block_on(refresh_stored(&vault, &url, "credentials", "tokens")).unwrap_err();
+        assert!(matches!(err, TokenError::Network(_)));
+    }
+
+    #[test]
+    fn revoke_treats_unknown_token_as_revoked() {
+        let (url, _server) = stub_endpoint(400, r#"{"error":"invalid_token"}"#);
+        assert!(block_on(revoke_token(&url, "at")).is_ok());
+    }
+
+    #[test]
+    fn error_serializes_with_kind_tag() {
+        let json = serde_json::to_value(TokenError::InvalidGrant("gone".into())).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "kind": "invalid_grant", "message": "gone" })
+        );
+    }
+}
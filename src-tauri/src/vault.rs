@@ -0,0 +1,352 @@
+//! Encrypted secret vault
+//!
+//! Stores OAuth tokens and sync credentials in `vault/vault.bin` under the app
+//! data directory instead of webview localStorage. Entries are a flat string
+//! map, sealed with XChaCha20-Poly1305 under a 256-bit key kept in
+//! `vault/vault.key` next to it. The key file is plain random bytes, so the
+//! vault moves between operating systems together with the data directory.
+//! The `vault` directory is denied in the fs plugin scope (see
+//! `capabilities/default.json`) so webview scripts can't read either file.
+//!
+//! The OAuth commands (`oauth::token`) read the client secret and refresh
+//! token here themselves. Secrets only cross into JS through `vault_get`,
+//! which asks the user first, for an explicit export; everything else
+//! (`vault_has`, `vault_set`, `vault_delete`) never returns a value.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+/// Subdirectory of the app data dir holding the vault files
+pub const VAULT_DIR: &str = "vault";
+const VAULT_FILE: &str = "vault.bin";
+const KEY_FILE: &str = "vault.key";
+const FORMAT_VERSION: u32 = 1;
+const KEY_LEN: usize = 32;
+
+#[derive(Debug)]
+pub enum VaultError {
+    Io(io::Error),
+    /// The key file exists but isn't a 256-bit key
+    InvalidKey,
+    /// The vault file is malformed, was written by a newer version, or fails
+    /// authentication (tampered or sealed with another key)
+    Corrupt(String),
+    /// Empty or otherwise unusable entry name
+    InvalidName,
+}
+
+impl fmt::Display for VaultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VaultError::Io(e) => write!(f, "Vault I/O error: {}", e),
+            VaultError::InvalidKey => write!(f, "Vault key file is invalid"),
+            VaultError::Corrupt(msg) => write!(f, "Vault is unreadable: {}", msg),
+            VaultError::InvalidName => write!(f, "Vault entry name must not be empty"),
+        }
+    }
+}
+
+impl std::error::Error for VaultError {}
+
+impl From<io::Error> for VaultError {
+    fn from(e: io::Error) -> Self {
+        VaultError::Io(e)
+    }
+}
+
+/// On-disk envelope around the sealed entry map
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SealedVault {
+    version: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Secret store managed as Tauri state
+pub struct Vault {
+    path: PathBuf,
+    cipher: XChaCha20Poly1305,
+    entries: Mutex<BTreeMap<String, String>>,
+}
+
+impl Vault {
+    /// Open (or create) the vault in `dir`.
+    ///
+    /// An unreadable vault file is moved aside to `vault.bin.corrupt` and an
+    /// empty vault is started, so a damaged file costs a reconnect rather than
+    /// blocking app startup. A key file that isn't a 256-bit key is moved
+    /// aside to `vault.key.corrupt` the same way, with a new key and vault.
+    pub fn open(dir: &Path) -> Result<Self, VaultError> {
+        fs::create_dir_all(dir)?;
+        let key_path = dir.join(KEY_FILE);
+        let path = dir.join(VAULT_FILE);
+        let key = match load_or_create_key(&key_path) {
+            Err(VaultError::InvalidKey) => {
+                log::error!("Vault key file is invalid; starting with a new key and vault");
+                fs::rename(&key_path, key_path.with_extension("key.corrupt"))?;
+                if path.exists() {
+                    fs::rename(&path, path.with_extension("bin.corrupt"))?;
+                }
+                load_or_create_key(&key_path)?
+            }
+            key => key?,
+        };
+        let cipher = XChaCha20Poly1305::new(&key);
+
+        let entries = match fs::read(&path) {
+            Ok(bytes) => match unseal(&cipher, &bytes) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    log::error!("{}; starting with an empty vault", e);
+                    fs::rename(&path, path.with_extension("bin.corrupt"))?;
+                    BTreeMap::new()
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path,
+            cipher,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.entries.lock().unwrap().get(name).cloned()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.lock().unwrap().contains_key(name)
+    }
+
+    pub fn set(&self, name: &str, value: &str) -> Result<(), VaultError> {
+        if name.trim().is_empty() {
+            return Err(VaultError::InvalidName);
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(name.to_string(), value.to_string());
+        self.persist(&entries)
+    }
+
+    /// Remove an entry. Removing a missing entry is not an error.
+    pub fn delete(&self, name: &str) -> Result<(), VaultError> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.remove(name).is_some() {
+            self.persist(&entries)?;
+        }
+        Ok(())
+    }
+
+    /// Seal and atomically replace the vault file
+    fn persist(&self, entries: &BTreeMap<String, String>) -> Result<(), VaultError> {
+        let plaintext =
+            serde_json::to_vec(entries).map_err(|e| VaultError::Corrupt(e.to_string()))?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| VaultError::Corrupt("encryption failed".to_string()))?;
+        let sealed = SealedVault {
+            version: FORMAT_VERSION,
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+        let json = serde_json::to_vec(&sealed).map_err(|e| VaultError::Corrupt(e.to_string()))?;
+        write_private(&self.path, &json)
+    }
+}
+
+fn unseal(
+    cipher: &XChaCha20Poly1305,
+    bytes: &[u8],
+) -> Result<BTreeMap<String, String>, VaultError> {
+    let sealed: SealedVault =
+        serde_json::from_slice(bytes).map_err(|e| VaultError::Corrupt(e.to_string()))?;
+    if sealed.version != FORMAT_VERSION {
+        return Err(VaultError::Corrupt(format!(
+            "unsupported vault version {}",
+            sealed.version
+        )));
+    }
+    let nonce = BASE64
+        .decode(&sealed.nonce)
+        .map_err(|e| VaultError::Corrupt(e.to_string()))?;
+    if nonce.len() != 24 {
+        return Err(VaultError::Corrupt("bad nonce length".to_string()));
+    }
+    let ciphertext = BASE64
+        .decode(&sealed.ciphertext)
+        .map_err(|e| VaultError::Corrupt(e.to_string()))?;
+    let plaintext = cipher
+        .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| VaultError::Corrupt("authentication failed".to_string()))?;
+    serde_json::from_slice(&plaintext).map_err(|e| VaultError::Corrupt(e.to_string()))
+}
+
+fn load_or_create_key(path: &Path) -> Result<Key, VaultError> {
+    match fs::read(path) {
+        Ok(bytes) if bytes.len() == KEY_LEN => Ok(*Key::from_slice(&bytes)),
+        Ok(_) => Err(VaultError::InvalidKey),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+            write_private(path, key.as_slice())?;
+            Ok(key)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Write via a temp file + rename, readable by the current user only
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), VaultError> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, contents)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&tmp, fs::Permissions::from_mode(0o600))?;
+    }
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Read a secret, for an explicit export. This is the only command that
+/// returns vault contents to JS, and only after the user agrees in a native
+/// dialog, so a script in the webview can't read secrets on its own.
+#[tauri::command]
+pub async fn vault_get(app: tauri::AppHandle, name: String) -> Result<Option<String>, String> {
+    let Some(value) = app.state::<Vault>().get(&name) else {
+        return Ok(None);
+    };
+    let dialog = app
+        .dialog()
+        .message(format!(
+            "Show the stored secret \"{}\"? Only allow this if you asked to export it.",
+            name
+        ))
+        .title("Reveal secret")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Reveal".to_string(),
+            "Cancel".to_string(),
+        ));
+    let allowed = tauri::async_runtime::spawn_blocking(move || dialog.blocking_show())
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(allowed.then_some(value))
+}
+
+/// Check whether a secret exists without reading it
+#[tauri::command]
+pub fn vault_has(vault: tauri::State<'_, Vault>, name: String) -> bool {
+    vault.contains(&name)
+}
+
+#[tauri::command]
+pub fn vault_set(
+    vault: tauri::State<'_, Vault>,
+    name: String,
+    value: String,
+) -> Result<(), String> {
+    vault.set(&name, &value).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn vault_delete(vault: tauri::State<'_, Vault>, name: String) -> Result<(), String> {
+    vault.delete(&name).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = Vault::open(dir.path()).unwrap();
+        vault
+            .set("puffin_oauth_tokens", r#"{"refresh_token":"rt"}"#)
+            .unwrap();
+        vault.set("other", "value").unwrap();
+        vault.delete("other").unwrap();
+        drop(vault);
+
+        let reopened = Vault::open(dir.path()).unwrap();
+        assert_eq!(
+            reopened.get("puffin_oauth_tokens").as_deref(),
+            Some(r#"{"refresh_token":"rt"}"#)
+        );
+        assert!(!reopened.contains("other"));
+    }
+
+    #[test]
+    fn secrets_are_not_stored_in_plaintext() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = Vault::open(dir.path()).unwrap();
+        vault.set("token", "very-secret-refresh-token").unwrap();
+
+        let raw = fs::read_to_string(dir.path().join(VAULT_FILE)).unwrap();
+        assert!(!raw.contains("very-secret-refresh-token"));
+    }
+
+    #[test]
+    fn tampered_vault_is_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        Vault::open(dir.path())
+            .unwrap()
+            .set("token", "secret")
+            .unwrap();
+
+        // Replace the key so the existing vault no longer authenticates
+        fs::write(dir.path().join(KEY_FILE), [7u8; KEY_LEN]).unwrap();
+        let vault = Vault::open(dir.path()).unwrap();
+
+        assert!(vault.get("token").is_none());
+        assert!(dir.path().join("vault.bin.corrupt").exists());
+    }
+
+    #[test]
+    fn truncated_key_is_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        Vault::open(dir.path())
+            .unwrap()
+            .set("token", "secret")
+            .unwrap();
+        fs::write(dir.path().join(KEY_FILE), [1u8; 16]).unwrap();
+
+        let vault = Vault::open(dir.path()).unwrap();
+        assert!(vault.get("token").is_none());
+        assert_eq!(
+            fs::read(dir.path().join("vault.key.corrupt")).unwrap(),
+            [1u8; 16]
+        );
+        assert!(dir.path().join("vault.bin.corrupt").exists());
+        assert_eq!(fs::read(dir.path().join(KEY_FILE)).unwrap().len(), KEY_LEN);
+
+        // The new vault works and survives a reopen
+        vault.set("token", "new").unwrap();
+        drop(vault);
+        assert_eq!(
+            Vault::open(dir.path()).unwrap().get("token").as_deref(),
+            Some("new")
+        );
+    }
+
+    #[test]
+    fn rejects_empty_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = Vault::open(dir.path()).unwrap();
+        assert!(matches!(vault.set("  ", "x"), Err(VaultError::InvalidName)));
+    }
+}